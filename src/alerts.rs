@@ -0,0 +1,304 @@
+#![allow(dead_code)]
+//! Operational alerts for the appointment scheduling system.
+//!
+//! Monitors inspect the calendar and scheduler and raise Alerts when staff
+//! should act, e.g. when free capacity runs low. Alerts can be shown as a
+//! CLI banner or sent through any NotificationChannel.
+
+use crate::calendar::DoctorCalendar;
+use crate::events::CalendarEvent;
+use crate::ids::IdGenerator;
+use crate::models::{AttendanceStatus, Priority};
+use crate::notifications::{Notification, NotificationChannel};
+use crate::scheduler::AppointmentScheduler;
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A condition that needs staff attention.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Alert {
+    LowCapacity {
+        horizon_days: i64,
+        free_slots: usize,
+        threshold: usize,
+    },
+    DayFullyBooked {
+        date: NaiveDate,
+    },
+    QueueBacklog {
+        pending: usize,
+        threshold: usize,
+    },
+    StaleRoutineRequest {
+        patient_name: String,
+        age_minutes: i64,
+        max_age_minutes: i64,
+    },
+    QueueUnavailable {
+        error: String,
+    },
+    AppointmentImminent {
+        patient_name: String,
+        start_time: DateTime<Local>,
+    },
+    EmergencyBooked {
+        patient_name: String,
+        start_time: DateTime<Local>,
+    },
+}
+
+impl Alert {
+    /// One-line description suitable for a banner.
+    pub fn message(&self) -> String {
+        match self {
+            Alert::LowCapacity {
+                horizon_days,
+                free_slots,
+                threshold,
+            } => format!(
+                "Low capacity: only {} free slots in the next {} days (threshold {})",
+                free_slots, horizon_days, threshold
+            ),
+            Alert::DayFullyBooked { date } => {
+                format!("{} is fully booked", date.format("%A, %Y-%m-%d"))
+            }
+            Alert::QueueBacklog { pending, threshold } => format!(
+                "Request backlog: {} pending requests (threshold {})",
+                pending, threshold
+            ),
+            Alert::StaleRoutineRequest {
+                patient_name,
+                age_minutes,
+                max_age_minutes,
+            } => format!(
+                "Routine request for {} has waited {} minutes (limit {})",
+                patient_name, age_minutes, max_age_minutes
+            ),
+            Alert::QueueUnavailable { error } => format!("Request queue unavailable: {}", error),
+            Alert::AppointmentImminent {
+                patient_name,
+                start_time,
+            } => format!("{} is due at {}", patient_name, start_time.format("%H:%M")),
+            Alert::EmergencyBooked {
+                patient_name,
+                start_time,
+            } => format!(
+                "Emergency booked: {} at {}",
+                patient_name,
+                start_time.format("%Y-%m-%d %H:%M")
+            ),
+        }
+    }
+
+    /// Build a notification for this alert.
+    pub fn to_notification(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        recipient: &str,
+    ) -> Result<Notification, String> {
+        Notification::new(
+            ids,
+            now,
+            recipient.to_string(),
+            "Scheduling alert".to_string(),
+            self.message(),
+        )
+    }
+}
+
+/// Send every alert through a channel, returning how many were delivered.
+///
+/// Notification IDs and times come from the calendar's generator and clock.
+pub fn send_alerts(
+    calendar: &DoctorCalendar,
+    alerts: &[Alert],
+    channel: &mut dyn NotificationChannel,
+    recipient: &str,
+) -> Result<usize, String> {
+    let now = calendar.now();
+    for alert in alerts {
+        channel.send(&alert.to_notification(calendar.id_generator(), now, recipient)?)?;
+    }
+    Ok(alerts.len())
+}
+
+/// Alert for appointments whose reminder time passed in `(since, now]`.
+///
+/// An appointment's reminder time is `lead` before its start. Calling this
+/// on every tick with the previous tick's time raises each reminder once.
+/// Patients who have already checked in are skipped.
+pub fn imminent_appointments(
+    calendar: &DoctorCalendar,
+    lead: Duration,
+    since: DateTime<Local>,
+) -> Vec<Alert> {
+    let now = calendar.now();
+    calendar
+        .appointments()
+        .into_iter()
+        .filter(|apt| {
+            let remind_at = apt.time_slot.start_time - lead;
+            remind_at > since
+                && remind_at <= now
+                && apt.attendance_status(now) == AttendanceStatus::Upcoming
+        })
+        .map(|apt| Alert::AppointmentImminent {
+            patient_name: apt.patient.name,
+            start_time: apt.time_slot.start_time,
+        })
+        .collect()
+}
+
+/// Alert for a calendar event that books an emergency appointment.
+pub fn emergency_booking_alert(event: &CalendarEvent) -> Option<Alert> {
+    match event {
+        CalendarEvent::AppointmentBooked { appointment }
+            if appointment.priority == Priority::Emergency =>
+        {
+            Some(Alert::EmergencyBooked {
+                patient_name: appointment.patient.name.clone(),
+                start_time: appointment.time_slot.start_time,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Watches upcoming free capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapacityMonitor {
+    pub horizon_days: i64,
+    pub min_free_slots: usize,
+}
+
+impl CapacityMonitor {
+    /// Create a monitor with validation.
+    pub fn new(horizon_days: i64, min_free_slots: usize) -> Result<Self, String> {
+        if horizon_days <= 0 {
+            return Err("Capacity horizon must be at least one day".to_string());
+        }
+
+        Ok(CapacityMonitor {
+            horizon_days,
+            min_free_slots,
+        })
+    }
+
+    /// Check the calendar from its clock's current time forward.
+    ///
+    /// Raises LowCapacity when free future slots within the horizon fall
+    /// below the threshold, and DayFullyBooked for each day in the horizon
+    /// that has slots but none left free.
+    pub fn check(&self, calendar: &DoctorCalendar) -> Vec<Alert> {
+        let now = calendar.now();
+        let horizon_end = now + Duration::days(self.horizon_days);
+        let mut alerts = Vec::new();
+
+        let mut days: BTreeMap<NaiveDate, (usize, usize)> = BTreeMap::new();
+        for slot in calendar.time_slots() {
+            if slot.start_time < now || slot.start_time >= horizon_end {
+                continue;
+            }
+            let entry = days.entry(slot.start_time.date_naive()).or_insert((0, 0));
+            entry.0 += 1;
+            if slot.is_available {
+                entry.1 += 1;
+            }
+        }
+
+        let free_slots: usize = days.values().map(|(_, free)| free).sum();
+        if free_slots < self.min_free_slots {
+            alerts.push(Alert::LowCapacity {
+                horizon_days: self.horizon_days,
+                free_slots,
+                threshold: self.min_free_slots,
+            });
+        }
+
+        for (date, (total, free)) in days {
+            if total > 0 && free == 0 {
+                alerts.push(Alert::DayFullyBooked { date });
+            }
+        }
+
+        alerts
+    }
+}
+
+impl Default for CapacityMonitor {
+    fn default() -> Self {
+        CapacityMonitor {
+            horizon_days: 7,
+            min_free_slots: 5,
+        }
+    }
+}
+
+/// Watches the scheduler's pending request queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueMonitor {
+    pub max_pending: usize,
+    pub max_routine_age_minutes: i64,
+}
+
+impl QueueMonitor {
+    /// Create a monitor with validation.
+    pub fn new(max_pending: usize, max_routine_age_minutes: i64) -> Result<Self, String> {
+        if max_routine_age_minutes <= 0 {
+            return Err("Maximum routine request age must be positive".to_string());
+        }
+
+        Ok(QueueMonitor {
+            max_pending,
+            max_routine_age_minutes,
+        })
+    }
+
+    /// Check the queue against the backlog and age limits.
+    ///
+    /// Age is measured with the scheduler calendar's clock. Only the oldest
+    /// pending routine request is reported.
+    pub fn check(&self, scheduler: &AppointmentScheduler) -> Vec<Alert> {
+        let pending = match scheduler.pending_requests() {
+            Ok(pending) => pending,
+            Err(error) => return vec![Alert::QueueUnavailable { error }],
+        };
+        let mut alerts = Vec::new();
+
+        if pending.len() > self.max_pending {
+            alerts.push(Alert::QueueBacklog {
+                pending: pending.len(),
+                threshold: self.max_pending,
+            });
+        }
+
+        let now = scheduler.calendar.now();
+        if let Some(oldest) = pending
+            .iter()
+            .filter(|request| request.priority == Priority::Routine)
+            .min_by_key(|request| request.created_at)
+        {
+            let age_minutes = (now - oldest.created_at).num_minutes();
+            if age_minutes > self.max_routine_age_minutes {
+                alerts.push(Alert::StaleRoutineRequest {
+                    patient_name: oldest.patient.name.clone(),
+                    age_minutes,
+                    max_age_minutes: self.max_routine_age_minutes,
+                });
+            }
+        }
+
+        alerts
+    }
+}
+
+impl Default for QueueMonitor {
+    fn default() -> Self {
+        QueueMonitor {
+            max_pending: 20,
+            max_routine_age_minutes: 120,
+        }
+    }
+}
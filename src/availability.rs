@@ -0,0 +1,150 @@
+#![allow(dead_code)]
+//! Self-service availability management for doctors.
+//!
+//! A DoctorAvailability handle is opened for one doctor's calendar and
+//! only ever touches that calendar, so in a clinic with several doctors a
+//! doctor can block time, extend a day or take leave without access to
+//! anyone else's schedule.
+
+use crate::calendar::{DoctorCalendar, GenerationReport, SkippedSlot};
+use crate::ids::IdKind;
+use crate::models::{Appointment, TimeSlot};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+
+/// Outcome of taking time out of a calendar.
+#[derive(Debug, Clone, Default)]
+pub struct BlockReport {
+    /// Free slots removed from the calendar.
+    pub removed: Vec<TimeSlot>,
+    /// Free slots the calendar refused to remove, e.g. on a locked day.
+    pub refused: Vec<TimeSlot>,
+    /// Appointments in the blocked period, left for staff to move or cancel.
+    pub conflicts: Vec<Appointment>,
+}
+
+/// Availability commands scoped to a single doctor's calendar.
+pub struct DoctorAvailability<'a> {
+    calendar: &'a mut DoctorCalendar,
+}
+
+impl<'a> DoctorAvailability<'a> {
+    /// Open a doctor's own calendar, refusing anyone else's.
+    pub fn for_doctor(calendar: &'a mut DoctorCalendar, doctor_id: &str) -> Result<Self, String> {
+        if calendar.doctor_id != doctor_id {
+            return Err("Doctors can only manage their own availability".to_string());
+        }
+        Ok(DoctorAvailability { calendar })
+    }
+
+    /// Get the calendar being managed.
+    pub fn calendar(&self) -> &DoctorCalendar {
+        self.calendar
+    }
+
+    /// Remove every free slot overlapping `[start, end)`.
+    ///
+    /// Booked slots are kept and their appointments reported as conflicts,
+    /// and free slots the calendar won't remove are reported as refused.
+    pub fn block_time(
+        &mut self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<BlockReport, String> {
+        if end <= start {
+            return Err("End time must be after start time".to_string());
+        }
+
+        let mut report = BlockReport::default();
+        for slot in self.calendar.time_slots() {
+            if slot.start_time >= end || slot.end_time <= start {
+                continue;
+            }
+            if slot.is_available {
+                if self.calendar.remove_time_slot(&slot.slot_id) {
+                    report.removed.push(slot);
+                } else {
+                    report.refused.push(slot);
+                }
+            } else if let Some(apt) = self
+                .calendar
+                .appointments()
+                .into_iter()
+                .find(|apt| apt.time_slot.slot_id == slot.slot_id)
+            {
+                report.conflicts.push(apt);
+            }
+        }
+        Ok(report)
+    }
+
+    /// Take whole days off, from `first_day` to `last_day` inclusive.
+    ///
+    /// The days stay blocked against new slots and bookings until
+    /// `DoctorCalendar::unblock` lifts them.
+    pub fn set_vacation(
+        &mut self,
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+    ) -> Result<BlockReport, String> {
+        if last_day < first_day {
+            return Err("Vacation cannot end before it starts".to_string());
+        }
+
+        let start = local_midnight(first_day)?;
+        let end = local_midnight(last_day + Duration::days(1))?;
+        self.calendar
+            .block_period(start, end, "Vacation".to_string())
+    }
+
+    /// Add slots after the day's last slot until `until_hour`.
+    ///
+    /// Slots use the calendar's default duration. Days without any slots
+    /// cannot be extended; generate them first.
+    pub fn extend_day(
+        &mut self,
+        date: NaiveDate,
+        until_hour: u32,
+    ) -> Result<GenerationReport, String> {
+        let until = date
+            .and_hms_opt(until_hour, 0, 0)
+            .ok_or("Invalid hour")?
+            .and_local_timezone(Local)
+            .single()
+            .ok_or("Invalid local time")?;
+        let mut current = self
+            .calendar
+            .time_slots()
+            .iter()
+            .filter(|slot| slot.start_time.date_naive() == date)
+            .map(|slot| slot.end_time)
+            .max()
+            .ok_or("No slots on that day to extend")?;
+
+        let duration = Duration::minutes(self.calendar.default_slot_duration);
+        let mut report = GenerationReport::default();
+        while current + duration <= until {
+            let mut slot = TimeSlot::new(current, current + duration).map_err(String::from)?;
+            if let Some(reason) = self.calendar.unavailable_reason(slot.start_time, slot.end_time) {
+                report.skipped_busy.push(SkippedSlot { slot, reason });
+                current += duration;
+                continue;
+            }
+            slot.slot_id = self.calendar.next_id(IdKind::Slot);
+            match self.calendar.add_time_slot(slot.clone()) {
+                Ok(()) => report.created.push(slot),
+                Err(reason) => report.skipped_overlap.push(SkippedSlot { slot, reason }),
+            }
+            current += duration;
+        }
+        Ok(report)
+    }
+}
+
+/// Get the start of a day in local time.
+fn local_midnight(date: NaiveDate) -> Result<DateTime<Local>, String> {
+    date.and_hms_opt(0, 0, 0)
+        .unwrap()
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(|| format!("Invalid local date: {}", date))
+}
@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+//! Synthetic load generator for timing the scheduler.
+//!
+//! Enabled by the `bench` feature and run with
+//! `easyappoint --bench-mode [scale...]`, e.g. `--bench-mode 1000 10000`.
+//! Each scale builds a calendar with that many slots, then times slot
+//! generation, `find_available_slot` lookups and a full `process_queue`
+//! run over that many requests. Build with `--release`: debug builds check
+//! the calendar invariants after every mutation, which dominates the timings.
+
+use crate::calendar::DoctorCalendar;
+use crate::compact::{time_slot_memory_bytes, CompactSlots};
+use crate::models::{AppointmentRequest, Patient, Priority};
+use crate::scheduler::AppointmentScheduler;
+use chrono::{DateTime, Duration, Local, Timelike};
+use std::time::{Duration as StdDuration, Instant};
+
+/// Scales used when none are given on the command line.
+pub const DEFAULT_SCALES: [usize; 3] = [1_000, 10_000, 100_000];
+
+/// Lookups timed per scale.
+const LOOKUPS: usize = 1_000;
+
+/// Timings for one scale.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    pub scale: usize,
+    pub slots: usize,
+    pub generation: StdDuration,
+    pub lookups: StdDuration,
+    pub process_queue: StdDuration,
+    pub confirmed: usize,
+    /// Bytes held by the calendar's slots as TimeSlot values and packed.
+    pub slot_bytes: usize,
+    pub compact_bytes: usize,
+}
+
+/// Parse `--bench-mode` arguments, falling back to the default scales.
+pub fn parse_scales(args: &[String]) -> Result<Vec<usize>, String> {
+    if args.is_empty() {
+        return Ok(DEFAULT_SCALES.to_vec());
+    }
+    args.iter()
+        .map(|arg| {
+            arg.replace('_', "")
+                .parse::<usize>()
+                .ok()
+                .filter(|scale| *scale > 0)
+                .ok_or_else(|| format!("Invalid scale: {}", arg))
+        })
+        .collect()
+}
+
+/// Run every scale and print a table of timings.
+pub fn run(scales: &[usize]) -> Result<(), String> {
+    println!(
+        "{:>8} {:>8} {:>12} {:>12} {:>14} {:>10} {:>12} {:>12}",
+        "scale", "slots", "generate", "lookups", "process_queue", "booked", "slot bytes", "packed"
+    );
+    for &scale in scales {
+        let report = run_scale(scale)?;
+        println!(
+            "{:>8} {:>8} {:>10.1}ms {:>10.1}ms {:>12.1}ms {:>10} {:>12} {:>12}",
+            report.scale,
+            report.slots,
+            millis(report.generation),
+            millis(report.lookups),
+            millis(report.process_queue),
+            report.confirmed,
+            report.slot_bytes,
+            report.compact_bytes
+        );
+    }
+    Ok(())
+}
+
+/// Build a calendar and queue of `scale` slots and requests and time them.
+pub fn run_scale(scale: usize) -> Result<BenchReport, String> {
+    let mut calendar = DoctorCalendar::new("Dr. Bench".to_string(), 30)?;
+    let first_day = Local::now() + Duration::days(1);
+
+    // 20 half-hour slots per day between 08:00 and 18:00
+    let days = scale.div_ceil(20);
+    let started = Instant::now();
+    for day in 0..days {
+        calendar.generate_daily_slots(
+            first_day + Duration::days(day as i64),
+            8,
+            18,
+            None,
+            None,
+            None,
+        );
+    }
+    let generation = started.elapsed();
+    let all_slots = calendar.time_slots();
+    let slots = all_slots.len();
+    let slot_bytes = time_slot_memory_bytes(&all_slots);
+    let compact_bytes = CompactSlots::from_slots(&all_slots)?.memory_bytes();
+
+    let mut random = Lcg::new(scale as u64);
+    let started = Instant::now();
+    for _ in 0..LOOKUPS {
+        calendar.find_available_slot(random_time(&mut random, days), 60);
+    }
+    let lookups = started.elapsed();
+
+    let mut scheduler = AppointmentScheduler::new(calendar, true);
+    for index in 0..scale {
+        let patient = Patient::new(
+            format!("P{}", index),
+            format!("Patient {}", index),
+            "555-0100".to_string(),
+        )
+        .map_err(String::from)?;
+        let priority = match random.next() % 10 {
+            0 => Priority::Emergency,
+            1 | 2 => Priority::Urgent,
+            _ => Priority::Routine,
+        };
+        let request = AppointmentRequest::new(
+            scheduler.calendar.id_generator(),
+            scheduler.calendar.now(),
+            patient,
+            priority,
+            random_time(&mut random, days),
+            "Checkup".to_string(),
+            60,
+        )
+        .map_err(String::from)?;
+        scheduler.add_request(request)?;
+    }
+
+    let started = Instant::now();
+    let result = scheduler.process_queue();
+    let process_queue = started.elapsed();
+
+    Ok(BenchReport {
+        scale,
+        slots,
+        generation,
+        lookups,
+        process_queue,
+        confirmed: result.confirmed.len(),
+        slot_bytes,
+        compact_bytes,
+    })
+}
+
+/// Pick a working-hours time on one of the generated days.
+fn random_time(random: &mut Lcg, days: usize) -> DateTime<Local> {
+    let day = (random.next() % days as u64) as i64;
+    let minute = (random.next() % 600) as i64;
+    let base = Local::now() + Duration::days(1 + day);
+    let midnight = base - Duration::seconds(base.num_seconds_from_midnight() as i64);
+    midnight + Duration::minutes(8 * 60 + minute)
+}
+
+fn millis(duration: StdDuration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Small deterministic generator so runs are repeatable without extra dependencies.
+struct Lcg(u64);
+
+impl Lcg {
+    fn new(seed: u64) -> Self {
+        Lcg(seed ^ 0x5DEE_CE66)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        self.0 >> 33
+    }
+}
@@ -0,0 +1,160 @@
+#![allow(dead_code)]
+//! Waiting-room queue board.
+//!
+//! The board shows who is being seen, who is next among the patients
+//! already checked in, and who is still due later today. It hangs where
+//! everyone in the waiting room can read it, so patients appear only as
+//! their initials and the last characters of their confirmation code; the
+//! full code is what they use to check in or reschedule, so it is never
+//! shown. The board is rebuilt from the calendar on every refresh, as
+//! plain text for a terminal or as an HTML page that reloads itself.
+
+use crate::calendar::DoctorCalendar;
+use crate::card::escape_html;
+use crate::models::{Appointment, AppointmentStatus};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+/// Confirmation code characters shown on the board.
+const CODE_SUFFIX_LENGTH: usize = 4;
+
+/// One anonymized patient on the board.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BoardEntry {
+    pub initials: String,
+    /// The last characters of the confirmation code.
+    pub code: String,
+    pub appointment_time: DateTime<Local>,
+}
+
+impl BoardEntry {
+    fn from_appointment(appointment: &Appointment) -> Self {
+        let initials: String = appointment
+            .patient
+            .name
+            .split_whitespace()
+            .filter_map(|part| part.chars().next())
+            .flat_map(|initial| initial.to_uppercase().chain(['.']))
+            .collect();
+        let code = &appointment.confirmation_code;
+        let suffix_start = code
+            .char_indices()
+            .rev()
+            .nth(CODE_SUFFIX_LENGTH - 1)
+            .map_or(0, |(index, _)| index);
+        BoardEntry {
+            initials,
+            code: code[suffix_start..].to_string(),
+            appointment_time: appointment.time_slot.start_time,
+        }
+    }
+
+    /// Format the entry as `A.L. ...9691 (10:00)`.
+    pub fn label(&self) -> String {
+        format!(
+            "{} ...{} ({})",
+            self.initials,
+            self.code,
+            self.appointment_time.format("%H:%M")
+        )
+    }
+}
+
+/// Today's queue for one doctor at a moment in time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct QueueBoard {
+    pub doctor_name: String,
+    pub generated_at: DateTime<Local>,
+    /// The checked-in patient whose appointment started first.
+    pub now_serving: Option<BoardEntry>,
+    /// The other checked-in patients, in appointment order.
+    pub next_up: Vec<BoardEntry>,
+    /// Patients not yet arrived for appointments still to start today.
+    pub later_today: Vec<BoardEntry>,
+}
+
+impl QueueBoard {
+    /// Build the board for today from a calendar, listing at most
+    /// `max_entries` patients under next up and later today.
+    pub fn for_calendar(calendar: &DoctorCalendar, max_entries: usize) -> Self {
+        let now = calendar.now();
+        let mut appointments = calendar.get_appointments_on_date(now);
+        appointments.sort_by_key(|apt| apt.time_slot.start_time);
+
+        let mut checked_in = appointments
+            .iter()
+            .filter(|apt| apt.status == AppointmentStatus::CheckedIn)
+            .map(BoardEntry::from_appointment);
+        let now_serving = checked_in.next();
+        let next_up = checked_in.take(max_entries).collect();
+        let later_today = appointments
+            .iter()
+            .filter(|apt| apt.status == AppointmentStatus::Confirmed)
+            .filter(|apt| apt.time_slot.start_time >= now)
+            .take(max_entries)
+            .map(BoardEntry::from_appointment)
+            .collect();
+
+        QueueBoard {
+            doctor_name: calendar.doctor_name.clone(),
+            generated_at: now,
+            now_serving,
+            next_up,
+            later_today,
+        }
+    }
+
+    fn sections(&self) -> [(&'static str, Vec<String>); 3] {
+        let labels = |entries: &[BoardEntry]| entries.iter().map(BoardEntry::label).collect();
+        [
+            (
+                "Now serving",
+                self.now_serving.iter().map(BoardEntry::label).collect(),
+            ),
+            ("Next up", labels(&self.next_up)),
+            ("Later today", labels(&self.later_today)),
+        ]
+    }
+
+    /// Render the board as plain text for a terminal.
+    pub fn to_text(&self) -> String {
+        let mut output = format!(
+            "{} - {}\n",
+            self.doctor_name,
+            self.generated_at.format("%A %H:%M")
+        );
+        for (title, labels) in self.sections() {
+            output.push_str(&format!("\n{}\n", title.to_uppercase()));
+            if labels.is_empty() {
+                output.push_str("  -\n");
+            }
+            for label in labels {
+                output.push_str(&format!("  {}\n", label));
+            }
+        }
+        output
+    }
+
+    /// Render the board as an HTML page that reloads every
+    /// `refresh_seconds`.
+    pub fn to_html(&self, refresh_seconds: u64) -> String {
+        let mut output = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <meta http-equiv=\"refresh\" content=\"{}\">\n<title>{}</title>\n</head>\n\
+             <body class=\"queue-board\">\n<h1>{}</h1>\n<p>{}</p>\n",
+            refresh_seconds,
+            escape_html(&self.doctor_name),
+            escape_html(&self.doctor_name),
+            self.generated_at.format("%A %H:%M")
+        );
+        for (title, labels) in self.sections() {
+            output.push_str(&format!("<h2>{}</h2>\n<ul>\n", title));
+            for label in labels {
+                output.push_str(&format!("<li>{}</li>\n", escape_html(&label)));
+            }
+            output.push_str("</ul>\n");
+        }
+        output.push_str("</body>\n</html>\n");
+        output
+    }
+}
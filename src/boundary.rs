@@ -0,0 +1,214 @@
+#![allow(dead_code)]
+//! Checks for input arriving from outside the process.
+//!
+//! Kiosk requests, offline bookings pushed by remote clients and imported
+//! files are read with size limits, and the names, reasons and times they
+//! carry are normalized and sanity-checked before they reach the calendar.
+//! Anything turned away comes back as an InputError carrying the HTTP-style
+//! status a server front end would answer with.
+
+use crate::models::Patient;
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Duration, Local};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Largest request body accepted, in bytes.
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+/// Largest imported file accepted, in bytes.
+pub const MAX_FILE_BYTES: usize = 16 * 1024 * 1024;
+/// Most records accepted in one request body.
+pub const MAX_ITEMS: usize = 500;
+/// Longest name, reason or other free text accepted, in characters.
+pub const MAX_TEXT_CHARS: usize = 200;
+/// How far in the past a time may lie.
+pub const MAX_DAYS_PAST: i64 = 366;
+/// How far in the future a time may lie.
+pub const MAX_DAYS_AHEAD: i64 = 2 * 366;
+
+/// Why input was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputErrorKind {
+    TooLarge,
+    Malformed,
+    Invalid,
+}
+
+impl InputErrorKind {
+    /// Get the HTTP status for this kind of error.
+    pub fn status(self) -> u16 {
+        match self {
+            InputErrorKind::TooLarge => 413,
+            InputErrorKind::Malformed => 400,
+            InputErrorKind::Invalid => 422,
+        }
+    }
+}
+
+/// Input turned away at the boundary.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputError {
+    pub kind: InputErrorKind,
+    pub status: u16,
+    pub message: String,
+    /// Problems by field, for invalid values.
+    pub fields: BTreeMap<String, Vec<String>>,
+}
+
+impl InputError {
+    fn new(kind: InputErrorKind, message: String) -> Self {
+        InputError {
+            kind,
+            status: kind.status(),
+            message,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    /// Input over a size or count limit.
+    pub fn too_large(message: String) -> Self {
+        InputError::new(InputErrorKind::TooLarge, message)
+    }
+
+    /// Input that could not be read at all.
+    pub fn malformed(message: String) -> Self {
+        InputError::new(InputErrorKind::Malformed, message)
+    }
+
+    /// Input that was read but holds invalid values.
+    pub fn invalid(errors: ValidationErrors) -> Self {
+        let mut error = InputError::new(InputErrorKind::Invalid, errors.to_string());
+        for (field, message) in errors.iter() {
+            error
+                .fields
+                .entry(field.to_string())
+                .or_default()
+                .push(message.to_string());
+        }
+        error
+    }
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<InputError> for String {
+    fn from(error: InputError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Refuse text longer than `limit` bytes.
+pub fn check_size(text: &str, limit: usize) -> Result<(), InputError> {
+    if text.len() > limit {
+        return Err(InputError::too_large(format!(
+            "Input is {} bytes, more than the limit of {}",
+            text.len(),
+            limit
+        )));
+    }
+    Ok(())
+}
+
+/// Refuse more than `MAX_ITEMS` records.
+pub fn check_count(count: usize) -> Result<(), InputError> {
+    if count > MAX_ITEMS {
+        return Err(InputError::too_large(format!(
+            "{} records is more than the limit of {}",
+            count, MAX_ITEMS
+        )));
+    }
+    Ok(())
+}
+
+/// Read a JSON request body no larger than `MAX_BODY_BYTES`.
+pub fn parse_json<T: DeserializeOwned>(body: &str) -> Result<T, InputError> {
+    check_size(body, MAX_BODY_BYTES)?;
+    serde_json::from_str(body).map_err(|e| InputError::malformed(format!("Invalid JSON: {}", e)))
+}
+
+/// Normalize free text to a single trimmed line.
+///
+/// Control characters and invisible formatting characters such as
+/// zero-width spaces and direction overrides are dropped, and runs of
+/// whitespace become one space. Text is not recomposed to NFC, which
+/// would need Unicode tables the crate doesn't carry.
+pub fn normalize_text(value: &str) -> String {
+    let mut normalized = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_whitespace() {
+            if !normalized.is_empty() && !normalized.ends_with(' ') {
+                normalized.push(' ');
+            }
+        } else if !c.is_control() && !is_invisible(c) {
+            normalized.push(c);
+        }
+    }
+    normalized.truncate(normalized.trim_end().len());
+    normalized
+}
+
+/// Normalize a text field, recording a problem if it is too long.
+pub fn check_text(field: &str, value: &str, errors: &mut ValidationErrors) -> String {
+    let normalized = normalize_text(value);
+    if normalized.chars().count() > MAX_TEXT_CHARS {
+        errors.add(
+            field,
+            format!("Must be at most {} characters", MAX_TEXT_CHARS),
+        );
+    }
+    normalized
+}
+
+/// Record a problem if `value` is implausibly far from `now`.
+pub fn check_time(
+    field: &str,
+    value: DateTime<Local>,
+    now: DateTime<Local>,
+    errors: &mut ValidationErrors,
+) {
+    if value < now - Duration::days(MAX_DAYS_PAST) || value > now + Duration::days(MAX_DAYS_AHEAD) {
+        errors.add(
+            field,
+            format!(
+                "{} is not within {} days before or {} days after today",
+                value.format("%Y-%m-%d %H:%M"),
+                MAX_DAYS_PAST,
+                MAX_DAYS_AHEAD
+            ),
+        );
+    }
+}
+
+/// Normalize a patient's details and validate them as `Patient::new` does.
+pub fn check_patient(
+    patient_id: &str,
+    name: &str,
+    contact: &str,
+    errors: &mut ValidationErrors,
+) -> Option<Patient> {
+    let patient_id = check_text("patient_id", patient_id, errors);
+    let name = check_text("name", name, errors);
+    let contact = check_text("contact", contact, errors);
+    Patient::new(patient_id, name, contact)
+        .map_err(|e| errors.merge(e))
+        .ok()
+}
+
+/// Check for format characters that render as nothing.
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{FEFF}'
+    )
+}
@@ -0,0 +1,74 @@
+#![allow(dead_code)]
+//! Bringing patients forward into freed slots.
+//!
+//! A cancellation often frees a slot earlier than other patients' own
+//! bookings, and some of them would rather come sooner. Before the slot
+//! goes to the waitlist it can be offered to one of them: they are sent a
+//! token, and their appointment only moves once they confirm with it. The
+//! preferences stored with their booking (times they can't make, slot tier,
+//! visit length, date window) still apply to the earlier slot.
+
+use crate::ids::IdGenerator;
+use crate::models::{Patient, TimeSlot};
+use crate::notifications::Notification;
+use chrono::{DateTime, Local};
+
+/// An earlier slot offered to a patient who is already booked.
+#[derive(Debug, Clone)]
+pub struct BringForwardOffer {
+    /// Code the patient confirms the move with.
+    pub token: String,
+    pub appointment_id: String,
+    pub patient: Patient,
+    /// Slot the patient is booked into now.
+    pub current_slot: TimeSlot,
+    /// Earlier slot they are offered.
+    pub slot: TimeSlot,
+    pub offered_at: DateTime<Local>,
+}
+
+impl BringForwardOffer {
+    /// Create an offer with a fresh token from `ids`.
+    pub fn new(
+        ids: &dyn IdGenerator,
+        appointment_id: String,
+        patient: Patient,
+        current_slot: TimeSlot,
+        slot: TimeSlot,
+        offered_at: DateTime<Local>,
+    ) -> Self {
+        BringForwardOffer {
+            token: ids.confirmation_code(offered_at),
+            appointment_id,
+            patient,
+            current_slot,
+            slot,
+            offered_at,
+        }
+    }
+
+    /// Build the message asking the patient to confirm the move.
+    pub fn to_notification(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+    ) -> Result<Notification, String> {
+        Notification::new(
+            ids,
+            now,
+            self.patient.contact.clone(),
+            "An earlier appointment is available".to_string(),
+            format!(
+                "Dear {}, a slot has opened on {} at {}, earlier than your appointment \
+                 on {} at {}. To move to it, reply with code {}. Otherwise your \
+                 appointment stays as it is.",
+                self.patient.name,
+                self.slot.start_time.format("%A, %Y-%m-%d"),
+                self.slot.start_time.format("%H:%M"),
+                self.current_slot.start_time.format("%A, %Y-%m-%d"),
+                self.current_slot.start_time.format("%H:%M"),
+                self.token
+            ),
+        )
+    }
+}
@@ -0,0 +1,125 @@
+#![allow(dead_code)]
+//! Composite bookings made of several linked appointments.
+//!
+//! A bundle is an ordered list of steps (e.g. prep, procedure, review).
+//! Every step after the first must start within a gap window measured from
+//! the end of the previous step. Bundles are booked as a unit; cancelling
+//! one step cancels the others according to the calendar's
+//! BundleCancellation policy.
+
+use crate::models::{Appointment, Patient, Priority};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// A single step in a bundle.
+#[derive(Debug, Clone)]
+pub struct BundleStep {
+    pub reason: String,
+    pub min_gap_minutes: i64,
+    pub max_gap_minutes: i64,
+}
+
+impl BundleStep {
+    /// Create a step that must start between `min_gap_minutes` and
+    /// `max_gap_minutes` after the previous step ends.
+    ///
+    /// The gap is ignored for the first step of a bundle.
+    pub fn new(reason: String, min_gap_minutes: i64, max_gap_minutes: i64) -> Result<Self, String> {
+        if reason.is_empty() {
+            return Err("Bundle step reason cannot be empty".to_string());
+        }
+        if min_gap_minutes < 0 {
+            return Err("Minimum gap cannot be negative".to_string());
+        }
+        if max_gap_minutes < min_gap_minutes {
+            return Err("Maximum gap must not be less than minimum gap".to_string());
+        }
+
+        Ok(BundleStep {
+            reason,
+            min_gap_minutes,
+            max_gap_minutes,
+        })
+    }
+}
+
+/// A request to book a whole bundle for one patient.
+#[derive(Debug, Clone)]
+pub struct BundleRequest {
+    pub patient: Patient,
+    pub priority: Priority,
+    pub preferred_time: DateTime<Local>,
+    pub flexibility_minutes: i64,
+    pub steps: Vec<BundleStep>,
+}
+
+impl BundleRequest {
+    /// Create a new bundle request with validation.
+    ///
+    /// `preferred_time` and `flexibility_minutes` apply to the first step.
+    pub fn new(
+        patient: Patient,
+        priority: Priority,
+        preferred_time: DateTime<Local>,
+        flexibility_minutes: i64,
+        steps: Vec<BundleStep>,
+    ) -> Result<Self, String> {
+        if steps.is_empty() {
+            return Err("Bundle must contain at least one step".to_string());
+        }
+        if flexibility_minutes < 0 {
+            return Err("Flexibility minutes cannot be negative".to_string());
+        }
+
+        Ok(BundleRequest {
+            patient,
+            priority,
+            preferred_time,
+            flexibility_minutes,
+            steps,
+        })
+    }
+}
+
+/// What happens to the rest of a bundle when one of its appointments is
+/// cancelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BundleCancellation {
+    /// Cancel every other step that hasn't started yet.
+    CancelAll,
+    /// Cancel the steps after the cancelled one.
+    CancelRemaining,
+    /// Leave the other steps booked.
+    #[default]
+    KeepOthers,
+}
+
+impl BundleCancellation {
+    /// Convert a string to a BundleCancellation value.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "all" | "cancel-all" => Ok(BundleCancellation::CancelAll),
+            "remaining" | "cancel-remaining" => Ok(BundleCancellation::CancelRemaining),
+            "keep" | "keep-others" => Ok(BundleCancellation::KeepOthers),
+            _ => Err(format!(
+                "Invalid bundle cancellation: '{}'. Must be one of: all, remaining, keep",
+                value
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            BundleCancellation::CancelAll => "all",
+            BundleCancellation::CancelRemaining => "remaining",
+            BundleCancellation::KeepOthers => "keep",
+        }
+    }
+}
+
+/// A successfully booked bundle.
+#[derive(Debug, Clone)]
+pub struct BundleBooking {
+    pub bundle_id: String,
+    pub appointments: Vec<Appointment>,
+}
@@ -1,26 +1,288 @@
 #![allow(dead_code)]
-/// Calendar management for the appointment scheduling system.
+//! Calendar management for the appointment scheduling system.
+//!
+//! This module provides the DoctorCalendar class which manages available
+//! time slots and booked appointments for a doctor's schedule.
+
+use crate::availability::BlockReport;
+use crate::bundle::{BundleBooking, BundleCancellation, BundleRequest};
+use crate::card::CardFormat;
+use crate::caselist::{Case, CaseList};
+use crate::clock::{Clock, SystemClock};
+use crate::events::{CalendarEvent, EventPublisher, OutboxEntry};
+use crate::ics::{parse_busy_times, BusyTime};
+use crate::ids::{IdGenerator, IdKind, UuidIds};
+use crate::models::{
+    Appointment, AppointmentChange, AppointmentChangeKind, AppointmentStatus, AttendanceStatus,
+    CancellationActor, CancellationPolicy, CancellationRecord, FeeSchedule, Patient, Priority,
+    SlotTier, TimeSlot,
+};
+use crate::compact::CompactSlots;
+use crate::labels::LabelScheme;
+use crate::overlay::CalendarOverlay;
+use crate::preferences::DoctorPreferences;
+use crate::snapshot::CalendarSnapshot;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime};  // REMOVED Timelike (unused), ADDED Datelike
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+/// An appointment moved by a day shift.
+#[derive(Debug, Clone)]
+pub struct ShiftedAppointment {
+    pub appointment: Appointment,
+    pub previous_start: DateTime<Local>,
+}
+
+/// Outcome of shifting a day's appointments.
+#[derive(Debug, Clone, Default)]
+pub struct DayShiftReport {
+    pub moved: Vec<ShiftedAppointment>,
+    /// Appointments with no slot at the shifted time; left unchanged.
+    pub unfit: Vec<Appointment>,
+}
+
+impl DayShiftReport {
+    /// Every patient affected by the shift, moved or not.
+    pub fn patients_to_notify(&self) -> Vec<&Patient> {
+        self.moved
+            .iter()
+            .map(|shifted| &shifted.appointment.patient)
+            .chain(self.unfit.iter().map(|apt| &apt.patient))
+            .collect()
+    }
+}
+
+/// One booking in a bulk import.
+#[derive(Debug, Clone)]
+pub struct BookingSpec {
+    pub slot_id: String,
+    pub patient: Patient,
+    pub priority: Priority,
+    pub reason: String,
+}
+
+/// A candidate slot that could not be added during generation.
+#[derive(Debug, Clone)]
+pub struct SkippedSlot {
+    pub slot: TimeSlot,
+    pub reason: String,
+}
+
+/// What slot generation created and what it skipped.
+#[derive(Debug, Clone, Default)]
+pub struct GenerationReport {
+    pub created: Vec<TimeSlot>,
+    /// Candidates that fell inside the break.
+    pub skipped_break: Vec<TimeSlot>,
+    /// Candidates that overlapped an existing slot.
+    pub skipped_overlap: Vec<SkippedSlot>,
+    /// Candidates that overlapped a busy time from an external calendar or a
+    /// blocked period.
+    pub skipped_busy: Vec<SkippedSlot>,
+    /// Candidates matching an existing slot exactly (idempotent generation only).
+    pub already_present: usize,
+}
+
+impl GenerationReport {
+    /// True when every candidate already existed and nothing new was created.
+    pub fn is_already_generated(&self) -> bool {
+        self.already_present > 0 && self.created.is_empty() && self.skipped_overlap.is_empty()
+    }
+
+    /// Fold another report into this one.
+    pub fn merge(&mut self, other: GenerationReport) {
+        self.created.extend(other.created);
+        self.skipped_break.extend(other.skipped_break);
+        self.skipped_overlap.extend(other.skipped_overlap);
+        self.skipped_busy.extend(other.skipped_busy);
+        self.already_present += other.already_present;
+    }
+}
+
+/// Outcome of importing busy times from an external calendar.
+#[derive(Debug, Clone, Default)]
+pub struct BusyImportReport {
+    pub busy_times: usize,
+    /// Free slots removed because they overlap a busy time.
+    pub removed: Vec<TimeSlot>,
+    /// Appointments overlapping a busy time, left for staff to move or cancel.
+    pub conflicts: Vec<Appointment>,
+}
+
+/// A period the doctor is away, e.g. on vacation, in which nothing is booked.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockedPeriod {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub reason: String,
+}
+
+impl BlockedPeriod {
+    /// Check whether the period overlaps `[start, end)`.
+    pub fn overlaps(&self, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// Working-hours template describing a recurring block of slots.
 ///
-/// This module provides the DoctorCalendar class which manages available
-/// time slots and booked appointments for a doctor's schedule.
+/// Used to generate slots over an arbitrary date range, e.g. to open
+/// Saturday morning hours (`working_days: vec![5], start_hour: 9, end_hour: 12`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotTemplate {
+    pub working_days: Vec<u32>,
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub slot_duration_minutes: Option<i64>,
+    pub break_start: Option<NaiveTime>,
+    pub break_end: Option<NaiveTime>,
+}
+
+impl SlotTemplate {
+    /// Create a template without a break, using the calendar's default slot duration.
+    pub fn new(working_days: Vec<u32>, start_hour: u32, end_hour: u32) -> Result<Self, String> {
+        if working_days.iter().any(|d| *d > 6) {
+            return Err("Working days must be between 0 (Monday) and 6 (Sunday)".to_string());
+        }
+        if end_hour > 24 || start_hour >= end_hour {
+            return Err("Working hours end must be after start".to_string());
+        }
 
-use crate::models::{Appointment, Patient, Priority, TimeSlot};
-use chrono::{DateTime, Datelike, Duration, Local, NaiveTime};  // REMOVED Timelike (unused), ADDED Datelike
-use std::collections::HashMap;
-use uuid::Uuid;
+        Ok(SlotTemplate {
+            working_days,
+            start_hour,
+            end_hour,
+            slot_duration_minutes: None,
+            break_start: None,
+            break_end: None,
+        })
+    }
+}
 
-#[derive(Clone)]  // ADDED: Derive Clone instead of manual impl in main.rs
 pub struct DoctorCalendar {
     pub doctor_name: String,
     pub doctor_id: String,
     pub default_slot_duration: i64,
+    pub cancellation_policy: Option<CancellationPolicy>,
+    pub fee_schedule: FeeSchedule,
+    pub location: Option<String>,
+    /// Specialty used to find availability across a clinic, e.g. `GP`.
+    pub specialty: Option<String>,
+    /// Working-hours templates the clinic uses to open this doctor's slots.
+    pub templates: Vec<SlotTemplate>,
+    /// Color and emoji labels used when listing this doctor's appointments.
+    pub labels: LabelScheme,
+    /// Treat every day before today as locked for booking, cancellation and
+    /// slot changes. Attendance can still be recorded so it can be closed out.
+    pub lock_past_days: bool,
+    /// Book new appointments as Requested, for staff to `confirm` later.
+    pub require_confirmation: bool,
+    /// What cancelling one appointment of a bundle does to the others.
+    pub bundle_cancellation: BundleCancellation,
+    /// The doctor's own scheduling preferences, honoured by the scheduler.
+    pub preferences: DoctorPreferences,
+    /// Weeks of slots to keep generated from the templates ahead of today,
+    /// topped up by `extend_horizon`. `None` leaves generation to the clinic.
+    pub horizon_weeks: Option<u32>,
+    /// Last day the templates have been applied to by `extend_horizon`.
+    generated_until: Option<NaiveDate>,
+    feed_token: String,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
     time_slots: HashMap<String, TimeSlot>,
     appointments: HashMap<String, Appointment>,
+    history: HashMap<String, Vec<AppointmentChange>>,
+    cancellations: Vec<CancellationRecord>,
+    locked_days: BTreeSet<NaiveDate>,
+    day_notes: BTreeMap<NaiveDate, Vec<String>>,
+    case_lists: BTreeMap<NaiveDate, CaseList>,
+    busy_times: Vec<BusyTime>,
+    blocked_periods: Vec<BlockedPeriod>,
+    subscribers: Vec<Sender<CalendarEvent>>,
+    outbox: Vec<OutboxEntry>,
+    next_event_sequence: u64,
+    /// Sequence of the last event delivered by `dispatch_outbox`.
+    dispatched_through: u64,
+    /// Sequence of the last event written to an event log.
+    logged_through: u64,
+    /// When the events were last written to an event log.
+    last_saved_at: Option<DateTime<Local>>,
+    in_transaction: bool,
+    deferred_broadcasts: Vec<CalendarEvent>,
+}
+
+/// A clone is a detached copy for simulations such as what-if runs.
+///
+/// It starts with no subscribers and an empty outbox, so nothing done to the
+/// copy is broadcast or published as if it had happened to the original,
+/// and draws IDs from a fork of the original's generator, so it never uses
+/// up the original's IDs.
+impl Clone for DoctorCalendar {
+    fn clone(&self) -> Self {
+        DoctorCalendar {
+            doctor_name: self.doctor_name.clone(),
+            doctor_id: self.doctor_id.clone(),
+            default_slot_duration: self.default_slot_duration,
+            cancellation_policy: self.cancellation_policy,
+            fee_schedule: self.fee_schedule,
+            location: self.location.clone(),
+            specialty: self.specialty.clone(),
+            templates: self.templates.clone(),
+            labels: self.labels.clone(),
+            lock_past_days: self.lock_past_days,
+            require_confirmation: self.require_confirmation,
+            bundle_cancellation: self.bundle_cancellation,
+            preferences: self.preferences.clone(),
+            horizon_weeks: self.horizon_weeks,
+            generated_until: self.generated_until,
+            feed_token: self.feed_token.clone(),
+            clock: self.clock.clone(),
+            ids: self.ids.fork(),
+            time_slots: self.time_slots.clone(),
+            appointments: self.appointments.clone(),
+            history: self.history.clone(),
+            cancellations: self.cancellations.clone(),
+            locked_days: self.locked_days.clone(),
+            day_notes: self.day_notes.clone(),
+            case_lists: self.case_lists.clone(),
+            busy_times: self.busy_times.clone(),
+            blocked_periods: self.blocked_periods.clone(),
+            subscribers: Vec::new(),
+            outbox: Vec::new(),
+            next_event_sequence: self.next_event_sequence,
+            dispatched_through: self.dispatched_through,
+            logged_through: self.logged_through,
+            last_saved_at: self.last_saved_at,
+            in_transaction: false,
+            deferred_broadcasts: Vec::new(),
+        }
+    }
 }
 
 impl DoctorCalendar {
     /// Initialize a new doctor calendar.
     pub fn new(doctor_name: String, default_slot_duration: i64) -> Result<Self, String> {
+        DoctorCalendar::with_generators(
+            doctor_name,
+            default_slot_duration,
+            Arc::new(SystemClock),
+            Arc::new(UuidIds),
+        )
+    }
+
+    /// Initialize a new doctor calendar on a given clock and ID generator.
+    ///
+    /// Unlike calling `set_clock` and `set_id_generator` afterwards, the
+    /// doctor ID and feed token come from the generator too, so a calendar
+    /// on a fixed clock with seeded IDs is the same on every run.
+    pub fn with_generators(
+        doctor_name: String,
+        default_slot_duration: i64,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) -> Result<Self, String> {
         if doctor_name.is_empty() {
             return Err("Doctor name cannot be empty".to_string());
         }
@@ -28,15 +290,323 @@ impl DoctorCalendar {
             return Err("Slot duration must be positive".to_string());
         }
 
+        let now = clock.now();
         Ok(DoctorCalendar {
             doctor_name,
-            doctor_id: Uuid::new_v4().to_string(),
+            doctor_id: ids.next_id(IdKind::Doctor, now),
             default_slot_duration,
+            cancellation_policy: None,
+            fee_schedule: FeeSchedule::default(),
+            location: None,
+            specialty: None,
+            templates: Vec::new(),
+            labels: LabelScheme::default(),
+            lock_past_days: false,
+            require_confirmation: false,
+            bundle_cancellation: BundleCancellation::default(),
+            preferences: DoctorPreferences::new(),
+            horizon_weeks: None,
+            generated_until: None,
+            feed_token: ids.secret_token(now),
+            clock,
+            ids,
             time_slots: HashMap::new(),
             appointments: HashMap::new(),
+            history: HashMap::new(),
+            cancellations: Vec::new(),
+            locked_days: BTreeSet::new(),
+            day_notes: BTreeMap::new(),
+            case_lists: BTreeMap::new(),
+            busy_times: Vec::new(),
+            blocked_periods: Vec::new(),
+            subscribers: Vec::new(),
+            outbox: Vec::new(),
+            next_event_sequence: 1,
+            dispatched_through: 0,
+            logged_through: 0,
+            last_saved_at: None,
+            in_transaction: false,
+            deferred_broadcasts: Vec::new(),
         })
     }
 
+    /// Capture the calendar's full state.
+    pub fn snapshot(&self) -> CalendarSnapshot {
+        CalendarSnapshot {
+            doctor_name: self.doctor_name.clone(),
+            doctor_id: self.doctor_id.clone(),
+            default_slot_duration: self.default_slot_duration,
+            feed_token: self.feed_token.clone(),
+            time_slots: self.time_slots(),
+            appointments: self
+                .appointments()
+                .into_iter()
+                .chain(self.cancelled_appointments())
+                .collect(),
+            outbox: self.outbox.clone(),
+            next_event_sequence: self.next_event_sequence,
+            dispatched_through: self.dispatched_through,
+            logged_through: self.logged_through,
+            history: self.history.clone().into_iter().collect(),
+            cancellation_policy: self.cancellation_policy,
+            fee_schedule: self.fee_schedule,
+            cancellations: self.cancellations.clone(),
+            location: self.location.clone(),
+            specialty: self.specialty.clone(),
+            templates: self.templates.clone(),
+            labels: self.labels.clone(),
+            locked_days: self.locked_days.clone(),
+            lock_past_days: self.lock_past_days,
+            require_confirmation: self.require_confirmation,
+            bundle_cancellation: self.bundle_cancellation,
+            day_notes: self.day_notes.clone(),
+            case_lists: self.case_lists.clone(),
+            busy_times: self.busy_times.clone(),
+            blocked_periods: self.blocked_periods.clone(),
+            preferences: self.preferences.to_lines(),
+            horizon_weeks: self.horizon_weeks,
+            generated_until: self.generated_until,
+        }
+    }
+
+    /// Rebuild a calendar from a snapshot.
+    pub fn from_snapshot(snapshot: CalendarSnapshot) -> Result<Self, String> {
+        let mut calendar = DoctorCalendar::new(snapshot.doctor_name, snapshot.default_slot_duration)?;
+        calendar.doctor_id = snapshot.doctor_id;
+        calendar.feed_token = snapshot.feed_token;
+
+        for slot in snapshot.time_slots {
+            calendar.insert_time_slot(slot)?;
+        }
+        for mut appointment in snapshot.appointments {
+            appointment.upgrade_status();
+            if appointment.status != AppointmentStatus::Cancelled
+                && !calendar
+                    .time_slots
+                    .contains_key(&appointment.time_slot.slot_id)
+            {
+                return Err(format!(
+                    "Snapshot appointment {} references unknown slot {}",
+                    appointment.appointment_id, appointment.time_slot.slot_id
+                ));
+            }
+            calendar
+                .appointments
+                .insert(appointment.appointment_id.clone(), appointment);
+        }
+        calendar.outbox = snapshot.outbox;
+        calendar.next_event_sequence = snapshot.next_event_sequence.max(1);
+        calendar.dispatched_through = snapshot.dispatched_through;
+        calendar.logged_through = snapshot.logged_through;
+        calendar.history = snapshot.history.into_iter().collect();
+        calendar.cancellation_policy = snapshot.cancellation_policy;
+        calendar.fee_schedule = snapshot.fee_schedule;
+        calendar.cancellations = snapshot.cancellations;
+        calendar.location = snapshot.location;
+        calendar.specialty = snapshot.specialty;
+        calendar.templates = snapshot.templates;
+        calendar.labels = snapshot.labels;
+        calendar.locked_days = snapshot.locked_days;
+        calendar.lock_past_days = snapshot.lock_past_days;
+        calendar.require_confirmation = snapshot.require_confirmation;
+        calendar.bundle_cancellation = snapshot.bundle_cancellation;
+        calendar.day_notes = snapshot.day_notes;
+        calendar.case_lists = snapshot.case_lists;
+        calendar.busy_times = snapshot.busy_times;
+        calendar.blocked_periods = snapshot.blocked_periods;
+        calendar.preferences = DoctorPreferences::from_lines(&snapshot.preferences)?;
+        calendar.horizon_weeks = snapshot.horizon_weeks;
+        calendar.generated_until = snapshot.generated_until;
+
+        calendar
+            .verify_invariants()
+            .map_err(|violations| format!("Inconsistent snapshot: {}", violations.join("; ")))?;
+        Ok(calendar)
+    }
+
+    /// Replace the calendar's time source.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// The current time according to the calendar's clock.
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.now()
+    }
+
+    /// Replace the generator used for new slot, appointment and request IDs.
+    ///
+    /// The generator is told about the calendar's existing IDs first, so a
+    /// sequential generator carries on after the highest one.
+    pub fn set_id_generator(&mut self, ids: Arc<dyn IdGenerator>) {
+        for slot_id in self.time_slots.keys() {
+            ids.observe(IdKind::Slot, slot_id);
+        }
+        for appointment_id in self.appointments.keys() {
+            ids.observe(IdKind::Appointment, appointment_id);
+        }
+        for case in self.case_lists.values().flat_map(|list| list.cases()) {
+            ids.observe(IdKind::Case, &case.case_id);
+        }
+        self.ids = ids;
+    }
+
+    /// Get the generator used for new IDs.
+    pub fn id_generator(&self) -> &dyn IdGenerator {
+        self.ids.as_ref()
+    }
+
+    /// Generate a new ID of the given kind.
+    pub fn next_id(&self, kind: IdKind) -> String {
+        self.ids.next_id(kind, self.clock.now())
+    }
+
+    /// Subscribe to slot and booking changes made after this call.
+    pub fn subscribe(&mut self) -> Receiver<CalendarEvent> {
+        let (sender, receiver) = mpsc::channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Record an event in the outbox and broadcast it to subscribers.
+    ///
+    /// Inside a transaction the broadcast is deferred until commit.
+    fn emit(&mut self, event: CalendarEvent) {
+        if self.in_transaction {
+            self.deferred_broadcasts.push(event.clone());
+        } else {
+            self.broadcast(&event);
+        }
+
+        self.outbox.push(OutboxEntry {
+            sequence: self.next_event_sequence,
+            occurred_at: self.clock.now(),
+            event,
+        });
+        self.next_event_sequence += 1;
+    }
+
+    fn broadcast(&mut self, event: &CalendarEvent) {
+        self.subscribers
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+
+    /// Run several mutations atomically.
+    ///
+    /// If the closure returns an error every change it made to the calendar
+    /// is rolled back, and subscribers never see its events.
+    /// Transactions may be nested; an inner failure only rolls back the
+    /// inner closure's changes.
+    pub fn transaction<T, F>(&mut self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&mut DoctorCalendar) -> Result<T, String>,
+    {
+        let saved = self.clone();
+        let deferred_len = self.deferred_broadcasts.len();
+        let outermost = !self.in_transaction;
+
+        self.in_transaction = true;
+        let result = f(self);
+        if outermost {
+            self.in_transaction = false;
+        }
+
+        match result {
+            Ok(value) => {
+                if outermost {
+                    for event in std::mem::take(&mut self.deferred_broadcasts) {
+                        self.broadcast(&event);
+                    }
+                }
+                Ok(value)
+            }
+            Err(e) => {
+                // The clone has no subscribers or outbox and a forked ID
+                // generator, so those are carried over from the live calendar.
+                let ids = Arc::clone(&self.ids);
+                let subscribers = std::mem::take(&mut self.subscribers);
+                let mut outbox = std::mem::take(&mut self.outbox);
+                let mut deferred_broadcasts = std::mem::take(&mut self.deferred_broadcasts);
+                let in_transaction = self.in_transaction;
+                outbox.retain(|entry| entry.sequence < saved.next_event_sequence);
+                deferred_broadcasts.truncate(deferred_len);
+                *self = saved;
+                self.ids = ids;
+                self.subscribers = subscribers;
+                self.outbox = outbox;
+                self.deferred_broadcasts = deferred_broadcasts;
+                self.in_transaction = in_transaction;
+                self.debug_verify();
+                Err(e)
+            }
+        }
+    }
+
+    /// Get events not yet both published and logged, oldest first.
+    ///
+    /// An entry stays in the outbox until `dispatch_outbox` has delivered it
+    /// and `store::save_calendar` has written it to the event log, so
+    /// publishing and saving can happen in either order.
+    pub fn outbox(&self) -> &[OutboxEntry] {
+        &self.outbox
+    }
+
+    /// Get events not yet published, oldest first.
+    pub fn undispatched_events(&self) -> Vec<OutboxEntry> {
+        self.outbox
+            .iter()
+            .filter(|entry| entry.sequence > self.dispatched_through)
+            .cloned()
+            .collect()
+    }
+
+    /// Publish pending outbox entries in order, returning how many were delivered.
+    ///
+    /// Delivery stops at the first failure so events are never published out
+    /// of order; the failed entry and everything after it are tried again
+    /// next time.
+    pub fn dispatch_outbox(&mut self, publisher: &mut dyn EventPublisher) -> usize {
+        let pending = self.undispatched_events();
+        let delivered = pending
+            .iter()
+            .take_while(|entry| publisher.publish(entry).is_ok())
+            .count();
+        if let Some(last) = pending[..delivered].last() {
+            self.dispatched_through = last.sequence;
+        }
+        self.trim_outbox();
+        delivered
+    }
+
+    /// Record that every event emitted so far has been written to an event
+    /// log. Calendars saved without a log can call this to stop keeping
+    /// published events.
+    pub fn mark_events_logged(&mut self) {
+        self.logged_through = self.next_event_sequence - 1;
+        self.last_saved_at = Some(self.clock.now());
+        self.trim_outbox();
+    }
+
+    /// When the calendar was last saved with its events, or `None` if it
+    /// hasn't been since it was created or loaded.
+    pub fn last_saved_at(&self) -> Option<DateTime<Local>> {
+        self.last_saved_at
+    }
+
+    /// Count the events not yet written to an event log.
+    pub fn unlogged_event_count(&self) -> usize {
+        self.outbox
+            .iter()
+            .filter(|entry| entry.sequence > self.logged_through)
+            .count()
+    }
+
+    /// Drop outbox entries that are both published and logged.
+    fn trim_outbox(&mut self) {
+        let done_through = self.dispatched_through.min(self.logged_through);
+        self.outbox.retain(|entry| entry.sequence > done_through);
+    }
+
     /// Get all time slots sorted by start time.
     pub fn time_slots(&self) -> Vec<TimeSlot> {
         let mut slots: Vec<TimeSlot> = self.time_slots.values().cloned().collect();
@@ -44,27 +614,225 @@ impl DoctorCalendar {
         slots
     }
 
-    /// Get all available (unbooked) time slots.
+    /// Get all available (unbooked) time slots that are open for booking.
+    ///
+    /// Slots whose `bookable_from` time has not yet been reached are hidden.
     pub fn available_slots(&self) -> Vec<TimeSlot> {
+        let now = self.clock.now();
+        let mut slots: Vec<TimeSlot> = self
+            .time_slots
+            .values()
+            .filter(|s| s.is_available && s.is_released(now))
+            .cloned()
+            .collect();
+        slots.sort_by_key(|s| s.start_time);
+        slots
+    }
+
+    /// Get all booked or otherwise unavailable time slots sorted by start time.
+    pub fn unavailable_slots(&self) -> Vec<TimeSlot> {
         let mut slots: Vec<TimeSlot> = self
             .time_slots
             .values()
-            .filter(|s| s.is_available)
+            .filter(|s| !s.is_available)
             .cloned()
             .collect();
         slots.sort_by_key(|s| s.start_time);
         slots
     }
 
-    /// Get all confirmed appointments sorted by time.
+    /// Start a copy-on-write overlay for trying out changes without cloning.
+    pub fn overlay(&self) -> CalendarOverlay<'_> {
+        CalendarOverlay::new(self)
+    }
+
+    /// Pack every slot into compact form, e.g. for long-term storage.
+    pub fn compact_slots(&self) -> Result<CompactSlots, String> {
+        CompactSlots::from_slots(&self.time_slots())
+    }
+
+    /// Look up a time slot by ID.
+    pub fn slot_by_id(&self, slot_id: &str) -> Option<&TimeSlot> {
+        self.time_slots.get(slot_id)
+    }
+
+    /// Check whether a slot exists, is unbooked and is open for booking.
+    pub fn is_slot_free(&self, slot_id: &str) -> bool {
+        let now = self.clock.now();
+        self.time_slots
+            .get(slot_id)
+            .is_some_and(|slot| slot.is_available && slot.is_released(now))
+    }
+
+    /// Set when a slot opens for booking, returning false if the slot is unknown.
+    pub fn set_bookable_from(
+        &mut self,
+        slot_id: &str,
+        bookable_from: Option<DateTime<Local>>,
+    ) -> bool {
+        match self.time_slots.get_mut(slot_id) {
+            Some(slot) => {
+                slot.bookable_from = bookable_from;
+                for apt in self.appointments.values_mut() {
+                    if apt.time_slot.slot_id == slot_id {
+                        apt.time_slot.bookable_from = bookable_from;
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Hold back every slot starting in `[start, end)` until `bookable_from`.
+    ///
+    /// For example, publish next month's schedule on the 15th at 08:00.
+    /// Returns how many slots were updated.
+    pub fn schedule_release(
+        &mut self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        bookable_from: DateTime<Local>,
+    ) -> usize {
+        let slot_ids: Vec<String> = self
+            .time_slots
+            .values()
+            .filter(|slot| slot.start_time >= start && slot.start_time < end)
+            .map(|slot| slot.slot_id.clone())
+            .collect();
+
+        for slot_id in &slot_ids {
+            self.set_bookable_from(slot_id, Some(bookable_from));
+        }
+        slot_ids.len()
+    }
+
+    /// Get all booked appointments sorted by time, leaving out cancelled ones.
     pub fn appointments(&self) -> Vec<Appointment> {
-        let mut appointments: Vec<Appointment> = self.appointments.values().cloned().collect();
+        let mut appointments: Vec<Appointment> = self.active_appointments().cloned().collect();
         appointments.sort_by_key(|a| a.time_slot.start_time);
         appointments
     }
 
+    /// Get all cancelled appointments sorted by time.
+    pub fn cancelled_appointments(&self) -> Vec<Appointment> {
+        let mut appointments: Vec<Appointment> = self
+            .appointments
+            .values()
+            .filter(|apt| apt.status == AppointmentStatus::Cancelled)
+            .cloned()
+            .collect();
+        appointments.sort_by(|a, b| {
+            (a.time_slot.start_time, &a.appointment_id)
+                .cmp(&(b.time_slot.start_time, &b.appointment_id))
+        });
+        appointments
+    }
+
+    fn active_appointments(&self) -> impl Iterator<Item = &Appointment> {
+        self.appointments
+            .values()
+            .filter(|apt| apt.status != AppointmentStatus::Cancelled)
+    }
+
+    fn active_appointment(&self, appointment_id: &str) -> Option<&Appointment> {
+        self.appointments
+            .get(appointment_id)
+            .filter(|apt| apt.status != AppointmentStatus::Cancelled)
+    }
+
+    /// Check the calendar's internal consistency.
+    ///
+    /// Every appointment must reference an existing slot with the same
+    /// times that is marked unavailable, no two slots may overlap, and every
+    /// unavailable slot must belong to an appointment. Returns all
+    /// violations found.
+    pub fn verify_invariants(&self) -> Result<(), Vec<String>> {
+        let mut violations = Vec::new();
+        let mut booked_slots: HashMap<&str, &str> = HashMap::new();
+
+        for apt in self.active_appointments() {
+            let slot_id = apt.time_slot.slot_id.as_str();
+            match self.time_slots.get(slot_id) {
+                None => violations.push(format!(
+                    "Appointment {} references missing slot {}",
+                    apt.appointment_id, slot_id
+                )),
+                Some(slot) => {
+                    if slot.is_available {
+                        violations.push(format!(
+                            "Slot {} is booked by appointment {} but marked available",
+                            slot_id, apt.appointment_id
+                        ));
+                    }
+                    if slot.start_time != apt.time_slot.start_time
+                        || slot.end_time != apt.time_slot.end_time
+                    {
+                        violations.push(format!(
+                            "Appointment {} has stale times for slot {}",
+                            apt.appointment_id, slot_id
+                        ));
+                    }
+                }
+            }
+            if let Some(other) = booked_slots.insert(slot_id, &apt.appointment_id) {
+                violations.push(format!(
+                    "Slot {} is booked by both {} and {}",
+                    slot_id, other, apt.appointment_id
+                ));
+            }
+        }
+
+        for slot in self.time_slots.values() {
+            if !slot.is_available && !booked_slots.contains_key(slot.slot_id.as_str()) {
+                violations.push(format!(
+                    "Slot {} is marked unavailable but has no appointment",
+                    slot.slot_id
+                ));
+            }
+        }
+
+        let slots = self.time_slots();
+        for pair in slots.windows(2) {
+            if pair[0].overlaps_with(&pair[1]) {
+                violations.push(format!(
+                    "Slots {} and {} overlap",
+                    pair[0].slot_id, pair[1].slot_id
+                ));
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Panic on invariant violations in debug builds.
+    fn debug_verify(&self) {
+        if cfg!(debug_assertions) {
+            if let Err(violations) = self.verify_invariants() {
+                panic!("Calendar invariants violated: {}", violations.join("; "));
+            }
+        }
+    }
+
     /// Add a time slot to the calendar.
     pub fn add_time_slot(&mut self, slot: TimeSlot) -> Result<(), String> {
+        if let Some(reason) = self.blocked_reason(slot.start_time, slot.end_time) {
+            return Err(reason);
+        }
+        self.insert_time_slot(slot)?;
+        self.debug_verify();
+        Ok(())
+    }
+
+    /// Add a time slot without checking invariants, for bulk loading.
+    fn insert_time_slot(&mut self, slot: TimeSlot) -> Result<(), String> {
+        if slot.slot_id.is_empty() {
+            return Err("Time slot has no ID".to_string());
+        }
         for existing in self.time_slots.values() {
             if slot.overlaps_with(existing) {
                 return Err(format!(
@@ -74,16 +842,73 @@ impl DoctorCalendar {
                 ));
             }
         }
-        self.time_slots.insert(slot.slot_id.clone(), slot);
+        self.time_slots.insert(slot.slot_id.clone(), slot.clone());
+        self.emit(CalendarEvent::SlotAdded { slot });
         Ok(())
     }
 
-    /// Remove a time slot from the calendar.
+    /// Remove a free time slot from the calendar.
+    ///
+    /// Booked slots are kept; cancel or move the appointment first.
     pub fn remove_time_slot(&mut self, slot_id: &str) -> bool {
-        self.time_slots.remove(slot_id).is_some()
+        if !self.time_slots.get(slot_id).is_some_and(|slot| {
+            slot.is_available && self.ensure_unlocked(slot.start_time).is_ok()
+        }) {
+            return false;
+        }
+
+        self.time_slots.remove(slot_id);
+        self.emit(CalendarEvent::SlotRemoved {
+            slot_id: slot_id.to_string(),
+        });
+        self.debug_verify();
+        true
+    }
+
+    /// Set the pricing tier of a slot, returning false if the slot is unknown.
+    pub fn set_slot_tier(&mut self, slot_id: &str, tier: SlotTier) -> bool {
+        match self.time_slots.get_mut(slot_id) {
+            Some(slot) => {
+                slot.tier = tier;
+                for apt in self.appointments.values_mut() {
+                    if apt.time_slot.slot_id == slot_id {
+                        apt.time_slot.tier = tier;
+                    }
+                }
+                self.debug_verify();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Mark evening and/or weekend slots as premium, returning how many changed.
+    ///
+    /// A slot is an evening slot if it starts at or after `evening_from`.
+    pub fn mark_premium_slots(&mut self, evening_from: Option<NaiveTime>, weekends: bool) -> usize {
+        let premium_ids: Vec<String> = self
+            .time_slots
+            .values()
+            .filter(|slot| slot.tier != SlotTier::Premium)
+            .filter(|slot| {
+                let evening = evening_from.is_some_and(|from| slot.start_time.time() >= from);
+                let weekend = weekends && slot.start_time.weekday().num_days_from_monday() >= 5;
+                evening || weekend
+            })
+            .map(|slot| slot.slot_id.clone())
+            .collect();
+
+        for slot_id in &premium_ids {
+            self.set_slot_tier(slot_id, SlotTier::Premium);
+        }
+        premium_ids.len()
     }
 
     /// Generate time slots for a single day.
+    ///
+    /// The report lists the slots created and every candidate skipped,
+    /// either because it fell in the break or because it overlapped an
+    /// existing slot.
     pub fn generate_daily_slots(
         &mut self,
         date: DateTime<Local>,
@@ -92,7 +917,88 @@ impl DoctorCalendar {
         slot_duration_minutes: Option<i64>,
         break_start: Option<NaiveTime>,
         break_end: Option<NaiveTime>,
-    ) -> Vec<TimeSlot> {
+    ) -> GenerationReport {
+        let candidates = self.daily_slot_candidates(
+            date,
+            start_hour,
+            end_hour,
+            slot_duration_minutes,
+            break_start,
+            break_end,
+        );
+        self.add_candidates(candidates, false)
+    }
+
+    /// Generate a day's slots only where the day is not already covered.
+    ///
+    /// Slots matching an existing slot's start and end are counted as
+    /// already present rather than as overlaps, so running the same
+    /// generation twice is harmless.
+    pub fn ensure_daily_slots(
+        &mut self,
+        date: DateTime<Local>,
+        start_hour: u32,
+        end_hour: u32,
+        slot_duration_minutes: Option<i64>,
+        break_start: Option<NaiveTime>,
+        break_end: Option<NaiveTime>,
+    ) -> GenerationReport {
+        let candidates = self.daily_slot_candidates(
+            date,
+            start_hour,
+            end_hour,
+            slot_duration_minutes,
+            break_start,
+            break_end,
+        );
+        self.add_candidates(candidates, true)
+    }
+
+    /// Add candidate slots, recording why any were skipped.
+    fn add_candidates(
+        &mut self,
+        candidates: Vec<(TimeSlot, bool)>,
+        idempotent: bool,
+    ) -> GenerationReport {
+        let mut report = GenerationReport::default();
+
+        for (mut slot, in_break) in candidates {
+            if in_break {
+                report.skipped_break.push(slot);
+                continue;
+            }
+            if idempotent
+                && self.time_slots.values().any(|other| {
+                    other.start_time == slot.start_time && other.end_time == slot.end_time
+                })
+            {
+                report.already_present += 1;
+                continue;
+            }
+            if let Some(reason) = self.unavailable_reason(slot.start_time, slot.end_time) {
+                report.skipped_busy.push(SkippedSlot { slot, reason });
+                continue;
+            }
+            slot.slot_id = self.next_id(IdKind::Slot);
+            match self.add_time_slot(slot.clone()) {
+                Ok(()) => report.created.push(slot),
+                Err(reason) => report.skipped_overlap.push(SkippedSlot { slot, reason }),
+            }
+        }
+
+        report
+    }
+
+    /// Build the slots a day's working hours would contain, flagging those in the break.
+    fn daily_slot_candidates(
+        &self,
+        date: DateTime<Local>,
+        start_hour: u32,
+        end_hour: u32,
+        slot_duration_minutes: Option<i64>,
+        break_start: Option<NaiveTime>,
+        break_end: Option<NaiveTime>,
+    ) -> Vec<(TimeSlot, bool)> {
         let duration = slot_duration_minutes.unwrap_or(self.default_slot_duration);
         let mut slots = Vec::new();
 
@@ -113,22 +1019,18 @@ impl DoctorCalendar {
         while current + Duration::minutes(duration) <= end {
             let slot_end = current + Duration::minutes(duration);
 
-            let mut skip = false;
+            let mut in_break = false;
             if let (Some(break_start), Some(break_end)) = (break_start, break_end) {
                 let slot_start_time = current.time();
                 let slot_end_time = slot_end.time();
 
                 if slot_start_time < break_end && slot_end_time > break_start {
-                    skip = true;
+                    in_break = true;
                 }
             }
 
-            if !skip {
-                if let Ok(slot) = TimeSlot::new(current, slot_end) {
-                    if self.add_time_slot(slot.clone()).is_ok() {
-                        slots.push(slot);
-                    }
-                }
+            if let Ok(slot) = TimeSlot::new(current, slot_end) {
+                slots.push((slot, in_break));
             }
 
             current = slot_end;
@@ -138,6 +1040,7 @@ impl DoctorCalendar {
     }
 
     /// Generate time slots for multiple weeks.
+    #[allow(clippy::too_many_arguments)]
     pub fn generate_weekly_slots(
         &mut self,
         start_date: DateTime<Local>,
@@ -148,15 +1051,15 @@ impl DoctorCalendar {
         slot_duration_minutes: Option<i64>,
         break_start: Option<NaiveTime>,
         break_end: Option<NaiveTime>,
-    ) -> Vec<TimeSlot> {
+    ) -> GenerationReport {
         let working_days = working_days.unwrap_or_else(|| vec![0, 1, 2, 3, 4]);
-        let mut all_slots = Vec::new();
+        let mut report = GenerationReport::default();
         let mut current_date = start_date;
 
         for _ in 0..(weeks * 7) {
             // FIXED: Datelike trait now in scope
             if working_days.contains(&current_date.weekday().num_days_from_monday()) {
-                let slots = self.generate_daily_slots(
+                let day = self.generate_daily_slots(
                     current_date,
                     start_hour,
                     end_hour,
@@ -164,12 +1067,91 @@ impl DoctorCalendar {
                     break_start,
                     break_end,
                 );
-                all_slots.extend(slots);
+                report.merge(day);
+            }
+            current_date += Duration::days(1);
+        }
+
+        report
+    }
+
+    /// Generate slots from a template for every matching day in `[start, end]`.
+    pub fn apply_template(
+        &mut self,
+        template: &SlotTemplate,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> GenerationReport {
+        let mut report = GenerationReport::default();
+        let mut current_date = start;
+
+        while current_date.date_naive() <= end.date_naive() {
+            if template
+                .working_days
+                .contains(&current_date.weekday().num_days_from_monday())
+            {
+                let day = self.generate_daily_slots(
+                    current_date,
+                    template.start_hour,
+                    template.end_hour,
+                    template.slot_duration_minutes,
+                    template.break_start,
+                    template.break_end,
+                );
+                report.merge(day);
+            }
+            current_date += Duration::days(1);
+        }
+
+        report
+    }
+
+    /// Keep the next `horizon_weeks` weeks generated from the templates.
+    ///
+    /// Only days after the last one already generated are filled in, so
+    /// slots removed by hand stay removed. Call it periodically; each day
+    /// that passes opens one more day at the end of the horizon.
+    pub fn extend_horizon(&mut self) -> GenerationReport {
+        let mut report = GenerationReport::default();
+        let Some(weeks) = self.horizon_weeks else {
+            return report;
+        };
+        let now = self.now();
+        let today = now.date_naive();
+        let last_day = today + Duration::weeks(weeks as i64) - Duration::days(1);
+        let first_day = match self.generated_until {
+            Some(until) if until >= today => until + Duration::days(1),
+            _ => today,
+        };
+
+        let mut current_date = now + (first_day - today);
+        while current_date.date_naive() <= last_day {
+            let weekday = current_date.weekday().num_days_from_monday();
+            for template in self.templates.clone() {
+                if template.working_days.contains(&weekday) {
+                    let day = self.ensure_daily_slots(
+                        current_date,
+                        template.start_hour,
+                        template.end_hour,
+                        template.slot_duration_minutes,
+                        template.break_start,
+                        template.break_end,
+                    );
+                    report.merge(day);
+                }
             }
-            current_date = current_date + Duration::days(1);
+            current_date += Duration::days(1);
+        }
+
+        if first_day <= last_day {
+            self.generated_until = Some(last_day);
         }
+        report
+    }
 
-        all_slots
+    /// Get the last day `extend_horizon` has generated slots for.
+    pub fn generated_until(&self) -> Option<NaiveDate> {
+        self.generated_until
     }
 
     /// Find an available slot near the preferred time.
@@ -223,14 +1205,523 @@ impl DoctorCalendar {
             .collect()
     }
 
-    /// Book a time slot for a patient.
-    pub fn book_slot(
-        &mut self,
-        slot: &TimeSlot,
+    /// Find runs of back-to-back free slots long enough for a `minutes`
+    /// visit that no single slot fits.
+    ///
+    /// Each span starts at a free slot, carries its ID and lasts exactly
+    /// `minutes`. It is premium if any slot in it is. Book a span by
+    /// merging its slots with `merge_slots` first.
+    pub fn free_spans(&self, minutes: i64) -> Vec<TimeSlot> {
+        let slots = self.available_slots();
+        let mut spans = Vec::new();
+        for (index, first) in slots.iter().enumerate() {
+            if first.duration_minutes() >= minutes {
+                continue;
+            }
+            let wanted_end = first.start_time + Duration::minutes(minutes);
+            let mut span = first.clone();
+            for next in &slots[index + 1..] {
+                if next.start_time != span.end_time {
+                    break;
+                }
+                span.end_time = next.end_time;
+                if next.tier == SlotTier::Premium {
+                    span.tier = SlotTier::Premium;
+                }
+                if span.end_time >= wanted_end {
+                    span.end_time = wanted_end;
+                    spans.push(span);
+                    break;
+                }
+            }
+        }
+        spans
+    }
+
+    /// Merge the back-to-back free slots from `first_slot_id` into one
+    /// slot lasting `minutes`, e.g. to book a 60-minute procedure on a
+    /// 30-minute grid.
+    ///
+    /// Whatever is left of the last slot merged stays free as a shorter
+    /// slot. Returns the merged slot.
+    pub fn merge_slots(&mut self, first_slot_id: &str, minutes: i64) -> Result<TimeSlot, String> {
+        if minutes <= 0 {
+            return Err("Duration must be positive".to_string());
+        }
+        let first = self
+            .slot_by_id(first_slot_id)
+            .cloned()
+            .ok_or("Time slot not found in calendar")?;
+        let wanted_end = first.start_time + Duration::minutes(minutes);
+        let slots = self.time_slots();
+        let mut run = Vec::new();
+        let mut end = first.start_time;
+        for slot in slots.iter().skip_while(|slot| slot.slot_id != first.slot_id) {
+            if slot.start_time != end || end >= wanted_end {
+                break;
+            }
+            if !slot.is_available {
+                return Err("Time slot is not available".to_string());
+            }
+            end = slot.end_time;
+            run.push(slot.clone());
+        }
+        if end < wanted_end {
+            return Err(format!(
+                "Not enough free time after {} for a {}-minute visit",
+                first.start_time.format("%Y-%m-%d %H:%M"),
+                minutes
+            ));
+        }
+
+        self.transaction(|calendar| {
+            for slot in &run {
+                if !calendar.remove_time_slot(&slot.slot_id) {
+                    return Err(format!(
+                        "Cannot change the slot at {}",
+                        slot.start_time.format("%Y-%m-%d %H:%M")
+                    ));
+                }
+            }
+            let mut merged = TimeSlot::new(first.start_time, wanted_end)?;
+            merged.slot_id = calendar.next_id(IdKind::Slot);
+            if run.iter().any(|slot| slot.tier == SlotTier::Premium) {
+                merged.tier = SlotTier::Premium;
+            }
+            merged.bookable_from = run.iter().filter_map(|slot| slot.bookable_from).max();
+            calendar.add_time_slot(merged.clone())?;
+
+            if end > wanted_end {
+                let mut rest = TimeSlot::new(wanted_end, end)?;
+                rest.slot_id = calendar.next_id(IdKind::Slot);
+                rest.tier = run.last().map_or(SlotTier::Standard, |slot| slot.tier);
+                rest.bookable_from = run.last().and_then(|slot| slot.bookable_from);
+                calendar.add_time_slot(rest)?;
+            }
+            Ok(merged)
+        })
+    }
+
+    /// Lock a day against changes, e.g. once it has been closed out.
+    ///
+    /// Appointments on a locked day can't be booked, cancelled, moved or
+    /// handed over, its free slots can't be removed, and attendance and
+    /// notes can't be recorded. Returns false if the day was already locked.
+    pub fn lock_day(&mut self, date: NaiveDate) -> bool {
+        self.locked_days.insert(date)
+    }
+
+    /// Unlock a day, for an administrator correcting a closed day.
+    ///
+    /// Past days stay locked while `lock_past_days` is set.
+    pub fn unlock_day(&mut self, date: NaiveDate) -> bool {
+        self.locked_days.remove(&date)
+    }
+
+    /// Get the days locked with `lock_day`, in date order.
+    pub fn locked_days(&self) -> Vec<NaiveDate> {
+        self.locked_days.iter().copied().collect()
+    }
+
+    /// Check whether a day was locked with `lock_day`.
+    pub fn is_day_closed(&self, date: NaiveDate) -> bool {
+        self.locked_days.contains(&date)
+    }
+
+    /// Check whether a day's schedule is locked, either with `lock_day` or
+    /// because it has passed while `lock_past_days` is set.
+    pub fn is_day_locked(&self, date: NaiveDate) -> bool {
+        self.is_day_closed(date) || self.is_past_and_locked(date)
+    }
+
+    /// Attach a note to a day, e.g. "locum covering" or "flu clinic after 3pm".
+    pub fn add_day_note(&mut self, date: NaiveDate, note: String) -> Result<(), String> {
+        let note = note.trim();
+        if note.is_empty() {
+            return Err("Note cannot be empty".to_string());
+        }
+        self.day_notes
+            .entry(date)
+            .or_default()
+            .push(note.to_string());
+        Ok(())
+    }
+
+    /// Remove a day's notes, returning false if it had none.
+    pub fn clear_day_notes(&mut self, date: NaiveDate) -> bool {
+        self.day_notes.remove(&date).is_some()
+    }
+
+    /// Get a day's notes in the order they were added.
+    pub fn day_notes(&self, date: NaiveDate) -> &[String] {
+        self.day_notes.get(&date).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get every day with notes, in date order.
+    pub fn all_day_notes(&self) -> Vec<(NaiveDate, &[String])> {
+        self.day_notes
+            .iter()
+            .map(|(date, notes)| (*date, notes.as_slice()))
+            .collect()
+    }
+
+    /// Block out the commitments in a doctor's external ICS calendar.
+    ///
+    /// The feed's events replace any busy times imported before. Free slots
+    /// overlapping them are removed and no new slots are generated over
+    /// them; booked slots are kept and their appointments reported.
+    pub fn import_busy_from_ics(&mut self, path: &str) -> Result<BusyImportReport, String> {
+        let text =
+            std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        Ok(self.set_busy_times(parse_busy_times(&text)?))
+    }
+
+    /// Replace the busy times, removing the free slots they overlap.
+    pub fn set_busy_times(&mut self, mut busy_times: Vec<BusyTime>) -> BusyImportReport {
+        busy_times.sort_by_key(|busy| busy.start);
+        self.busy_times = busy_times;
+        let mut report = BusyImportReport {
+            busy_times: self.busy_times.len(),
+            ..BusyImportReport::default()
+        };
+
+        for slot in self.time_slots() {
+            if self.busy_reason(slot.start_time, slot.end_time).is_none() {
+                continue;
+            }
+            if slot.is_available {
+                if self.remove_time_slot(&slot.slot_id) {
+                    report.removed.push(slot);
+                }
+            } else if let Some(apt) = self
+                .active_appointments()
+                .find(|apt| apt.time_slot.slot_id == slot.slot_id)
+            {
+                report.conflicts.push(apt.clone());
+            }
+        }
+        report.conflicts.sort_by_key(|apt| apt.time_slot.start_time);
+        report
+    }
+
+    /// Get the busy times imported from external calendars, in start order.
+    pub fn busy_times(&self) -> &[BusyTime] {
+        &self.busy_times
+    }
+
+    /// Describe the first busy time overlapping `[start, end)`, if any.
+    pub fn busy_reason(&self, start: DateTime<Local>, end: DateTime<Local>) -> Option<String> {
+        self.busy_times
+            .iter()
+            .find(|busy| busy.overlaps(start, end))
+            .map(|busy| {
+                format!(
+                    "Busy in external calendar: {} ({} - {})",
+                    busy.summary,
+                    busy.start.format("%Y-%m-%d %H:%M"),
+                    busy.end.format("%Y-%m-%d %H:%M")
+                )
+            })
+    }
+
+    /// Block `[start, end)` off, e.g. for a vacation.
+    ///
+    /// Free slots in the period are removed and no slots can be added,
+    /// generated or booked there until it is unblocked. Booked slots are
+    /// kept and their appointments reported; a slot freed by cancelling or
+    /// moving one of them is removed too.
+    pub fn block_period(
+        &mut self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        reason: String,
+    ) -> Result<BlockReport, String> {
+        if end <= start {
+            return Err("End time must be after start time".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("Block reason cannot be empty".to_string());
+        }
+        self.blocked_periods.push(BlockedPeriod {
+            start,
+            end,
+            reason: reason.trim().to_string(),
+        });
+        self.blocked_periods.sort_by_key(|period| period.start);
+
+        let mut report = BlockReport::default();
+        for slot in self.time_slots() {
+            if slot.start_time >= end || slot.end_time <= start {
+                continue;
+            }
+            if slot.is_available {
+                if self.remove_time_slot(&slot.slot_id) {
+                    report.removed.push(slot);
+                } else {
+                    report.refused.push(slot);
+                }
+            } else if let Some(apt) = self
+                .active_appointments()
+                .find(|apt| apt.time_slot.slot_id == slot.slot_id)
+            {
+                report.conflicts.push(apt.clone());
+            }
+        }
+        report.conflicts.sort_by_key(|apt| apt.time_slot.start_time);
+        Ok(report)
+    }
+
+    /// Lift every blocked period overlapping `[start, end)`, returning them.
+    ///
+    /// Slots removed by the block are not restored; generate them again.
+    pub fn unblock(&mut self, start: DateTime<Local>, end: DateTime<Local>) -> Vec<BlockedPeriod> {
+        let (lifted, kept) = std::mem::take(&mut self.blocked_periods)
+            .into_iter()
+            .partition(|period| period.overlaps(start, end));
+        self.blocked_periods = kept;
+        lifted
+    }
+
+    /// Get the blocked periods, in start order.
+    pub fn blocked_periods(&self) -> &[BlockedPeriod] {
+        &self.blocked_periods
+    }
+
+    /// Describe the first blocked period overlapping `[start, end)`, if any.
+    pub fn blocked_reason(&self, start: DateTime<Local>, end: DateTime<Local>) -> Option<String> {
+        self.blocked_periods
+            .iter()
+            .find(|period| period.overlaps(start, end))
+            .map(|period| {
+                format!(
+                    "Blocked: {} ({} - {})",
+                    period.reason,
+                    period.start.format("%Y-%m-%d %H:%M"),
+                    period.end.format("%Y-%m-%d %H:%M")
+                )
+            })
+    }
+
+    /// Describe why no slot can be opened in `[start, end)`: a busy time or
+    /// a blocked period.
+    pub fn unavailable_reason(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Option<String> {
+        self.busy_reason(start, end)
+            .or_else(|| self.blocked_reason(start, end))
+    }
+
+    /// Remove a freed slot if it lies in a blocked period.
+    fn drop_if_blocked(&mut self, slot_id: &str) {
+        let blocked = match self.time_slots.get(slot_id) {
+            Some(slot) => self
+                .blocked_reason(slot.start_time, slot.end_time)
+                .is_some(),
+            None => false,
+        };
+        if blocked {
+            self.remove_time_slot(slot_id);
+        }
+    }
+
+    /// Run a day as an ordered case list starting at `starts_at`, instead
+    /// of in fixed slots, e.g. for a procedure day.
+    ///
+    /// The day must have no appointments. Its free slots are removed, and
+    /// the number removed is returned.
+    pub fn open_case_list(&mut self, starts_at: DateTime<Local>) -> Result<usize, String> {
+        let date = starts_at.date_naive();
+        self.ensure_unlocked(starts_at)?;
+        if self.is_case_list_day(date) {
+            return Err(format!(
+                "{} already has a case list",
+                date.format("%Y-%m-%d")
+            ));
+        }
+        if !self.get_appointments_on_date(starts_at).is_empty() {
+            return Err(format!(
+                "{} already has slot appointments",
+                date.format("%Y-%m-%d")
+            ));
+        }
+
+        let free: Vec<String> = self
+            .find_available_slots_on_date(starts_at)
+            .into_iter()
+            .map(|slot| slot.slot_id)
+            .collect();
+        let removed = free.iter().filter(|id| self.remove_time_slot(id)).count();
+        self.case_lists.insert(date, CaseList::new(starts_at));
+        Ok(removed)
+    }
+
+    /// Check whether a day runs as a case list.
+    pub fn is_case_list_day(&self, date: NaiveDate) -> bool {
+        self.case_lists.contains_key(&date)
+    }
+
+    /// Get a day's case list.
+    pub fn case_list(&self, date: NaiveDate) -> Option<&CaseList> {
+        self.case_lists.get(&date)
+    }
+
+    /// Get a day's case list for reordering cases or recording their
+    /// progress.
+    pub fn case_list_mut(&mut self, date: NaiveDate) -> Result<&mut CaseList, String> {
+        self.ensure_not_closed(date)?;
+        self.case_lists
+            .get_mut(&date)
+            .ok_or_else(|| format!("{} has no case list", date.format("%Y-%m-%d")))
+    }
+
+    /// Add a case to the end of a day's case list.
+    pub fn add_case(
+        &mut self,
+        date: NaiveDate,
+        patient: Patient,
+        procedure: String,
+        priority: Priority,
+        estimated_minutes: i64,
+    ) -> Result<Case, String> {
+        let starts_at = self.case_list(date).map(|list| list.starts_at);
+        if let Some(starts_at) = starts_at {
+            self.ensure_unlocked(starts_at)?;
+        }
+        let case_id = self.next_id(IdKind::Case);
+        let case = Case::new(case_id, patient, procedure, priority, estimated_minutes)?;
+        self.case_list_mut(date)?.add(case.clone());
+        Ok(case)
+    }
+
+    fn is_past_and_locked(&self, date: NaiveDate) -> bool {
+        self.lock_past_days && date < self.clock.now().date_naive()
+    }
+
+    /// Reject a schedule change at `time` if its day is locked.
+    fn ensure_unlocked(&self, time: DateTime<Local>) -> Result<(), String> {
+        let date = time.date_naive();
+        self.ensure_not_closed(date)?;
+        if self.is_past_and_locked(date) {
+            return Err(format!(
+                "{} has passed and past days are locked against changes",
+                date.format("%Y-%m-%d")
+            ));
+        }
+        Ok(())
+    }
+
+    fn ensure_not_closed(&self, date: NaiveDate) -> Result<(), String> {
+        if self.is_day_closed(date) {
+            return Err(format!(
+                "{} is locked; unlock the day to change it",
+                date.format("%Y-%m-%d")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that a visit in `[start, end)` could be booked: its day is not
+    /// locked or run as a case list, and it overlaps no busy time or
+    /// blocked period.
+    pub fn ensure_bookable(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<(), String> {
+        self.ensure_unlocked(start)?;
+        if let Some(reason) = self.unavailable_reason(start, end) {
+            return Err(reason);
+        }
+        if self.is_case_list_day(start.date_naive()) {
+            return Err(format!(
+                "{} runs as a case list; add a case instead",
+                start.format("%Y-%m-%d")
+            ));
+        }
+        Ok(())
+    }
+
+    /// Book a time slot for a patient.
+    pub fn book_slot(
+        &mut self,
+        slot: &TimeSlot,
         patient: Patient,
         priority: Priority,
         reason: String,
     ) -> Result<Appointment, String> {
+        self.book_slot_in_bundle(slot, patient, priority, reason, None)
+    }
+
+    /// Book many slots at once, all or nothing.
+    ///
+    /// Every spec is checked before anything is booked: its slot must exist,
+    /// be free and open for booking, and appear only once in the batch, and
+    /// it must have a reason. If any spec fails, nothing is booked and the
+    /// valid specs report that they were skipped. Results are in spec order.
+    pub fn book_slots_bulk(&mut self, specs: Vec<BookingSpec>) -> Vec<Result<Appointment, String>> {
+        let count = specs.len();
+        let now = self.clock.now();
+        let mut seen = HashMap::new();
+        let checks: Vec<Result<(), String>> = specs
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| {
+                let slot = self
+                    .time_slots
+                    .get(&spec.slot_id)
+                    .ok_or("Time slot not found in calendar")?;
+                if !slot.is_available {
+                    return Err("Time slot is not available".to_string());
+                }
+                self.ensure_unlocked(slot.start_time)?;
+                if !slot.is_released(now) {
+                    return Err("Time slot is not open for booking yet".to_string());
+                }
+                if spec.reason.is_empty() {
+                    return Err("Appointment reason cannot be empty".to_string());
+                }
+                if let Some(first) = seen.insert(spec.slot_id.as_str(), index) {
+                    return Err(format!("Time slot is already booked by item {}", first + 1));
+                }
+                Ok(())
+            })
+            .collect();
+
+        if checks.iter().any(Result::is_err) {
+            let skipped = "Not booked: other items in the batch failed".to_string();
+            return checks
+                .into_iter()
+                .map(|check| check.and(Err(skipped.clone())))
+                .collect();
+        }
+
+        let booked = self.transaction(|calendar| {
+            specs
+                .into_iter()
+                .map(|spec| {
+                    let slot = calendar.time_slots[&spec.slot_id].clone();
+                    calendar.book_slot(&slot, spec.patient, spec.priority, spec.reason)
+                })
+                .collect::<Result<Vec<Appointment>, String>>()
+        });
+
+        match booked {
+            Ok(appointments) => appointments.into_iter().map(Ok).collect(),
+            Err(e) => (0..count).map(|_| Err(e.clone())).collect(),
+        }
+    }
+
+    fn book_slot_in_bundle(
+        &mut self,
+        slot: &TimeSlot,
+        patient: Patient,
+        priority: Priority,
+        reason: String,
+        bundle_id: Option<String>,
+    ) -> Result<Appointment, String> {
+        self.ensure_bookable(slot.start_time, slot.end_time)?;
         let stored_slot = self
             .time_slots
             .get_mut(&slot.slot_id)
@@ -239,26 +1730,664 @@ impl DoctorCalendar {
         if !stored_slot.is_available {
             return Err("Time slot is not available".to_string());
         }
+        if !stored_slot.is_released(self.clock.now()) {
+            return Err(format!(
+                "Time slot is not open for booking until {}",
+                stored_slot
+                    .bookable_from
+                    .map(|from| from.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_default()
+            ));
+        }
 
-        stored_slot.is_available = false;
+        let mut appointment = Appointment::new(
+            self.ids.as_ref(),
+            self.clock.now(),
+            patient,
+            stored_slot.clone(),
+            priority,
+            reason,
+        )?;
+        appointment.bundle_id = bundle_id;
+        if self.require_confirmation {
+            appointment.status = AppointmentStatus::Requested;
+        }
 
-        let appointment = Appointment::new(patient, stored_slot.clone(), priority, reason)?;
+        stored_slot.is_available = false;
+        appointment.time_slot.is_available = false;
         self.appointments
             .insert(appointment.appointment_id.clone(), appointment.clone());
+        self.record_change(&appointment.appointment_id, AppointmentChangeKind::Created);
+        self.emit(CalendarEvent::AppointmentBooked {
+            appointment: appointment.clone(),
+        });
+        self.debug_verify();
 
         Ok(appointment)
     }
 
     /// Cancel an appointment and free up the time slot.
     pub fn cancel_appointment(&mut self, appointment_id: &str) -> bool {
-        if let Some(appointment) = self.appointments.remove(appointment_id) {
-            if let Some(slot) = self.time_slots.get_mut(&appointment.time_slot.slot_id) {
-                slot.is_available = true;
+        self.cancel_appointment_by(appointment_id, CancellationActor::Staff)
+            .is_ok()
+    }
+
+    /// Cancel an appointment on behalf of the patient or staff.
+    ///
+    /// Patient cancellations inside the cancellation policy's notice window
+    /// are recorded as late and carry the policy's fee, if any. Other steps
+    /// of the appointment's bundle are cancelled as `bundle_cancellation`
+    /// says.
+    pub fn cancel_appointment_by(
+        &mut self,
+        appointment_id: &str,
+        actor: CancellationActor,
+    ) -> Result<CancellationRecord, String> {
+        let policy = self.bundle_cancellation;
+        self.cancel_with_bundle(appointment_id, actor, policy)
+            .map(|mut records| records.remove(0))
+    }
+
+    /// Cancel an appointment and the other steps of its bundle the policy
+    /// covers, all or nothing.
+    ///
+    /// Steps that have already started or been checked in are left alone.
+    /// The linked steps are cancelled by staff, so they never carry a late
+    /// fee of their own. Returns the appointment's record first.
+    pub fn cancel_with_bundle(
+        &mut self,
+        appointment_id: &str,
+        actor: CancellationActor,
+        policy: BundleCancellation,
+    ) -> Result<Vec<CancellationRecord>, String> {
+        let appointment = self
+            .appointments
+            .get(appointment_id)
+            .cloned()
+            .ok_or("Appointment not found")?;
+        let now = self.clock.now();
+        let linked: Vec<String> = match (&appointment.bundle_id, policy) {
+            (None, _) | (_, BundleCancellation::KeepOthers) => Vec::new(),
+            (Some(bundle_id), policy) => self
+                .bundle_appointments(bundle_id)
+                .into_iter()
+                .filter(|apt| apt.appointment_id != appointment_id)
+                .filter(|apt| apt.time_slot.start_time > now && apt.checked_in_at.is_none())
+                .filter(|apt| {
+                    policy == BundleCancellation::CancelAll
+                        || apt.time_slot.start_time > appointment.time_slot.start_time
+                })
+                .map(|apt| apt.appointment_id)
+                .collect(),
+        };
+
+        self.transaction(|calendar| {
+            let mut records = vec![calendar.cancel_single(appointment_id, actor)?];
+            for id in &linked {
+                records.push(calendar.cancel_single(id, CancellationActor::Staff)?);
             }
-            true
+            Ok(records)
+        })
+    }
+
+    fn cancel_single(
+        &mut self,
+        appointment_id: &str,
+        actor: CancellationActor,
+    ) -> Result<CancellationRecord, String> {
+        let (start, status) = self
+            .appointments
+            .get(appointment_id)
+            .map(|apt| (apt.time_slot.start_time, apt.status))
+            .ok_or("Appointment not found")?;
+        if !status.can_become(AppointmentStatus::Cancelled) {
+            return Err(format!("Cannot cancel a {} appointment", status.name()));
+        }
+        self.ensure_unlocked(start)?;
+        let appointment = self
+            .appointments
+            .get_mut(appointment_id)
+            .ok_or("Appointment not found")?;
+        appointment.transition(AppointmentStatus::Cancelled)?;
+        let appointment = appointment.clone();
+
+        let now = self.clock.now();
+        let late = actor == CancellationActor::Patient
+            && self
+                .cancellation_policy
+                .is_some_and(|policy| policy.is_late(appointment.time_slot.start_time, now));
+        let fee_cents = if late {
+            self.cancellation_policy.and_then(|policy| policy.late_fee_cents)
         } else {
-            false
+            None
+        };
+
+        let slot_id = appointment.time_slot.slot_id;
+        if let Some(slot) = self.time_slots.get_mut(&slot_id) {
+            slot.is_available = true;
+        }
+
+        let record = CancellationRecord {
+            appointment_id: appointment_id.to_string(),
+            patient_id: appointment.patient.patient_id,
+            scheduled_start: appointment.time_slot.start_time,
+            cancelled_at: now,
+            actor,
+            late,
+            fee_cents,
+        };
+        self.cancellations.push(record.clone());
+        self.record_change(appointment_id, AppointmentChangeKind::Cancelled);
+        self.emit(CalendarEvent::AppointmentCancelled {
+            appointment_id: appointment_id.to_string(),
+            slot_id: slot_id.clone(),
+        });
+        self.drop_if_blocked(&slot_id);
+        self.debug_verify();
+
+        Ok(record)
+    }
+
+    /// Get all cancellations, oldest first.
+    pub fn cancellations(&self) -> &[CancellationRecord] {
+        &self.cancellations
+    }
+
+    /// Get patient cancellations that fell inside the notice window.
+    pub fn late_cancellations(&self) -> Vec<CancellationRecord> {
+        self.cancellations
+            .iter()
+            .filter(|record| record.late)
+            .cloned()
+            .collect()
+    }
+
+    /// Book every step of a bundle, or nothing if any step cannot be placed.
+    ///
+    /// The first step is placed as close as possible to the preferred time
+    /// within its flexibility window; each later step takes the earliest
+    /// free slot inside its gap window after the previous step ends.
+    pub fn book_bundle(&mut self, request: &BundleRequest) -> Result<BundleBooking, String> {
+        let mut planned: Vec<TimeSlot> = Vec::new();
+
+        for (i, step) in request.steps.iter().enumerate() {
+            let slot = match planned.last() {
+                None => self.find_available_slot(request.preferred_time, request.flexibility_minutes),
+                Some(previous) => {
+                    let earliest = previous.end_time + Duration::minutes(step.min_gap_minutes);
+                    let latest = previous.end_time + Duration::minutes(step.max_gap_minutes);
+                    self.available_slots()
+                        .into_iter()
+                        .find(|s| s.start_time >= earliest && s.start_time <= latest)
+                }
+            };
+
+            match slot {
+                Some(s) => planned.push(s),
+                None => {
+                    return Err(format!(
+                        "No available slot for bundle step {} ({})",
+                        i + 1,
+                        step.reason
+                    ));
+                }
+            }
+        }
+
+        let bundle_id = self.next_id(IdKind::Bundle);
+        let appointments = self.transaction(|calendar| {
+            let mut appointments = Vec::new();
+            for (slot, step) in planned.iter().zip(&request.steps) {
+                let appointment = calendar
+                    .book_slot_in_bundle(
+                        slot,
+                        request.patient.clone(),
+                        request.priority,
+                        step.reason.clone(),
+                        Some(bundle_id.clone()),
+                    )
+                    .map_err(|e| format!("Failed to book bundle: {}", e))?;
+                appointments.push(appointment);
+            }
+            Ok(appointments)
+        })?;
+
+        Ok(BundleBooking {
+            bundle_id,
+            appointments,
+        })
+    }
+
+    /// Get all appointments belonging to a bundle, in time order.
+    pub fn bundle_appointments(&self, bundle_id: &str) -> Vec<Appointment> {
+        self.appointments()
+            .into_iter()
+            .filter(|apt| apt.bundle_id.as_deref() == Some(bundle_id))
+            .collect()
+    }
+
+    /// Cancel every appointment in a bundle, returning how many were cancelled.
+    ///
+    /// Either the whole bundle is cancelled or, if any appointment can't be,
+    /// none of it is and the first error is returned.
+    pub fn cancel_bundle(&mut self, bundle_id: &str) -> Result<usize, String> {
+        let ids: Vec<String> = self
+            .bundle_appointments(bundle_id)
+            .into_iter()
+            .map(|apt| apt.appointment_id)
+            .collect();
+
+        self.transaction(|calendar| {
+            for id in &ids {
+                calendar.cancel_single(id, CancellationActor::Staff)?;
+            }
+            Ok(ids.len())
+        })
+    }
+
+    /// Path of the read-only ICS subscription feed, including its access token.
+    pub fn feed_path(&self) -> String {
+        format!("/calendars/{}/feed.ics?token={}", self.doctor_id, self.feed_token)
+    }
+
+    /// Replace the feed token, invalidating previously shared feed URLs.
+    pub fn rotate_feed_token(&mut self) -> String {
+        self.feed_token = self.ids.secret_token(self.clock.now());
+        self.feed_token.clone()
+    }
+
+    /// Render the ICS feed if the token matches this calendar's feed token.
+    pub fn ics_feed(&self, token: &str) -> Result<String, String> {
+        let expected = self.feed_token.as_bytes();
+        let given = token.as_bytes();
+        let matches = expected.len() == given.len()
+            && expected
+                .iter()
+                .zip(given)
+                .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+                == 0;
+
+        if !matches {
+            return Err("Invalid feed token".to_string());
         }
+        Ok(crate::ics::render_calendar(self))
+    }
+
+    /// Move an appointment to another free slot, keeping its ID and details.
+    ///
+    /// The move is atomic: if the new slot cannot be booked the original
+    /// appointment is left untouched.
+    pub fn move_appointment(
+        &mut self,
+        appointment_id: &str,
+        new_slot: &TimeSlot,
+    ) -> Result<Appointment, String> {
+        let previous_slot = self
+            .active_appointment(appointment_id)
+            .map(|apt| apt.time_slot.clone())
+            .ok_or("Appointment not found")?;
+        self.ensure_unlocked(previous_slot.start_time)?;
+        let target = self
+            .time_slots
+            .get(&new_slot.slot_id)
+            .ok_or("Time slot not found in calendar")?;
+
+        if !target.is_available {
+            return Err("Time slot is not available".to_string());
+        }
+        self.ensure_bookable(target.start_time, target.end_time)?;
+
+        let target = self.time_slots.get_mut(&new_slot.slot_id).unwrap();
+        target.is_available = false;
+        let target = target.clone();
+        if let Some(slot) = self.time_slots.get_mut(&previous_slot.slot_id) {
+            slot.is_available = true;
+        }
+
+        let appointment = match self.appointments.get_mut(appointment_id) {
+            Some(apt) => {
+                apt.time_slot = target;
+                apt.clone()
+            }
+            None => return Err("Appointment not found".to_string()),
+        };
+
+        self.record_change(
+            appointment_id,
+            AppointmentChangeKind::Moved {
+                from: previous_slot.start_time,
+                to: appointment.time_slot.start_time,
+            },
+        );
+        self.emit(CalendarEvent::AppointmentRescheduled {
+            previous_slot_id: previous_slot.slot_id.clone(),
+            appointment: appointment.clone(),
+        });
+        self.drop_if_blocked(&previous_slot.slot_id);
+        self.debug_verify();
+
+        Ok(appointment)
+    }
+
+    /// Hand a booked appointment and its slot over to another doctor's calendar.
+    ///
+    /// The appointment keeps its time, ID and history, and the handover is
+    /// added to its history. Fails if the other calendar could not have
+    /// booked the slot itself: the day is locked or runs as a case list, the
+    /// time is busy or blocked, or it already has a slot overlapping the
+    /// appointment.
+    pub fn transfer_appointment(
+        &mut self,
+        appointment_id: &str,
+        to: &mut DoctorCalendar,
+    ) -> Result<Appointment, String> {
+        if self.doctor_id == to.doctor_id {
+            return Err("Cannot transfer an appointment to the same doctor".to_string());
+        }
+        let appointment = self
+            .active_appointment(appointment_id)
+            .cloned()
+            .ok_or("Appointment not found")?;
+        self.ensure_unlocked(appointment.time_slot.start_time)?;
+        let slot = self
+            .time_slots
+            .get(&appointment.time_slot.slot_id)
+            .cloned()
+            .ok_or("Time slot not found in calendar")?;
+        to.ensure_bookable(slot.start_time, slot.end_time)
+            .map_err(|e| format!("{}: {}", to.doctor_name, e))?;
+
+        to.insert_time_slot(slot.clone())?;
+        self.time_slots.remove(&slot.slot_id);
+        self.appointments.remove(appointment_id);
+        let history = self.history.remove(appointment_id).unwrap_or_default();
+
+        to.history.insert(appointment_id.to_string(), history);
+        to.appointments
+            .insert(appointment_id.to_string(), appointment.clone());
+        to.record_change(
+            appointment_id,
+            AppointmentChangeKind::Reassigned {
+                from_doctor: self.doctor_name.clone(),
+                to_doctor: to.doctor_name.clone(),
+            },
+        );
+
+        let event = CalendarEvent::AppointmentReassigned {
+            from_doctor_id: self.doctor_id.clone(),
+            to_doctor_id: to.doctor_id.clone(),
+            appointment: appointment.clone(),
+        };
+        self.emit(CalendarEvent::SlotRemoved {
+            slot_id: slot.slot_id,
+        });
+        self.emit(event.clone());
+        to.emit(event);
+        self.debug_verify();
+        to.debug_verify();
+
+        Ok(appointment)
+    }
+
+    /// Work out where each of a day's appointments would land if shifted by `offset`.
+    ///
+    /// Each appointment needs an existing slot of the same length starting
+    /// exactly `offset` later (or earlier), so breaks and the end of the
+    /// working day are respected by the slot grid. Appointments are planned
+    /// from the end of the day for a forward shift (from the start for a
+    /// backward one) so they can move into slots vacated by their
+    /// neighbours. Nothing is changed.
+    pub fn plan_day_shift(
+        &self,
+        date: DateTime<Local>,
+        offset: Duration,
+    ) -> Result<DayShiftReport, String> {
+        if offset.is_zero() {
+            return Err("Shift offset cannot be zero".to_string());
+        }
+
+        let mut appointments = self.get_appointments_on_date(date);
+        if offset > Duration::zero() {
+            appointments.reverse();
+        }
+
+        let mut occupied: HashMap<String, String> = appointments
+            .iter()
+            .map(|apt| (apt.time_slot.slot_id.clone(), apt.appointment_id.clone()))
+            .collect();
+        let mut report = DayShiftReport::default();
+
+        for apt in appointments {
+            let start = apt.time_slot.start_time + offset;
+            let end = apt.time_slot.end_time + offset;
+            let target = self.time_slots.values().find(|slot| {
+                slot.start_time == start
+                    && slot.end_time == end
+                    && (slot.is_available || !occupied.contains_key(&slot.slot_id))
+                    && start.date_naive() == date.date_naive()
+            });
+
+            match target {
+                Some(slot) => {
+                    occupied.remove(&apt.time_slot.slot_id);
+                    occupied.insert(slot.slot_id.clone(), apt.appointment_id.clone());
+                    let previous_start = apt.time_slot.start_time;
+                    let mut shifted = apt;
+                    shifted.time_slot = slot.clone();
+                    shifted.time_slot.is_available = false;
+                    report.moved.push(ShiftedAppointment {
+                        appointment: shifted,
+                        previous_start,
+                    });
+                }
+                None => report.unfit.push(apt),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Shift a day's appointments by `offset`, e.g. when the doctor starts late.
+    ///
+    /// Appointments that fit are moved as planned by `plan_day_shift`, all
+    /// or nothing; those that don't fit stay where they are and are listed
+    /// in the report so staff can contact them.
+    pub fn shift_day(
+        &mut self,
+        date: DateTime<Local>,
+        offset: Duration,
+    ) -> Result<DayShiftReport, String> {
+        let report = self.plan_day_shift(date, offset)?;
+
+        self.transaction(|calendar| {
+            for shifted in &report.moved {
+                calendar.move_appointment(
+                    &shifted.appointment.appointment_id,
+                    &shifted.appointment.time_slot,
+                )?;
+            }
+            Ok(())
+        })?;
+
+        Ok(report)
+    }
+
+    fn record_change(&mut self, appointment_id: &str, kind: AppointmentChangeKind) {
+        self.history
+            .entry(appointment_id.to_string())
+            .or_default()
+            .push(AppointmentChange::new(kind, self.clock.now()));
+    }
+
+    /// Attach a free-text note to an appointment.
+    pub fn add_appointment_note(&mut self, appointment_id: &str, note: String) -> Result<(), String> {
+        if note.trim().is_empty() {
+            return Err("Note cannot be empty".to_string());
+        }
+        self.ensure_appointment_unlocked(appointment_id)?;
+        self.record_change(appointment_id, AppointmentChangeKind::NoteAdded { note });
+        Ok(())
+    }
+
+    /// Confirm an appointment that was booked as Requested.
+    pub fn confirm(&mut self, appointment_id: &str) -> Result<Appointment, String> {
+        self.ensure_appointment_unlocked(appointment_id)?;
+        let apt = self
+            .appointments
+            .get_mut(appointment_id)
+            .ok_or("Appointment not found")?;
+        if apt.status == AppointmentStatus::Confirmed {
+            return Err("Appointment is already confirmed".to_string());
+        }
+        let previous_status = apt.status;
+        apt.transition(AppointmentStatus::Confirmed)?;
+        let apt = apt.clone();
+        self.record_change(appointment_id, AppointmentChangeKind::Confirmed);
+        self.emit_status_change(previous_status, &apt);
+        Ok(apt)
+    }
+
+    /// Record that the patient has arrived for an appointment.
+    pub fn check_in(&mut self, appointment_id: &str) -> Result<Appointment, String> {
+        let now = self.clock.now();
+        self.ensure_appointment_unlocked(appointment_id)?;
+        let apt = self
+            .appointments
+            .get_mut(appointment_id)
+            .ok_or("Appointment not found")?;
+        if apt.checked_in_at.is_some() {
+            return Err("Patient is already checked in".to_string());
+        }
+        let previous_status = apt.status;
+        apt.transition(AppointmentStatus::CheckedIn)?;
+
+        apt.checked_in_at = Some(now);
+        let apt = apt.clone();
+        self.record_change(appointment_id, AppointmentChangeKind::CheckedIn);
+        self.emit_status_change(previous_status, &apt);
+        Ok(apt)
+    }
+
+    /// Record that a checked-in patient's visit has finished.
+    pub fn complete(&mut self, appointment_id: &str) -> Result<Appointment, String> {
+        let now = self.clock.now();
+        self.ensure_appointment_unlocked(appointment_id)?;
+        let apt = self
+            .appointments
+            .get_mut(appointment_id)
+            .ok_or("Appointment not found")?;
+        if apt.checked_in_at.is_none() {
+            return Err("Patient has not checked in".to_string());
+        }
+        if apt.completed_at.is_some() {
+            return Err("Visit is already completed".to_string());
+        }
+        let previous_status = apt.status;
+        apt.transition(AppointmentStatus::Completed)?;
+
+        apt.completed_at = Some(now);
+        let apt = apt.clone();
+        self.record_change(appointment_id, AppointmentChangeKind::Completed);
+        self.emit_status_change(previous_status, &apt);
+        Ok(apt)
+    }
+
+    /// Record that the patient never came to an appointment that has ended.
+    pub fn mark_no_show(&mut self, appointment_id: &str) -> Result<Appointment, String> {
+        let now = self.clock.now();
+        self.ensure_appointment_unlocked(appointment_id)?;
+        let apt = self
+            .appointments
+            .get_mut(appointment_id)
+            .ok_or("Appointment not found")?;
+        if apt.time_slot.end_time > now {
+            return Err("Appointment has not ended yet".to_string());
+        }
+        if apt.checked_in_at.is_some() {
+            return Err("Patient checked in".to_string());
+        }
+        if apt.no_show_at.is_some() {
+            return Err("Appointment is already marked as a no-show".to_string());
+        }
+        let previous_status = apt.status;
+        apt.transition(AppointmentStatus::NoShow)?;
+
+        apt.no_show_at = Some(now);
+        let apt = apt.clone();
+        self.record_change(appointment_id, AppointmentChangeKind::NoShow);
+        self.emit_status_change(previous_status, &apt);
+        Ok(apt)
+    }
+
+    fn emit_status_change(
+        &mut self,
+        previous_status: AppointmentStatus,
+        appointment: &Appointment,
+    ) {
+        self.emit(CalendarEvent::AppointmentStatusChanged {
+            previous_status,
+            appointment: appointment.clone(),
+        });
+    }
+
+    /// Reject an attendance or note change if the appointment's day has been
+    /// locked with `lock_day`. Past days stay open for these so that they
+    /// can still be closed out.
+    fn ensure_appointment_unlocked(&self, appointment_id: &str) -> Result<(), String> {
+        match self.appointments.get(appointment_id) {
+            Some(apt) => self.ensure_not_closed(apt.time_slot.start_time.date_naive()),
+            None => Err("Appointment not found".to_string()),
+        }
+    }
+
+    /// Get today's appointments by time with their attendance status.
+    pub fn today(&self) -> Vec<(Appointment, AttendanceStatus)> {
+        let now = self.clock.now();
+        self.get_appointments_on_date(now)
+            .into_iter()
+            .map(|apt| {
+                let status = apt.attendance_status(now);
+                (apt, status)
+            })
+            .collect()
+    }
+
+    /// Render a printable card for an appointment.
+    pub fn render_appointment_card(
+        &self,
+        appointment_id: &str,
+        format: CardFormat,
+    ) -> Result<String, String> {
+        let appointment = self
+            .appointments
+            .get(appointment_id)
+            .ok_or("Appointment not found")?;
+        Ok(crate::card::render_card(self, appointment, format))
+    }
+
+    /// Get every recorded change to an appointment, oldest first.
+    ///
+    /// History is kept after cancellation, so cancelled appointments can
+    /// still be looked up.
+    pub fn appointment_history(&self, appointment_id: &str) -> Vec<AppointmentChange> {
+        self.history.get(appointment_id).cloned().unwrap_or_default()
+    }
+
+    /// Get every change recorded on a day with the ID of the appointment
+    /// it was made to, oldest first.
+    pub fn changes_on(&self, date: NaiveDate) -> Vec<(String, AppointmentChange)> {
+        let mut changes: Vec<(String, AppointmentChange)> = self
+            .history
+            .iter()
+            .flat_map(|(appointment_id, changes)| {
+                changes
+                    .iter()
+                    .filter(|change| change.changed_at.date_naive() == date)
+                    .map(move |change| (appointment_id.clone(), change.clone()))
+            })
+            .collect();
+        changes.sort_by_key(|(_, change)| change.changed_at);
+        changes
     }
 
     /// Get all appointments on a specific date.
@@ -270,7 +2399,15 @@ impl DoctorCalendar {
             .collect()
     }
 
-    /// Get an appointment by its ID.
+    /// Find an appointment by its patient-facing confirmation code.
+    pub fn find_appointment_by_code(&self, confirmation_code: &str) -> Option<Appointment> {
+        let code = confirmation_code.trim();
+        self.active_appointments()
+            .find(|apt| apt.confirmation_code.eq_ignore_ascii_case(code))
+            .cloned()
+    }
+
+    /// Get an appointment by its ID, including a cancelled one.
     pub fn get_appointment_by_id(&self, appointment_id: &str) -> Option<Appointment> {
         self.appointments.get(appointment_id).cloned()
     }
@@ -283,7 +2420,7 @@ impl std::fmt::Display for DoctorCalendar {
             "DoctorCalendar({}, slots={}, appointments={})",
             self.doctor_name,
             self.time_slots.len(),
-            self.appointments.len()
+            self.active_appointments().count()
         )
     }
 }
@@ -0,0 +1,225 @@
+#![allow(dead_code)]
+//! Recall and screening campaigns.
+//!
+//! A campaign books a list of patients who are due for the same kind of
+//! visit (annual flu shots, diabetic eye checks) somewhere in a target
+//! period. Patients are spread evenly over the period's working days so
+//! they don't all compete for the first morning, then scheduled together
+//! as one batch. Everyone who gets a slot is notified individually.
+
+use crate::boundary;
+use crate::calendar::DoctorCalendar;
+use crate::import::split_csv_line;
+use crate::models::{Appointment, AppointmentRequest, DateWindow, Patient, Priority};
+use crate::notifications::{Notification, NotificationChannel};
+use crate::scheduler::{AppointmentScheduler, SchedulingResult};
+use crate::validation::ValidationErrors;
+use chrono::{Datelike, Local, NaiveDate, NaiveTime, Weekday};
+
+/// A batch of recall appointments to book within a period.
+#[derive(Debug, Clone)]
+pub struct RecallCampaign {
+    pub name: String,
+    pub reason: String,
+    pub priority: Priority,
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    /// Time each patient is aimed at on their assigned day.
+    pub preferred_time: NaiveTime,
+    pub patients: Vec<Patient>,
+}
+
+/// Outcome of running a campaign.
+#[derive(Debug, Clone, Default)]
+pub struct CampaignReport {
+    pub booked: Vec<Appointment>,
+    pub unscheduled: Vec<SchedulingResult>,
+    /// Patients notified of their booking.
+    pub notified: usize,
+    /// Notifications that could not be sent, by patient ID.
+    pub notification_errors: Vec<(String, String)>,
+}
+
+impl RecallCampaign {
+    /// Create a routine campaign aimed at 09:00 each day.
+    pub fn new(
+        name: String,
+        reason: String,
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        patients: Vec<Patient>,
+    ) -> Result<Self, String> {
+        if name.trim().is_empty() {
+            return Err("Campaign name cannot be empty".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("Appointment reason cannot be empty".to_string());
+        }
+        if last_day < first_day {
+            return Err("Campaign cannot end before it starts".to_string());
+        }
+
+        Ok(RecallCampaign {
+            name,
+            reason,
+            priority: Priority::Routine,
+            first_day,
+            last_day,
+            preferred_time: NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+            patients,
+        })
+    }
+
+    /// Get the weekdays in the campaign period.
+    pub fn working_days(&self) -> Vec<NaiveDate> {
+        self.first_day
+            .iter_days()
+            .take_while(|day| *day <= self.last_day)
+            .filter(|day| !matches!(day.weekday(), Weekday::Sat | Weekday::Sun))
+            .collect()
+    }
+
+    /// Build one request per patient, spread evenly over the working days.
+    ///
+    /// Each patient is aimed at a day of their own but will take any
+    /// weekday slot in the period.
+    pub fn requests(&self, calendar: &DoctorCalendar) -> Result<Vec<AppointmentRequest>, String> {
+        let days = self.working_days();
+        if days.is_empty() {
+            return Err("Campaign period has no working days".to_string());
+        }
+        let window = DateWindow::new(
+            self.first_day,
+            self.last_day,
+            vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            Vec::new(),
+        )?;
+
+        self.patients
+            .iter()
+            .enumerate()
+            .map(|(index, patient)| {
+                let day = days[index * days.len() / self.patients.len()];
+                let preferred_time = day
+                    .and_time(self.preferred_time)
+                    .and_local_timezone(Local)
+                    .earliest()
+                    .ok_or_else(|| format!("Invalid local time on {}", day))?;
+                let mut request = AppointmentRequest::new(
+                    calendar.id_generator(),
+                    calendar.now(),
+                    patient.clone(),
+                    self.priority,
+                    preferred_time,
+                    self.reason.clone(),
+                    0,
+                )
+                .map_err(String::from)?;
+                request.date_window = Some(window.clone());
+                Ok(request)
+            })
+            .collect()
+    }
+
+    /// Schedule every patient in one batch and notify those who were booked.
+    ///
+    /// Requests already queued are scheduled in the same batch, but only
+    /// the campaign's own bookings are reported and notified, even for a
+    /// patient who had another request waiting.
+    ///
+    /// A failed notification is recorded in the report and does not undo
+    /// the booking.
+    pub fn run(
+        &self,
+        scheduler: &mut AppointmentScheduler,
+        channel: &mut dyn NotificationChannel,
+    ) -> Result<CampaignReport, String> {
+        let requests = self.requests(&scheduler.calendar)?;
+        let request_ids: Vec<String> = requests.iter().map(|r| r.request_id.clone()).collect();
+        let result = scheduler.schedule_batch(requests);
+
+        let mut report = CampaignReport::default();
+        for appointment in result.confirmed {
+            let from_campaign = scheduler
+                .request_for_appointment(&appointment.appointment_id)
+                .is_some_and(|request| request_ids.contains(&request.request_id));
+            if !from_campaign {
+                continue;
+            }
+            match self.notify(&scheduler.calendar, channel, &appointment) {
+                Ok(()) => report.notified += 1,
+                Err(e) => report
+                    .notification_errors
+                    .push((appointment.patient.patient_id.clone(), e)),
+            }
+            report.booked.push(appointment);
+        }
+        report.unscheduled = result
+            .failed
+            .into_iter()
+            .filter(|failed| request_ids.contains(&failed.request.request_id))
+            .collect();
+
+        Ok(report)
+    }
+
+    fn notify(
+        &self,
+        calendar: &DoctorCalendar,
+        channel: &mut dyn NotificationChannel,
+        appointment: &Appointment,
+    ) -> Result<(), String> {
+        let start = appointment.time_slot.start_time;
+        let notification = Notification::new(
+            calendar.id_generator(),
+            calendar.now(),
+            appointment.patient.contact.clone(),
+            format!("{}: your appointment", self.name),
+            format!(
+                "Dear {}, your {} appointment is on {} at {} ({} minutes). \
+                 Confirmation code: {}",
+                appointment.patient.name,
+                self.reason,
+                start.format("%A, %Y-%m-%d"),
+                start.format("%H:%M"),
+                appointment.time_slot.duration_minutes(),
+                appointment.confirmation_code
+            ),
+        )?;
+        channel.send(&notification)
+    }
+}
+
+/// Read the patients due for recall from CSV with a
+/// `patient_id,patient_name,contact` header row.
+///
+/// Names and contacts are normalized, and overlong ones are refused.
+pub fn parse_patients_csv(text: &str) -> Result<Vec<Patient>, String> {
+    boundary::check_size(text, boundary::MAX_FILE_BYTES)?;
+    let mut patients = Vec::new();
+    for (index, line) in text.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+        let [patient_id, name, contact] = fields.as_slice() else {
+            return Err(format!(
+                "Line {}: Expected 3 columns, found {}",
+                index + 1,
+                fields.len()
+            ));
+        };
+        let mut errors = ValidationErrors::new();
+        match boundary::check_patient(patient_id, name, contact, &mut errors) {
+            Some(patient) if errors.is_empty() => patients.push(patient),
+            _ => return Err(format!("Line {}: {}", index + 1, errors)),
+        }
+    }
+    Ok(patients)
+}
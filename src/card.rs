@@ -0,0 +1,117 @@
+#![allow(dead_code)]
+//! Printable appointment cards handed to patients at checkout.
+//!
+//! A card is a compact summary of one appointment, rendered as plain text
+//! for printing or as an HTML fragment for emailing.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::Appointment;
+
+/// Output format of an appointment card.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardFormat {
+    Text,
+    Html,
+}
+
+impl CardFormat {
+    /// Convert a string to a CardFormat.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "text" | "txt" => Ok(CardFormat::Text),
+            "html" => Ok(CardFormat::Html),
+            _ => Err(format!("Invalid card format: {}", value)),
+        }
+    }
+}
+
+/// Render the card for one of a calendar's appointments.
+pub fn render_card(
+    calendar: &DoctorCalendar,
+    appointment: &Appointment,
+    format: CardFormat,
+) -> String {
+    let rows = card_rows(calendar, appointment);
+    let instructions = cancellation_instructions(calendar, appointment);
+
+    match format {
+        CardFormat::Text => {
+            let mut output = String::from("APPOINTMENT CARD\n");
+            output.push_str(&"-".repeat(32));
+            output.push('\n');
+            for (label, value) in &rows {
+                output.push_str(&format!("{:<10} {}\n", format!("{}:", label), value));
+            }
+            output.push_str(&"-".repeat(32));
+            output.push('\n');
+            output.push_str(&instructions);
+            output.push('\n');
+            output
+        }
+        CardFormat::Html => {
+            let mut output =
+                String::from("<div class=\"appointment-card\">\n<h2>Appointment Card</h2>\n<dl>\n");
+            for (label, value) in &rows {
+                output.push_str(&format!(
+                    "<dt>{}</dt><dd>{}</dd>\n",
+                    escape_html(label),
+                    escape_html(value)
+                ));
+            }
+            output.push_str("</dl>\n");
+            output.push_str(&format!("<p>{}</p>\n", escape_html(&instructions)));
+            output.push_str("</div>\n");
+            output
+        }
+    }
+}
+
+fn card_rows(calendar: &DoctorCalendar, appointment: &Appointment) -> Vec<(&'static str, String)> {
+    let start = appointment.time_slot.start_time;
+    let mut rows = vec![
+        ("Patient", appointment.patient.name.clone()),
+        ("Doctor", calendar.doctor_name.clone()),
+        ("Date", start.format("%A, %d %B %Y").to_string()),
+        (
+            "Time",
+            format!(
+                "{} - {}",
+                start.format("%H:%M"),
+                appointment.time_slot.end_time.format("%H:%M")
+            ),
+        ),
+    ];
+    if let Some(location) = &calendar.location {
+        rows.push(("Location", location.clone()));
+    }
+    rows.push(("Code", appointment.confirmation_code.clone()));
+    rows
+}
+
+fn cancellation_instructions(calendar: &DoctorCalendar, appointment: &Appointment) -> String {
+    let code = &appointment.confirmation_code;
+    match calendar.cancellation_policy {
+        Some(policy) => {
+            let fee = match policy.late_fee_cents {
+                Some(fee) => format!(" to avoid a {}.{:02} late fee", fee / 100, fee % 100),
+                None => String::new(),
+            };
+            format!(
+                "To cancel or reschedule, contact us at least {} hours before your appointment{} and quote code {}.",
+                policy.min_notice_hours, fee, code
+            )
+        }
+        None => format!(
+            "To cancel or reschedule, contact us and quote code {}.",
+            code
+        ),
+    }
+}
+
+/// Escape text for inclusion in HTML.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
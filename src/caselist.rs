@@ -0,0 +1,199 @@
+#![allow(dead_code)]
+//! Procedure days run as an ordered case list.
+//!
+//! On a theatre or procedure day cases don't fit fixed slots: they run one
+//! after another, each taking roughly its estimated time. A CaseList keeps
+//! the day's cases in running order and projects when each will start.
+//! The projection follows what has actually happened, so when a case
+//! finishes early or runs long, every case after it moves with it.
+
+use crate::models::{Patient, Priority};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// One case on a procedure list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Case {
+    pub case_id: String,
+    pub patient: Patient,
+    pub procedure: String,
+    pub priority: Priority,
+    pub estimated_minutes: i64,
+    #[serde(default)]
+    pub started_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub finished_at: Option<DateTime<Local>>,
+}
+
+impl Case {
+    /// Create a case that has not started yet.
+    pub fn new(
+        case_id: String,
+        patient: Patient,
+        procedure: String,
+        priority: Priority,
+        estimated_minutes: i64,
+    ) -> Result<Self, String> {
+        if procedure.trim().is_empty() {
+            return Err("Procedure cannot be empty".to_string());
+        }
+        if estimated_minutes <= 0 {
+            return Err("Estimated duration must be positive".to_string());
+        }
+        Ok(Case {
+            case_id,
+            patient,
+            procedure,
+            priority,
+            estimated_minutes,
+            started_at: None,
+            finished_at: None,
+        })
+    }
+}
+
+/// When a case is expected to run, or actually ran.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectedCase {
+    pub case_id: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub started: bool,
+    pub finished: bool,
+}
+
+/// A day's cases in the order they run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseList {
+    pub date: NaiveDate,
+    /// When the first case is due to start.
+    pub starts_at: DateTime<Local>,
+    cases: Vec<Case>,
+}
+
+impl CaseList {
+    /// Create an empty list for a day starting at `starts_at`.
+    pub fn new(starts_at: DateTime<Local>) -> Self {
+        CaseList {
+            date: starts_at.date_naive(),
+            starts_at,
+            cases: Vec::new(),
+        }
+    }
+
+    /// Get the cases in running order.
+    pub fn cases(&self) -> &[Case] {
+        &self.cases
+    }
+
+    /// Add a case to the end of the list.
+    pub fn add(&mut self, case: Case) {
+        self.cases.push(case);
+    }
+
+    /// Move a case that hasn't started to a new position in the list.
+    ///
+    /// Cases can't be moved ahead of one that has already started.
+    pub fn move_case(&mut self, case_id: &str, position: usize) -> Result<(), String> {
+        let index = self.index_of(case_id)?;
+        if self.cases[index].started_at.is_some() {
+            return Err("Case has already started".to_string());
+        }
+        let first_open = self
+            .cases
+            .iter()
+            .position(|case| case.started_at.is_none())
+            .unwrap_or(self.cases.len());
+        let case = self.cases.remove(index);
+        let position = position.clamp(first_open, self.cases.len());
+        self.cases.insert(position, case);
+        Ok(())
+    }
+
+    /// Take a case that hasn't started off the list.
+    pub fn remove(&mut self, case_id: &str) -> Result<Case, String> {
+        let index = self.index_of(case_id)?;
+        if self.cases[index].started_at.is_some() {
+            return Err("Case has already started".to_string());
+        }
+        Ok(self.cases.remove(index))
+    }
+
+    /// Record that a case has started.
+    ///
+    /// Cases start in list order, once the one before has finished.
+    pub fn start(&mut self, case_id: &str, now: DateTime<Local>) -> Result<&Case, String> {
+        let index = self.index_of(case_id)?;
+        if self.cases[index].started_at.is_some() {
+            return Err("Case has already started".to_string());
+        }
+        if index > 0 && self.cases[index - 1].finished_at.is_none() {
+            return Err(format!(
+                "Case {} has not finished yet",
+                self.cases[index - 1].case_id
+            ));
+        }
+        self.cases[index].started_at = Some(now);
+        Ok(&self.cases[index])
+    }
+
+    /// Record that a started case has finished.
+    pub fn finish(&mut self, case_id: &str, now: DateTime<Local>) -> Result<&Case, String> {
+        let index = self.index_of(case_id)?;
+        let case = &mut self.cases[index];
+        let Some(started_at) = case.started_at else {
+            return Err("Case has not started".to_string());
+        };
+        if case.finished_at.is_some() {
+            return Err("Case has already finished".to_string());
+        }
+        case.finished_at = Some(now.max(started_at));
+        Ok(&self.cases[index])
+    }
+
+    /// Project when each case runs, as of `now`.
+    ///
+    /// Finished cases keep their actual times. A case in progress is
+    /// expected to take its estimate, and no less than it has taken so far.
+    /// Each case after that starts when the one before it ends, but never
+    /// before `now` or the list's start time.
+    pub fn projected(&self, now: DateTime<Local>) -> Vec<ProjectedCase> {
+        let mut cursor = self.starts_at;
+        self.cases
+            .iter()
+            .map(|case| {
+                let estimate = Duration::minutes(case.estimated_minutes);
+                let (start, end) = match (case.started_at, case.finished_at) {
+                    (Some(start), Some(end)) => (start, end),
+                    (Some(start), None) => (start, (start + estimate).max(now)),
+                    _ => {
+                        let start = cursor.max(now);
+                        (start, start + estimate)
+                    }
+                };
+                cursor = end;
+                ProjectedCase {
+                    case_id: case.case_id.clone(),
+                    start,
+                    end,
+                    started: case.started_at.is_some(),
+                    finished: case.finished_at.is_some(),
+                }
+            })
+            .collect()
+    }
+
+    /// Project when the last case will finish, as of `now`.
+    pub fn projected_finish(&self, now: DateTime<Local>) -> DateTime<Local> {
+        self.projected(now)
+            .last()
+            .map_or(self.starts_at, |case| case.end)
+    }
+
+    fn index_of(&self, case_id: &str) -> Result<usize, String> {
+        self.cases
+            .iter()
+            .position(|case| case.case_id == case_id)
+            .ok_or_else(|| "Case not found".to_string())
+    }
+}
@@ -0,0 +1,180 @@
+#![allow(dead_code)]
+//! Claims on freed slots offered to several waitlisted patients at once.
+//!
+//! When a sought-after slot is cancelled, offering it to one patient at a
+//! time can leave it unfilled until it's too late. Instead it can be opened
+//! as a claim: the top waitlisted patients it fits are each sent a token,
+//! and the first to confirm gets the slot. The claim is resolved exactly
+//! once, so a confirmation that arrives after the slot has been taken is
+//! turned down, and every other invited patient is told the slot has gone.
+
+use crate::ids::IdGenerator;
+use crate::models::{Appointment, AppointmentRequest, Patient, TimeSlot};
+use crate::notifications::Notification;
+use chrono::{DateTime, Local};
+
+/// One waitlisted patient invited to claim a slot.
+#[derive(Debug, Clone)]
+pub struct ClaimInvite {
+    /// Code the patient confirms with.
+    pub token: String,
+    pub request: AppointmentRequest,
+}
+
+impl ClaimInvite {
+    /// Build the message inviting the patient to claim the slot.
+    pub fn to_notification(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        slot: &TimeSlot,
+    ) -> Result<Notification, String> {
+        Notification::new(
+            ids,
+            now,
+            self.request.patient.contact.clone(),
+            "An appointment is available".to_string(),
+            format!(
+                "Dear {}, a slot has opened on {} at {}. It has been offered to \
+                 several patients and goes to whoever confirms first. To take \
+                 it, reply with code {}.",
+                self.request.patient.name,
+                slot.start_time.format("%A, %Y-%m-%d"),
+                slot.start_time.format("%H:%M"),
+                self.token
+            ),
+        )
+    }
+
+    /// Build the message telling the patient someone else took the slot.
+    pub fn taken_notification(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        slot: &TimeSlot,
+    ) -> Result<Notification, String> {
+        Notification::new(
+            ids,
+            now,
+            self.request.patient.contact.clone(),
+            "The offered appointment has been taken".to_string(),
+            format!(
+                "Dear {}, the slot on {} at {} has been taken by another patient. \
+                 You are still on the waitlist.",
+                self.request.patient.name,
+                slot.start_time.format("%A, %Y-%m-%d"),
+                slot.start_time.format("%H:%M")
+            ),
+        )
+    }
+
+    /// Build the message telling the patient the slot could not be booked
+    /// after all.
+    pub fn withdrawn_notification(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        slot: &TimeSlot,
+    ) -> Result<Notification, String> {
+        Notification::new(
+            ids,
+            now,
+            self.request.patient.contact.clone(),
+            "The offered appointment is no longer available".to_string(),
+            format!(
+                "Dear {}, the slot on {} at {} is no longer available. \
+                 You are still on the waitlist.",
+                self.request.patient.name,
+                slot.start_time.format("%A, %Y-%m-%d"),
+                slot.start_time.format("%H:%M")
+            ),
+        )
+    }
+}
+
+/// A freed slot offered to several patients until one claims it.
+#[derive(Debug, Clone)]
+pub struct SlotClaim {
+    pub slot: TimeSlot,
+    pub invites: Vec<ClaimInvite>,
+    pub opened_at: DateTime<Local>,
+    /// Token of the invite that claimed the slot.
+    winner: Option<String>,
+}
+
+impl SlotClaim {
+    /// Open a claim on a slot, giving each request a fresh token from `ids`.
+    pub fn new(
+        ids: &dyn IdGenerator,
+        slot: TimeSlot,
+        requests: Vec<AppointmentRequest>,
+        opened_at: DateTime<Local>,
+    ) -> Self {
+        let invites = requests
+            .into_iter()
+            .map(|request| ClaimInvite {
+                token: ids.confirmation_code(opened_at),
+                request,
+            })
+            .collect();
+        SlotClaim {
+            slot,
+            invites,
+            opened_at,
+            winner: None,
+        }
+    }
+
+    /// Check whether the slot is still waiting to be claimed.
+    pub fn is_open(&self) -> bool {
+        self.winner.is_none()
+    }
+
+    /// Get the invite that claimed the slot, if any.
+    pub fn winner(&self) -> Option<&ClaimInvite> {
+        let token = self.winner.as_ref()?;
+        self.invite(token)
+    }
+
+    /// Find the invite with a token.
+    pub fn invite(&self, token: &str) -> Option<&ClaimInvite> {
+        self.invites
+            .iter()
+            .find(|invite| invite.token.eq_ignore_ascii_case(token.trim()))
+    }
+
+    /// Award the slot to the invite with `token`, unless it has already
+    /// gone to someone.
+    pub fn resolve(&mut self, token: &str) -> Result<&ClaimInvite, String> {
+        let token = self
+            .invite(token)
+            .map(|invite| invite.token.clone())
+            .ok_or("Unknown claim token")?;
+        match &self.winner {
+            Some(winner) if *winner == token => {
+                return Err("You have already claimed this slot".to_string())
+            }
+            Some(_) => return Err("The slot has already been taken".to_string()),
+            None => self.winner = Some(token.clone()),
+        }
+        Ok(self.invite(&token).unwrap())
+    }
+
+    /// Get the invites that did not win the slot.
+    pub fn others(&self) -> Vec<&ClaimInvite> {
+        self.invites
+            .iter()
+            .filter(|invite| Some(&invite.token) != self.winner.as_ref())
+            .collect()
+    }
+}
+
+/// Outcome of a patient claiming a slot first.
+#[derive(Debug, Clone)]
+pub struct ClaimResolution {
+    pub appointment: Appointment,
+    /// Other invited patients told the slot has gone.
+    pub informed: Vec<Patient>,
+    /// Other invited patients who could not be told, with the reason.
+    pub undelivered: Vec<(Patient, String)>,
+}
@@ -0,0 +1,253 @@
+#![allow(dead_code)]
+//! Scheduling across all the doctors of a clinic.
+//!
+//! Each doctor keeps their own scheduler and calendar. The clinic view
+//! merges their free slots into one availability list, so front-desk staff
+//! can answer "when is the next appointment with any GP?" without going
+//! through every doctor's calendar in turn. Requests can be scheduled with
+//! whichever doctor has the slot nearest the patient's preferred time, or
+//! with one doctor, in which case a failure lists the other doctors who
+//! could have taken the patient.
+
+use crate::models::{AppointmentRequest, TimeSlot};
+use crate::overrun::{overrun_stats, OverrunStats};
+use crate::scheduler::{AppointmentScheduler, SchedulingResult};
+use chrono::{DateTime, Local};
+use std::ops::RangeBounds;
+
+/// A free slot in one doctor's calendar.
+#[derive(Debug, Clone)]
+pub struct ClinicSlot {
+    pub doctor_id: String,
+    pub doctor_name: String,
+    pub specialty: Option<String>,
+    pub slot: TimeSlot,
+}
+
+/// Outcome of scheduling a request with the clinic.
+#[derive(Debug, Clone)]
+pub struct ClinicResult {
+    /// Doctor the request was booked with, or `None` if nobody had room.
+    pub doctor_id: Option<String>,
+    pub result: SchedulingResult,
+}
+
+/// The schedulers of every doctor in a clinic.
+#[derive(Default)]
+pub struct ClinicScheduler {
+    doctors: Vec<AppointmentScheduler>,
+}
+
+impl ClinicScheduler {
+    /// Create a clinic with no doctors.
+    pub fn new() -> Self {
+        ClinicScheduler::default()
+    }
+
+    /// Add a doctor's scheduler to the clinic.
+    pub fn add_doctor(&mut self, scheduler: AppointmentScheduler) -> Result<(), String> {
+        if self.doctor(&scheduler.calendar.doctor_id).is_some() {
+            return Err("Doctor is already part of the clinic".to_string());
+        }
+        self.doctors.push(scheduler);
+        Ok(())
+    }
+
+    /// Get every doctor's scheduler, in the order they were added.
+    pub fn doctors(&self) -> &[AppointmentScheduler] {
+        &self.doctors
+    }
+
+    /// Get a doctor's scheduler by doctor ID.
+    pub fn doctor(&self, doctor_id: &str) -> Option<&AppointmentScheduler> {
+        self.doctors
+            .iter()
+            .find(|scheduler| scheduler.calendar.doctor_id == doctor_id)
+    }
+
+    /// Get a doctor's scheduler by doctor ID for booking.
+    pub fn doctor_mut(&mut self, doctor_id: &str) -> Option<&mut AppointmentScheduler> {
+        self.doctors
+            .iter_mut()
+            .find(|scheduler| scheduler.calendar.doctor_id == doctor_id)
+    }
+
+    /// Merge every doctor's bookable free slots starting in `range`.
+    ///
+    /// With a specialty, only doctors with that specialty (ignoring case)
+    /// are included. Slots are sorted by start time, then doctor name.
+    pub fn aggregate_availability(
+        &self,
+        range: impl RangeBounds<DateTime<Local>>,
+        specialty: Option<&str>,
+    ) -> Vec<ClinicSlot> {
+        let mut slots: Vec<ClinicSlot> = self
+            .doctors
+            .iter()
+            .filter(|scheduler| has_specialty(scheduler, specialty))
+            .map(|scheduler| &scheduler.calendar)
+            .flat_map(|calendar| {
+                let now = calendar.now();
+                calendar
+                    .available_slots()
+                    .into_iter()
+                    .filter(move |slot| slot.is_released(now))
+                    .map(|slot| ClinicSlot {
+                        doctor_id: calendar.doctor_id.clone(),
+                        doctor_name: calendar.doctor_name.clone(),
+                        specialty: calendar.specialty.clone(),
+                        slot,
+                    })
+            })
+            .filter(|free| range.contains(&free.slot.start_time))
+            .collect();
+
+        slots.sort_by(|a, b| {
+            a.slot
+                .start_time
+                .cmp(&b.slot.start_time)
+                .then_with(|| a.doctor_name.cmp(&b.doctor_name))
+        });
+        slots
+    }
+
+    /// Work out visit overrun statistics for every doctor, by doctor and
+    /// appointment type.
+    pub fn overrun_stats(&self) -> Vec<OverrunStats> {
+        self.doctors
+            .iter()
+            .flat_map(|scheduler| overrun_stats(&scheduler.calendar))
+            .collect()
+    }
+
+    /// Find the free slot starting nearest to `preferred` with any doctor,
+    /// optionally of one specialty. Ties go to the earlier slot.
+    pub fn nearest_available(
+        &self,
+        preferred: DateTime<Local>,
+        specialty: Option<&str>,
+    ) -> Option<ClinicSlot> {
+        self.aggregate_availability(.., specialty)
+            .into_iter()
+            .min_by_key(|free| (free.slot.start_time - preferred).num_seconds().abs())
+    }
+
+    /// Schedule a request with the doctor whose slot for it starts nearest
+    /// the preferred time, optionally only among doctors of one specialty.
+    ///
+    /// Each doctor's own rules, strategy and fallback settings decide which
+    /// slot they would offer. Ties go to the earlier slot, then to the
+    /// doctor added first.
+    pub fn schedule_single(
+        &mut self,
+        request: AppointmentRequest,
+        specialty: Option<&str>,
+    ) -> ClinicResult {
+        let preferred = request.preferred_time;
+        let best = self
+            .doctors
+            .iter()
+            .enumerate()
+            .filter(|(_, scheduler)| has_specialty(scheduler, specialty))
+            .filter_map(|(index, scheduler)| {
+                scheduler.preview_slot(&request).map(|slot| {
+                    (
+                        (slot.start_time - preferred).num_seconds().abs(),
+                        slot.start_time,
+                        index,
+                    )
+                })
+            })
+            .min();
+
+        match best {
+            Some((_, _, index)) => {
+                let scheduler = &mut self.doctors[index];
+                ClinicResult {
+                    doctor_id: Some(scheduler.calendar.doctor_id.clone()),
+                    result: scheduler.schedule_single(request),
+                }
+            }
+            None => ClinicResult {
+                doctor_id: None,
+                result: SchedulingResult::failure(request, "No doctor has an available time slot"),
+            },
+        }
+    }
+
+    /// Schedule a request with one doctor.
+    ///
+    /// If the doctor has no slot for it, the result's remediation lists
+    /// the slot each other doctor (of the specialty, if given) would offer,
+    /// nearest the preferred time first.
+    pub fn schedule_with_doctor(
+        &mut self,
+        doctor_id: &str,
+        request: AppointmentRequest,
+        specialty: Option<&str>,
+    ) -> Result<SchedulingResult, String> {
+        let scheduler = self
+            .doctor_mut(doctor_id)
+            .ok_or_else(|| "Doctor not found".to_string())?;
+        let mut result = scheduler.schedule_single(request);
+        if let Some(remediation) = &mut result.remediation {
+            remediation.other_doctors = self.other_doctors(doctor_id, &result.request, specialty);
+        }
+        Ok(result)
+    }
+
+    /// Get the slot each doctor but one would book for a request, nearest
+    /// the preferred time first.
+    fn other_doctors(
+        &self,
+        doctor_id: &str,
+        request: &AppointmentRequest,
+        specialty: Option<&str>,
+    ) -> Vec<ClinicSlot> {
+        let mut slots: Vec<ClinicSlot> = self
+            .doctors
+            .iter()
+            .filter(|scheduler| scheduler.calendar.doctor_id != doctor_id)
+            .filter(|scheduler| has_specialty(scheduler, specialty))
+            .filter_map(|scheduler| {
+                let calendar = &scheduler.calendar;
+                scheduler.preview_slot(request).map(|slot| ClinicSlot {
+                    doctor_id: calendar.doctor_id.clone(),
+                    doctor_name: calendar.doctor_name.clone(),
+                    specialty: calendar.specialty.clone(),
+                    slot,
+                })
+            })
+            .collect();
+        slots.sort_by_key(|other| {
+            (other.slot.start_time - request.preferred_time)
+                .num_seconds()
+                .abs()
+        });
+        slots
+    }
+
+    /// Find the earliest free slot at or after `after` with any doctor,
+    /// optionally of one specialty.
+    pub fn next_available(
+        &self,
+        after: DateTime<Local>,
+        specialty: Option<&str>,
+    ) -> Option<ClinicSlot> {
+        self.aggregate_availability(after.., specialty)
+            .into_iter()
+            .next()
+    }
+}
+
+/// Check whether a doctor has the wanted specialty, ignoring case; any
+/// doctor matches when none is wanted.
+fn has_specialty(scheduler: &AppointmentScheduler, specialty: Option<&str>) -> bool {
+    specialty.is_none_or(|wanted| {
+        scheduler
+            .calendar
+            .specialty
+            .as_deref()
+            .is_some_and(|specialty| specialty.eq_ignore_ascii_case(wanted))
+    })
+}
@@ -0,0 +1,57 @@
+#![allow(dead_code)]
+//! Time source for the scheduling system.
+//!
+//! Components that depend on the current time ask a Clock instead of
+//! calling `Local::now()` directly, so tests and demos can pin or advance
+//! time deterministically.
+
+use chrono::{DateTime, Duration, Local};
+use std::sync::Mutex;
+
+/// Source of the current time.
+pub trait Clock: Send + Sync {
+    /// The current time.
+    fn now(&self) -> DateTime<Local>;
+}
+
+/// Clock that reads the system time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// Clock that only moves when told to.
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<DateTime<Local>>,
+}
+
+impl FixedClock {
+    /// Create a clock pinned at `now`.
+    pub fn new(now: DateTime<Local>) -> Self {
+        FixedClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: DateTime<Local>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Move the current time forward.
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap();
+        *now += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Local> {
+        *self.now.lock().unwrap()
+    }
+}
@@ -0,0 +1,120 @@
+#![allow(dead_code)]
+//! End-of-day closeout.
+//!
+//! Once the last patient has left, staff give every appointment of the day
+//! an outcome (completed or no-show), and the day is closed: its figures
+//! and billing export are produced, and the day is locked so the records
+//! that were billed can't drift afterwards. An administrator can unlock a
+//! closed day to correct it.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::Appointment;
+use crate::reports::billing_export;
+use chrono::NaiveDate;
+
+/// Final figures for a closed day.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub appointments: usize,
+    pub completed: usize,
+    pub no_shows: usize,
+    pub cancellations: usize,
+    pub late_cancellations: usize,
+    /// Appointment fees plus late-cancellation fees, in cents.
+    pub billed_cents: u64,
+}
+
+/// Outcome of closing a day.
+#[derive(Debug, Clone)]
+pub struct CloseoutReport {
+    pub stats: DayStats,
+    /// The day's billing CSV, as produced by `billing_export`.
+    pub billing_csv: String,
+}
+
+/// Get the day's appointments that have ended but have no outcome yet.
+pub fn awaiting_outcome(calendar: &DoctorCalendar, date: NaiveDate) -> Vec<Appointment> {
+    let now = calendar.now();
+    appointments_on(calendar, date)
+        .into_iter()
+        .filter(|apt| apt.time_slot.end_time <= now && !has_outcome(apt))
+        .collect()
+}
+
+/// Work out the day's figures as they stand.
+pub fn day_stats(calendar: &DoctorCalendar, date: NaiveDate) -> DayStats {
+    let appointments = appointments_on(calendar, date);
+    let cancellations: Vec<_> = calendar
+        .cancellations()
+        .iter()
+        .filter(|record| record.scheduled_start.date_naive() == date)
+        .collect();
+
+    let appointment_fees: u64 = appointments
+        .iter()
+        .map(|apt| calendar.fee_schedule.fee_for(apt.time_slot.tier) as u64)
+        .sum();
+    let cancellation_fees: u64 = cancellations
+        .iter()
+        .filter_map(|record| record.fee_cents)
+        .map(u64::from)
+        .sum();
+
+    DayStats {
+        date,
+        appointments: appointments.len(),
+        completed: appointments
+            .iter()
+            .filter(|apt| apt.completed_at.is_some())
+            .count(),
+        no_shows: appointments
+            .iter()
+            .filter(|apt| apt.no_show_at.is_some())
+            .count(),
+        cancellations: cancellations.len(),
+        late_cancellations: cancellations.iter().filter(|record| record.late).count(),
+        billed_cents: appointment_fees + cancellation_fees,
+    }
+}
+
+/// Close a day: produce its figures and billing export, then lock it.
+///
+/// Every appointment of the day must have ended and been given an outcome.
+pub fn close_day(calendar: &mut DoctorCalendar, date: NaiveDate) -> Result<CloseoutReport, String> {
+    if calendar.is_day_closed(date) {
+        return Err(format!("{} is already closed", date.format("%Y-%m-%d")));
+    }
+    let now = calendar.now();
+    let appointments = appointments_on(calendar, date);
+    let running = appointments
+        .iter()
+        .filter(|apt| apt.time_slot.end_time > now)
+        .count();
+    if running > 0 {
+        return Err(format!("{} appointments have not ended yet", running));
+    }
+    let open = appointments.iter().filter(|apt| !has_outcome(apt)).count();
+    if open > 0 {
+        return Err(format!("{} appointments still need an outcome", open));
+    }
+
+    let report = CloseoutReport {
+        stats: day_stats(calendar, date),
+        billing_csv: billing_export(calendar, Some(date)),
+    };
+    calendar.lock_day(date);
+    Ok(report)
+}
+
+fn appointments_on(calendar: &DoctorCalendar, date: NaiveDate) -> Vec<Appointment> {
+    calendar
+        .appointments()
+        .into_iter()
+        .filter(|apt| apt.time_slot.start_time.date_naive() == date)
+        .collect()
+}
+
+fn has_outcome(appointment: &Appointment) -> bool {
+    appointment.completed_at.is_some() || appointment.no_show_at.is_some()
+}
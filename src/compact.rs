@@ -0,0 +1,165 @@
+#![allow(dead_code)]
+//! Compact storage for large numbers of time slots.
+//!
+//! A TimeSlot carries a heap-allocated UUID string and three full
+//! `DateTime<Local>` values, which adds up for year-long calendars across
+//! many doctors. CompactSlots stores the same information as fixed-size
+//! records: a `u64` index into a table of 128-bit UUIDs, the start as UTC
+//! minutes since the Unix epoch and the length in minutes. On a 64-bit
+//! target a slot shrinks from about 116 bytes (struct plus string
+//! allocation) to 40. Conversion happens at the API boundary, so callers
+//! still see ordinary TimeSlot values.
+
+use crate::models::{SlotTier, TimeSlot};
+use chrono::{DateTime, Local, TimeZone, Utc};
+use uuid::Uuid;
+
+const AVAILABLE: u8 = 0b01;
+const PREMIUM: u8 = 0b10;
+
+/// One slot packed into a fixed-size record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactSlot {
+    /// Index of the slot's UUID in the owning CompactSlots.
+    pub id: u64,
+    /// Start as UTC minutes since the Unix epoch.
+    pub start_minute: i32,
+    pub duration_minutes: u16,
+    /// When the slot opens for booking, as UTC minutes since the Unix epoch.
+    pub bookable_from_minute: Option<i32>,
+    flags: u8,
+}
+
+impl CompactSlot {
+    /// Check whether the slot is unbooked.
+    pub fn is_available(&self) -> bool {
+        self.flags & AVAILABLE != 0
+    }
+
+    /// Get the slot's pricing tier.
+    pub fn tier(&self) -> SlotTier {
+        if self.flags & PREMIUM != 0 {
+            SlotTier::Premium
+        } else {
+            SlotTier::Standard
+        }
+    }
+
+    /// End as UTC minutes since the Unix epoch.
+    pub fn end_minute(&self) -> i32 {
+        self.start_minute + self.duration_minutes as i32
+    }
+}
+
+/// A set of slots in compact form, sorted by start time.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CompactSlots {
+    slots: Vec<CompactSlot>,
+    uuids: Vec<u128>,
+}
+
+impl CompactSlots {
+    /// Pack slots into compact form.
+    ///
+    /// Fails if a slot ID is not a UUID, a time is not on a whole minute,
+    /// or a slot is longer than `u16::MAX` minutes.
+    pub fn from_slots(slots: &[TimeSlot]) -> Result<Self, String> {
+        let mut compact = CompactSlots::default();
+        for slot in slots {
+            compact.push(slot)?;
+        }
+        compact.slots.sort_by_key(|slot| slot.start_minute);
+        Ok(compact)
+    }
+
+    /// Pack one slot, keeping the order of insertion.
+    fn push(&mut self, slot: &TimeSlot) -> Result<(), String> {
+        let uuid = Uuid::parse_str(&slot.slot_id)
+            .map_err(|_| format!("Slot ID is not a UUID: {}", slot.slot_id))?;
+        let start_minute = to_minutes(slot.start_time)?;
+        let duration_minutes = u16::try_from(to_minutes(slot.end_time)? - start_minute)
+            .map_err(|_| format!("Slot {} is too long to pack", slot.slot_id))?;
+        let bookable_from_minute = slot.bookable_from.map(to_minutes).transpose()?;
+
+        let mut flags = 0;
+        if slot.is_available {
+            flags |= AVAILABLE;
+        }
+        if slot.tier == SlotTier::Premium {
+            flags |= PREMIUM;
+        }
+
+        self.slots.push(CompactSlot {
+            id: self.uuids.len() as u64,
+            start_minute,
+            duration_minutes,
+            bookable_from_minute,
+            flags,
+        });
+        self.uuids.push(uuid.as_u128());
+        Ok(())
+    }
+
+    /// Get the number of slots.
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Check whether there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    /// Get the packed records, sorted by start time.
+    pub fn records(&self) -> &[CompactSlot] {
+        &self.slots
+    }
+
+    /// Unpack one record into a TimeSlot.
+    pub fn unpack(&self, slot: &CompactSlot) -> TimeSlot {
+        TimeSlot {
+            start_time: from_minutes(slot.start_minute),
+            end_time: from_minutes(slot.end_minute()),
+            is_available: slot.is_available(),
+            slot_id: Uuid::from_u128(self.uuids[slot.id as usize]).to_string(),
+            tier: slot.tier(),
+            bookable_from: slot.bookable_from_minute.map(from_minutes),
+        }
+    }
+
+    /// Unpack every slot, sorted by start time.
+    pub fn to_slots(&self) -> Vec<TimeSlot> {
+        self.slots.iter().map(|slot| self.unpack(slot)).collect()
+    }
+
+    /// Bytes held by the records and UUID table, excluding spare capacity.
+    pub fn memory_bytes(&self) -> usize {
+        self.slots.len() * std::mem::size_of::<CompactSlot>()
+            + self.uuids.len() * std::mem::size_of::<u128>()
+    }
+}
+
+/// Estimate the bytes held by slots stored as TimeSlot values.
+pub fn time_slot_memory_bytes(slots: &[TimeSlot]) -> usize {
+    slots
+        .iter()
+        .map(|slot| std::mem::size_of::<TimeSlot>() + slot.slot_id.capacity())
+        .sum()
+}
+
+fn to_minutes(time: DateTime<Local>) -> Result<i32, String> {
+    let seconds = time.timestamp();
+    if seconds % 60 != 0 {
+        return Err(format!(
+            "Time is not on a whole minute: {}",
+            time.format("%Y-%m-%d %H:%M:%S")
+        ));
+    }
+    i32::try_from(seconds / 60).map_err(|_| "Time is out of range".to_string())
+}
+
+fn from_minutes(minutes: i32) -> DateTime<Local> {
+    Utc.timestamp_opt(minutes as i64 * 60, 0)
+        .unwrap()
+        .with_timezone(&Local)
+}
@@ -0,0 +1,261 @@
+#![allow(dead_code)]
+//! Clinic configuration for provisioning new sites.
+//!
+//! A ClinicConfig holds everything needed to set up a scheduler the way an
+//! existing site runs it: the doctor's settings and slot templates, the
+//! scheduling policy, business rules and visit templates, and
+//! notification settings. It
+//! never contains patient data, slots, appointments or queued requests, so
+//! a golden configuration can be shared between sites. Request validators
+//! are code rather than settings and have to be registered after import.
+
+use crate::alerts::{CapacityMonitor, QueueMonitor};
+use crate::bundle::BundleCancellation;
+use crate::calendar::{DoctorCalendar, SlotTemplate};
+use crate::clock::SystemClock;
+use crate::ids::{id_generator_by_name, UuidIds};
+use crate::labels::LabelScheme;
+use crate::lateness::LateArrivalPolicy;
+use crate::models::{CancellationPolicy, FeeSchedule, RequestTemplate};
+use crate::notifications::RetryPolicy;
+use crate::preferences::DoctorPreferences;
+use crate::rules::RuleSet;
+use crate::scheduler::{AppointmentScheduler, BatchMode, ProcessingMode, ProcessingPolicy};
+use crate::strategy::strategy_by_name;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::Arc;
+
+/// The doctor's calendar settings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DoctorConfig {
+    pub doctor_name: String,
+    pub default_slot_duration: i64,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub specialty: Option<String>,
+    #[serde(default)]
+    pub cancellation_policy: Option<CancellationPolicy>,
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    #[serde(default)]
+    pub templates: Vec<SlotTemplate>,
+    #[serde(default)]
+    pub labels: LabelScheme,
+    #[serde(default)]
+    pub lock_past_days: bool,
+    #[serde(default)]
+    pub require_confirmation: bool,
+    #[serde(default)]
+    pub bundle_cancellation: BundleCancellation,
+    /// Scheduling preferences, one per entry in the `DoctorPreferences::parse` syntax.
+    #[serde(default)]
+    pub preferences: Vec<String>,
+    /// Weeks of slots to keep generated from the templates ahead of today.
+    #[serde(default)]
+    pub horizon_weeks: Option<u32>,
+    /// Format of new record IDs: `uuid`, `ulid`, `sequential` or `seeded:<seed>`.
+    #[serde(default)]
+    pub id_format: Option<String>,
+}
+
+/// How requests are scheduled.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchedulingConfig {
+    pub allow_fallback: bool,
+    #[serde(default)]
+    pub snap_to_grid: bool,
+    /// Name of the slot strategy, e.g. `nearest-preferred` or `compact-day`.
+    pub strategy: String,
+    pub emergency: ProcessingMode,
+    pub urgent: ProcessingMode,
+    pub routine: ProcessingMode,
+    #[serde(default)]
+    pub batch_interval_minutes: Option<i64>,
+    #[serde(default)]
+    pub batch_mode: BatchMode,
+    /// Business rules, one per entry in the `RuleSet::parse` syntax.
+    #[serde(default)]
+    pub rules: Vec<String>,
+    /// Weekly booking quotas by referral partner.
+    #[serde(default)]
+    pub referral_quotas: BTreeMap<String, usize>,
+    /// Defaults for common kinds of visit, selectable by name.
+    #[serde(default)]
+    pub request_templates: Vec<RequestTemplate>,
+    #[serde(default)]
+    pub late_arrival: Option<LateArrivalPolicy>,
+}
+
+/// Delivery retries and operational alert thresholds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    pub retry: RetryPolicy,
+    pub capacity: CapacityMonitor,
+    pub queue: QueueMonitor,
+}
+
+/// A site's full configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClinicConfig {
+    pub doctor: DoctorConfig,
+    pub scheduling: SchedulingConfig,
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+}
+
+impl ClinicConfig {
+    /// Encode the configuration as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to encode configuration: {}", e))
+    }
+
+    /// Decode a configuration produced by `to_json`.
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| format!("Failed to decode configuration: {}", e))
+    }
+
+    /// Write the configuration to a file as JSON.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        fs::write(path, self.to_json()?).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    /// Load a configuration from a JSON file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        ClinicConfig::from_json(&text)
+    }
+}
+
+/// Capture a scheduler's configuration, leaving out all patient data.
+pub fn export_config(
+    scheduler: &AppointmentScheduler,
+    notifications: NotificationSettings,
+) -> ClinicConfig {
+    let calendar = &scheduler.calendar;
+    let policy = &scheduler.processing_policy;
+
+    ClinicConfig {
+        doctor: DoctorConfig {
+            doctor_name: calendar.doctor_name.clone(),
+            default_slot_duration: calendar.default_slot_duration,
+            location: calendar.location.clone(),
+            specialty: calendar.specialty.clone(),
+            cancellation_policy: calendar.cancellation_policy,
+            fee_schedule: calendar.fee_schedule,
+            templates: calendar.templates.clone(),
+            labels: calendar.labels.clone(),
+            lock_past_days: calendar.lock_past_days,
+            require_confirmation: calendar.require_confirmation,
+            bundle_cancellation: calendar.bundle_cancellation,
+            preferences: calendar.preferences.to_lines(),
+            horizon_weeks: calendar.horizon_weeks,
+            id_format: Some(calendar.id_generator().name().to_string()),
+        },
+        scheduling: SchedulingConfig {
+            allow_fallback: scheduler.allow_fallback,
+            snap_to_grid: scheduler.snap_to_grid,
+            strategy: scheduler.strategy().name().to_string(),
+            emergency: policy.emergency,
+            urgent: policy.urgent,
+            routine: policy.routine,
+            batch_interval_minutes: policy.batch_interval.map(|interval| interval.num_minutes()),
+            batch_mode: scheduler.batch_mode,
+            rules: scheduler
+                .rules
+                .rules()
+                .iter()
+                .map(|rule| rule.to_string())
+                .collect(),
+            referral_quotas: scheduler
+                .referral_quotas()
+                .iter()
+                .map(|(referrer, quota)| (referrer.clone(), *quota))
+                .collect(),
+            request_templates: scheduler.request_templates().to_vec(),
+            late_arrival: scheduler.late_arrival_policy,
+        },
+        notifications,
+    }
+}
+
+/// Build a scheduler with an empty calendar from a configuration.
+///
+/// The new calendar gets a fresh doctor ID and feed token. When the doctor
+/// has a slot horizon, its weeks are generated from the templates straight
+/// away; otherwise no slots are generated.
+pub fn import_config(config: &ClinicConfig) -> Result<AppointmentScheduler, String> {
+    let doctor = &config.doctor;
+    let scheduling = &config.scheduling;
+    let notifications = &config.notifications;
+    CapacityMonitor::new(
+        notifications.capacity.horizon_days,
+        notifications.capacity.min_free_slots,
+    )?;
+    QueueMonitor::new(
+        notifications.queue.max_pending,
+        notifications.queue.max_routine_age_minutes,
+    )?;
+
+    let ids = match &doctor.id_format {
+        Some(format) => id_generator_by_name(format)?,
+        None => Arc::new(UuidIds),
+    };
+    let mut calendar = DoctorCalendar::with_generators(
+        doctor.doctor_name.clone(),
+        doctor.default_slot_duration,
+        Arc::new(SystemClock),
+        ids,
+    )?;
+    calendar.location = doctor.location.clone();
+    calendar.specialty = doctor.specialty.clone();
+    calendar.cancellation_policy = doctor.cancellation_policy;
+    calendar.fee_schedule = doctor.fee_schedule;
+    calendar.templates = doctor.templates.clone();
+    calendar.labels = doctor.labels.clone();
+    calendar.lock_past_days = doctor.lock_past_days;
+    calendar.require_confirmation = doctor.require_confirmation;
+    calendar.bundle_cancellation = doctor.bundle_cancellation;
+    calendar.preferences = DoctorPreferences::from_lines(&doctor.preferences)?;
+    calendar.horizon_weeks = doctor.horizon_weeks;
+    calendar.extend_horizon();
+
+    let batch_interval = match scheduling.batch_interval_minutes {
+        Some(minutes) if minutes <= 0 => {
+            return Err("Batch interval must be positive".to_string());
+        }
+        minutes => minutes.map(Duration::minutes),
+    };
+
+    let mut scheduler = AppointmentScheduler::new(calendar, scheduling.allow_fallback);
+    scheduler.snap_to_grid = scheduling.snap_to_grid;
+    scheduler.batch_mode = scheduling.batch_mode;
+    scheduler.set_strategy(strategy_by_name(&scheduling.strategy)?);
+    scheduler.processing_policy = ProcessingPolicy {
+        emergency: scheduling.emergency,
+        urgent: scheduling.urgent,
+        routine: scheduling.routine,
+        batch_interval,
+    };
+    scheduler.rules = RuleSet::parse(&scheduling.rules.join("\n"))?;
+    for (referrer, quota) in &scheduling.referral_quotas {
+        scheduler.set_referral_quota(referrer, *quota);
+    }
+    for template in &scheduling.request_templates {
+        scheduler.add_request_template(template.clone());
+    }
+    if let Some(policy) = scheduling.late_arrival {
+        scheduler.late_arrival_policy = Some(LateArrivalPolicy::new(
+            policy.grace_minutes,
+            policy.action,
+            policy.release_to_waitlist,
+        )?);
+    }
+
+    Ok(scheduler)
+}
@@ -0,0 +1,51 @@
+#![allow(dead_code)]
+//! Desktop notifications (enabled with the `desktop` feature).
+//!
+//! DesktopChannel shows notifications as native desktop pop-ups on the
+//! machine running the scheduler, e.g. the reception PC. Combine it with
+//! `alerts::imminent_appointments` and `alerts::emergency_booking_alert`
+//! to announce upcoming patients and new emergencies.
+
+use crate::notifications::{Notification, NotificationChannel};
+
+/// Channel that shows each notification as a desktop pop-up.
+///
+/// The recipient is ignored; the pop-up appears for whoever is logged in.
+#[derive(Debug, Clone)]
+pub struct DesktopChannel {
+    pub app_name: String,
+    /// How long the pop-up stays visible; `None` uses the desktop default.
+    pub timeout_ms: Option<u32>,
+}
+
+impl DesktopChannel {
+    /// Create a channel that labels pop-ups with `app_name`.
+    pub fn new(app_name: &str) -> Self {
+        DesktopChannel {
+            app_name: app_name.to_string(),
+            timeout_ms: None,
+        }
+    }
+}
+
+impl NotificationChannel for DesktopChannel {
+    fn name(&self) -> &str {
+        "desktop"
+    }
+
+    fn send(&mut self, notification: &Notification) -> Result<(), String> {
+        let mut popup = notify_rust::Notification::new();
+        popup
+            .appname(&self.app_name)
+            .summary(&notification.subject)
+            .body(&notification.body);
+        if let Some(timeout) = self.timeout_ms {
+            popup.timeout(notify_rust::Timeout::Milliseconds(timeout));
+        }
+
+        popup
+            .show()
+            .map(|_| ())
+            .map_err(|e| format!("Failed to show desktop notification: {}", e))
+    }
+}
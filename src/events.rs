@@ -0,0 +1,89 @@
+#![allow(dead_code)]
+//! Domain events emitted by the calendar.
+//!
+//! Every change to slot availability or bookings produces a CalendarEvent,
+//! which is broadcast to subscribers so front-desk screens can stay in sync
+//! without polling.
+//!
+//! Events are also appended to the calendar's outbox as part of the same
+//! mutation, and an EventPublisher later publishes them in order. Since
+//! the outbox is stored with the calendar state, a booking can never be
+//! persisted without the event that announces it.
+
+use crate::models::{Appointment, AppointmentStatus, TimeSlot};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// A change to a doctor's calendar.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CalendarEvent {
+    SlotAdded { slot: TimeSlot },
+    SlotRemoved { slot_id: String },
+    AppointmentBooked { appointment: Appointment },
+    AppointmentCancelled { appointment_id: String, slot_id: String },
+    AppointmentRescheduled {
+        previous_slot_id: String,
+        appointment: Appointment,
+    },
+    /// Emitted by both calendars when a booking moves between doctors.
+    AppointmentReassigned {
+        from_doctor_id: String,
+        to_doctor_id: String,
+        appointment: Appointment,
+    },
+    /// Emitted when an appointment is confirmed, checked in, completed or
+    /// marked as a no-show.
+    AppointmentStatusChanged {
+        previous_status: AppointmentStatus,
+        appointment: Appointment,
+    },
+}
+
+impl CalendarEvent {
+    /// Event name used on the wire.
+    pub fn name(&self) -> &str {
+        match self {
+            CalendarEvent::SlotAdded { .. } => "slot_added",
+            CalendarEvent::SlotRemoved { .. } => "slot_removed",
+            CalendarEvent::AppointmentBooked { .. } => "appointment_booked",
+            CalendarEvent::AppointmentCancelled { .. } => "appointment_cancelled",
+            CalendarEvent::AppointmentRescheduled { .. } => "appointment_rescheduled",
+            CalendarEvent::AppointmentReassigned { .. } => "appointment_reassigned",
+            CalendarEvent::AppointmentStatusChanged { .. } => "appointment_status_changed",
+        }
+    }
+
+    /// Encode the event as a Server-Sent Events frame.
+    pub fn to_sse(&self) -> String {
+        let data = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+        format!("event: {}\ndata: {}\n\n", self.name(), data)
+    }
+}
+
+/// An event waiting in the outbox for publication.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutboxEntry {
+    pub sequence: u64,
+    pub occurred_at: DateTime<Local>,
+    pub event: CalendarEvent,
+}
+
+/// Destination for outbox entries (webhook, message queue, ...).
+pub trait EventPublisher {
+    /// Publish one entry; an error leaves it unpublished for a later attempt.
+    fn publish(&mut self, entry: &OutboxEntry) -> Result<(), String>;
+}
+
+/// Publisher that records every entry in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryPublisher {
+    pub published: Vec<OutboxEntry>,
+}
+
+impl EventPublisher for InMemoryPublisher {
+    fn publish(&mut self, entry: &OutboxEntry) -> Result<(), String> {
+        self.published.push(entry.clone());
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+#![allow(dead_code)]
+//! Standby "fill my day" planning.
+//!
+//! When a day still has free slots, patients on the waitlist can often be
+//! brought in on short notice. A fill plan pairs waitlisted requests with
+//! the day's remaining slots, filling as many slots as possible without
+//! ever leaving a more urgent patient out in favour of a less urgent one.
+//! Nothing is booked until staff have reviewed the plan, dropped or moved
+//! proposals as they see fit, and committed it.
+
+use crate::models::{Appointment, AppointmentRequest, TimeSlot};
+use chrono::NaiveDate;
+
+/// A waitlisted request proposed for one of the day's free slots.
+#[derive(Debug, Clone)]
+pub struct FillProposal {
+    pub request: AppointmentRequest,
+    pub slot: TimeSlot,
+}
+
+/// Proposed use of a day's free slots, open for review before committing.
+#[derive(Debug, Clone)]
+pub struct FillPlan {
+    pub date: NaiveDate,
+    /// Proposals by slot start time.
+    pub proposals: Vec<FillProposal>,
+    /// Free slots the plan leaves empty.
+    pub open_slots: Vec<TimeSlot>,
+    /// Waitlisted requests the plan does not place.
+    pub unplaced: Vec<AppointmentRequest>,
+}
+
+impl FillPlan {
+    /// Drop a proposal, returning its slot to the open slots.
+    pub fn drop_proposal(&mut self, request_id: &str) -> bool {
+        let Some(index) = self.position(request_id) else {
+            return false;
+        };
+        let proposal = self.proposals.remove(index);
+        self.open_slots.push(proposal.slot);
+        self.open_slots.sort_by_key(|slot| slot.start_time);
+        self.unplaced.push(proposal.request);
+        true
+    }
+
+    /// Move a proposal to one of the open slots.
+    ///
+    /// The patient must be willing to take the new slot.
+    pub fn move_proposal(&mut self, request_id: &str, slot_id: &str) -> Result<(), String> {
+        let index = self.position(request_id).ok_or("Proposal not found")?;
+        let slot_index = self
+            .open_slots
+            .iter()
+            .position(|slot| slot.slot_id == slot_id)
+            .ok_or("Time slot is not open in this plan")?;
+        let request = &self.proposals[index].request;
+        if !fits(request, &self.open_slots[slot_index]) {
+            return Err("Patient does not accept that slot".to_string());
+        }
+
+        let slot = self.open_slots.remove(slot_index);
+        let previous = std::mem::replace(&mut self.proposals[index].slot, slot);
+        self.open_slots.push(previous);
+        self.open_slots.sort_by_key(|slot| slot.start_time);
+        self.proposals.sort_by_key(|proposal| proposal.slot.start_time);
+        Ok(())
+    }
+
+    fn position(&self, request_id: &str) -> Option<usize> {
+        self.proposals
+            .iter()
+            .position(|proposal| proposal.request.request_id == request_id)
+    }
+}
+
+/// Outcome of committing a fill plan.
+#[derive(Debug, Clone, Default)]
+pub struct FillReport {
+    pub booked: Vec<Appointment>,
+    /// Proposals that could no longer be booked, with the reason.
+    pub failed: Vec<(FillProposal, String)>,
+}
+
+/// Check whether a waitlisted patient would take a slot: it must suit the
+/// request and fall inside its date window, if it has one.
+pub fn fits(request: &AppointmentRequest, slot: &TimeSlot) -> bool {
+    request.accepts_slot(slot)
+        && request
+            .date_window
+            .as_ref()
+            .is_none_or(|window| window.contains(slot))
+}
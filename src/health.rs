@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+//! Health and readiness checks for a long-running service.
+//!
+//! A server or daemon built on the library answers liveness and readiness
+//! probes, such as Kubernetes' `/healthz` and `/readyz` or a systemd
+//! watchdog, from a HealthReport: whether the store and the request queue
+//! can be reached, how much work is waiting, and when the calendar was last
+//! saved. The report serializes to JSON for the probe's response body.
+
+use crate::notifications::DeliveryQueue;
+use crate::scheduler::AppointmentScheduler;
+use crate::store::CalendarStore;
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+
+/// A service's state at one moment, as of the calendar's clock.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthReport {
+    pub doctor_id: String,
+    pub checked_at: DateTime<Local>,
+    /// Why the store could not be reached, if it couldn't.
+    pub store_error: Option<String>,
+    /// Why the request queue could not be read, if it couldn't.
+    pub queue_error: Option<String>,
+    /// Requests waiting for the next batch run; 0 if the queue couldn't
+    /// be read.
+    pub requests_queued: usize,
+    pub notifications_pending: usize,
+    pub notifications_dead: usize,
+    /// Events emitted since the calendar was last saved with its log.
+    pub events_unsaved: usize,
+    pub last_saved_at: Option<DateTime<Local>>,
+}
+
+impl HealthReport {
+    /// Check whether the service can take work: its store and request
+    /// queue can both be reached.
+    pub fn is_ready(&self) -> bool {
+        self.store_error.is_none() && self.queue_error.is_none()
+    }
+
+    /// Check whether changes have gone unsaved for longer than `max_age`.
+    ///
+    /// A calendar with nothing to save is never overdue.
+    pub fn is_save_overdue(&self, max_age: Duration) -> bool {
+        self.events_unsaved > 0
+            && self
+                .last_saved_at
+                .is_none_or(|saved| self.checked_at - saved > max_age)
+    }
+
+    /// Check whether the service is ready and saving its changes.
+    pub fn is_healthy(&self, max_age: Duration) -> bool {
+        self.is_ready() && !self.is_save_overdue(max_age)
+    }
+
+    /// Get the HTTP status for a readiness probe: 200 or 503.
+    pub fn readiness_status(&self) -> u16 {
+        if self.is_ready() {
+            200
+        } else {
+            503
+        }
+    }
+
+    /// Get the HTTP status for a liveness probe: 200 or 503.
+    pub fn health_status(&self, max_age: Duration) -> u16 {
+        if self.is_healthy(max_age) {
+            200
+        } else {
+            503
+        }
+    }
+}
+
+/// Check the store, the request queue and the notification queue.
+///
+/// Nothing is changed; a probe can call this as often as it likes.
+pub fn check_health(
+    scheduler: &AppointmentScheduler,
+    store: &dyn CalendarStore,
+    deliveries: &DeliveryQueue,
+) -> HealthReport {
+    let calendar = &scheduler.calendar;
+    let queue_depth = scheduler.queue_depth();
+    HealthReport {
+        doctor_id: calendar.doctor_id.clone(),
+        checked_at: calendar.now(),
+        store_error: store.ping().err(),
+        requests_queued: queue_depth.clone().unwrap_or(0),
+        queue_error: queue_depth.err(),
+        notifications_pending: deliveries.pending().len(),
+        notifications_dead: deliveries.dead_letters().len(),
+        events_unsaved: calendar.unlogged_event_count(),
+        last_saved_at: calendar.last_saved_at(),
+    }
+}
@@ -0,0 +1,333 @@
+#![allow(dead_code)]
+//! iCalendar (RFC 5545) export of a doctor's appointments, and import of
+//! their busy times from other calendars.
+//!
+//! The feed is regenerated from the calendar's current appointments every
+//! time it is rendered, so subscribers always see the live schedule. Day
+//! notes are included as all-day events that don't block time.
+//!
+//! Importing reads the events of another feed, such as a doctor's hospital
+//! or personal calendar, as busy times. Transparent and cancelled events
+//! don't block time, and neither do events from this system's own feeds.
+//! Times with a TZID are read as local time, and recurring events block
+//! their first occurrence only.
+
+use crate::boundary;
+use crate::calendar::DoctorCalendar;
+use crate::labels::LabelScheme;
+use crate::models::Appointment;
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+const PRODUCT_ID: &str = "-//EasyAppoint//Appointment Feed//EN";
+
+/// A commitment from an external calendar during which no slots are opened.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BusyTime {
+    pub uid: String,
+    pub summary: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+impl BusyTime {
+    /// Check whether the commitment overlaps `[start, end)`.
+    pub fn overlaps(&self, start: DateTime<Local>, end: DateTime<Local>) -> bool {
+        self.start < end && start < self.end
+    }
+}
+
+/// Render all of a calendar's appointments as an ICS document.
+pub fn render_calendar(calendar: &DoctorCalendar) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape_text(&calendar.doctor_name)),
+    ];
+
+    let stamp = format_utc(&calendar.now());
+    for appointment in calendar.appointments() {
+        lines.extend(render_event(
+            appointment,
+            &calendar.doctor_name,
+            &calendar.labels,
+            &stamp,
+        ));
+    }
+    for (date, notes) in calendar.all_day_notes() {
+        for (index, note) in notes.iter().enumerate() {
+            let uid = format!(
+                "note-{}-{}-{}",
+                date.format("%Y%m%d"),
+                index,
+                calendar.doctor_id
+            );
+            lines.extend(render_day_note(&uid, date, note, &stamp));
+        }
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut output = String::new();
+    for line in lines {
+        output.push_str(&fold_line(&line));
+        output.push_str("\r\n");
+    }
+    output
+}
+
+fn render_event(
+    appointment: Appointment,
+    doctor_name: &str,
+    labels: &LabelScheme,
+    stamp: &str,
+) -> Vec<String> {
+    let categories: Vec<String> = labels
+        .categories(doctor_name, appointment.priority)
+        .iter()
+        .map(|category| escape_text(category))
+        .collect();
+
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@easyappoint", appointment.appointment_id),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART:{}", format_utc(&appointment.time_slot.start_time)),
+        format!("DTEND:{}", format_utc(&appointment.time_slot.end_time)),
+        format!(
+            "SUMMARY:{}",
+            escape_text(&format!("{} - {}", appointment.patient.name, appointment.reason))
+        ),
+        format!("DESCRIPTION:Priority: {}", appointment.priority.name()),
+        format!("CATEGORIES:{}", categories.join(",")),
+    ];
+    if let Some(color) = labels.color(appointment.priority) {
+        lines.push(format!("COLOR:{}", color.name()));
+    }
+    lines.push("STATUS:CONFIRMED".to_string());
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+fn render_day_note(uid: &str, date: NaiveDate, note: &str, stamp: &str) -> Vec<String> {
+    vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@easyappoint", uid),
+        format!("DTSTAMP:{}", stamp),
+        format!("DTSTART;VALUE=DATE:{}", date.format("%Y%m%d")),
+        format!(
+            "DTEND;VALUE=DATE:{}",
+            (date + Duration::days(1)).format("%Y%m%d")
+        ),
+        format!("SUMMARY:{}", escape_text(note)),
+        "TRANSP:TRANSPARENT".to_string(),
+        "END:VEVENT".to_string(),
+    ]
+}
+
+fn format_utc(dt: &DateTime<Local>) -> String {
+    dt.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escape a value for use in an ICS TEXT property.
+fn escape_text(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Fold a content line to the 75-octet limit required by RFC 5545.
+fn fold_line(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+
+    for ch in line.chars() {
+        let len = ch.len_utf8();
+        if width + len > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(ch);
+        width += len;
+    }
+
+    folded
+}
+
+/// Read the busy times from an ICS document, in start time order.
+///
+/// Documents over the file size limit are refused, and summaries are
+/// normalized and cut to the text limit.
+pub fn parse_busy_times(text: &str) -> Result<Vec<BusyTime>, String> {
+    boundary::check_size(text, boundary::MAX_FILE_BYTES)?;
+    let mut busy = Vec::new();
+    let mut event: Option<Vec<(String, String)>> = None;
+
+    for line in unfold_lines(text) {
+        match line.as_str() {
+            "BEGIN:VEVENT" => event = Some(Vec::new()),
+            "END:VEVENT" => {
+                let properties = event.take().ok_or("END:VEVENT without BEGIN:VEVENT")?;
+                if let Some(time) = busy_time(&properties)? {
+                    busy.push(time);
+                }
+            }
+            _ => {
+                if let (Some(properties), Some((name, value))) =
+                    (event.as_mut(), line.split_once(':'))
+                {
+                    properties.push((name.to_string(), value.to_string()));
+                }
+            }
+        }
+    }
+    if event.is_some() {
+        return Err("Unterminated VEVENT".to_string());
+    }
+
+    busy.sort_by_key(|time| time.start);
+    Ok(busy)
+}
+
+/// Turn one event's properties into a busy time, or `None` if it doesn't
+/// block time.
+fn busy_time(properties: &[(String, String)]) -> Result<Option<BusyTime>, String> {
+    let property = |wanted: &str| {
+        properties.iter().find(|(name, _)| {
+            name.split(';')
+                .next()
+                .is_some_and(|name| name.eq_ignore_ascii_case(wanted))
+        })
+    };
+    let value = |wanted: &str| property(wanted).map(|(_, value)| value.trim());
+
+    let uid = value("UID").unwrap_or_default().to_string();
+    if uid.ends_with("@easyappoint")
+        || value("TRANSP").is_some_and(|transp| transp.eq_ignore_ascii_case("TRANSPARENT"))
+        || value("STATUS").is_some_and(|status| status.eq_ignore_ascii_case("CANCELLED"))
+    {
+        return Ok(None);
+    }
+
+    let (name, start) =
+        property("DTSTART").ok_or_else(|| format!("Event {} has no DTSTART", uid))?;
+    let (start, all_day) = parse_date_time(name, start)?;
+    let end = if let Some((name, end)) = property("DTEND") {
+        parse_date_time(name, end)?.0
+    } else if let Some(duration) = value("DURATION") {
+        start + parse_duration(duration)?
+    } else if all_day {
+        start + Duration::days(1)
+    } else {
+        return Ok(None);
+    };
+    if end <= start {
+        return Ok(None);
+    }
+
+    Ok(Some(BusyTime {
+        uid,
+        summary: boundary::normalize_text(&unescape_text(value("SUMMARY").unwrap_or_default()))
+            .chars()
+            .take(boundary::MAX_TEXT_CHARS)
+            .collect(),
+        start,
+        end,
+    }))
+}
+
+/// Parse a DATE or DATE-TIME value, returning whether it was a date only.
+fn parse_date_time(name: &str, value: &str) -> Result<(DateTime<Local>, bool), String> {
+    let value = value.trim();
+    let invalid = || format!("Invalid {}: {}", name, value);
+
+    if value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| invalid())?;
+        let start = date
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .ok_or_else(invalid)?;
+        return Ok((start, true));
+    }
+
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+        return Ok((time.and_utc().with_timezone(&Local), false));
+    }
+    let time = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").map_err(|_| invalid())?;
+    let time = time
+        .and_local_timezone(Local)
+        .earliest()
+        .ok_or_else(invalid)?;
+    Ok((time, false))
+}
+
+/// Parse a DURATION value such as `PT1H30M`, `P1D` or `P2W`.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid DURATION: {}", value);
+    let rest = value.strip_prefix('+').unwrap_or(value);
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' if number.is_empty() => in_time = true,
+            'W' | 'D' | 'H' | 'M' | 'S' => {
+                let amount: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+                total += match (c, in_time) {
+                    ('W', false) => Duration::weeks(amount),
+                    ('D', false) => Duration::days(amount),
+                    ('H', true) => Duration::hours(amount),
+                    ('M', true) => Duration::minutes(amount),
+                    ('S', true) => Duration::seconds(amount),
+                    _ => return Err(invalid()),
+                };
+            }
+            _ => return Err(invalid()),
+        }
+    }
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
+/// Join folded content lines back together.
+fn unfold_lines(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Undo `escape_text`.
+fn unescape_text(value: &str) -> String {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => text.push('\n'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
+}
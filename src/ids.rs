@@ -0,0 +1,287 @@
+#![allow(dead_code)]
+//! Identifier generation for the records the calendar and scheduler create.
+//!
+//! IDs are random UUIDs by default. Components that create records ask the
+//! calendar's IdGenerator instead, so a clinic whose billing system needs
+//! sortable or human-readable identifiers can switch to ULIDs or to
+//! sequential IDs such as `APT-2024-0001`. Seeded IDs look like UUIDs but
+//! come out the same on every run, for snapshot tests and repeatable demos.
+
+use chrono::{DateTime, Datelike, Local};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use uuid::{Builder, Uuid};
+
+/// Kind of record an ID is generated for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IdKind {
+    Appointment,
+    Bundle,
+    Case,
+    Doctor,
+    Notification,
+    /// A patient registered without an ID of their own.
+    Patient,
+    Request,
+    /// A scheduling run, e.g. one `process_queue` batch.
+    Run,
+    Slot,
+}
+
+/// Source of new record IDs.
+pub trait IdGenerator: Send + Sync {
+    /// Short name used in clinic configuration files.
+    fn name(&self) -> &str;
+
+    /// Generate a new ID for a record created at `now`.
+    fn next_id(&self, kind: IdKind, now: DateTime<Local>) -> String;
+
+    /// Take note of an ID already in use, so it is never generated again.
+    fn observe(&self, _kind: IdKind, _id: &str) {}
+
+    /// Generate the code a patient quotes for a new appointment.
+    ///
+    /// Codes are random by default so they can't be guessed.
+    fn confirmation_code(&self, _now: DateTime<Local>) -> String {
+        Uuid::new_v4().simple().to_string()[..8].to_uppercase()
+    }
+
+    /// Generate a token that grants access, such as a calendar feed token.
+    ///
+    /// Tokens are random by default so they can't be guessed.
+    fn secret_token(&self, _now: DateTime<Local>) -> String {
+        Uuid::new_v4().simple().to_string()
+    }
+
+    /// Get a separate generator carrying on from this one's state, for
+    /// simulations whose IDs must not use up real ones.
+    ///
+    /// The copy hands out random UUIDs by default.
+    fn fork(&self) -> Arc<dyn IdGenerator> {
+        Arc::new(UuidIds)
+    }
+}
+
+/// Random UUID v4 IDs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UuidIds;
+
+impl IdGenerator for UuidIds {
+    fn name(&self) -> &str {
+        "uuid"
+    }
+
+    fn next_id(&self, _kind: IdKind, _now: DateTime<Local>) -> String {
+        Uuid::new_v4().to_string()
+    }
+}
+
+/// ULIDs: 26-character IDs that sort by creation time.
+///
+/// IDs created in the same millisecond still sort in creation order.
+#[derive(Debug, Default)]
+pub struct UlidIds {
+    last: Mutex<(i64, u128)>,
+}
+
+impl UlidIds {
+    /// Create a ULID generator.
+    pub fn new() -> Self {
+        UlidIds::default()
+    }
+}
+
+impl IdGenerator for UlidIds {
+    fn name(&self) -> &str {
+        "ulid"
+    }
+
+    fn next_id(&self, _kind: IdKind, now: DateTime<Local>) -> String {
+        const RANDOM_BITS: u32 = 80;
+        let mut last = self.last.lock().unwrap();
+        let millis = now.timestamp_millis().max(last.0);
+        let random = if millis == last.0 {
+            (last.1 + 1) & ((1 << RANDOM_BITS) - 1)
+        } else {
+            Uuid::new_v4().as_u128() >> (128 - RANDOM_BITS)
+        };
+        *last = (millis, random);
+
+        let value = ((millis as u128 & ((1 << 48) - 1)) << RANDOM_BITS) | random;
+        encode_crockford(value)
+    }
+
+    fn fork(&self) -> Arc<dyn IdGenerator> {
+        Arc::new(UlidIds {
+            last: Mutex::new(*self.last.lock().unwrap()),
+        })
+    }
+}
+
+/// Sequential IDs with a prefix per kind and a counter per year, such as
+/// `APT-2024-0001`, `REQ-2024-0001` and `SLOT-2024-0001`.
+#[derive(Debug, Default)]
+pub struct SequentialIds {
+    counters: Mutex<HashMap<(IdKind, i32), u64>>,
+}
+
+impl SequentialIds {
+    /// Create a generator starting at 1 for every kind and year.
+    pub fn new() -> Self {
+        SequentialIds::default()
+    }
+
+    fn prefix(kind: IdKind) -> &'static str {
+        match kind {
+            IdKind::Appointment => "APT",
+            IdKind::Bundle => "BND",
+            IdKind::Case => "CASE",
+            IdKind::Doctor => "DOC",
+            IdKind::Notification => "MSG",
+            IdKind::Patient => "PAT",
+            IdKind::Request => "REQ",
+            IdKind::Run => "RUN",
+            IdKind::Slot => "SLOT",
+        }
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn name(&self) -> &str {
+        "sequential"
+    }
+
+    fn next_id(&self, kind: IdKind, now: DateTime<Local>) -> String {
+        let year = now.year();
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry((kind, year)).or_default();
+        *counter += 1;
+        format!("{}-{}-{:04}", SequentialIds::prefix(kind), year, counter)
+    }
+
+    fn observe(&self, kind: IdKind, id: &str) {
+        let mut parts = id.splitn(3, '-');
+        let (Some(prefix), Some(year), Some(number)) = (parts.next(), parts.next(), parts.next())
+        else {
+            return;
+        };
+        let (Ok(year), Ok(number)) = (year.parse::<i32>(), number.parse::<u64>()) else {
+            return;
+        };
+        if prefix != SequentialIds::prefix(kind) {
+            return;
+        }
+        let mut counters = self.counters.lock().unwrap();
+        let counter = counters.entry((kind, year)).or_default();
+        *counter = (*counter).max(number);
+    }
+
+    fn fork(&self) -> Arc<dyn IdGenerator> {
+        Arc::new(SequentialIds {
+            counters: Mutex::new(self.counters.lock().unwrap().clone()),
+        })
+    }
+}
+
+/// UUID-shaped IDs from a seeded generator, the same on every run with
+/// the same seed, e.g. `seeded:42`.
+///
+/// Confirmation codes and access tokens come from the same sequence, so
+/// are predictable too; use these only for tests and demos. IDs already
+/// in use are skipped.
+#[derive(Debug)]
+pub struct SeededIds {
+    name: String,
+    state: Mutex<u64>,
+    in_use: Mutex<HashSet<String>>,
+}
+
+impl SeededIds {
+    /// Create a generator starting from `seed`.
+    pub fn new(seed: u64) -> Self {
+        SeededIds {
+            name: format!("seeded:{}", seed),
+            state: Mutex::new(seed),
+            in_use: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Take the next 64 bits of the sequence (SplitMix64).
+    fn next_bits(&self) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut bits = *state;
+        bits = (bits ^ (bits >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        bits = (bits ^ (bits >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        bits ^ (bits >> 31)
+    }
+}
+
+impl IdGenerator for SeededIds {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn next_id(&self, _kind: IdKind, _now: DateTime<Local>) -> String {
+        loop {
+            let mut bytes = [0u8; 16];
+            bytes[..8].copy_from_slice(&self.next_bits().to_be_bytes());
+            bytes[8..].copy_from_slice(&self.next_bits().to_be_bytes());
+            let id = Builder::from_random_bytes(bytes).into_uuid().to_string();
+            if self.in_use.lock().unwrap().insert(id.clone()) {
+                return id;
+            }
+        }
+    }
+
+    fn observe(&self, _kind: IdKind, id: &str) {
+        self.in_use.lock().unwrap().insert(id.to_string());
+    }
+
+    fn confirmation_code(&self, _now: DateTime<Local>) -> String {
+        format!("{:016X}", self.next_bits())[..8].to_string()
+    }
+
+    fn secret_token(&self, _now: DateTime<Local>) -> String {
+        format!("{:016x}{:016x}", self.next_bits(), self.next_bits())
+    }
+
+    fn fork(&self) -> Arc<dyn IdGenerator> {
+        Arc::new(SeededIds {
+            name: self.name.clone(),
+            state: Mutex::new(*self.state.lock().unwrap()),
+            in_use: Mutex::new(self.in_use.lock().unwrap().clone()),
+        })
+    }
+}
+
+/// Look up an ID generator by its configuration name.
+pub fn id_generator_by_name(name: &str) -> Result<Arc<dyn IdGenerator>, String> {
+    match name.trim() {
+        "uuid" => Ok(Arc::new(UuidIds)),
+        "ulid" => Ok(Arc::new(UlidIds::new())),
+        "sequential" => Ok(Arc::new(SequentialIds::new())),
+        "seeded" => Ok(Arc::new(SeededIds::new(0))),
+        other => {
+            let seed = other.strip_prefix("seeded:").ok_or_else(|| {
+                format!(
+                    "Unknown ID format: '{}'. Must be one of: uuid, ulid, sequential, seeded[:<seed>]",
+                    name
+                )
+            })?;
+            let seed = seed
+                .parse::<u64>()
+                .map_err(|_| format!("Invalid ID seed: '{}'", seed))?;
+            Ok(Arc::new(SeededIds::new(seed)))
+        }
+    }
+}
+
+/// Encode 128 bits as 26 Crockford base32 digits, most significant first.
+fn encode_crockford(value: u128) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+    (0..26)
+        .rev()
+        .map(|digit| ALPHABET[((value >> (digit * 5)) & 31) as usize] as char)
+        .collect()
+}
@@ -0,0 +1,242 @@
+#![allow(dead_code)]
+//! Import of already-confirmed appointments from other products.
+//!
+//! Clinics migrating to this system export their schedule as CSV with a
+//! header row and these columns:
+//!
+//! ```text
+//! patient_id,patient_name,contact,start,end,priority,reason
+//! P001,Jane Doe,555-0100,2024-03-04 09:00,2024-03-04 09:30,routine,Checkup
+//! ```
+//!
+//! Times use `YYYY-MM-DD HH:MM` in local time. Slots are created where the
+//! calendar has none; rows whose time is already booked or overlaps a
+//! different slot are reported as conflicts and skipped. Names and reasons
+//! are normalized, and rows with overlong text or times more than a year
+//! or two from today are reported as errors.
+
+use crate::boundary;
+use crate::calendar::{BookingSpec, DoctorCalendar};
+use crate::ids::IdKind;
+use crate::models::{Patient, Priority, TimeSlot};
+use crate::overlay::CalendarOverlay;
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Local, NaiveDateTime};
+
+const COLUMNS: usize = 7;
+
+/// A row that was, or in a dry run would be, imported.
+#[derive(Debug, Clone)]
+pub struct ImportedRow {
+    pub line: usize,
+    pub patient: Patient,
+    pub slot: TimeSlot,
+    /// Whether the slot had to be created for this row.
+    pub slot_created: bool,
+}
+
+/// Outcome of importing a CSV file.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub imported: Vec<ImportedRow>,
+    /// Rows clashing with existing bookings or slots, by line number.
+    pub conflicts: Vec<(usize, String)>,
+    /// Rows that could not be read, by line number.
+    pub errors: Vec<(usize, String)>,
+}
+
+impl ImportReport {
+    /// Get the number of slots created for imported rows.
+    pub fn slots_created(&self) -> usize {
+        self.imported.iter().filter(|row| row.slot_created).count()
+    }
+}
+
+/// A parsed row waiting to be checked against the calendar.
+struct ImportRow {
+    line: usize,
+    patient: Patient,
+    priority: Priority,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    reason: String,
+}
+
+/// Import confirmed appointments from CSV text.
+///
+/// All rows are first planned against a copy-on-write overlay of the
+/// calendar, so conflicts between rows are caught too. With `dry_run` the
+/// plan is returned without touching the calendar; otherwise the planned
+/// slots and bookings are applied in one transaction.
+pub fn import_appointments_csv(
+    calendar: &mut DoctorCalendar,
+    text: &str,
+    dry_run: bool,
+) -> Result<ImportReport, String> {
+    let mut report = ImportReport {
+        dry_run,
+        ..ImportReport::default()
+    };
+    let mut rows = Vec::new();
+    boundary::check_size(text, boundary::MAX_FILE_BYTES)?;
+    let now = calendar.now();
+
+    for (index, line) in text.lines().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_row(index + 1, line, now) {
+            Ok(row) => rows.push(row),
+            Err(e) => report.errors.push((index + 1, e)),
+        }
+    }
+
+    let mut specs = Vec::new();
+    let mut overlay = CalendarOverlay::new(calendar);
+    for row in rows {
+        match plan_row(&mut overlay, &row) {
+            Ok((slot, slot_created)) => {
+                specs.push(BookingSpec {
+                    slot_id: slot.slot_id.clone(),
+                    patient: row.patient.clone(),
+                    priority: row.priority,
+                    reason: row.reason,
+                });
+                report.imported.push(ImportedRow {
+                    line: row.line,
+                    patient: row.patient,
+                    slot,
+                    slot_created,
+                });
+            }
+            Err(e) => report.conflicts.push((row.line, e)),
+        }
+    }
+
+    if dry_run {
+        return Ok(report);
+    }
+
+    let new_slots: Vec<TimeSlot> = report
+        .imported
+        .iter()
+        .filter(|row| row.slot_created)
+        .map(|row| row.slot.clone())
+        .collect();
+    calendar.transaction(|calendar| {
+        for slot in new_slots {
+            calendar.id_generator().observe(IdKind::Slot, &slot.slot_id);
+            calendar.add_time_slot(slot)?;
+        }
+        calendar
+            .book_slots_bulk(specs)
+            .into_iter()
+            .collect::<Result<Vec<_>, String>>()
+    })?;
+
+    Ok(report)
+}
+
+/// Find or create the row's slot in the overlay and book it there.
+fn plan_row(overlay: &mut CalendarOverlay, row: &ImportRow) -> Result<(TimeSlot, bool), String> {
+    let existing = overlay
+        .time_slots()
+        .into_iter()
+        .find(|slot| slot.start_time < row.end && slot.end_time > row.start);
+
+    let (slot, slot_created) = match existing {
+        Some(slot) if slot.start_time == row.start && slot.end_time == row.end => {
+            if !slot.is_available {
+                return Err(format!(
+                    "{} is already booked",
+                    row.start.format("%Y-%m-%d %H:%M")
+                ));
+            }
+            (slot, false)
+        }
+        Some(slot) => {
+            return Err(format!(
+                "Overlaps existing slot {} - {}",
+                slot.start_time.format("%Y-%m-%d %H:%M"),
+                slot.end_time.format("%H:%M")
+            ));
+        }
+        None => {
+            let mut slot = TimeSlot::new(row.start, row.end)?;
+            slot.slot_id = overlay.next_id(IdKind::Slot);
+            overlay.add_time_slot(slot.clone())?;
+            (slot, true)
+        }
+    };
+
+    overlay.book_slot(&slot, row.patient.clone(), row.priority, row.reason.clone())?;
+    Ok((slot, slot_created))
+}
+
+fn parse_row(line: usize, text: &str, now: DateTime<Local>) -> Result<ImportRow, String> {
+    let fields = split_csv_line(text)?;
+    if fields.len() != COLUMNS {
+        return Err(format!(
+            "Expected {} columns, found {}",
+            COLUMNS,
+            fields.len()
+        ));
+    }
+
+    let start = parse_time(&fields[3])?;
+    let end = parse_time(&fields[4])?;
+    let priority = Priority::from_string(&fields[5])?;
+
+    let mut errors = ValidationErrors::new();
+    let patient = boundary::check_patient(&fields[0], &fields[1], &fields[2], &mut errors);
+    let reason = boundary::check_text("reason", &fields[6], &mut errors);
+    if reason.is_empty() {
+        errors.add("reason", "Appointment reason cannot be empty");
+    }
+    boundary::check_time("start", start, now, &mut errors);
+    match patient {
+        Some(patient) if errors.is_empty() => Ok(ImportRow {
+            line,
+            patient,
+            priority,
+            start,
+            end,
+            reason,
+        }),
+        _ => Err(errors.into()),
+    }
+}
+
+/// Parse a `YYYY-MM-DD HH:MM` local time.
+pub fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    NaiveDateTime::parse_from_str(value.trim(), "%Y-%m-%d %H:%M")
+        .ok()
+        .and_then(|time| time.and_local_timezone(Local).single())
+        .ok_or_else(|| format!("Invalid time: {}", value.trim()))
+}
+
+/// Split one CSV line, honouring double-quoted fields and `""` escapes.
+pub fn split_csv_line(line: &str) -> Result<Vec<String>, String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', _) => in_quotes = !in_quotes,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    if in_quotes {
+        return Err("Unterminated quote".to_string());
+    }
+    fields.push(field);
+    Ok(fields)
+}
@@ -0,0 +1,178 @@
+#![allow(dead_code)]
+//! Self check-in at a reception kiosk.
+//!
+//! A patient types the confirmation code from their booking and the kiosk
+//! checks them in, timestamping their arrival and letting the doctor know
+//! they are waiting. Anything the kiosk can't settle on its own, such as a
+//! code for another day or an arrival past the late policy's grace period,
+//! sends the patient to reception with nothing changed. Messages never name
+//! the patient, since others in the waiting room can see the screen.
+
+use crate::boundary::{self, InputError};
+use crate::lateness::Arrival;
+use crate::notifications::{Notification, NotificationChannel};
+use crate::scheduler::AppointmentScheduler;
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// What the patient entered at the kiosk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KioskRequest {
+    pub confirmation_code: String,
+}
+
+/// Whether the patient was checked in.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KioskStatus {
+    CheckedIn {
+        appointment_time: DateTime<Local>,
+        arrived_at: DateTime<Local>,
+        /// False if the doctor's channel could not be reached.
+        doctor_notified: bool,
+    },
+    SeeReception,
+    /// The request itself was turned away, e.g. as malformed.
+    Rejected(InputError),
+}
+
+/// What the kiosk shows the patient.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KioskResponse {
+    pub status: KioskStatus,
+    pub message: String,
+}
+
+impl KioskResponse {
+    fn see_reception(message: &str) -> Self {
+        KioskResponse {
+            status: KioskStatus::SeeReception,
+            message: message.to_string(),
+        }
+    }
+}
+
+/// A check-in kiosk for one doctor's patients.
+#[derive(Debug, Clone)]
+pub struct Kiosk {
+    /// Where arrivals are sent on the doctor's channel.
+    pub doctor_recipient: String,
+}
+
+impl Kiosk {
+    /// Create a kiosk that tells `doctor_recipient` about arrivals.
+    pub fn new(doctor_recipient: String) -> Result<Self, String> {
+        if doctor_recipient.trim().is_empty() {
+            return Err("Doctor recipient cannot be empty".to_string());
+        }
+        Ok(Kiosk { doctor_recipient })
+    }
+
+    /// Check in the patient holding `confirmation_code`.
+    ///
+    /// Only appointments today can be checked in. With a late arrival
+    /// policy, patients past its grace period are sent to reception, where
+    /// staff can use `AppointmentScheduler::arrive` to offer another slot.
+    pub fn check_in(
+        &self,
+        scheduler: &mut AppointmentScheduler,
+        channel: &mut dyn NotificationChannel,
+        confirmation_code: &str,
+    ) -> KioskResponse {
+        let Some(appointment) = scheduler
+            .calendar
+            .find_appointment_by_code(confirmation_code)
+        else {
+            return KioskResponse::see_reception(
+                "We couldn't find that code. Please check it and try again, or see reception.",
+            );
+        };
+        let now = scheduler.calendar.now();
+        let start = appointment.time_slot.start_time;
+        if start.date_naive() != now.date_naive() {
+            return KioskResponse::see_reception(&format!(
+                "Your appointment is on {}. Please see reception.",
+                start.format("%A, %Y-%m-%d")
+            ));
+        }
+        if appointment.checked_in_at.is_some() {
+            return KioskResponse::see_reception("You are already checked in. Please take a seat.");
+        }
+        let minutes_late = (now - start).num_minutes();
+        if scheduler
+            .late_arrival_policy
+            .is_some_and(|policy| minutes_late > policy.grace_minutes)
+        {
+            return KioskResponse::see_reception(
+                "You have missed your appointment time. Please see reception.",
+            );
+        }
+
+        let appointment = match scheduler.arrive(&appointment.appointment_id) {
+            Ok(report) => match report.arrival {
+                Arrival::CheckedIn(appointment) => appointment,
+                _ => return KioskResponse::see_reception("Please see reception."),
+            },
+            Err(_) => return KioskResponse::see_reception("Please see reception."),
+        };
+        let arrived_at = appointment.checked_in_at.unwrap_or(now);
+
+        let doctor_notified = Notification::new(
+            scheduler.calendar.id_generator(),
+            now,
+            self.doctor_recipient.clone(),
+            format!("{} has arrived", appointment.patient.name),
+            format!(
+                "{} checked in at the kiosk at {} for their {} appointment ({}).",
+                appointment.patient.name,
+                arrived_at.format("%H:%M"),
+                start.format("%H:%M"),
+                appointment.reason
+            ),
+        )
+        .and_then(|notification| channel.send(&notification))
+        .is_ok();
+
+        KioskResponse {
+            status: KioskStatus::CheckedIn {
+                appointment_time: start,
+                arrived_at,
+                doctor_notified,
+            },
+            message: format!(
+                "Thank you, you are checked in for {}. Please take a seat.",
+                start.format("%H:%M")
+            ),
+        }
+    }
+
+    /// Answer a JSON `KioskRequest` with a JSON `KioskResponse`.
+    ///
+    /// Oversized, malformed or invalid bodies are rejected before the
+    /// calendar is looked at.
+    pub fn handle_json(
+        &self,
+        scheduler: &mut AppointmentScheduler,
+        channel: &mut dyn NotificationChannel,
+        body: &str,
+    ) -> String {
+        let response = match boundary::parse_json::<KioskRequest>(body).and_then(checked_code) {
+            Ok(code) => self.check_in(scheduler, channel, &code),
+            Err(error) => KioskResponse {
+                status: KioskStatus::Rejected(error),
+                message: "Please enter your confirmation code.".to_string(),
+            },
+        };
+        serde_json::to_string(&response).unwrap_or_default()
+    }
+}
+
+/// Normalize the code entered, which must be non-empty and short.
+fn checked_code(request: KioskRequest) -> Result<String, InputError> {
+    let mut errors = ValidationErrors::new();
+    let code = boundary::check_text("confirmation_code", &request.confirmation_code, &mut errors);
+    if code.is_empty() {
+        errors.add("confirmation_code", "Confirmation code cannot be empty");
+    }
+    errors.finish(code).map_err(InputError::invalid)
+}
@@ -0,0 +1,151 @@
+#![allow(dead_code)]
+//! Color and emoji labels for doctors and priorities.
+//!
+//! Labels are applied the same way everywhere appointments are listed: the
+//! appointment listings and today's sheet in the CLI, and the ICS export,
+//! where they become event categories. With several doctors' schedules side
+//! by side, they make it easy to see whose appointment is whose.
+
+use crate::models::Priority;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// A color usable both in terminals and in ICS `COLOR` properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LabelColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+}
+
+impl LabelColor {
+    /// Convert a string to a LabelColor.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "red" => Ok(LabelColor::Red),
+            "green" => Ok(LabelColor::Green),
+            "yellow" => Ok(LabelColor::Yellow),
+            "blue" => Ok(LabelColor::Blue),
+            "magenta" => Ok(LabelColor::Magenta),
+            "cyan" => Ok(LabelColor::Cyan),
+            _ => Err(format!(
+                "Invalid color: '{}'. Must be one of: red, green, yellow, blue, magenta, cyan",
+                value
+            )),
+        }
+    }
+
+    /// CSS color name, as used by the ICS `COLOR` property.
+    pub fn name(&self) -> &str {
+        match self {
+            LabelColor::Red => "red",
+            LabelColor::Green => "green",
+            LabelColor::Yellow => "yellow",
+            LabelColor::Blue => "blue",
+            LabelColor::Magenta => "magenta",
+            LabelColor::Cyan => "cyan",
+        }
+    }
+
+    fn ansi_code(&self) -> u8 {
+        match self {
+            LabelColor::Red => 31,
+            LabelColor::Green => 32,
+            LabelColor::Yellow => 33,
+            LabelColor::Blue => 34,
+            LabelColor::Magenta => 35,
+            LabelColor::Cyan => 36,
+        }
+    }
+}
+
+/// An optional emoji and color shown with a name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Label {
+    pub emoji: Option<String>,
+    pub color: Option<LabelColor>,
+}
+
+impl Label {
+    /// Create a label, treating a blank emoji as none.
+    pub fn new(emoji: Option<String>, color: Option<LabelColor>) -> Self {
+        Label {
+            emoji: emoji
+                .map(|emoji| emoji.trim().to_string())
+                .filter(|emoji| !emoji.is_empty()),
+            color,
+        }
+    }
+
+    /// Check whether the label has neither emoji nor color.
+    pub fn is_empty(&self) -> bool {
+        self.emoji.is_none() && self.color.is_none()
+    }
+
+    /// Prefix `text` with the emoji, if any.
+    pub fn tag(&self, text: &str) -> String {
+        match &self.emoji {
+            Some(emoji) => format!("{} {}", emoji, text),
+            None => text.to_string(),
+        }
+    }
+
+    /// Prefix `text` with the emoji and, when `ansi` is set, wrap it in the
+    /// label's terminal color.
+    pub fn paint(&self, text: &str, ansi: bool) -> String {
+        match self.color {
+            Some(color) if ansi => format!("\x1b[{}m{}\x1b[0m", color.ansi_code(), self.tag(text)),
+            _ => self.tag(text),
+        }
+    }
+}
+
+/// Labels for a doctor and for each priority.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LabelScheme {
+    pub doctor: Label,
+    priorities: BTreeMap<Priority, Label>,
+}
+
+impl LabelScheme {
+    /// A scheme with the usual emoji and colors for each priority.
+    pub fn standard() -> Self {
+        let mut scheme = LabelScheme::default();
+        scheme.set_priority(
+            Priority::Emergency,
+            Label::new(Some("🚨".to_string()), Some(LabelColor::Red)),
+        );
+        scheme.set_priority(
+            Priority::Urgent,
+            Label::new(Some("⚠️".to_string()), Some(LabelColor::Yellow)),
+        );
+        scheme.set_priority(Priority::Routine, Label::new(None, Some(LabelColor::Green)));
+        scheme
+    }
+
+    /// Set the label for a priority.
+    pub fn set_priority(&mut self, priority: Priority, label: Label) {
+        self.priorities.insert(priority, label);
+    }
+
+    /// Get the label for a priority, empty if none is set.
+    pub fn priority(&self, priority: Priority) -> Label {
+        self.priorities.get(&priority).cloned().unwrap_or_default()
+    }
+
+    /// ICS categories for an appointment: the doctor and the priority.
+    pub fn categories(&self, doctor_name: &str, priority: Priority) -> Vec<String> {
+        vec![
+            self.doctor.tag(doctor_name),
+            self.priority(priority).tag(priority.name()),
+        ]
+    }
+
+    /// Color for an appointment: the priority's, falling back to the doctor's.
+    pub fn color(&self, priority: Priority) -> Option<LabelColor> {
+        self.priority(priority).color.or(self.doctor.color)
+    }
+}
@@ -0,0 +1,97 @@
+#![allow(dead_code)]
+//! Late arrivals.
+//!
+//! A patient who turns up within the grace period is checked in as usual.
+//! Past it, seeing them in their own slot would push the rest of the day
+//! back, so they are either offered the next free slot today or turned
+//! into a walk-in, to be seen whenever the doctor has a gap. The slot they
+//! leave can be handed straight to the waitlist.
+
+use crate::models::{Appointment, TimeSlot};
+use crate::scheduler::SchedulingResult;
+use serde::{Deserialize, Serialize};
+
+/// What happens to a patient who arrives after the grace period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LateArrivalAction {
+    /// Offer the next free slot today, falling back to a walk-in if none.
+    OfferNextGap,
+    /// Cancel the booking and queue the patient as a walk-in for today.
+    WalkIn,
+}
+
+impl LateArrivalAction {
+    /// Convert a string to a LateArrivalAction.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "gap" | "next-gap" => Ok(LateArrivalAction::OfferNextGap),
+            "walk-in" | "walkin" => Ok(LateArrivalAction::WalkIn),
+            _ => Err(format!(
+                "Invalid late arrival action: '{}'. Must be one of: next-gap, walk-in",
+                value
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            LateArrivalAction::OfferNextGap => "next-gap",
+            LateArrivalAction::WalkIn => "walk-in",
+        }
+    }
+}
+
+/// How late arrivals are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LateArrivalPolicy {
+    /// Minutes after the slot start a patient can still be checked in as usual.
+    pub grace_minutes: i64,
+    pub action: LateArrivalAction,
+    /// Offer the slot a late patient leaves to the waitlist straight away.
+    pub release_to_waitlist: bool,
+}
+
+impl LateArrivalPolicy {
+    /// Create a policy with validation.
+    pub fn new(
+        grace_minutes: i64,
+        action: LateArrivalAction,
+        release_to_waitlist: bool,
+    ) -> Result<Self, String> {
+        if grace_minutes < 0 {
+            return Err("Grace period cannot be negative".to_string());
+        }
+        Ok(LateArrivalPolicy {
+            grace_minutes,
+            action,
+            release_to_waitlist,
+        })
+    }
+}
+
+/// What happened when a patient arrived.
+#[derive(Debug, Clone)]
+pub enum Arrival {
+    /// Checked in to their own (or an accepted) slot.
+    CheckedIn(Appointment),
+    /// Too late for their slot; `slot` is offered and nothing has changed yet.
+    Offered {
+        appointment: Appointment,
+        slot: TimeSlot,
+        minutes_late: i64,
+    },
+    /// Booking cancelled and the patient queued as a walk-in request.
+    WalkIn {
+        minutes_late: i64,
+        /// Result of the walk-in request, or `None` if it was queued.
+        result: Option<Box<SchedulingResult>>,
+    },
+}
+
+/// Outcome of handling an arrival.
+#[derive(Debug, Clone)]
+pub struct ArrivalReport {
+    pub arrival: Arrival,
+    /// Waitlisted patient booked into the slot the late patient left.
+    pub backfilled: Option<Appointment>,
+}
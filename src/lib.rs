@@ -0,0 +1,67 @@
+//! Priority-based medical appointment scheduling.
+//!
+//! The library holds the doctor calendar, the data models and the
+//! priority scheduler, along with everything built on them: reports,
+//! imports, notifications and storage backends. The `easyappoint` binary
+//! is an interactive CLI on top of it.
+
+pub mod alerts;
+pub mod availability;
+pub mod boundary;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod board;
+pub mod bringforward;
+pub mod bundle;
+pub mod campaign;
+pub mod calendar;
+pub mod clinic;
+pub mod card;
+pub mod caselist;
+pub mod claims;
+pub mod clock;
+pub mod closeout;
+pub mod compact;
+pub mod config;
+#[cfg(feature = "desktop")]
+pub mod desktop;
+pub mod events;
+pub mod fill;
+pub mod health;
+pub mod ics;
+pub mod ids;
+pub mod import;
+pub mod kiosk;
+pub mod labels;
+pub mod lateness;
+pub mod models;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod notifications;
+pub mod overrun;
+pub mod overlay;
+#[cfg(feature = "postgres")]
+pub mod postgres_store;
+pub mod preferences;
+pub mod projection;
+pub mod query;
+pub mod queue;
+#[cfg(feature = "redis")]
+pub mod redis_queue;
+pub mod reports;
+pub mod rules;
+pub mod scheduler;
+pub mod shutdown;
+pub mod snapshot;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
+pub mod store;
+pub mod strategy;
+pub mod swap;
+pub mod sync;
+#[cfg(feature = "test_util")]
+pub mod test_util;
+pub mod timeoff;
+pub mod validation;
+pub mod waitlist;
+pub mod week;
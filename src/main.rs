@@ -1,40 +1,102 @@
 #![allow(dead_code)]
-/// Command-line interface for the appointment scheduling system.
-///
-/// This module provides an interactive CLI for managing doctor calendars,
-/// submitting appointment requests, and viewing scheduled appointments.
-
-mod calendar;
-mod models;
-mod scheduler;
-
-use calendar::DoctorCalendar;
-use chrono::{Datelike, Duration, Local, NaiveTime};  // Added Datelike
-use models::create_appointment_request;  // Removed Priority (unused)
-use scheduler::AppointmentScheduler;
-use std::io::{self, Write};
+//! Command-line interface for the appointment scheduling system.
+//!
+//! This module provides an interactive CLI for managing doctor calendars,
+//! submitting appointment requests, and viewing scheduled appointments.
+//! Run without arguments it opens the menu; subcommands such as
+//! `slots generate`, `request add`, `schedule` and `list` do the same jobs
+//! non-interactively against a calendar snapshot file, for scripts and
+//! cron jobs.
+
+use easyappoint::alerts::{CapacityMonitor, QueueMonitor};
+use easyappoint::availability::{BlockReport, DoctorAvailability};
+#[cfg(feature = "bench")]
+use easyappoint::bench;
+use easyappoint::board::QueueBoard;
+use easyappoint::calendar::DoctorCalendar;
+use easyappoint::campaign::{parse_patients_csv, RecallCampaign};
+use easyappoint::card::CardFormat;
+use easyappoint::closeout::{awaiting_outcome, close_day};
+use easyappoint::config::{export_config, import_config, ClinicConfig, NotificationSettings};
+use easyappoint::ids::IdKind;
+use easyappoint::import::{import_appointments_csv, parse_time};
+use easyappoint::labels::{Label, LabelColor, LabelScheme};
+use easyappoint::lateness::{Arrival, ArrivalReport, LateArrivalAction, LateArrivalPolicy};
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveTime, Timelike, Weekday};  // Added Datelike
+use easyappoint::models::{
+    create_appointment_request, Appointment, CancellationActor, CancellationPolicy, DateWindow, Priority,
+    RequestSource, RequestTemplate, TimeSlot,
+};
+use easyappoint::notifications::InMemoryChannel;
+use easyappoint::preferences::DoctorPreferences;
+use easyappoint::query::parse_query;
+use easyappoint::queue::FileQueue;
+use easyappoint::reports;
+use easyappoint::rules::RuleSet;
+use easyappoint::scheduler::{AppointmentScheduler, ProcessingMode, SchedulingResult};
+use easyappoint::snapshot::{CalendarSnapshot, SnapshotFormat};
+use easyappoint::strategy::CompactDay;
+use easyappoint::timeoff::TimeOffRequest;
+use easyappoint::validation::{ContactFormatValidator, DuplicateRequestValidator};
+use chrono::DateTime;
+use clap::{Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 struct AppointmentCLI {
-    calendar: Option<DoctorCalendar>,
     scheduler: Option<AppointmentScheduler>,
+    capacity_monitor: CapacityMonitor,
+    queue_monitor: QueueMonitor,
     running: bool,
 }
 
 impl AppointmentCLI {
     fn new() -> Self {
         AppointmentCLI {
-            calendar: None,
             scheduler: None,
+            capacity_monitor: CapacityMonitor::default(),
+            queue_monitor: QueueMonitor::default(),
             running: true,
         }
     }
 
+    /// Get the calendar, which the scheduler owns.
+    fn calendar(&self) -> Option<&DoctorCalendar> {
+        self.scheduler.as_ref().map(|scheduler| &scheduler.calendar)
+    }
+
+    fn calendar_mut(&mut self) -> Option<&mut DoctorCalendar> {
+        self.scheduler.as_mut().map(|scheduler| &mut scheduler.calendar)
+    }
+
+    /// The current time on the calendar's clock, once there is a calendar.
+    fn now(&self) -> DateTime<Local> {
+        self.calendar().map_or_else(Local::now, DoctorCalendar::now)
+    }
+
     fn print_header(&self) {
         println!("\n{}", "=".repeat(60));
         println!("       APPOINTMENT SCHEDULING SYSTEM");
         println!("{}", "=".repeat(60));
     }
 
+    fn print_alerts(&self) {
+        if let Some(calendar) = self.calendar() {
+            if !calendar.time_slots().is_empty() {
+                for alert in self.capacity_monitor.check(calendar) {
+                    println!("\n!! {}", alert.message());
+                }
+            }
+        }
+        if let Some(scheduler) = &self.scheduler {
+            for alert in self.queue_monitor.check(scheduler) {
+                println!("\n!! {}", alert.message());
+            }
+        }
+    }
+
     fn print_menu(&self) {
         println!("\n--- Main Menu ---");
         println!("1. Setup doctor calendar");
@@ -44,8 +106,17 @@ impl AppointmentCLI {
         println!("5. View available slots");
         println!("6. View confirmed appointments");
         println!("7. Cancel appointment");
-        println!("8. Run demo");
-        println!("9. Exit");
+        println!("8. View appointment details");
+        println!("9. Dashboard");
+        println!("10. Today");
+        println!("11. Shift a day's appointments");
+        println!("12. Manage my availability");
+        println!("13. Import appointments from CSV");
+        println!("14. Time-off approvals");
+        println!("15. Run recall campaign");
+        println!("16. Close out a day");
+        println!("17. Run demo");
+        println!("18. Exit");
         println!("{}", "-".repeat(20));
     }
 
@@ -80,27 +151,266 @@ impl AppointmentCLI {
         }
     }
 
+    /// Only color listings when writing to a terminal.
+    fn use_color() -> bool {
+        io::stdout().is_terminal()
+    }
+
+    fn print_alternatives(result: &SchedulingResult) {
+        for slot in &result.alternatives {
+            println!(
+                "     Alternative: {}",
+                slot.start_time.format("%Y-%m-%d %H:%M")
+            );
+        }
+        if let Some(remediation) = &result.remediation {
+            for hint in remediation.hints() {
+                println!("     Hint: {}", hint);
+            }
+        }
+        if result.waitlisted {
+            println!("     Kept on the waitlist for the next run");
+        }
+    }
+
     fn setup_calendar(&mut self) {
         println!("\n--- Setup Doctor Calendar ---");
 
+        let config_path =
+            self.get_input("Clinic configuration file (blank to set up manually)", Some(""));
+        if !config_path.is_empty() {
+            self.load_config(&config_path);
+            return;
+        }
+
         let doctor_name = self.get_input("Doctor name", Some("Dr. Smith"));
         let slot_duration = self.get_int_input("Default appointment duration (minutes)", Some(30));
+        let location = self.get_input("Clinic location (optional)", Some(""));
+        let emoji = self.get_input("Doctor label emoji (optional)", Some(""));
+        let color = self.get_input(
+            "Doctor label color: red, green, yellow, blue, magenta, cyan (optional)",
+            Some(""),
+        );
+        let color = if color.is_empty() {
+            None
+        } else {
+            match LabelColor::from_string(&color) {
+                Ok(color) => Some(color),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        };
+        let priority_labels =
+            self.get_input("Label priorities with emoji and colors? (y/n)", Some("n"));
+        let notice_hours =
+            self.get_int_input("Free cancellation notice (hours, 0 = none)", Some(24));
+        let lock_past = self.get_input("Lock past days against changes? (y/n)", Some("n"));
+        let immediate = self.get_input("Book emergencies immediately on submit? (y/n)", Some("y"));
+        let snap = self.get_input(
+            "Round off-grid preferred times to slot starts? (y/n)",
+            Some("y"),
+        );
+        let compact = self.get_input("Keep the doctor's day compact? (y/n)", Some("n"));
+        let grace = self.get_input("Late arrival grace period (minutes, blank for none)", Some(""));
+        let late_arrival_policy = if grace.is_empty() {
+            None
+        } else {
+            let action = self.get_input(
+                "After the grace period: offer the next gap or make a walk-in (next-gap/walk-in)",
+                Some("next-gap"),
+            );
+            let release = self.get_input(
+                "Offer late patients' slots to the waitlist? (y/n)",
+                Some("y"),
+            );
+            let policy = grace
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid grace period: {}", grace))
+                .and_then(|minutes| {
+                    LateArrivalPolicy::new(
+                        minutes,
+                        LateArrivalAction::from_string(&action)?,
+                        release.to_lowercase() == "y",
+                    )
+                });
+            match policy {
+                Ok(policy) => Some(policy),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        };
+        let rules_path = self.get_input("Business rules file (blank for none)", Some(""));
+        let preferences_path = self.get_input("Doctor preferences file (blank for none)", Some(""));
+        let quotas = self.get_input(
+            "Weekly partner quotas, e.g. Northside=5 (comma separated, blank for none)",
+            Some(""),
+        );
+        let visits = self.get_input(
+            "Visit templates, e.g. New patient intake=45 (name=minutes, comma separated, blank for none)",
+            Some(""),
+        );
+        let mut request_templates = Vec::new();
+        for entry in visits.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(name, minutes)| Some((name.trim(), minutes.trim().parse().ok()?)))
+                .ok_or_else(|| format!("Invalid visit template: {}", entry.trim()))
+                .and_then(|(name, minutes)| {
+                    RequestTemplate::new(name.to_string(), name.to_string(), Some(minutes))
+                });
+            match parsed {
+                Ok(template) => request_templates.push(template),
+                Err(e) => {
+                    println!("{}", e);
+                    return;
+                }
+            }
+        }
+        let mut referral_quotas = Vec::new();
+        for entry in quotas.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let parsed = entry
+                .split_once('=')
+                .and_then(|(name, slots)| Some((name.trim(), slots.trim().parse().ok()?)));
+            match parsed {
+                Some((name, slots)) if !name.is_empty() => {
+                    referral_quotas.push((name.to_string(), slots))
+                }
+                _ => {
+                    println!("Invalid quota: {}", entry.trim());
+                    return;
+                }
+            }
+        }
+        let rules = if rules_path.is_empty() {
+            RuleSet::new()
+        } else {
+            match RuleSet::load(&rules_path) {
+                Ok(rules) => rules,
+                Err(e) => {
+                    println!("Error loading rules: {}", e);
+                    return;
+                }
+            }
+        };
+        let preferences = if preferences_path.is_empty() {
+            DoctorPreferences::new()
+        } else {
+            match DoctorPreferences::load(&preferences_path) {
+                Ok(preferences) => preferences,
+                Err(e) => {
+                    println!("Error loading preferences: {}", e);
+                    return;
+                }
+            }
+        };
 
         match DoctorCalendar::new(doctor_name.clone(), slot_duration as i64) {
-            Ok(calendar) => {
-                let scheduler = AppointmentScheduler::new(calendar.clone(), true);
-                self.calendar = Some(calendar);
-                self.scheduler = Some(scheduler);
+            Ok(mut calendar) => {
+                if !location.is_empty() {
+                    calendar.location = Some(location);
+                }
+                if priority_labels.to_lowercase() == "y" {
+                    calendar.labels = LabelScheme::standard();
+                }
+                calendar.labels.doctor = Label::new(Some(emoji), color);
+                if notice_hours > 0 {
+                    calendar.cancellation_policy =
+                        CancellationPolicy::new(notice_hours as i64, None).ok();
+                }
+                calendar.lock_past_days = lock_past.to_lowercase() == "y";
+                if !preferences.preferences().is_empty() {
+                    println!(
+                        "Loaded {} doctor preferences",
+                        preferences.preferences().len()
+                    );
+                }
+                calendar.preferences = preferences;
+
+                let mut scheduler = AppointmentScheduler::new(calendar, true);
+                scheduler.snap_to_grid = snap.to_lowercase() == "y";
+                if compact.to_lowercase() == "y" {
+                    scheduler.set_strategy(Arc::new(CompactDay));
+                }
+                scheduler.late_arrival_policy = late_arrival_policy;
+                if immediate.to_lowercase() == "y" {
+                    scheduler
+                        .processing_policy
+                        .set_mode(Priority::Emergency, ProcessingMode::Immediate);
+                }
+                if !rules.rules().is_empty() {
+                    println!("Loaded {} business rules", rules.rules().len());
+                }
+                scheduler.rules = rules;
+                for (referrer, slots) in &referral_quotas {
+                    scheduler.set_referral_quota(referrer, *slots);
+                }
+                for template in request_templates {
+                    scheduler.add_request_template(template);
+                }
+                scheduler.add_validator(Box::new(ContactFormatValidator));
+                scheduler.add_validator(Box::new(DuplicateRequestValidator));
 
                 println!("\nCalendar created for {}", doctor_name);
                 println!("Default slot duration: {} minutes", slot_duration);
+
+                let save_path =
+                    self.get_input("Save clinic configuration to (blank to skip)", Some(""));
+                if !save_path.is_empty() {
+                    let settings = NotificationSettings {
+                        capacity: self.capacity_monitor,
+                        queue: self.queue_monitor,
+                        ..NotificationSettings::default()
+                    };
+                    match export_config(&scheduler, settings).save(&save_path) {
+                        Ok(()) => println!("Configuration saved to {}", save_path),
+                        Err(e) => println!("Error saving configuration: {}", e),
+                    }
+                }
+
+                self.scheduler = Some(scheduler);
             }
             Err(e) => println!("Error creating calendar: {}", e),
         }
     }
 
+    fn load_config(&mut self, path: &str) {
+        let config = match ClinicConfig::load(path) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("Error loading configuration: {}", e);
+                return;
+            }
+        };
+        let mut scheduler = match import_config(&config) {
+            Ok(scheduler) => scheduler,
+            Err(e) => {
+                println!("Error applying configuration: {}", e);
+                return;
+            }
+        };
+        scheduler.add_validator(Box::new(ContactFormatValidator));
+        scheduler.add_validator(Box::new(DuplicateRequestValidator));
+
+        self.capacity_monitor = config.notifications.capacity;
+        self.queue_monitor = config.notifications.queue;
+        self.scheduler = Some(scheduler);
+
+        println!("\nCalendar created for {}", config.doctor.doctor_name);
+        println!(
+            "Default slot duration: {} minutes",
+            config.doctor.default_slot_duration
+        );
+        if !config.scheduling.rules.is_empty() {
+            println!("Loaded {} business rules", config.scheduling.rules.len());
+        }
+    }
+
     fn generate_slots(&mut self) {
-        if self.calendar.is_none() {
+        if self.calendar().is_none() {
             println!("\nPlease setup a calendar first (option 1)");
             return;
         }
@@ -124,13 +434,16 @@ impl AppointmentCLI {
         };
 
         let mut total_slots = 0;
-        let mut current_date = Local::now() + Duration::days(1);
+        let mut skipped_days = 0;
+        let mut skipped_overlap = 0;
+        let mut skipped_busy = 0;
+        let mut current_date = self.now() + Duration::days(1);
 
         // Fixed: Datelike trait is now in scope via use chrono::Datelike
-        if let Some(mut calendar) = self.calendar.take() {
+        if let Some(calendar) = self.calendar_mut() {
             for _ in 0..days {
                 if current_date.weekday().num_days_from_monday() < 5 {
-                    let slots = calendar.generate_daily_slots(
+                    let report = calendar.ensure_daily_slots(
                         current_date,
                         start_hour,
                         end_hour,
@@ -138,17 +451,29 @@ impl AppointmentCLI {
                         break_start,
                         break_end,
                     );
-                    total_slots += slots.len();
+                    if report.is_already_generated() {
+                        skipped_days += 1;
+                    }
+                    total_slots += report.created.len();
+                    skipped_overlap += report.skipped_overlap.len();
+                    skipped_busy += report.skipped_busy.len();
                 }
-                current_date = current_date + Duration::days(1);
+                current_date += Duration::days(1);
             }
 
             println!("\nGenerated {} time slots", total_slots);
-
-            // Create new scheduler with updated calendar
-            let new_scheduler = AppointmentScheduler::new(calendar.clone(), true);
-            self.calendar = Some(calendar);
-            self.scheduler = Some(new_scheduler);
+            if skipped_days > 0 {
+                println!(
+                    "{} days were already generated and left unchanged",
+                    skipped_days
+                );
+            }
+            if skipped_overlap > 0 {
+                println!("{} slots skipped: overlap with existing slots", skipped_overlap);
+            }
+            if skipped_busy > 0 {
+                println!("{} slots skipped: busy in external calendar", skipped_busy);
+            }
         }
     }
 
@@ -162,48 +487,222 @@ impl AppointmentCLI {
 
         let patient_name = self.get_input("Patient name", None);
         let patient_contact = self.get_input("Patient contact (phone/email)", None);
-        let reason = self.get_input("Reason for appointment", None);
 
-        println!("\nPriority levels:");
-        println!("  1. Routine");
-        println!("  2. Urgent");
-        println!("  3. Emergency");
-        let priority_choice = self.get_int_input("Select priority", Some(1));
+        let templates = self
+            .scheduler
+            .as_ref()
+            .map(|scheduler| scheduler.request_templates().to_vec())
+            .unwrap_or_default();
+        let template = if templates.is_empty() {
+            None
+        } else {
+            println!("\nVisit templates:");
+            let scheduler = self.scheduler.as_ref().unwrap();
+            for template in &templates {
+                let mut details = Vec::new();
+                if let Some(minutes) = template.duration_minutes {
+                    details.push(format!("{} min", minutes));
+                }
+                if let Some(minutes) = scheduler.suggested_template_duration(&template.name) {
+                    details.push(format!("usually needs {} min", minutes));
+                }
+                if details.is_empty() {
+                    println!("  - {}", template.name);
+                } else {
+                    println!("  - {} ({})", template.name, details.join(", "));
+                }
+            }
+            let name = self.get_input("Visit template (blank for none)", Some(""));
+            if name.is_empty() {
+                None
+            } else {
+                match templates
+                    .iter()
+                    .find(|template| template.name.eq_ignore_ascii_case(name.trim()))
+                {
+                    Some(template) => Some(template.clone()),
+                    None => {
+                        println!("Unknown visit template: {}", name);
+                        return;
+                    }
+                }
+            }
+        };
 
-        let priority = match priority_choice {
-            1 => "routine",
-            2 => "urgent",
-            3 => "emergency",
-            _ => "routine",
+        let (reason, priority) = match &template {
+            Some(template) => (template.reason.clone(), template.priority.name().to_lowercase()),
+            None => {
+                let reason = self.get_input("Reason for appointment", None);
+
+                println!("\nPriority levels:");
+                println!("  1. Routine");
+                println!("  2. Urgent");
+                println!("  3. Emergency");
+                let priority_choice = self.get_int_input("Select priority", Some(1));
+
+                let priority = match priority_choice {
+                    1 => "routine",
+                    2 => "urgent",
+                    3 => "emergency",
+                    _ => "routine",
+                };
+                (reason, priority.to_string())
+            }
         };
+        let default_flexibility = template
+            .as_ref()
+            .map_or(60, |template| template.flexibility_minutes as i32);
 
         println!("\nPreferred time (tomorrow at 10:00 AM as default)");
         let hours = self.get_int_input("Hour (0-23)", Some(10));
         let minutes = self.get_int_input("Minute (0-59)", Some(0));
 
-        let preferred_time = (Local::now() + Duration::days(1))
+        let preferred_time = (self.now() + Duration::days(1))
             .date_naive()
             .and_hms_opt(hours as u32, minutes as u32, 0)
             .unwrap()
             .and_local_timezone(Local)
             .unwrap();
 
-        let flexibility = self.get_int_input("Time flexibility (minutes)", Some(60)) as i64;
+        let earlier =
+            self.get_int_input("Can come earlier by (minutes)", Some(default_flexibility)) as i64;
+        let later =
+            self.get_int_input("Can come later by (minutes)", Some(default_flexibility)) as i64;
+        let flexibility = earlier.max(later);
+        let blackouts = self.get_input(
+            "Times the patient cannot attend, e.g. 15:00-16:00 (comma separated, blank for none)",
+            Some(""),
+        );
+        let window_days = self.get_int_input(
+            "Or search several days: number of days (0 = just that day)",
+            Some(0),
+        );
+        let date_window = if window_days > 0 {
+            let weekdays_only = self.get_input("Weekdays only? (y/n)", Some("y"));
+            let time_of_day = self.get_input("Time of day (any/morning/afternoon)", Some("any"));
+            let first_day = preferred_time.date_naive();
+            let weekdays = if weekdays_only.to_lowercase() == "y" {
+                vec![
+                    Weekday::Mon,
+                    Weekday::Tue,
+                    Weekday::Wed,
+                    Weekday::Thu,
+                    Weekday::Fri,
+                ]
+            } else {
+                Vec::new()
+            };
+            let hours = |from, until| {
+                vec![(
+                    NaiveTime::from_hms_opt(from, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(until, 0, 0).unwrap(),
+                )]
+            };
+            let times_of_day = match time_of_day.to_lowercase().as_str() {
+                "morning" => hours(8, 12),
+                "afternoon" => hours(12, 18),
+                _ => Vec::new(),
+            };
+            match DateWindow::new(
+                first_day,
+                first_day + Duration::days(window_days as i64 - 1),
+                weekdays,
+                times_of_day,
+            ) {
+                Ok(window) => Some(window),
+                Err(e) => {
+                    println!("Error: {}", e);
+                    return;
+                }
+            }
+        } else {
+            None
+        };
+
+        let source = loop {
+            let value = self.get_input("Source (phone/walk-in/web/referral)", Some("phone"));
+            match RequestSource::from_string(&value) {
+                Ok(source) => break source,
+                Err(e) => println!("{}", e),
+            }
+        };
+        let referrer = if source == RequestSource::Referral {
+            self.get_input("Referring partner (blank for none)", Some(""))
+        } else {
+            String::new()
+        };
 
-        let patient_id = uuid::Uuid::new_v4().to_string()[..8].to_string();
+        let Some(calendar) = self.calendar() else {
+            return;
+        };
+        let patient_id = calendar.next_id(IdKind::Patient);
 
         match create_appointment_request(
+            calendar.id_generator(),
+            calendar.now(),
             patient_id,
             patient_name.clone(),
             patient_contact,
-            priority,
+            &priority,
             preferred_time,
             reason,
             flexibility,
         ) {
-            Ok(request) => {
+            Ok(mut request) => {
+                request.source = source;
+                request.duration_minutes = template.and_then(|template| template.duration_minutes);
+                request.date_window = date_window;
+                if earlier != later {
+                    if let Err(e) = request.set_window(earlier, later) {
+                        println!("Error: {}", e);
+                        return;
+                    }
+                }
+                for range in blackouts.split(',').filter(|range| !range.trim().is_empty()) {
+                    let times = range.split_once('-').and_then(|(start, end)| {
+                        let date = preferred_time.date_naive();
+                        let start = NaiveTime::parse_from_str(start.trim(), "%H:%M").ok()?;
+                        let end = NaiveTime::parse_from_str(end.trim(), "%H:%M").ok()?;
+                        Some((
+                            date.and_time(start).and_local_timezone(Local).single()?,
+                            date.and_time(end).and_local_timezone(Local).single()?,
+                        ))
+                    });
+                    let added = match times {
+                        Some((start, end)) => request.add_unavailable_time(start, end),
+                        None => Err(format!("Invalid time range: {}", range.trim())),
+                    };
+                    if let Err(e) = added {
+                        println!("Error: {}", e);
+                        return;
+                    }
+                }
+                if !referrer.is_empty() {
+                    request.referrer = Some(referrer);
+                }
                 if let Some(scheduler) = &mut self.scheduler {
-                    scheduler.add_request(request);
+                    match scheduler.add_request(request) {
+                        Ok(Some(result)) => {
+                            println!("\nRequest for {} processed immediately", patient_name);
+                            match &result.appointment {
+                                Some(apt) => println!(
+                                    "Booked: {} (code {})",
+                                    apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                                    apt.confirmation_code
+                                ),
+                                None => {
+                                    println!("Not booked: {}", result.message);
+                                    Self::print_alternatives(&result);
+                                }
+                            }
+                            return;
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            println!("Request rejected: {}", e);
+                            return;
+                        }
+                    }
                     println!("\nRequest submitted for {}", patient_name);
                     println!("Priority: {}", priority.to_uppercase());
                     println!(
@@ -213,7 +712,12 @@ impl AppointmentCLI {
                     println!("Pending requests in queue: {}", scheduler.get_pending_count());
                 }
             }
-            Err(e) => println!("Error creating request: {}", e),
+            Err(errors) => {
+                println!("Error creating request:");
+                for (field, message) in errors.iter() {
+                    println!("  - {}: {}", field, message);
+                }
+            }
         }
     }
 
@@ -240,6 +744,9 @@ impl AppointmentCLI {
         println!("  Confirmed: {}", result.confirmed.len());
         println!("  Failed: {}", result.failed.len());
         println!("  Success rate: {:.1}%", result.success_rate());
+        if let Some(e) = &result.queue_error {
+            println!("  Queue error: {}", e);
+        }
 
         if !result.confirmed.is_empty() {
             println!("\nConfirmed appointments:");
@@ -255,23 +762,37 @@ impl AppointmentCLI {
 
         if !result.failed.is_empty() {
             println!("\nFailed requests:");
-            for fail in &result.failed {
-                println!("  - {}: {}", fail.request.patient.name, fail.message);
+            for (i, fail) in result.failed.iter().enumerate() {
+                println!("  {}. {}: {}", i + 1, fail.request.patient.name, fail.message);
+                Self::print_alternatives(fail);
+            }
+
+            loop {
+                let choice =
+                    self.get_int_input("Add a disposition to a failed request (0 to skip)", Some(0));
+                if choice <= 0 || choice as usize > result.failed.len() {
+                    break;
+                }
+                let note = self.get_input("Disposition (e.g. patient will call back)", None);
+                let request_id = &result.failed[choice as usize - 1].request.request_id;
+                match scheduler.set_disposition(&result.run_id, request_id, &note) {
+                    Ok(()) => println!("Disposition recorded"),
+                    Err(e) => println!("Error: {}", e),
+                }
             }
         }
 
         // Put back the scheduler and update calendar
-        self.calendar = Some(scheduler.calendar.clone());
         self.scheduler = Some(scheduler);
     }
 
     fn view_available_slots(&self) {
-        if self.calendar.is_none() {
+        if self.calendar().is_none() {
             println!("\nPlease setup a calendar first (option 1)");
             return;
         }
 
-        if let Some(calendar) = &self.calendar {
+        if let Some(calendar) = self.calendar() {
             // Fixed: Using getter method, not direct field access
             let slots = calendar.available_slots();
 
@@ -295,6 +816,7 @@ impl AppointmentCLI {
                 if Some(slot_date) != current_date {
                     current_date = Some(slot_date);
                     println!("\n{}:", slot_date.format("%A, %Y-%m-%d"));
+                    Self::print_day_notes(calendar, slot_date);
                 }
 
                 println!(
@@ -307,12 +829,12 @@ impl AppointmentCLI {
     }
 
     fn view_appointments(&self) {
-        if self.calendar.is_none() {
+        if self.calendar().is_none() {
             println!("\nPlease setup a calendar first (option 1)");
             return;
         }
 
-        if let Some(calendar) = &self.calendar {
+        if let Some(calendar) = self.calendar() {
             // Fixed: Using getter method, not direct field access
             let appointments = calendar.appointments();
 
@@ -321,7 +843,13 @@ impl AppointmentCLI {
                 return;
             }
 
-            println!("\n--- Confirmed Appointments ({}) ---", appointments.len());
+            let labels = &calendar.labels;
+            let ansi = Self::use_color();
+            println!(
+                "\n--- Confirmed Appointments for {} ({}) ---",
+                labels.doctor.paint(&calendar.doctor_name, ansi),
+                appointments.len()
+            );
 
             let mut current_date = None;
             for apt in appointments {
@@ -329,27 +857,38 @@ impl AppointmentCLI {
                 if Some(apt_date) != current_date {
                     current_date = Some(apt_date);
                     println!("\n{}:", apt_date.format("%A, %Y-%m-%d"));
+                    Self::print_day_notes(calendar, apt_date);
                 }
 
                 println!(
                     "  {} - {} ({}) - {}",
                     apt.time_slot.start_time.format("%H:%M"),
                     apt.patient.name,
-                    apt.priority.name(),
+                    labels.priority(apt.priority).paint(apt.priority.name(), ansi),
                     apt.reason
                 );
-                println!("    ID: {}...", &apt.appointment_id[..8]);
+                println!(
+                    "    ID: {}...  Code: {}",
+                    &apt.appointment_id[..8],
+                    apt.confirmation_code
+                );
             }
         }
     }
 
+    fn print_day_notes(calendar: &DoctorCalendar, date: NaiveDate) {
+        for note in calendar.day_notes(date) {
+            println!("  Note: {}", note);
+        }
+    }
+
     fn cancel_appointment(&mut self) {
-        if self.calendar.is_none() {
+        if self.calendar().is_none() {
             println!("\nPlease setup a calendar first (option 1)");
             return;
         }
 
-        if let Some(calendar) = &self.calendar {
+        if let Some(calendar) = self.calendar() {
             // Fixed: Using getter method
             let appointments = calendar.appointments();
             if appointments.is_empty() {
@@ -378,122 +917,1094 @@ impl AppointmentCLI {
                 let apt_to_cancel = &appointments[choice as usize - 1];
                 let apt_id = apt_to_cancel.appointment_id.clone();
                 let patient_name = apt_to_cancel.patient.name.clone();
+                let slot = apt_to_cancel.time_slot.clone();
+                let mut slot_free = false;
 
-                if let Some(calendar) = &mut self.calendar {
-                    if calendar.cancel_appointment(&apt_id) {
-                        println!("\nAppointment for {} cancelled", patient_name);
-                        println!("Time slot is now available again");
+                let by_patient = self.get_input("Cancelled by the patient? (y/n)", Some("n"));
+                let actor = if by_patient.to_lowercase() == "y" {
+                    CancellationActor::Patient
+                } else {
+                    CancellationActor::Staff
+                };
 
-                        // Update scheduler
-                        if let Some(scheduler) = &mut self.scheduler {
-                            scheduler.calendar = calendar.clone();
+                if let Some(scheduler) = &mut self.scheduler {
+                    match scheduler.cancel_appointment(&apt_id, actor) {
+                        Ok(outcome) => {
+                            let record = &outcome.records[0];
+                            println!("\nAppointment for {} cancelled", patient_name);
+                            if record.late {
+                                println!("Recorded as a LATE cancellation");
+                                if let Some(fee) = record.fee_cents {
+                                    println!("Late cancellation fee: {}.{:02}", fee / 100, fee % 100);
+                                }
+                            }
+                            if outcome.records.len() > 1 {
+                                println!(
+                                    "{} linked bundle appointments also cancelled",
+                                    outcome.records.len() - 1
+                                );
+                            }
+                            for apt in &outcome.promoted {
+                                println!(
+                                    "Slot at {} given to {} from the waitlist",
+                                    apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                                    apt.patient.name
+                                );
+                            }
+
+                            slot_free = scheduler.calendar.is_slot_free(&slot.slot_id);
+                            if slot_free {
+                                println!("Time slot is now available again");
+                            }
                         }
-                    } else {
-                        println!("\nFailed to cancel appointment");
+                        Err(e) => println!("\nFailed to cancel appointment: {}", e),
                     }
                 }
+
+                if slot_free && slot.start_time > self.now() {
+                    self.offer_bring_forward(&slot);
+                }
             }
         }
     }
 
-    fn run_demo(&mut self) {
-        println!("\n--- Running Demo ---");
-
-        let calendar = DoctorCalendar::new("Dr. Demo".to_string(), 30).unwrap();
-        let mut scheduler = AppointmentScheduler::new(calendar, true);
-
-        let tomorrow = Local::now() + Duration::days(1);
-        // Fixed: Datelike trait in scope
-        scheduler.calendar.generate_daily_slots(
-            tomorrow,
-            9,
-            17,
-            None,
-            Some(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
-            Some(NaiveTime::from_hms_opt(13, 0, 0).unwrap()),
-        );
+    fn offer_bring_forward(&mut self, slot: &TimeSlot) {
+        let offer = self.get_input("Offer the slot to a patient booked later? (y/n)", Some("n"));
+        if offer.to_lowercase() != "y" {
+            return;
+        }
 
+        let scheduler = self.scheduler.as_mut().unwrap();
+        let mut channel = InMemoryChannel::default();
+        let offer = match scheduler.offer_bring_forward(&slot.slot_id, &mut channel) {
+            Ok(Some(offer)) => offer,
+            Ok(None) => {
+                println!("\nNo patient booked later can take this slot");
+                return;
+            }
+            Err(e) => {
+                println!("\nError: {}", e);
+                return;
+            }
+        };
         println!(
-            "Created calendar with {} slots",
-            scheduler.calendar.available_slots().len()
+            "\nOffered to {} (booked {})",
+            offer.patient.name,
+            offer.current_slot.start_time.format("%Y-%m-%d %H:%M")
         );
+        for notification in &channel.sent {
+            println!("  Notified {}: {}", notification.recipient, notification.body);
+        }
 
-        let requests = vec![
-            create_appointment_request(
-                "P001".to_string(),
-                "John Smith".to_string(),
-                "john@email.com".to_string(),
-                "routine",
-                tomorrow
-                    .date_naive()
-                    .and_hms_opt(10, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap(),
-                "Annual checkup".to_string(),
-                60,
-            )
-            .unwrap(),
-            create_appointment_request(
-                "P002".to_string(),
-                "Jane Doe".to_string(),
-                "jane@email.com".to_string(),
-                "emergency",
-                tomorrow
-                    .date_naive()
-                    .and_hms_opt(10, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap(),
-                "Severe chest pain".to_string(),
-                30,
-            )
-            .unwrap(),
-            create_appointment_request(
-                "P003".to_string(),
-                "Bob Wilson".to_string(),
-                "bob@email.com".to_string(),
-                "urgent",
-                tomorrow
-                    .date_naive()
-                    .and_hms_opt(14, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap(),
-                "Follow-up on test results".to_string(),
-                60,
-            )
-            .unwrap(),
-            create_appointment_request(
-                "P004".to_string(),
-                "Alice Brown".to_string(),
-                "alice@email.com".to_string(),
-                "routine",
-                tomorrow
-                    .date_naive()
-                    .and_hms_opt(11, 0, 0)
-                    .unwrap()
-                    .and_local_timezone(Local)
-                    .unwrap(),
-                "Prescription renewal".to_string(),
-                120,
-            )
-            .unwrap(),
-        ];
-
-        println!("\nSubmitting {} appointment requests...", requests.len());
-        println!("  - John Smith: Routine at 10:00");
-        println!("  - Jane Doe: EMERGENCY at 10:00");
-        println!("  - Bob Wilson: Urgent at 14:00");
-        println!("  - Alice Brown: Routine at 11:00");
+        let answer = self.get_input("Did they accept? (y/n)", Some("n"));
+        let scheduler = self.scheduler.as_mut().unwrap();
+        if answer.to_lowercase() == "y" {
+            match scheduler.accept_bring_forward(&offer.token) {
+                Ok(apt) => println!(
+                    "\n{} moved to {}",
+                    apt.patient.name,
+                    apt.time_slot.start_time.format("%Y-%m-%d %H:%M")
+                ),
+                Err(e) => println!("\nError: {}", e),
+            }
+        } else {
+            match scheduler.decline_bring_forward(&offer.token) {
+                Ok(Some(apt)) => println!("\nSlot given to {} from the waitlist", apt.patient.name),
+                Ok(None) => println!("\nSlot is free again"),
+                Err(e) => println!("\nError: {}", e),
+            }
+        }
+    }
 
-        let result = scheduler.schedule_batch(requests);
+    fn view_appointment_details(&mut self) {
+        if self.calendar().is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
 
-        println!("\n--- Scheduling Results ---");
-        println!("Success rate: {:.1}%", result.success_rate());
-        println!("\nConfirmed appointments (in scheduled order):");
+        let appointments = self.calendar().unwrap().appointments();
+        if appointments.is_empty() {
+            println!("\nNo confirmed appointments");
+            return;
+        }
 
-        for apt in &result.confirmed {
+        println!("\n--- Appointment Details ---");
+        for (i, apt) in appointments.iter().enumerate() {
+            println!(
+                "  {}. {} - {}",
+                i + 1,
+                apt.patient.name,
+                apt.time_slot.start_time.format("%Y-%m-%d %H:%M")
+            );
+        }
+
+        let choice = self.get_int_input("Select appointment (0 to go back)", Some(0));
+        if choice <= 0 || (choice as usize) > appointments.len() {
+            return;
+        }
+
+        let apt = &appointments[choice as usize - 1];
+        println!("\nPatient:  {} ({})", apt.patient.name, apt.patient.contact);
+        println!(
+            "Time:     {} - {}",
+            apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+            apt.time_slot.end_time.format("%H:%M")
+        );
+        println!("Priority: {}", apt.priority.name());
+        println!("Reason:   {}", apt.reason);
+        println!("ID:       {}", apt.appointment_id);
+        println!("Code:     {}", apt.confirmation_code);
+        println!("Status:   {}", apt.status.name());
+
+        println!("\nHistory:");
+        let calendar = self.calendar().unwrap();
+        for change in calendar.appointment_history(&apt.appointment_id) {
+            println!(
+                "  {} - {}",
+                change.changed_at.format("%Y-%m-%d %H:%M"),
+                change.describe()
+            );
+        }
+
+        let print_card = self.get_input("\nPrint appointment card? (y/n)", Some("n"));
+        if print_card.to_lowercase() == "y" {
+            let calendar = self.calendar().unwrap();
+            match calendar.render_appointment_card(&apt.appointment_id, CardFormat::Text) {
+                Ok(card) => println!("\n{}", card),
+                Err(e) => println!("Error rendering card: {}", e),
+            }
+        }
+
+        let rebook = self.get_input("\nBook another visit like this one? (y/n)", Some("n"));
+        if rebook.to_lowercase() == "y" {
+            self.rebook_like(apt);
+            return;
+        }
+
+        let note = self.get_input("\nAdd a note (leave empty to skip)", None);
+        if note.is_empty() {
+            return;
+        }
+
+        if let Some(calendar) = self.calendar_mut() {
+            match calendar.add_appointment_note(&apt.appointment_id, note) {
+                Ok(()) => println!("Note added"),
+                Err(e) => println!("Error adding note: {}", e),
+            }
+        }
+    }
+
+    fn rebook_like(&mut self, apt: &Appointment) {
+        let days = self.get_int_input("In how many days", Some(7));
+        let start = apt.time_slot.start_time;
+        let hours = self.get_int_input("Hour (0-23)", Some(start.hour() as i32));
+        let minutes = self.get_int_input("Minute (0-59)", Some(start.minute() as i32));
+        let preferred_time = match (self.now() + Duration::days(days as i64))
+            .date_naive()
+            .and_hms_opt(hours as u32, minutes as u32, 0)
+            .and_then(|time| time.and_local_timezone(Local).earliest())
+        {
+            Some(time) => time,
+            None => {
+                println!("Invalid time");
+                return;
+            }
+        };
+
+        let scheduler = match &mut self.scheduler {
+            Some(scheduler) => scheduler,
+            None => return,
+        };
+        let request = match scheduler.rebook_like(&apt.appointment_id, preferred_time) {
+            Ok(request) => request,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+        match scheduler.add_request(request) {
+            Ok(Some(result)) => match &result.appointment {
+                Some(booked) => println!(
+                    "\nBooked: {} (code {})",
+                    booked.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                    booked.confirmation_code
+                ),
+                None => {
+                    println!("\nNot booked: {}", result.message);
+                    Self::print_alternatives(&result);
+                }
+            },
+            Ok(None) => println!(
+                "\nRequest for {} at {} added to the queue",
+                apt.patient.name,
+                preferred_time.format("%Y-%m-%d %H:%M")
+            ),
+            Err(e) => println!("Request rejected: {}", e),
+        }
+    }
+
+    fn view_dashboard(&self) {
+        let scheduler = match &self.scheduler {
+            Some(scheduler) => scheduler,
+            None => {
+                println!("\nPlease setup a calendar first (option 1)");
+                return;
+            }
+        };
+        let summary = reports::dashboard(scheduler);
+
+        println!("\n--- Dashboard: {} ---", scheduler.calendar.doctor_name);
+
+        println!("\nToday's appointments ({}):", summary.today.len());
+        for apt in &summary.today {
+            println!(
+                "  {}  {:<15} {:<10} {}",
+                apt.time_slot.start_time.format("%H:%M"),
+                apt.patient.name,
+                apt.priority.name(),
+                apt.reason
+            );
+        }
+
+        match &summary.next_free_slot {
+            Some(slot) => println!(
+                "\nNext free slot: {}",
+                slot.start_time.format("%a %Y-%m-%d %H:%M")
+            ),
+            None => println!("\nNext free slot: none"),
+        }
+
+        println!("\nPending requests:");
+        for (priority, count) in &summary.pending_by_priority {
+            println!("  {:<10} {}", priority.name(), count);
+        }
+        if let Some(e) = &summary.queue_error {
+            println!("  Queue error: {}", e);
+        }
+
+        let source_stats = scheduler.source_stats();
+        if !source_stats.is_empty() {
+            println!("\nRequests by channel:");
+            for (source, stats) in source_stats {
+                println!(
+                    "  {:<12} {} booked, {} failed ({:.1}% failure)",
+                    source.name(),
+                    stats.booked,
+                    stats.failed,
+                    stats.failure_rate()
+                );
+            }
+        }
+
+        println!("\nWaitlist (unscheduled): {}", summary.waitlist);
+        println!("Failed requests awaiting follow-up: {}", summary.open_failures);
+        println!(
+            "This week: {}/{} slots booked ({:.1}% utilization)",
+            summary.week_booked_slots,
+            summary.week_total_slots,
+            summary.week_utilization()
+        );
+        println!(
+            "Idle time between today's appointments: {} minutes",
+            summary.today_idle_gap_minutes
+        );
+
+        if !summary.duration_advice.is_empty() {
+            println!("\nSlot length suggestions:");
+            for advice in &summary.duration_advice {
+                let stats = &advice.stats;
+                let finding = if advice.is_longer() {
+                    format!("{} of {} visits ran over", stats.overrun_visits, stats.visits)
+                } else {
+                    format!("{} of {} visits finished early", stats.early_visits, stats.visits)
+                };
+                let target = match &advice.template {
+                    Some(name) => format!("template '{}'", name),
+                    None => format!("'{}' slots", stats.appointment_type),
+                };
+                println!(
+                    "  {}: {}; change {} from {} to {} min",
+                    stats.appointment_type,
+                    finding,
+                    target,
+                    advice.current_minutes,
+                    advice.suggested_minutes
+                );
+            }
+        }
+    }
+
+    fn view_today(&mut self) {
+        let calendar = match self.calendar() {
+            Some(calendar) => calendar,
+            None => {
+                println!("\nPlease setup a calendar first (option 1)");
+                return;
+            }
+        };
+
+        let today = calendar.today();
+        let labels = &calendar.labels;
+        let ansi = Self::use_color();
+        println!(
+            "\n--- Today: {} - {} ---",
+            labels.doctor.paint(&calendar.doctor_name, ansi),
+            calendar.now().format("%A, %Y-%m-%d")
+        );
+        Self::print_day_notes(calendar, calendar.now().date_naive());
+        if today.is_empty() {
+            println!("\nNo appointments today");
+        }
+
+        for (i, (apt, status)) in today.iter().enumerate() {
+            println!(
+                "  {}. {}  {:<12} {} {}",
+                i + 1,
+                apt.time_slot.start_time.format("%H:%M"),
+                status.name(),
+                labels
+                    .priority(apt.priority)
+                    .paint(&format!("{:<15}", apt.patient.name), ansi),
+                apt.reason
+            );
+        }
+
+        let action = self.get_input(
+            "Confirm (k), check in (c), complete (d), fill gaps from the waitlist (f) or Enter to go back",
+            Some(""),
+        );
+        let action = action.to_lowercase();
+        if action == "f" {
+            self.fill_today();
+            return;
+        }
+        if action != "k" && action != "c" && action != "d" {
+            return;
+        }
+
+        let choice = self.get_int_input("Appointment number", Some(0));
+        if choice <= 0 || choice as usize > today.len() {
+            return;
+        }
+        let apt_id = today[choice as usize - 1].0.appointment_id.clone();
+
+        if action == "c" && self.scheduler.is_some() {
+            self.arrive(&apt_id);
+            return;
+        }
+        if let Some(calendar) = self.calendar_mut() {
+            let result = match action.as_str() {
+                "k" => calendar.confirm(&apt_id),
+                "c" => calendar.check_in(&apt_id),
+                _ => calendar.complete(&apt_id),
+            };
+            match result {
+                Ok(apt) => {
+                    println!(
+                        "\n{} is now {}",
+                        apt.patient.name,
+                        apt.attendance_status(calendar.now()).name()
+                    );
+                }
+                Err(e) => println!("\nError: {}", e),
+            }
+        }
+    }
+
+    fn arrive(&mut self, apt_id: &str) {
+        let scheduler = self.scheduler.as_mut().unwrap();
+        let mut report = match scheduler.arrive(apt_id) {
+            Ok(report) => report,
+            Err(e) => {
+                println!("\nError: {}", e);
+                return;
+            }
+        };
+
+        if let Arrival::Offered {
+            appointment,
+            slot,
+            minutes_late,
+        } = &report.arrival
+        {
+            let prompt = format!(
+                "{} is {} minutes late. Move them to {}? (y/n)",
+                appointment.patient.name,
+                minutes_late,
+                slot.start_time.format("%H:%M")
+            );
+            let (apt_id, slot) = (appointment.appointment_id.clone(), slot.clone());
+            let accept = self.get_input(&prompt, Some("y"));
+            let scheduler = self.scheduler.as_mut().unwrap();
+            let result = if accept.to_lowercase() == "y" {
+                scheduler.accept_late_offer(&apt_id, &slot)
+            } else {
+                scheduler.calendar.check_in(&apt_id).map(|apt| ArrivalReport {
+                    arrival: Arrival::CheckedIn(apt),
+                    backfilled: None,
+                })
+            };
+            report = match result {
+                Ok(report) => report,
+                Err(e) => {
+                    println!("\nError: {}", e);
+                    return;
+                }
+            };
+        }
+
+        let scheduler = self.scheduler.as_mut().unwrap();
+        match &report.arrival {
+            Arrival::CheckedIn(apt) => println!(
+                "\n{} is now {} ({})",
+                apt.patient.name,
+                apt.attendance_status(scheduler.calendar.now()).name(),
+                apt.time_slot.start_time.format("%H:%M")
+            ),
+            Arrival::WalkIn {
+                minutes_late,
+                result,
+            } => {
+                println!(
+                    "\nPatient arrived {} minutes late and was converted to a walk-in",
+                    minutes_late
+                );
+                match result.as_ref().and_then(|result| result.appointment.as_ref()) {
+                    Some(booked) => println!(
+                        "Walk-in booked at {}",
+                        booked.time_slot.start_time.format("%H:%M")
+                    ),
+                    None => println!("Walk-in is waiting for the next gap"),
+                }
+            }
+            Arrival::Offered { .. } => {}
+        }
+        if let Some(backfilled) = &report.backfilled {
+            println!(
+                "Freed slot given to {} from the waitlist",
+                backfilled.patient.name
+            );
+        }
+    }
+
+    fn fill_today(&mut self) {
+        let scheduler = match &self.scheduler {
+            Some(scheduler) => scheduler,
+            None => return,
+        };
+        let mut plan = scheduler.plan_fill_day(scheduler.calendar.now().date_naive());
+        if plan.proposals.is_empty() {
+            println!("\nNothing on the waitlist fits today's free slots");
+            return;
+        }
+
+        loop {
+            println!("\nProposed fill plan:");
+            for (i, proposal) in plan.proposals.iter().enumerate() {
+                println!(
+                    "  {}. {}  {:<15} {:<10} {}",
+                    i + 1,
+                    proposal.slot.start_time.format("%H:%M"),
+                    proposal.request.patient.name,
+                    proposal.request.priority.name(),
+                    proposal.request.reason
+                );
+            }
+            println!(
+                "{} slots left open, {} waitlisted requests not placed",
+                plan.open_slots.len(),
+                plan.unplaced.len()
+            );
+
+            let choice = self.get_int_input("Proposal to drop (0 to continue)", Some(0));
+            if choice <= 0 || choice as usize > plan.proposals.len() {
+                break;
+            }
+            let request_id = plan.proposals[choice as usize - 1].request.request_id.clone();
+            plan.drop_proposal(&request_id);
+            if plan.proposals.is_empty() {
+                return;
+            }
+        }
+
+        let confirm = self.get_input("Book this plan? (y/n)", Some("y"));
+        if confirm.to_lowercase() != "y" {
+            return;
+        }
+
+        let scheduler = self.scheduler.as_mut().unwrap();
+        let report = scheduler.commit_fill(plan);
+        println!("\nBooked {} waitlisted patients", report.booked.len());
+        for (proposal, reason) in &report.failed {
+            println!(
+                "  Could not book {} at {}: {}",
+                proposal.request.patient.name,
+                proposal.slot.start_time.format("%H:%M"),
+                reason
+            );
+        }
+    }
+
+    fn shift_day(&mut self) {
+        if self.calendar().is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
+
+        println!("\n--- Shift a Day's Appointments ---");
+        let days_ahead = self.get_int_input("Day to shift (days from today)", Some(0));
+        let minutes = self.get_int_input("Shift by (minutes, negative = earlier)", Some(120));
+        let date = self.now() + Duration::days(days_ahead as i64);
+        let offset = Duration::minutes(minutes as i64);
+
+        let plan = match self.calendar().unwrap().plan_day_shift(date, offset) {
+            Ok(plan) => plan,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+        if plan.moved.is_empty() && plan.unfit.is_empty() {
+            println!("\nNo appointments on {}", date.format("%Y-%m-%d"));
+            return;
+        }
+
+        println!("\nPlanned changes:");
+        for shifted in &plan.moved {
+            println!(
+                "  {:<15} {} -> {}",
+                shifted.appointment.patient.name,
+                shifted.previous_start.format("%H:%M"),
+                shifted.appointment.time_slot.start_time.format("%H:%M")
+            );
+        }
+        for apt in &plan.unfit {
+            println!(
+                "  {:<15} {} -> no longer fits",
+                apt.patient.name,
+                apt.time_slot.start_time.format("%H:%M")
+            );
+        }
+
+        let confirm = self.get_input("Apply these changes? (y/n)", Some("n"));
+        if confirm.to_lowercase() != "y" {
+            return;
+        }
+
+        if let Some(calendar) = self.calendar_mut() {
+            match calendar.shift_day(date, offset) {
+                Ok(report) => {
+                    println!("\nMoved {} appointments", report.moved.len());
+                    println!("Patients to notify:");
+                    for patient in report.patients_to_notify() {
+                        println!("  - {} ({})", patient.name, patient.contact);
+                    }
+                }
+                Err(e) => println!("\nShift failed: {}", e),
+            }
+        }
+    }
+
+    fn manage_availability(&mut self) {
+        if self.calendar().is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
+
+        println!("\n--- Manage My Availability ---");
+        println!("1. Block time");
+        println!("2. Extend a day");
+        println!("3. Set vacation");
+        println!("4. Request time off");
+        println!("5. Add a day note");
+        println!("6. Import busy times from an ICS calendar");
+        let choice = self.get_int_input("Choice", Some(1));
+        if choice == 6 {
+            let path = self.get_input("ICS file path", None);
+            match self.calendar_mut().unwrap().import_busy_from_ics(&path) {
+                Ok(report) => {
+                    println!(
+                        "\nImported {} busy times, removed {} free slots",
+                        report.busy_times,
+                        report.removed.len()
+                    );
+                    for apt in report.conflicts {
+                        println!(
+                            "  ! {} at {} overlaps a busy time",
+                            apt.patient.name,
+                            apt.time_slot.start_time.format("%Y-%m-%d %H:%M")
+                        );
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            }
+            return;
+        }
+        let days_ahead = self.get_int_input("Day (days from today)", Some(0));
+        let date = (self.now() + Duration::days(days_ahead as i64)).date_naive();
+        if choice == 4 {
+            self.request_time_off(date);
+            return;
+        }
+        if choice == 5 {
+            let note = self.get_input("Note, e.g. locum covering", None);
+            match self.calendar_mut().unwrap().add_day_note(date, note) {
+                Ok(()) => println!("\nNote added to {}", date.format("%A, %Y-%m-%d")),
+                Err(e) => println!("Error: {}", e),
+            }
+            return;
+        }
+        let (first, second) = match choice {
+            1 => (
+                self.get_int_input("From hour", Some(12)),
+                self.get_int_input("To hour", Some(14)),
+            ),
+            2 => (self.get_int_input("Extend until hour", Some(19)), 0),
+            3 => (self.get_int_input("Number of days", Some(5)), 0),
+            _ => {
+                println!("Invalid choice");
+                return;
+            }
+        };
+
+        let calendar = self.calendar_mut().unwrap();
+        let doctor_id = calendar.doctor_id.clone();
+        let mut availability = match DoctorAvailability::for_doctor(calendar, &doctor_id) {
+            Ok(availability) => availability,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+
+        let outcome = match choice {
+            1 => match (
+                date.and_hms_opt(first as u32, 0, 0),
+                date.and_hms_opt(second as u32, 0, 0),
+            ) {
+                (Some(start), Some(end)) => availability
+                    .block_time(
+                        start.and_local_timezone(Local).unwrap(),
+                        end.and_local_timezone(Local).unwrap(),
+                    )
+                    .map(|report| Self::print_block_report(&report)),
+                _ => Err("Invalid hour".to_string()),
+            },
+            2 => availability
+                .extend_day(date, first as u32)
+                .map(|report| println!("\nAdded {} slots", report.created.len())),
+            _ => {
+                let last_day = date + Duration::days(first.max(1) as i64 - 1);
+                availability
+                    .set_vacation(date, last_day)
+                    .map(|report| Self::print_block_report(&report))
+            }
+        };
+
+        if let Err(e) = outcome {
+            println!("Error: {}", e);
+        }
+    }
+
+    fn request_time_off(&mut self, first_day: NaiveDate) {
+        let days = self.get_int_input("Number of days", Some(1)).max(1);
+        let reason = self.get_input("Reason", Some("Leave"));
+        let start = first_day.and_hms_opt(0, 0, 0).unwrap();
+        let end = start + Duration::days(days as i64);
+
+        let scheduler = match &mut self.scheduler {
+            Some(scheduler) => scheduler,
+            None => return,
+        };
+        match (
+            start.and_local_timezone(Local).earliest(),
+            end.and_local_timezone(Local).earliest(),
+        ) {
+            (Some(start), Some(end)) => match scheduler.request_time_off(start, end, reason) {
+                Ok(_) => println!("\nTime off requested; waiting for approval"),
+                Err(e) => println!("Error: {}", e),
+            },
+            _ => println!("Error: Invalid local date"),
+        }
+    }
+
+    fn time_off_approvals(&mut self) {
+        let pending: Vec<TimeOffRequest> = match &self.scheduler {
+            Some(scheduler) => scheduler.pending_time_off().into_iter().cloned().collect(),
+            None => {
+                println!("\nPlease setup a calendar first (option 1)");
+                return;
+            }
+        };
+
+        println!("\n--- Time-off Approvals ---");
+        if pending.is_empty() {
+            println!("\nNo pending time-off requests");
+            return;
+        }
+        for (i, request) in pending.iter().enumerate() {
+            println!(
+                "  {}. {} - {}  {}",
+                i + 1,
+                request.start.format("%Y-%m-%d %H:%M"),
+                request.end.format("%Y-%m-%d %H:%M"),
+                request.reason
+            );
+        }
+
+        let choice = self.get_int_input("Request number (0 to go back)", Some(0));
+        if choice <= 0 || choice as usize > pending.len() {
+            return;
+        }
+        let decision = self.get_input("Approve (a) or reject (r)", Some("a"));
+        let request_id = &pending[choice as usize - 1].request_id;
+
+        let scheduler = self.scheduler.as_mut().unwrap();
+        match decision.to_lowercase().as_str() {
+            "a" => match scheduler.approve_time_off(request_id) {
+                Ok(report) => {
+                    println!("\nApproved; removed {} slots", report.block.removed.len());
+                    for moved in &report.moved {
+                        println!(
+                            "  Moved {} from {} to {}",
+                            moved.appointment.patient.name,
+                            moved.previous_start.format("%Y-%m-%d %H:%M"),
+                            moved.appointment.time_slot.start_time.format("%Y-%m-%d %H:%M")
+                        );
+                    }
+                    for apt in &report.unresolved {
+                        println!(
+                            "  No slot found for {} at {}; please contact {}",
+                            apt.patient.name,
+                            apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                            apt.patient.contact
+                        );
+                    }
+                }
+                Err(e) => println!("Error: {}", e),
+            },
+            "r" => match scheduler.reject_time_off(request_id) {
+                Ok(()) => println!("\nTime-off request rejected"),
+                Err(e) => println!("Error: {}", e),
+            },
+            _ => println!("Invalid choice"),
+        }
+    }
+
+    fn run_campaign(&mut self) {
+        if self.scheduler.is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
+
+        println!("\n--- Recall Campaign ---");
+        let name = self.get_input("Campaign name", Some("Flu shots"));
+        let reason = self.get_input("Appointment reason", Some("Flu vaccination"));
+        let path = self.get_input("Patients CSV (patient_id,patient_name,contact)", None);
+        let starts_in = self.get_int_input("Start (days from today)", Some(1));
+        let weeks = self.get_int_input("Length (weeks)", Some(2)).max(1);
+
+        let patients = match std::fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path, e))
+            .and_then(|text| parse_patients_csv(&text))
+        {
+            Ok(patients) => patients,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+        let first_day = (self.now() + Duration::days(starts_in as i64)).date_naive();
+        let last_day = first_day + Duration::weeks(weeks as i64) - Duration::days(1);
+        let campaign = match RecallCampaign::new(name, reason, first_day, last_day, patients) {
+            Ok(campaign) => campaign,
+            Err(e) => {
+                println!("Error: {}", e);
+                return;
+            }
+        };
+
+        let scheduler = self.scheduler.as_mut().unwrap();
+        let mut channel = InMemoryChannel::default();
+        match campaign.run(scheduler, &mut channel) {
+            Ok(report) => {
+                println!(
+                    "\nBooked {} of {} patients between {} and {}",
+                    report.booked.len(),
+                    campaign.patients.len(),
+                    first_day.format("%Y-%m-%d"),
+                    last_day.format("%Y-%m-%d")
+                );
+                for notification in &channel.sent {
+                    println!("  Notified {}: {}", notification.recipient, notification.body);
+                }
+                for (patient_id, error) in &report.notification_errors {
+                    println!("  Could not notify {}: {}", patient_id, error);
+                }
+                for failed in &report.unscheduled {
+                    println!("  Not booked: {} - {}", failed.request.patient.name, failed.message);
+                }
+            }
+            Err(e) => println!("Error: {}", e),
+        }
+    }
+
+    fn import_appointments(&mut self) {
+        if self.calendar().is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
+
+        println!("\n--- Import Appointments from CSV ---");
+        println!("Columns: patient_id,patient_name,contact,start,end,priority,reason");
+        let path = self.get_input("CSV file", None);
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) => {
+                println!("Failed to read {}: {}", path, e);
+                return;
+            }
+        };
+
+        let calendar = self.calendar_mut().unwrap();
+        let preview = match import_appointments_csv(calendar, &text, true) {
+            Ok(report) => report,
+            Err(e) => {
+                println!("Import failed: {}", e);
+                return;
+            }
+        };
+        println!(
+            "\nPreview: {} appointments to import, {} new slots",
+            preview.imported.len(),
+            preview.slots_created()
+        );
+        for (line, message) in preview.conflicts.iter().chain(&preview.errors) {
+            println!("  Line {}: {}", line, message);
+        }
+        if preview.imported.is_empty() {
+            return;
+        }
+
+        let confirm = self.get_input("Import these appointments? (y/n)", Some("n"));
+        if confirm.to_lowercase() != "y" {
+            return;
+        }
+
+        let calendar = self.calendar_mut().unwrap();
+        match import_appointments_csv(calendar, &text, false) {
+            Ok(report) => println!("\nImported {} appointments", report.imported.len()),
+            Err(e) => println!("Import failed: {}", e),
+        }
+    }
+
+    fn close_out_day(&mut self) {
+        if self.calendar().is_none() {
+            println!("\nPlease setup a calendar first (option 1)");
+            return;
+        }
+
+        println!("\n--- Close Out a Day ---");
+        let days_ago = self.get_int_input("Days ago (0 for today)", Some(0));
+        let date = (self.now() - Duration::days(days_ago as i64)).date_naive();
+
+        if self.calendar().unwrap().is_day_closed(date) {
+            println!("\n{} is already closed", date.format("%Y-%m-%d"));
+            let reopen = self.get_input("Reopen it for corrections? (y/n)", Some("n"));
+            if reopen.to_lowercase() == "y" {
+                let calendar = self.calendar_mut().unwrap();
+                calendar.unlock_day(date);
+                println!("Reopened {}", date.format("%Y-%m-%d"));
+            }
+            return;
+        }
+
+        let open = awaiting_outcome(self.calendar().unwrap(), date);
+        if !open.is_empty() {
+            println!("\nAppointments still needing an outcome:");
+        }
+        for apt in open {
+            let prompt = format!(
+                "  {} {} - (c)ompleted, (n)o-show or (s)kip",
+                apt.time_slot.start_time.format("%H:%M"),
+                apt.patient.name
+            );
+            let outcome = self.get_input(&prompt, Some("s"));
+            let calendar = self.calendar_mut().unwrap();
+            let result = match outcome.to_lowercase().as_str() {
+                "c" => {
+                    let checked_in = match apt.checked_in_at {
+                        Some(_) => Ok(apt.clone()),
+                        None => calendar.check_in(&apt.appointment_id),
+                    };
+                    checked_in.and_then(|_| calendar.complete(&apt.appointment_id))
+                }
+                "n" => calendar.mark_no_show(&apt.appointment_id),
+                _ => continue,
+            };
+            if let Err(e) = result {
+                println!("  Error: {}", e);
+            }
+        }
+
+        let calendar = self.calendar_mut().unwrap();
+        let report = match close_day(calendar, date) {
+            Ok(report) => report,
+            Err(e) => {
+                println!("\nCannot close {}: {}", date.format("%Y-%m-%d"), e);
+                return;
+            }
+        };
+
+        let stats = &report.stats;
+        println!("\nClosed {}", date.format("%A, %Y-%m-%d"));
+        println!("  Appointments:  {}", stats.appointments);
+        println!("  Completed:     {}", stats.completed);
+        println!("  No-shows:      {}", stats.no_shows);
+        println!(
+            "  Cancellations: {} ({} late)",
+            stats.cancellations, stats.late_cancellations
+        );
+        println!(
+            "  Billed:        {}.{:02}",
+            stats.billed_cents / 100,
+            stats.billed_cents % 100
+        );
+
+        let path = self.get_input("Save billing export to (blank to print)", None);
+        if path.is_empty() {
+            println!("\n{}", report.billing_csv);
+        } else {
+            match std::fs::write(&path, &report.billing_csv) {
+                Ok(()) => println!("Billing export saved to {}", path),
+                Err(e) => println!("Failed to write {}: {}", path, e),
+            }
+        }
+    }
+
+    fn print_block_report(report: &BlockReport) {
+        println!("\nRemoved {} free slots", report.removed.len());
+        if !report.refused.is_empty() {
+            println!("Kept {} free slots on locked days", report.refused.len());
+        }
+        if !report.conflicts.is_empty() {
+            println!("Appointments still booked in that time:");
+            for apt in &report.conflicts {
+                println!(
+                    "  - {} at {}",
+                    apt.patient.name,
+                    apt.time_slot.start_time.format("%Y-%m-%d %H:%M")
+                );
+            }
+        }
+    }
+
+    fn run_demo(&mut self) {
+        println!("\n--- Running Demo ---");
+
+        let calendar = DoctorCalendar::new("Dr. Demo".to_string(), 30).unwrap();
+        let mut scheduler = AppointmentScheduler::new(calendar, true);
+
+        let tomorrow = scheduler.calendar.now() + Duration::days(1);
+        // Fixed: Datelike trait in scope
+        scheduler.calendar.generate_daily_slots(
+            tomorrow,
+            9,
+            17,
+            None,
+            Some(NaiveTime::from_hms_opt(12, 0, 0).unwrap()),
+            Some(NaiveTime::from_hms_opt(13, 0, 0).unwrap()),
+        );
+
+        println!(
+            "Created calendar with {} slots",
+            scheduler.calendar.available_slots().len()
+        );
+
+        let requests = vec![
+            create_appointment_request(
+                scheduler.calendar.id_generator(),
+                scheduler.calendar.now(),
+                "P001".to_string(),
+                "John Smith".to_string(),
+                "john@email.com".to_string(),
+                "routine",
+                tomorrow
+                    .date_naive()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap()
+                    .and_local_timezone(Local)
+                    .unwrap(),
+                "Annual checkup".to_string(),
+                60,
+            )
+            .unwrap(),
+            create_appointment_request(
+                scheduler.calendar.id_generator(),
+                scheduler.calendar.now(),
+                "P002".to_string(),
+                "Jane Doe".to_string(),
+                "jane@email.com".to_string(),
+                "emergency",
+                tomorrow
+                    .date_naive()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap()
+                    .and_local_timezone(Local)
+                    .unwrap(),
+                "Severe chest pain".to_string(),
+                30,
+            )
+            .unwrap(),
+            create_appointment_request(
+                scheduler.calendar.id_generator(),
+                scheduler.calendar.now(),
+                "P003".to_string(),
+                "Bob Wilson".to_string(),
+                "bob@email.com".to_string(),
+                "urgent",
+                tomorrow
+                    .date_naive()
+                    .and_hms_opt(14, 0, 0)
+                    .unwrap()
+                    .and_local_timezone(Local)
+                    .unwrap(),
+                "Follow-up on test results".to_string(),
+                60,
+            )
+            .unwrap(),
+            create_appointment_request(
+                scheduler.calendar.id_generator(),
+                scheduler.calendar.now(),
+                "P004".to_string(),
+                "Alice Brown".to_string(),
+                "alice@email.com".to_string(),
+                "routine",
+                tomorrow
+                    .date_naive()
+                    .and_hms_opt(11, 0, 0)
+                    .unwrap()
+                    .and_local_timezone(Local)
+                    .unwrap(),
+                "Prescription renewal".to_string(),
+                120,
+            )
+            .unwrap(),
+        ];
+
+        println!("\nSubmitting {} appointment requests...", requests.len());
+        println!("  - John Smith: Routine at 10:00");
+        println!("  - Jane Doe: EMERGENCY at 10:00");
+        println!("  - Bob Wilson: Urgent at 14:00");
+        println!("  - Alice Brown: Routine at 11:00");
+
+        let result = scheduler.schedule_batch(requests);
+
+        println!("\n--- Scheduling Results ---");
+        println!("Success rate: {:.1}%", result.success_rate());
+        println!("\nConfirmed appointments (in scheduled order):");
+
+        for apt in &result.confirmed {
             println!(
                 "  [{:9}] {:15} -> {}",
                 apt.priority.name(),
@@ -506,7 +2017,6 @@ impl AppointmentCLI {
         println!("even though routine patient John Smith requested the same time.");
 
         // Store the results
-        self.calendar = Some(scheduler.calendar.clone());
         self.scheduler = Some(scheduler);
     }
 
@@ -514,9 +2024,10 @@ impl AppointmentCLI {
         self.print_header();
 
         while self.running {
+            self.print_alerts();
             self.print_menu();
 
-            let choice = self.get_int_input("Enter choice", Some(8));
+            let choice = self.get_int_input("Enter choice", Some(17));
 
             match choice {
                 1 => self.setup_calendar(),
@@ -526,8 +2037,17 @@ impl AppointmentCLI {
                 5 => self.view_available_slots(),
                 6 => self.view_appointments(),
                 7 => self.cancel_appointment(),
-                8 => self.run_demo(),
-                9 => {
+                8 => self.view_appointment_details(),
+                9 => self.view_dashboard(),
+                10 => self.view_today(),
+                11 => self.shift_day(),
+                12 => self.manage_availability(),
+                13 => self.import_appointments(),
+                14 => self.time_off_approvals(),
+                15 => self.run_campaign(),
+                16 => self.close_out_day(),
+                17 => self.run_demo(),
+                18 => {
                     self.running = false;
                     println!("\nGoodbye!");
                 }
@@ -537,7 +2057,430 @@ impl AppointmentCLI {
     }
 }
 
+/// Priority-based medical appointment scheduling.
+///
+/// Without a subcommand the interactive menu is opened.
+#[derive(Parser)]
+#[command(name = "easyappoint", version)]
+struct Cli {
+    /// Calendar snapshot the subcommands read and write (.json or .msgpack).
+    #[arg(long, global = true, default_value = "calendar.json")]
+    calendar: PathBuf,
+    /// Print results as JSON instead of tab-separated lines.
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create an empty calendar for a doctor.
+    Init {
+        #[arg(long)]
+        doctor: String,
+        #[arg(long, default_value_t = 30)]
+        slot_minutes: i64,
+    },
+    /// Manage time slots.
+    Slots {
+        #[command(subcommand)]
+        action: SlotsCommand,
+    },
+    /// Manage queued appointment requests.
+    Request {
+        #[command(subcommand)]
+        action: RequestCommand,
+    },
+    /// Schedule every queued request; those that don't fit stay queued.
+    Schedule,
+    /// List appointments, free slots or queued requests.
+    List {
+        #[arg(value_enum, default_value_t = ListKind::Appointments)]
+        kind: ListKind,
+        /// Only list entries on this day (YYYY-MM-DD).
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Show today's waiting-room board: now serving, next up and later today.
+    Board {
+        /// Print an HTML page that reloads itself instead of text.
+        #[arg(long)]
+        html: bool,
+        /// Seconds between refreshes of the HTML page, or of the terminal with --watch.
+        #[arg(long, default_value_t = 30)]
+        refresh: u64,
+        /// Keep redrawing the board in the terminal.
+        #[arg(long, conflicts_with_all = ["html", "json"])]
+        watch: bool,
+    },
+    /// Query a calendar snapshot, e.g. `appointments where priority=urgent group by day`.
+    Report {
+        snapshot: String,
+        #[arg(required = true)]
+        query: Vec<String>,
+        #[arg(long)]
+        csv: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum SlotsCommand {
+    /// Generate slots over one or more consecutive days.
+    Generate {
+        /// First day (YYYY-MM-DD).
+        #[arg(long)]
+        from: NaiveDate,
+        #[arg(long, default_value_t = 1)]
+        days: u32,
+        #[arg(long, default_value_t = 9)]
+        start_hour: u32,
+        #[arg(long, default_value_t = 17)]
+        end_hour: u32,
+        /// Slot length; defaults to the calendar's.
+        #[arg(long)]
+        minutes: Option<i64>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RequestCommand {
+    /// Queue a request for the next `schedule` run.
+    Add {
+        #[arg(long)]
+        patient_id: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        contact: String,
+        #[arg(long, default_value = "routine")]
+        priority: String,
+        /// Preferred time (YYYY-MM-DD HH:MM).
+        #[arg(long, value_parser = parse_time)]
+        time: DateTime<Local>,
+        #[arg(long)]
+        reason: String,
+        #[arg(long, default_value_t = 60)]
+        flexibility: i64,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ListKind {
+    Appointments,
+    Slots,
+    Requests,
+}
+
+fn snapshot_format(path: &Path) -> SnapshotFormat {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("msgpack") => SnapshotFormat::MessagePack,
+        _ => SnapshotFormat::Json,
+    }
+}
+
+fn load_calendar_file(path: &Path) -> Result<DoctorCalendar, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    DoctorCalendar::from_snapshot(CalendarSnapshot::decode(&bytes, snapshot_format(path))?)
+}
+
+fn save_calendar_file(calendar: &DoctorCalendar, path: &Path) -> Result<(), String> {
+    let bytes = calendar.snapshot().encode(snapshot_format(path))?;
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Load the calendar with its request queue, kept next to it as `<name>.queue.json`.
+fn load_scheduler(path: &Path) -> Result<AppointmentScheduler, String> {
+    let calendar = load_calendar_file(path)?;
+    let queue = FileQueue::open(path.with_extension("queue.json"))?;
+    let mut scheduler = AppointmentScheduler::with_queue(calendar, true, Box::new(queue));
+    scheduler.add_validator(Box::new(ContactFormatValidator));
+    scheduler.add_validator(Box::new(DuplicateRequestValidator));
+    Ok(scheduler)
+}
+
+/// Patients listed under each heading of the queue board.
+const BOARD_ENTRIES: usize = 5;
+
+fn to_json(value: &impl Serialize) -> Result<String, String> {
+    serde_json::to_string_pretty(value)
+        .map(|json| json + "\n")
+        .map_err(|e| format!("Failed to encode JSON: {}", e))
+}
+
+fn run_command(calendar_path: &Path, command: Command, json: bool) -> Result<String, String> {
+    let mut output = String::new();
+    match command {
+        Command::Init {
+            doctor,
+            slot_minutes,
+        } => {
+            if calendar_path.exists() {
+                return Err(format!("{} already exists", calendar_path.display()));
+            }
+            let calendar = DoctorCalendar::new(doctor, slot_minutes)?;
+            save_calendar_file(&calendar, calendar_path)?;
+            output = if json {
+                to_json(&serde_json::json!({
+                    "doctor_id": calendar.doctor_id,
+                    "doctor_name": calendar.doctor_name,
+                    "calendar": calendar_path,
+                }))?
+            } else {
+                format!(
+                    "Created calendar for {} in {}\n",
+                    calendar.doctor_name,
+                    calendar_path.display()
+                )
+            };
+        }
+        Command::Slots {
+            action:
+                SlotsCommand::Generate {
+                    from,
+                    days,
+                    start_hour,
+                    end_hour,
+                    minutes,
+                },
+        } => {
+            let mut calendar = load_calendar_file(calendar_path)?;
+            let mut created = Vec::new();
+            for offset in 0..days {
+                let day = (from + Duration::days(offset as i64))
+                    .and_time(NaiveTime::MIN)
+                    .and_local_timezone(Local)
+                    .earliest()
+                    .ok_or("Invalid local date")?;
+                created.extend(
+                    calendar
+                        .generate_daily_slots(day, start_hour, end_hour, minutes, None, None)
+                        .created,
+                );
+            }
+            save_calendar_file(&calendar, calendar_path)?;
+            output = if json {
+                to_json(&created)?
+            } else {
+                format!("Created {} slots\n", created.len())
+            };
+        }
+        Command::Request {
+            action:
+                RequestCommand::Add {
+                    patient_id,
+                    name,
+                    contact,
+                    priority,
+                    time,
+                    reason,
+                    flexibility,
+                },
+        } => {
+            let mut scheduler = load_scheduler(calendar_path)?;
+            let request = create_appointment_request(
+                scheduler.calendar.id_generator(),
+                scheduler.calendar.now(),
+                patient_id,
+                name,
+                contact,
+                &priority,
+                time,
+                reason,
+                flexibility,
+            )
+            .map_err(|e| e.to_string())?;
+            let request_id = request.request_id.clone();
+            scheduler.add_request(request)?;
+            output = if json {
+                to_json(&serde_json::json!({ "request_id": request_id }))?
+            } else {
+                format!("Queued request {}\n", request_id)
+            };
+        }
+        Command::Schedule => {
+            let mut scheduler = load_scheduler(calendar_path)?;
+            let result = scheduler.process_queue();
+            save_calendar_file(&scheduler.calendar, calendar_path)?;
+            let mut unscheduled = Vec::new();
+            for failure in result.failed {
+                let request = failure.request.clone();
+                let (status, reason) = match scheduler.add_request(failure.request) {
+                    Ok(_) => ("queued", failure.message),
+                    Err(e) => ("dropped", e),
+                };
+                unscheduled.push((status, request, reason));
+            }
+            if let Some(e) = result.queue_error {
+                return Err(e);
+            }
+            if json {
+                let unscheduled: Vec<serde_json::Value> = unscheduled
+                    .iter()
+                    .map(|(status, request, reason)| {
+                        serde_json::json!({ "status": status, "request": request, "reason": reason })
+                    })
+                    .collect();
+                return to_json(&serde_json::json!({
+                    "booked": result.confirmed,
+                    "unscheduled": unscheduled,
+                }));
+            }
+            for apt in &result.confirmed {
+                output.push_str(&format!(
+                    "booked\t{}\t{}\t{}\n",
+                    apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                    apt.patient.name,
+                    apt.confirmation_code
+                ));
+            }
+            for (status, request, reason) in unscheduled {
+                output.push_str(&format!(
+                    "{}\t{}\t{}\t{}\n",
+                    status,
+                    request.preferred_time.format("%Y-%m-%d %H:%M"),
+                    request.patient.name,
+                    reason
+                ));
+            }
+        }
+        Command::List { kind, date } => {
+            let on_date = |time: DateTime<Local>| date.is_none_or(|date| time.date_naive() == date);
+            match kind {
+                ListKind::Appointments => {
+                    let calendar = load_calendar_file(calendar_path)?;
+                    let mut appointments = calendar.appointments();
+                    appointments.retain(|apt| on_date(apt.time_slot.start_time));
+                    if json {
+                        return to_json(&appointments);
+                    }
+                    for apt in appointments {
+                        output.push_str(&format!(
+                            "{}\t{}\t{}\t{}\t{}\n",
+                            apt.time_slot.start_time.format("%Y-%m-%d %H:%M"),
+                            apt.patient.name,
+                            apt.priority.name(),
+                            apt.reason,
+                            apt.confirmation_code
+                        ));
+                    }
+                }
+                ListKind::Slots => {
+                    let calendar = load_calendar_file(calendar_path)?;
+                    let mut slots = calendar.available_slots();
+                    slots.retain(|slot| on_date(slot.start_time));
+                    if json {
+                        return to_json(&slots);
+                    }
+                    for slot in slots {
+                        output.push_str(&format!(
+                            "{}\t{}\t{}\n",
+                            slot.start_time.format("%Y-%m-%d %H:%M"),
+                            slot.end_time.format("%H:%M"),
+                            slot.slot_id
+                        ));
+                    }
+                }
+                ListKind::Requests => {
+                    let scheduler = load_scheduler(calendar_path)?;
+                    let mut requests = scheduler.pending_requests()?;
+                    requests.retain(|request| on_date(request.preferred_time));
+                    if json {
+                        return to_json(&requests);
+                    }
+                    for request in requests {
+                        output.push_str(&format!(
+                            "{}\t{}\t{}\t{}\t{}\n",
+                            request.preferred_time.format("%Y-%m-%d %H:%M"),
+                            request.patient.name,
+                            request.priority.name(),
+                            request.reason,
+                            request.request_id
+                        ));
+                    }
+                }
+            }
+        }
+        Command::Board {
+            html,
+            refresh,
+            watch,
+        } => loop {
+            let calendar = load_calendar_file(calendar_path)?;
+            let board = QueueBoard::for_calendar(&calendar, BOARD_ENTRIES);
+            if json {
+                return to_json(&board);
+            }
+            if html {
+                return Ok(board.to_html(refresh));
+            }
+            if !watch {
+                return Ok(board.to_text());
+            }
+            let mut stdout = io::stdout();
+            write!(stdout, "\x1B[2J\x1B[H{}", board.to_text())
+                .and_then(|()| stdout.flush())
+                .map_err(|e| e.to_string())?;
+            std::thread::sleep(std::time::Duration::from_secs(refresh.max(1)));
+        },
+        Command::Report {
+            snapshot,
+            query,
+            csv,
+        } => {
+            let calendar = load_calendar_file(Path::new(&snapshot))?;
+            let result = parse_query(&query.join(" "), calendar.now().date_naive())?.run(&calendar);
+            output = if json {
+                let rows: Vec<serde_json::Map<String, serde_json::Value>> = result
+                    .rows
+                    .iter()
+                    .map(|row| {
+                        result
+                            .columns
+                            .iter()
+                            .cloned()
+                            .zip(row.iter().cloned().map(serde_json::Value::String))
+                            .collect()
+                    })
+                    .collect();
+                to_json(&rows)?
+            } else if csv {
+                result.to_csv()
+            } else {
+                result.to_table()
+            };
+        }
+    }
+    Ok(output)
+}
+
 fn main() {
+    #[cfg(feature = "bench")]
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.first().map(String::as_str) == Some("--bench-mode") {
+            let result = bench::parse_scales(&args[1..]).and_then(|scales| bench::run(&scales));
+            if let Err(e) = result {
+                eprintln!("Benchmark failed: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    let cli = Cli::parse();
+    if let Some(command) = cli.command {
+        match run_command(&cli.calendar, command, cli.json) {
+            Ok(output) => print!("{}", output),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let mut cli = AppointmentCLI::new();
     cli.run();
 }
@@ -1,22 +1,24 @@
-/// Data models for the appointment scheduling system.
-///
-/// This module defines the core data structures used throughout the system:
-/// - Priority: Enum for appointment urgency levels
-/// - Patient: Patient information
-/// - TimeSlot: Available time windows in the calendar
-/// - Appointment: Confirmed appointment details
-/// - AppointmentRequest: Patient request for an appointment
-
-use chrono::{DateTime, Duration, Local};
+//! Data models for the appointment scheduling system.
+//!
+//! This module defines the core data structures used throughout the system:
+//! - Priority: Enum for appointment urgency levels
+//! - Patient: Patient information
+//! - TimeSlot: Available time windows in the calendar
+//! - Appointment: Confirmed appointment details
+//! - AppointmentRequest: Patient request for an appointment
+
+use crate::ids::{IdGenerator, IdKind};
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
-use uuid::Uuid;
 
 /// Priority levels for appointments.
 ///
 /// Higher numeric values indicate higher priority.
 /// Emergency cases are scheduled first, followed by urgent,
 /// then routine appointments.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum Priority {
     Routine = 1,
     Urgent = 2,
@@ -47,7 +49,7 @@ impl Priority {
 }
 
 /// Represents a patient in the scheduling system.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Patient {
     pub patient_id: String,
     pub name: String,
@@ -56,18 +58,23 @@ pub struct Patient {
 
 impl Patient {
     /// Create a new patient with validation.
-    pub fn new(patient_id: String, name: String, contact: String) -> Result<Self, String> {
+    pub fn new(
+        patient_id: String,
+        name: String,
+        contact: String,
+    ) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::new();
         if patient_id.is_empty() {
-            return Err("Patient ID cannot be empty".to_string());
+            errors.add("patient_id", "Patient ID cannot be empty");
         }
         if name.is_empty() {
-            return Err("Patient name cannot be empty".to_string());
+            errors.add("name", "Patient name cannot be empty");
         }
         if contact.is_empty() {
-            return Err("Patient contact cannot be empty".to_string());
+            errors.add("contact", "Patient contact cannot be empty");
         }
 
-        Ok(Patient {
+        errors.finish(Patient {
             patient_id,
             name,
             contact,
@@ -75,27 +82,78 @@ impl Patient {
     }
 }
 
+/// Pricing tier of a time slot.
+///
+/// Premium slots (typically evenings and weekends) carry a higher fee and
+/// are only offered to patients who accept premium pricing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SlotTier {
+    #[default]
+    Standard,
+    Premium,
+}
+
+impl SlotTier {
+    pub fn name(&self) -> &str {
+        match self {
+            SlotTier::Standard => "STANDARD",
+            SlotTier::Premium => "PREMIUM",
+        }
+    }
+}
+
+/// Fees charged per appointment for each slot tier, in cents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub standard_cents: u32,
+    pub premium_cents: u32,
+}
+
+impl FeeSchedule {
+    /// Get the fee for a slot tier.
+    pub fn fee_for(&self, tier: SlotTier) -> u32 {
+        match tier {
+            SlotTier::Standard => self.standard_cents,
+            SlotTier::Premium => self.premium_cents,
+        }
+    }
+}
+
 /// Represents an available time slot in the doctor's calendar.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TimeSlot {
     pub start_time: DateTime<Local>,
     pub end_time: DateTime<Local>,
     pub is_available: bool,
     pub slot_id: String,
+    #[serde(default)]
+    pub tier: SlotTier,
+    /// When the slot opens for booking; `None` means it is open now.
+    #[serde(default)]
+    pub bookable_from: Option<DateTime<Local>>,
 }
 
 impl TimeSlot {
     /// Create a new time slot with validation.
-    pub fn new(start_time: DateTime<Local>, end_time: DateTime<Local>) -> Result<Self, String> {
+    ///
+    /// The slot has no ID yet; set one from the calendar's generator
+    /// before adding it.
+    pub fn new(
+        start_time: DateTime<Local>,
+        end_time: DateTime<Local>,
+    ) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::new();
         if end_time <= start_time {
-            return Err("End time must be after start time".to_string());
+            errors.add("end_time", "End time must be after start time");
         }
 
-        Ok(TimeSlot {
+        errors.finish(TimeSlot {
             start_time,
             end_time,
             is_available: true,
-            slot_id: Uuid::new_v4().to_string(),
+            slot_id: String::new(),
+            tier: SlotTier::Standard,
+            bookable_from: None,
         })
     }
 
@@ -114,6 +172,11 @@ impl TimeSlot {
         self.start_time < other.end_time && self.end_time > other.start_time
     }
 
+    /// Check whether the slot has been released for booking at `now`.
+    pub fn is_released(&self, now: DateTime<Local>) -> bool {
+        self.bookable_from.is_none_or(|from| from <= now)
+    }
+
     /// Check if a datetime falls within this time slot.
     pub fn contains(&self, dt: &DateTime<Local>) -> bool {
         &self.start_time <= dt && dt < &self.end_time
@@ -126,8 +189,8 @@ impl std::hash::Hash for TimeSlot {
     }
 }
 
-/// Represents a confirmed appointment.
-#[derive(Debug, Clone)]
+/// Represents a booked appointment, kept as Cancelled once cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Appointment {
     pub appointment_id: String,
     pub patient: Patient,
@@ -135,12 +198,25 @@ pub struct Appointment {
     pub priority: Priority,
     pub reason: String,
     pub created_at: DateTime<Local>,
-    pub confirmed: bool,
+    #[serde(default)]
+    pub status: AppointmentStatus,
+    pub bundle_id: Option<String>,
+    pub confirmation_code: String,
+    #[serde(default)]
+    pub checked_in_at: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Local>>,
+    /// When staff recorded that the patient never came.
+    #[serde(default)]
+    pub no_show_at: Option<DateTime<Local>>,
 }
 
 impl Appointment {
-    /// Create a new appointment with validation.
+    /// Create a new appointment with validation, taking its ID and
+    /// confirmation code from `ids`.
     pub fn new(
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
         patient: Patient,
         time_slot: TimeSlot,
         priority: Priority,
@@ -151,19 +227,364 @@ impl Appointment {
         }
 
         Ok(Appointment {
-            appointment_id: Uuid::new_v4().to_string(),
+            appointment_id: ids.next_id(IdKind::Appointment, now),
             patient,
             time_slot,
             priority,
             reason,
-            created_at: Local::now(),
-            confirmed: true,
+            created_at: now,
+            status: AppointmentStatus::Confirmed,
+            bundle_id: None,
+            confirmation_code: ids.confirmation_code(now),
+            checked_in_at: None,
+            completed_at: None,
+            no_show_at: None,
+        })
+    }
+
+    /// Work out where the patient is in their visit at `now`.
+    ///
+    /// A patient who has not checked in by the start of the slot is late,
+    /// and a no-show once the slot has ended.
+    pub fn attendance_status(&self, now: DateTime<Local>) -> AttendanceStatus {
+        if self.status == AppointmentStatus::Completed {
+            AttendanceStatus::Completed
+        } else if self.status == AppointmentStatus::NoShow {
+            AttendanceStatus::NoShow
+        } else if self.status == AppointmentStatus::CheckedIn {
+            AttendanceStatus::CheckedIn
+        } else if now >= self.time_slot.end_time {
+            AttendanceStatus::NoShow
+        } else if now >= self.time_slot.start_time {
+            AttendanceStatus::Late
+        } else {
+            AttendanceStatus::Upcoming
+        }
+    }
+
+    /// Move the appointment to a new status, if the workflow allows it.
+    pub fn transition(&mut self, next: AppointmentStatus) -> Result<(), String> {
+        if !self.status.can_become(next) {
+            return Err(format!(
+                "Cannot change a {} appointment to {}",
+                self.status.name(),
+                next.name()
+            ));
+        }
+        self.status = next;
+        Ok(())
+    }
+
+    /// Set the status from the attendance times, for appointments saved
+    /// before the status was stored.
+    pub fn upgrade_status(&mut self) {
+        if self.status != AppointmentStatus::Confirmed {
+            return;
+        }
+        if self.completed_at.is_some() {
+            self.status = AppointmentStatus::Completed;
+        } else if self.no_show_at.is_some() {
+            self.status = AppointmentStatus::NoShow;
+        } else if self.checked_in_at.is_some() {
+            self.status = AppointmentStatus::CheckedIn;
+        }
+    }
+
+    /// Minutes the visit actually took, from check-in (or the slot start,
+    /// for a patient who came early) to completion. `None` until completed.
+    pub fn actual_minutes(&self) -> Option<i64> {
+        let started = self.checked_in_at?.max(self.time_slot.start_time);
+        let finished = self.completed_at?;
+        Some((finished - started).num_minutes().max(0))
+    }
+}
+
+/// Stage of an appointment in the clinic's workflow.
+///
+/// A booking starts out Requested when it still needs confirming, or
+/// Confirmed otherwise. Confirmed visits end up CheckedIn and then
+/// Completed, or NoShow. A visit can be Cancelled until the patient
+/// checks in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AppointmentStatus {
+    Requested,
+    #[default]
+    Confirmed,
+    CheckedIn,
+    Completed,
+    Cancelled,
+    NoShow,
+}
+
+impl AppointmentStatus {
+    /// Convert a string such as `checked-in` to an AppointmentStatus.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "requested" => Ok(AppointmentStatus::Requested),
+            "confirmed" => Ok(AppointmentStatus::Confirmed),
+            "checked-in" | "checked_in" | "checkedin" => Ok(AppointmentStatus::CheckedIn),
+            "completed" => Ok(AppointmentStatus::Completed),
+            "cancelled" => Ok(AppointmentStatus::Cancelled),
+            "no-show" | "no_show" | "noshow" => Ok(AppointmentStatus::NoShow),
+            _ => Err(format!(
+                "Invalid status: '{}'. Must be one of: requested, confirmed, checked-in, completed, cancelled, no-show",
+                value
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            AppointmentStatus::Requested => "REQUESTED",
+            AppointmentStatus::Confirmed => "CONFIRMED",
+            AppointmentStatus::CheckedIn => "CHECKED-IN",
+            AppointmentStatus::Completed => "COMPLETED",
+            AppointmentStatus::Cancelled => "CANCELLED",
+            AppointmentStatus::NoShow => "NO-SHOW",
+        }
+    }
+
+    /// Check whether the workflow allows moving from this status to `next`.
+    pub fn can_become(&self, next: AppointmentStatus) -> bool {
+        match self {
+            AppointmentStatus::Requested => matches!(
+                next,
+                AppointmentStatus::Confirmed | AppointmentStatus::Cancelled
+            ),
+            AppointmentStatus::Confirmed => matches!(
+                next,
+                AppointmentStatus::CheckedIn
+                    | AppointmentStatus::Cancelled
+                    | AppointmentStatus::NoShow
+            ),
+            AppointmentStatus::CheckedIn => next == AppointmentStatus::Completed,
+            AppointmentStatus::Completed
+            | AppointmentStatus::Cancelled
+            | AppointmentStatus::NoShow => false,
+        }
+    }
+}
+
+/// Where a patient is in their visit on the day of the appointment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttendanceStatus {
+    Upcoming,
+    CheckedIn,
+    Completed,
+    Late,
+    NoShow,
+}
+
+impl AttendanceStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            AttendanceStatus::Upcoming => "UPCOMING",
+            AttendanceStatus::CheckedIn => "CHECKED-IN",
+            AttendanceStatus::Completed => "COMPLETED",
+            AttendanceStatus::Late => "LATE",
+            AttendanceStatus::NoShow => "NO-SHOW",
+        }
+    }
+}
+
+/// Kind of change recorded in an appointment's history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AppointmentChangeKind {
+    Created,
+    Moved {
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    },
+    Cancelled,
+    NoteAdded {
+        note: String,
+    },
+    Confirmed,
+    CheckedIn,
+    Completed,
+    NoShow,
+    Reassigned {
+        from_doctor: String,
+        to_doctor: String,
+    },
+}
+
+/// One entry in an appointment's modification history.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppointmentChange {
+    pub changed_at: DateTime<Local>,
+    pub kind: AppointmentChangeKind,
+}
+
+impl AppointmentChange {
+    /// Create a change entry made at `changed_at`.
+    pub fn new(kind: AppointmentChangeKind, changed_at: DateTime<Local>) -> Self {
+        AppointmentChange { changed_at, kind }
+    }
+
+    /// Human-readable description of the change.
+    pub fn describe(&self) -> String {
+        match &self.kind {
+            AppointmentChangeKind::Created => "Appointment booked".to_string(),
+            AppointmentChangeKind::Moved { from, to } => format!(
+                "Moved from {} to {}",
+                from.format("%Y-%m-%d %H:%M"),
+                to.format("%Y-%m-%d %H:%M")
+            ),
+            AppointmentChangeKind::Cancelled => "Appointment cancelled".to_string(),
+            AppointmentChangeKind::NoteAdded { note } => format!("Note added: {}", note),
+            AppointmentChangeKind::Confirmed => "Appointment confirmed".to_string(),
+            AppointmentChangeKind::CheckedIn => "Patient checked in".to_string(),
+            AppointmentChangeKind::Completed => "Visit completed".to_string(),
+            AppointmentChangeKind::NoShow => "Marked as no-show".to_string(),
+            AppointmentChangeKind::Reassigned {
+                from_doctor,
+                to_doctor,
+            } => format!("Reassigned from {} to {}", from_doctor, to_doctor),
+        }
+    }
+}
+
+/// Who initiated a cancellation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CancellationActor {
+    Patient,
+    Staff,
+}
+
+/// Rules for patient-initiated cancellations.
+///
+/// Patients may cancel freely up to `min_notice_hours` before the
+/// appointment. Later patient cancellations still go through but are
+/// recorded as late cancellations and, if a fee is configured, flagged
+/// for billing. Staff cancellations are never late.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CancellationPolicy {
+    pub min_notice_hours: i64,
+    pub late_fee_cents: Option<u32>,
+}
+
+impl CancellationPolicy {
+    /// Create a policy with validation.
+    pub fn new(min_notice_hours: i64, late_fee_cents: Option<u32>) -> Result<Self, String> {
+        if min_notice_hours < 0 {
+            return Err("Cancellation notice hours cannot be negative".to_string());
+        }
+
+        Ok(CancellationPolicy {
+            min_notice_hours,
+            late_fee_cents,
+        })
+    }
+
+    /// Check whether cancelling at `now` is late for an appointment starting at `start`.
+    pub fn is_late(&self, start: DateTime<Local>, now: DateTime<Local>) -> bool {
+        start - now < Duration::hours(self.min_notice_hours)
+    }
+}
+
+/// Record of a cancelled appointment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CancellationRecord {
+    pub appointment_id: String,
+    pub patient_id: String,
+    pub scheduled_start: DateTime<Local>,
+    pub cancelled_at: DateTime<Local>,
+    pub actor: CancellationActor,
+    pub late: bool,
+    pub fee_cents: Option<u32>,
+}
+
+/// Channel through which a request reached the clinic.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum RequestSource {
+    #[default]
+    Unspecified,
+    Phone,
+    WalkIn,
+    Web,
+    Referral,
+}
+
+impl RequestSource {
+    /// Convert a string to a RequestSource.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "" | "unspecified" => Ok(RequestSource::Unspecified),
+            "phone" => Ok(RequestSource::Phone),
+            "walk-in" | "walkin" | "walk_in" => Ok(RequestSource::WalkIn),
+            "web" => Ok(RequestSource::Web),
+            "referral" => Ok(RequestSource::Referral),
+            _ => Err(format!("Invalid request source: {}", value)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            RequestSource::Unspecified => "UNSPECIFIED",
+            RequestSource::Phone => "PHONE",
+            RequestSource::WalkIn => "WALK-IN",
+            RequestSource::Web => "WEB",
+            RequestSource::Referral => "REFERRAL",
+        }
+    }
+}
+
+/// A multi-day availability window, e.g. any weekday next week, mornings only.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DateWindow {
+    pub first_day: NaiveDate,
+    pub last_day: NaiveDate,
+    /// Acceptable weekdays; empty means every day.
+    pub weekdays: Vec<Weekday>,
+    /// Acceptable times of day as `[start, end)`; empty means any time.
+    pub times_of_day: Vec<(NaiveTime, NaiveTime)>,
+}
+
+impl DateWindow {
+    /// Create a new date window with validation.
+    pub fn new(
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+        weekdays: Vec<Weekday>,
+        times_of_day: Vec<(NaiveTime, NaiveTime)>,
+    ) -> Result<Self, String> {
+        if last_day < first_day {
+            return Err("Window cannot end before it starts".to_string());
+        }
+        if times_of_day.iter().any(|(start, end)| end <= start) {
+            return Err("Time of day ranges must end after they start".to_string());
+        }
+
+        Ok(DateWindow {
+            first_day,
+            last_day,
+            weekdays,
+            times_of_day,
         })
     }
+
+    /// Check whether a slot lies inside the window.
+    pub fn contains(&self, slot: &TimeSlot) -> bool {
+        let date = slot.start_time.date_naive();
+        let start = slot.start_time.time();
+        let end = slot.end_time.time();
+
+        date >= self.first_day
+            && date <= self.last_day
+            && (self.weekdays.is_empty() || self.weekdays.contains(&date.weekday()))
+            && (self.times_of_day.is_empty()
+                || self
+                    .times_of_day
+                    .iter()
+                    .any(|(from, until)| start >= *from && end <= *until))
+    }
 }
 
 /// Represents a patient's request for an appointment.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppointmentRequest {
     pub request_id: String,
     pub patient: Patient,
@@ -171,52 +592,232 @@ pub struct AppointmentRequest {
     pub preferred_time: DateTime<Local>,
     pub reason: String,
     pub flexibility_minutes: i64,
+    /// Minutes before the preferred time the patient can come, overriding `flexibility_minutes`.
+    #[serde(default)]
+    pub can_come_earlier: Option<i64>,
+    /// Minutes after the preferred time the patient can come, overriding `flexibility_minutes`.
+    #[serde(default)]
+    pub can_come_later: Option<i64>,
+    /// Time ranges the patient cannot attend, e.g. a school pickup.
+    #[serde(default)]
+    pub unavailable_times: Vec<(DateTime<Local>, DateTime<Local>)>,
+    /// Multi-day window searched instead of the minutes around the preferred time.
+    #[serde(default)]
+    pub date_window: Option<DateWindow>,
+    #[serde(default)]
+    pub accepts_premium: bool,
+    #[serde(default)]
+    pub source: RequestSource,
+    /// External partner that referred the patient, if any.
+    #[serde(default)]
+    pub referrer: Option<String>,
+    /// Length of the visit. A visit longer than the slots is booked over
+    /// back-to-back free slots merged into one; `None` takes any one slot.
+    #[serde(default)]
+    pub duration_minutes: Option<i64>,
     pub created_at: DateTime<Local>,
 }
 
 impl AppointmentRequest {
-    /// Create a new appointment request with validation.
+    /// Create a new appointment request with validation, taking its ID
+    /// from `ids`.
     pub fn new(
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
         patient: Patient,
         priority: Priority,
         preferred_time: DateTime<Local>,
         reason: String,
         flexibility_minutes: i64,
-    ) -> Result<Self, String> {
-        if reason.is_empty() {
-            return Err("Appointment reason cannot be empty".to_string());
-        }
-        if flexibility_minutes < 0 {
-            return Err("Flexibility minutes cannot be negative".to_string());
-        }
+    ) -> Result<Self, ValidationErrors> {
+        AppointmentRequest::with_id(
+            ids.next_id(IdKind::Request, now),
+            now,
+            patient,
+            priority,
+            preferred_time,
+            reason,
+            flexibility_minutes,
+        )
+    }
 
-        Ok(AppointmentRequest {
-            request_id: Uuid::new_v4().to_string(),
+    /// Rebuild a request that already has an ID, e.g. from an edited export.
+    pub fn with_id(
+        request_id: String,
+        created_at: DateTime<Local>,
+        patient: Patient,
+        priority: Priority,
+        preferred_time: DateTime<Local>,
+        reason: String,
+        flexibility_minutes: i64,
+    ) -> Result<Self, ValidationErrors> {
+        let mut errors = ValidationErrors::new();
+        AppointmentRequest::check_fields(&reason, flexibility_minutes, &mut errors);
+
+        errors.finish(AppointmentRequest {
+            request_id,
             patient,
             priority,
             preferred_time,
             reason,
             flexibility_minutes,
-            created_at: Local::now(),
+            can_come_earlier: None,
+            can_come_later: None,
+            unavailable_times: Vec::new(),
+            date_window: None,
+            accepts_premium: false,
+            source: RequestSource::Unspecified,
+            referrer: None,
+            duration_minutes: None,
+            created_at,
         })
     }
 
+    /// Record problems with the request's own fields.
+    fn check_fields(reason: &str, flexibility_minutes: i64, errors: &mut ValidationErrors) {
+        if reason.is_empty() {
+            errors.add("reason", "Appointment reason cannot be empty");
+        }
+        if flexibility_minutes < 0 {
+            errors.add("flexibility_minutes", "Flexibility minutes cannot be negative");
+        }
+    }
+
+    /// Set separate earlier and later flexibility in minutes.
+    ///
+    /// For example, `(0, 180)` means "any time in the three hours after".
+    pub fn set_window(&mut self, earlier_minutes: i64, later_minutes: i64) -> Result<(), String> {
+        if earlier_minutes < 0 || later_minutes < 0 {
+            return Err("Flexibility minutes cannot be negative".to_string());
+        }
+        self.can_come_earlier = Some(earlier_minutes);
+        self.can_come_later = Some(later_minutes);
+        Ok(())
+    }
+
     /// Calculate the earliest acceptable appointment time.
     pub fn earliest_acceptable(&self) -> DateTime<Local> {
-        self.preferred_time - Duration::minutes(self.flexibility_minutes)
+        if let Some(window) = &self.date_window {
+            if let Some(start) = window
+                .first_day
+                .and_time(NaiveTime::MIN)
+                .and_local_timezone(Local)
+                .earliest()
+            {
+                return start;
+            }
+        }
+        let minutes = self.can_come_earlier.unwrap_or(self.flexibility_minutes);
+        self.preferred_time - Duration::minutes(minutes)
     }
 
     /// Calculate the latest acceptable appointment time.
     pub fn latest_acceptable(&self) -> DateTime<Local> {
-        self.preferred_time + Duration::minutes(self.flexibility_minutes)
+        if let Some(window) = &self.date_window {
+            if let Some(end) = (window.last_day + Duration::days(1))
+                .and_time(NaiveTime::MIN)
+                .and_local_timezone(Local)
+                .earliest()
+            {
+                return end;
+            }
+        }
+        let minutes = self.can_come_later.unwrap_or(self.flexibility_minutes);
+        self.preferred_time + Duration::minutes(minutes)
+    }
+
+    /// Record a time range the patient cannot attend.
+    pub fn add_unavailable_time(
+        &mut self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Result<(), String> {
+        if end <= start {
+            return Err("End time must be after start time".to_string());
+        }
+        self.unavailable_times.push((start, end));
+        Ok(())
+    }
+
+    /// Check whether the patient is willing to take a slot at all, ignoring the window.
+    ///
+    /// Premium slots need the patient's consent, and slots too short for the
+    /// visit or overlapping one of their unavailable times are refused.
+    pub fn accepts_slot(&self, slot: &TimeSlot) -> bool {
+        (slot.tier == SlotTier::Standard || self.accepts_premium)
+            && self.duration_minutes.unwrap_or(0) <= slot.duration_minutes()
+            && !self
+                .unavailable_times
+                .iter()
+                .any(|(start, end)| slot.start_time < *end && slot.end_time > *start)
     }
 
     /// Check if a time slot falls within the acceptable range.
+    ///
+    /// Requests with a date window accept any slot inside it.
     pub fn is_time_acceptable(&self, slot: &TimeSlot) -> bool {
+        if let Some(window) = &self.date_window {
+            return window.contains(slot);
+        }
         slot.start_time >= self.earliest_acceptable() && slot.start_time <= self.latest_acceptable()
     }
 }
 
+/// Reusable defaults for a common kind of visit, e.g. "New patient intake".
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RequestTemplate {
+    pub name: String,
+    pub reason: String,
+    pub priority: Priority,
+    /// Shortest slot the visit fits in; `None` accepts any slot.
+    pub duration_minutes: Option<i64>,
+    pub flexibility_minutes: i64,
+}
+
+impl RequestTemplate {
+    /// Create a routine template with an hour of flexibility.
+    pub fn new(name: String, reason: String, duration_minutes: Option<i64>) -> Result<Self, String> {
+        if name.trim().is_empty() {
+            return Err("Template name cannot be empty".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("Appointment reason cannot be empty".to_string());
+        }
+        if duration_minutes.is_some_and(|minutes| minutes <= 0) {
+            return Err("Template duration must be positive".to_string());
+        }
+
+        Ok(RequestTemplate {
+            name: name.trim().to_string(),
+            reason: reason.trim().to_string(),
+            priority: Priority::Routine,
+            duration_minutes,
+            flexibility_minutes: 60,
+        })
+    }
+
+    /// Start a request for a patient from the template.
+    pub fn to_request(
+        &self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        patient: Patient,
+        preferred_time: DateTime<Local>,
+    ) -> Result<AppointmentRequest, ValidationErrors> {
+        let mut request = AppointmentRequest::new(
+            ids,
+            now,
+            patient,
+            self.priority,
+            preferred_time,
+            self.reason.clone(),
+            self.flexibility_minutes,
+        )?;
+        request.duration_minutes = self.duration_minutes;
+        Ok(request)
+    }
+}
+
 impl PartialEq for AppointmentRequest {
     fn eq(&self, other: &Self) -> bool {
         self.request_id == other.request_id
@@ -237,15 +838,21 @@ impl Ord for AppointmentRequest {
     /// Higher priority requests come first. For equal priorities,
     /// earlier requests are processed first.
     fn cmp(&self, other: &Self) -> Ordering {
-        match other.priority.cmp(&self.priority) {
-            Ordering::Equal => self.created_at.cmp(&other.created_at),
+        match self.priority.cmp(&other.priority) {
+            Ordering::Equal => other.created_at.cmp(&self.created_at),
             other_ordering => other_ordering,
         }
     }
 }
 
 /// Factory function to create an appointment request.
+///
+/// Every problem with the patient details, priority and request fields is
+/// reported together.
+#[allow(clippy::too_many_arguments)]
 pub fn create_appointment_request(
+    ids: &dyn IdGenerator,
+    now: DateTime<Local>,
     patient_id: String,
     patient_name: String,
     patient_contact: String,
@@ -253,15 +860,26 @@ pub fn create_appointment_request(
     preferred_time: DateTime<Local>,
     reason: String,
     flexibility_minutes: i64,
-) -> Result<AppointmentRequest, String> {
-    let patient = Patient::new(patient_id, patient_name, patient_contact)?;
-    let priority_enum = Priority::from_string(priority)?;
-
-    AppointmentRequest::new(
-        patient,
-        priority_enum,
-        preferred_time,
-        reason,
-        flexibility_minutes,
-    )
+) -> Result<AppointmentRequest, ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    let patient = Patient::new(patient_id, patient_name, patient_contact)
+        .map_err(|e| errors.merge(e))
+        .ok();
+    let priority_enum = Priority::from_string(priority)
+        .map_err(|e| errors.add("priority", e))
+        .ok();
+    AppointmentRequest::check_fields(&reason, flexibility_minutes, &mut errors);
+
+    match (patient, priority_enum) {
+        (Some(patient), Some(priority_enum)) if errors.is_empty() => AppointmentRequest::new(
+            ids,
+            now,
+            patient,
+            priority_enum,
+            preferred_time,
+            reason,
+            flexibility_minutes,
+        ),
+        _ => Err(errors),
+    }
 }
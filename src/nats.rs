@@ -0,0 +1,101 @@
+#![allow(dead_code)]
+//! NATS publisher for calendar events (enabled with the `nats` feature).
+//!
+//! Outbox entries are published as JSON to `<subject_prefix>.<event name>`,
+//! e.g. `clinic.schedule.appointment_booked`, using the plain-text NATS
+//! client protocol over TCP. Each publish is followed by a PING so the
+//! server acknowledges it before the entry counts as published.
+
+use crate::events::{EventPublisher, OutboxEntry};
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// Publishes outbox entries to a NATS server.
+pub struct NatsPublisher {
+    pub subject_prefix: String,
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl NatsPublisher {
+    /// Connect to a NATS server, e.g. `NatsPublisher::connect("127.0.0.1:4222", "clinic.schedule")`.
+    pub fn connect(address: &str, subject_prefix: &str) -> Result<Self, String> {
+        if subject_prefix.is_empty() || subject_prefix.contains(char::is_whitespace) {
+            return Err("NATS subject prefix must be non-empty and contain no whitespace".to_string());
+        }
+
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("Failed to connect to NATS at {}: {}", address, e))?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .map_err(|e| format!("Failed to configure NATS connection: {}", e))?;
+        let writer = stream
+            .try_clone()
+            .map_err(|e| format!("Failed to configure NATS connection: {}", e))?;
+
+        let mut publisher = NatsPublisher {
+            subject_prefix: subject_prefix.to_string(),
+            reader: BufReader::new(stream),
+            writer,
+        };
+
+        let info = publisher.read_line()?;
+        if !info.starts_with("INFO") {
+            return Err(format!("Unexpected NATS greeting: {}", info));
+        }
+        publisher.write(b"CONNECT {\"verbose\":false,\"pedantic\":false,\"name\":\"easyappoint\"}\r\n")?;
+
+        Ok(publisher)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(bytes)
+            .map_err(|e| format!("Failed to write to NATS: {}", e))
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        self.reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read from NATS: {}", e))?;
+        if line.is_empty() {
+            return Err("NATS connection closed".to_string());
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    /// Flush pending publishes by waiting for the server's PONG.
+    fn wait_for_pong(&mut self) -> Result<(), String> {
+        loop {
+            let line = self.read_line()?;
+            if line == "PONG" {
+                return Ok(());
+            }
+            if line == "PING" {
+                self.write(b"PONG\r\n")?;
+            } else if line.starts_with("-ERR") {
+                return Err(format!("NATS error: {}", line));
+            }
+        }
+    }
+}
+
+impl EventPublisher for NatsPublisher {
+    fn publish(&mut self, entry: &OutboxEntry) -> Result<(), String> {
+        let payload = serde_json::to_vec(entry)
+            .map_err(|e| format!("Failed to encode event: {}", e))?;
+        let header = format!(
+            "PUB {}.{} {}\r\n",
+            self.subject_prefix,
+            entry.event.name(),
+            payload.len()
+        );
+
+        self.write(header.as_bytes())?;
+        self.write(&payload)?;
+        self.write(b"\r\nPING\r\n")?;
+        self.wait_for_pong()
+    }
+}
@@ -0,0 +1,353 @@
+#![allow(dead_code)]
+//! Notification delivery for the appointment scheduling system.
+//!
+//! This module defines the Notification message, the NotificationChannel
+//! trait implemented by concrete transports (webhook, email, ...), and a
+//! DeliveryQueue that retries failed deliveries with exponential backoff
+//! before moving them to a dead-letter list.
+//!
+//! Recipients who'd rather not get a message for every change can be sent
+//! digests instead: a DigestChannel holds notifications back and combines
+//! them into one per recipient, and `doctor_digest` builds the doctor's
+//! evening summary of tomorrow's appointments and today's changes.
+
+use crate::calendar::DoctorCalendar;
+use crate::ids::{IdGenerator, IdKind};
+use chrono::{DateTime, Duration, Local};
+use serde::{Deserialize, Serialize};
+
+/// A message to be delivered to a single recipient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub notification_id: String,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    pub created_at: DateTime<Local>,
+}
+
+impl Notification {
+    /// Create a new notification with validation, taking its ID from `ids`.
+    pub fn new(
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        recipient: String,
+        subject: String,
+        body: String,
+    ) -> Result<Self, String> {
+        if recipient.is_empty() {
+            return Err("Notification recipient cannot be empty".to_string());
+        }
+        if subject.is_empty() {
+            return Err("Notification subject cannot be empty".to_string());
+        }
+
+        Ok(Notification {
+            notification_id: ids.next_id(IdKind::Notification, now),
+            recipient,
+            subject,
+            body,
+            created_at: now,
+        })
+    }
+}
+
+/// A transport capable of delivering notifications.
+pub trait NotificationChannel {
+    /// Short name of the channel, used in delivery errors.
+    fn name(&self) -> &str;
+
+    /// Attempt to deliver a notification.
+    fn send(&mut self, notification: &Notification) -> Result<(), String>;
+}
+
+/// Channel that records every notification in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryChannel {
+    pub sent: Vec<Notification>,
+}
+
+impl NotificationChannel for InMemoryChannel {
+    fn name(&self) -> &str {
+        "memory"
+    }
+
+    fn send(&mut self, notification: &Notification) -> Result<(), String> {
+        self.sent.push(notification.clone());
+        Ok(())
+    }
+}
+
+/// Channel that holds notifications back to be sent as digests.
+///
+/// Pass it wherever a channel is expected, then call `take_digests` when
+/// the digest is due, e.g. each evening, and queue the results on a
+/// DeliveryQueue so failed deliveries are retried.
+#[derive(Debug, Default)]
+pub struct DigestChannel {
+    held: Vec<Notification>,
+}
+
+impl DigestChannel {
+    /// Create a channel holding nothing.
+    pub fn new() -> Self {
+        DigestChannel::default()
+    }
+
+    /// Get the notifications held so far, oldest first.
+    pub fn held(&self) -> &[Notification] {
+        &self.held
+    }
+
+    /// Combine the held notifications into one per recipient and clear them.
+    ///
+    /// Digests come in the order their recipients were first held for, and
+    /// list each message under its own subject, oldest first.
+    pub fn take_digests(
+        &mut self,
+        ids: &dyn IdGenerator,
+        now: DateTime<Local>,
+        subject: &str,
+    ) -> Result<Vec<Notification>, String> {
+        let mut recipients: Vec<(String, Vec<Notification>)> = Vec::new();
+        for notification in std::mem::take(&mut self.held) {
+            match recipients
+                .iter_mut()
+                .find(|(recipient, _)| *recipient == notification.recipient)
+            {
+                Some((_, notifications)) => notifications.push(notification),
+                None => recipients.push((notification.recipient.clone(), vec![notification])),
+            }
+        }
+
+        recipients
+            .into_iter()
+            .map(|(recipient, notifications)| {
+                let body = notifications
+                    .iter()
+                    .map(|notification| format!("{}\n{}", notification.subject, notification.body))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                Notification::new(ids, now, recipient, subject.to_string(), body)
+            })
+            .collect()
+    }
+}
+
+impl NotificationChannel for DigestChannel {
+    fn name(&self) -> &str {
+        "digest"
+    }
+
+    fn send(&mut self, notification: &Notification) -> Result<(), String> {
+        self.held.push(notification.clone());
+        Ok(())
+    }
+}
+
+/// Build the doctor's evening digest: tomorrow's appointments and the
+/// changes made to the schedule today, as of the calendar's clock.
+pub fn doctor_digest(calendar: &DoctorCalendar, recipient: &str) -> Result<Notification, String> {
+    let now = calendar.now();
+    let tomorrow = now + Duration::days(1);
+    let mut lines = vec![format!("Dear {},", calendar.doctor_name), String::new()];
+
+    let appointments = calendar.get_appointments_on_date(tomorrow);
+    if appointments.is_empty() {
+        lines.push(format!(
+            "You have no appointments tomorrow, {}.",
+            tomorrow.format("%A %Y-%m-%d")
+        ));
+    } else {
+        lines.push(format!(
+            "Tomorrow, {}, you have {} appointment(s):",
+            tomorrow.format("%A %Y-%m-%d"),
+            appointments.len()
+        ));
+        for apt in &appointments {
+            lines.push(format!(
+                "  {}  {} ({}) - {}",
+                apt.time_slot.start_time.format("%H:%M"),
+                apt.patient.name,
+                apt.priority.name(),
+                apt.reason
+            ));
+        }
+    }
+
+    lines.push(String::new());
+    let changes = calendar.changes_on(now.date_naive());
+    if changes.is_empty() {
+        lines.push("No changes were made to your schedule today.".to_string());
+    } else {
+        lines.push("Changes made today:".to_string());
+        for (appointment_id, change) in &changes {
+            let who = match calendar.get_appointment_by_id(appointment_id) {
+                Some(apt) => apt.patient.name,
+                None => calendar
+                    .cancellations()
+                    .iter()
+                    .find(|record| record.appointment_id == *appointment_id)
+                    .map_or_else(
+                        || appointment_id.clone(),
+                        |record| format!("patient {}", record.patient_id),
+                    ),
+            };
+            lines.push(format!(
+                "  {}  {}: {}",
+                change.changed_at.format("%H:%M"),
+                who,
+                change.describe()
+            ));
+        }
+    }
+
+    Notification::new(
+        calendar.id_generator(),
+        now,
+        recipient.to_string(),
+        format!("Your schedule for {}", tomorrow.format("%A %Y-%m-%d")),
+        lines.join("\n"),
+    )
+}
+
+/// Retry behaviour for failed deliveries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff_seconds: i64,
+    pub max_backoff_seconds: i64,
+}
+
+impl RetryPolicy {
+    /// Delay before the next attempt after `attempts` failures.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        let exponent = attempts.saturating_sub(1).min(30);
+        let seconds = self
+            .initial_backoff_seconds
+            .saturating_mul(1i64 << exponent)
+            .min(self.max_backoff_seconds);
+        Duration::seconds(seconds)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 5,
+            initial_backoff_seconds: 30,
+            max_backoff_seconds: 3600,
+        }
+    }
+}
+
+/// Delivery state for one queued notification.
+#[derive(Debug, Clone)]
+pub struct PendingDelivery {
+    pub notification: Notification,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Local>,
+    pub last_error: Option<String>,
+}
+
+/// Counts from a single pass over the delivery queue.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryStats {
+    pub delivered: usize,
+    pub retried: usize,
+    pub dead_lettered: usize,
+}
+
+/// Queue of notifications awaiting delivery, with retry and dead-lettering.
+#[derive(Debug, Default)]
+pub struct DeliveryQueue {
+    pub policy: RetryPolicy,
+    pending: Vec<PendingDelivery>,
+    dead_letters: Vec<PendingDelivery>,
+}
+
+impl DeliveryQueue {
+    /// Create an empty queue with the given retry policy.
+    pub fn new(policy: RetryPolicy) -> Self {
+        DeliveryQueue {
+            policy,
+            pending: Vec::new(),
+            dead_letters: Vec::new(),
+        }
+    }
+
+    /// Queue a notification for immediate delivery.
+    pub fn enqueue(&mut self, notification: Notification, now: DateTime<Local>) {
+        self.pending.push(PendingDelivery {
+            notification,
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+        });
+    }
+
+    /// Attempt every delivery that is due at `now`.
+    ///
+    /// Failed deliveries are rescheduled with exponential backoff until the
+    /// policy's attempt limit is reached, then moved to the dead-letter list.
+    pub fn process_due(
+        &mut self,
+        channel: &mut dyn NotificationChannel,
+        now: DateTime<Local>,
+    ) -> DeliveryStats {
+        let mut stats = DeliveryStats::default();
+        let mut remaining = Vec::new();
+
+        for mut delivery in std::mem::take(&mut self.pending) {
+            if delivery.next_attempt_at > now {
+                remaining.push(delivery);
+                continue;
+            }
+
+            delivery.attempts += 1;
+            match channel.send(&delivery.notification) {
+                Ok(()) => stats.delivered += 1,
+                Err(e) => {
+                    delivery.last_error = Some(format!("{}: {}", channel.name(), e));
+                    if delivery.attempts >= self.policy.max_attempts {
+                        stats.dead_lettered += 1;
+                        self.dead_letters.push(delivery);
+                    } else {
+                        delivery.next_attempt_at = now + self.policy.backoff(delivery.attempts);
+                        stats.retried += 1;
+                        remaining.push(delivery);
+                    }
+                }
+            }
+        }
+
+        self.pending = remaining;
+        stats
+    }
+
+    /// Get deliveries still waiting for a (re)try.
+    pub fn pending(&self) -> &[PendingDelivery] {
+        &self.pending
+    }
+
+    /// Get deliveries that exhausted their retries.
+    pub fn dead_letters(&self) -> &[PendingDelivery] {
+        &self.dead_letters
+    }
+
+    /// Move a dead-lettered notification back onto the queue with a fresh attempt count.
+    pub fn requeue_dead_letter(&mut self, notification_id: &str, now: DateTime<Local>) -> bool {
+        match self
+            .dead_letters
+            .iter()
+            .position(|d| d.notification.notification_id == notification_id)
+        {
+            Some(index) => {
+                let delivery = self.dead_letters.remove(index);
+                self.enqueue(delivery.notification, now);
+                true
+            }
+            None => false,
+        }
+    }
+}
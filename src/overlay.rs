@@ -0,0 +1,206 @@
+#![allow(dead_code)]
+//! Copy-on-write overlays for trying out calendar changes.
+//!
+//! A CalendarOverlay borrows a base calendar and records hypothetical slot
+//! and appointment changes on top of it. Starting an overlay copies
+//! nothing, and only the slots and appointments that are actually changed
+//! are stored, so trying out a few changes on a large calendar no longer
+//! means cloning all of it. The base calendar is never modified and no
+//! events are emitted. Hypothetical appointments get their IDs from a fork
+//! of the base calendar's generator, so they don't use up real ones, and
+//! are refused wherever the base calendar would refuse a real booking.
+
+use crate::calendar::DoctorCalendar;
+use crate::ids::{IdGenerator, IdKind};
+use crate::models::{Appointment, AppointmentStatus, Patient, Priority, TimeSlot};
+use chrono::{DateTime, Duration, Local};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Hypothetical changes layered over a borrowed calendar.
+pub struct CalendarOverlay<'a> {
+    base: &'a DoctorCalendar,
+    /// Changed slots by ID; `None` marks a removed slot.
+    slots: HashMap<String, Option<TimeSlot>>,
+    /// Changed appointments by ID; `None` marks a cancelled appointment.
+    appointments: HashMap<String, Option<Appointment>>,
+    ids: Arc<dyn IdGenerator>,
+}
+
+impl<'a> CalendarOverlay<'a> {
+    /// Start an empty overlay on top of a calendar.
+    pub fn new(base: &'a DoctorCalendar) -> Self {
+        CalendarOverlay {
+            base,
+            slots: HashMap::new(),
+            appointments: HashMap::new(),
+            ids: base.id_generator().fork(),
+        }
+    }
+
+    /// Get the calendar the overlay is built on.
+    pub fn base(&self) -> &DoctorCalendar {
+        self.base
+    }
+
+    /// Generate an ID from the overlay's own generator, leaving the base
+    /// calendar's untouched.
+    pub fn next_id(&self, kind: IdKind) -> String {
+        self.ids.next_id(kind, self.base.now())
+    }
+
+    /// Get the number of slots and appointments the overlay has changed.
+    pub fn change_count(&self) -> usize {
+        self.slots.len() + self.appointments.len()
+    }
+
+    /// Look up a slot as it would be after the overlay's changes.
+    pub fn slot_by_id(&self, slot_id: &str) -> Option<TimeSlot> {
+        match self.slots.get(slot_id) {
+            Some(slot) => slot.clone(),
+            None => self.base.slot_by_id(slot_id).cloned(),
+        }
+    }
+
+    /// Get all time slots sorted by start time.
+    pub fn time_slots(&self) -> Vec<TimeSlot> {
+        let mut slots: Vec<TimeSlot> = self
+            .base
+            .time_slots()
+            .into_iter()
+            .filter(|slot| !self.slots.contains_key(&slot.slot_id))
+            .chain(self.slots.values().flatten().cloned())
+            .collect();
+        slots.sort_by_key(|s| s.start_time);
+        slots
+    }
+
+    /// Get all unbooked slots open for booking, sorted by start time.
+    pub fn available_slots(&self) -> Vec<TimeSlot> {
+        let now = self.base.now();
+        self.time_slots()
+            .into_iter()
+            .filter(|slot| slot.is_available && slot.is_released(now))
+            .collect()
+    }
+
+    /// Get all appointments sorted by time.
+    pub fn appointments(&self) -> Vec<Appointment> {
+        let mut appointments: Vec<Appointment> = self
+            .base
+            .appointments()
+            .into_iter()
+            .filter(|apt| !self.appointments.contains_key(&apt.appointment_id))
+            .chain(self.appointments.values().flatten().cloned())
+            .collect();
+        appointments.sort_by_key(|a| a.time_slot.start_time);
+        appointments
+    }
+
+    /// Add a time slot to the overlay.
+    pub fn add_time_slot(&mut self, slot: TimeSlot) -> Result<(), String> {
+        if let Some(existing) = self
+            .time_slots()
+            .into_iter()
+            .find(|existing| slot.overlaps_with(existing))
+        {
+            return Err(format!(
+                "Time slot overlaps with existing slot: {} - {}",
+                existing.start_time.format("%Y-%m-%d %H:%M"),
+                existing.end_time.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        self.slots.insert(slot.slot_id.clone(), Some(slot));
+        Ok(())
+    }
+
+    /// Remove a free time slot, returning false if it is unknown or booked.
+    pub fn remove_time_slot(&mut self, slot_id: &str) -> bool {
+        if !self
+            .slot_by_id(slot_id)
+            .is_some_and(|slot| slot.is_available)
+        {
+            return false;
+        }
+        self.slots.insert(slot_id.to_string(), None);
+        true
+    }
+
+    /// Find the free slot nearest the preferred time within the flexibility window.
+    pub fn find_available_slot(
+        &self,
+        preferred_time: DateTime<Local>,
+        flexibility_minutes: i64,
+    ) -> Option<TimeSlot> {
+        let earliest = preferred_time - Duration::minutes(flexibility_minutes);
+        let latest = preferred_time + Duration::minutes(flexibility_minutes);
+
+        self.available_slots()
+            .into_iter()
+            .filter(|slot| slot.start_time >= earliest && slot.start_time <= latest)
+            .min_by_key(|slot| (slot.start_time - preferred_time).num_seconds().abs())
+    }
+
+    /// Book a slot in the overlay, with the checks `DoctorCalendar::book_slot`
+    /// makes.
+    pub fn book_slot(
+        &mut self,
+        slot: &TimeSlot,
+        patient: Patient,
+        priority: Priority,
+        reason: String,
+    ) -> Result<Appointment, String> {
+        let mut stored_slot = self
+            .slot_by_id(&slot.slot_id)
+            .ok_or("Time slot not found in calendar")?;
+        if !stored_slot.is_available {
+            return Err("Time slot is not available".to_string());
+        }
+        if !stored_slot.is_released(self.base.now()) {
+            return Err("Time slot is not open for booking yet".to_string());
+        }
+        self.base
+            .ensure_bookable(stored_slot.start_time, stored_slot.end_time)?;
+
+        stored_slot.is_available = false;
+        let mut appointment = Appointment::new(
+            self.ids.as_ref(),
+            self.base.now(),
+            patient,
+            stored_slot.clone(),
+            priority,
+            reason,
+        )?;
+        if self.base.require_confirmation {
+            appointment.status = AppointmentStatus::Requested;
+        }
+        self.slots
+            .insert(stored_slot.slot_id.clone(), Some(stored_slot));
+        self.appointments.insert(
+            appointment.appointment_id.clone(),
+            Some(appointment.clone()),
+        );
+        Ok(appointment)
+    }
+
+    /// Cancel an appointment in the overlay, returning false if it is unknown.
+    pub fn cancel_appointment(&mut self, appointment_id: &str) -> bool {
+        let appointment = match self.appointments.get(appointment_id) {
+            Some(appointment) => appointment.clone(),
+            None => self
+                .base
+                .get_appointment_by_id(appointment_id)
+                .filter(|apt| apt.status != AppointmentStatus::Cancelled),
+        };
+        let Some(appointment) = appointment else {
+            return false;
+        };
+
+        if let Some(mut slot) = self.slot_by_id(&appointment.time_slot.slot_id) {
+            slot.is_available = true;
+            self.slots.insert(slot.slot_id.clone(), Some(slot));
+        }
+        self.appointments.insert(appointment_id.to_string(), None);
+        true
+    }
+}
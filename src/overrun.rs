@@ -0,0 +1,185 @@
+#![allow(dead_code)]
+//! How long visits actually take compared with their slots.
+//!
+//! Check-in and completion times recorded at the front desk give the
+//! actual length of every completed visit. Grouped by appointment type (the
+//! visit reason) they show which kinds of visit run over, and suggest slot
+//! lengths for the visit templates that book them.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::Appointment;
+use crate::scheduler::AppointmentScheduler;
+use std::collections::BTreeMap;
+
+/// Share of visits a suggested duration should be long enough for.
+const SUGGESTION_PERCENTILE: f64 = 0.8;
+
+/// Minutes short of its slot a visit must finish to count as early.
+const EARLY_FINISH_MINUTES: i64 = 10;
+
+/// Completed visits needed before a type's duration is questioned.
+const MIN_ADVICE_VISITS: usize = 5;
+
+/// Share of visits that must run over (or finish early) for a mismatch.
+const CONSISTENT_SHARE: f64 = 0.75;
+
+/// Actual against scheduled visit lengths for one doctor and appointment type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverrunStats {
+    pub doctor_id: String,
+    pub doctor_name: String,
+    pub appointment_type: String,
+    /// Completed visits measured.
+    pub visits: usize,
+    pub mean_scheduled_minutes: f64,
+    pub mean_actual_minutes: f64,
+    /// Mean minutes past the scheduled length; negative if visits finish early.
+    pub mean_overrun_minutes: f64,
+    /// Visits that took longer than their slot.
+    pub overrun_visits: usize,
+    /// Visits that finished at least ten minutes before their slot ended.
+    pub early_visits: usize,
+    /// Slot length, rounded up to 5 minutes, that four in five visits fit in.
+    pub suggested_minutes: i64,
+}
+
+impl OverrunStats {
+    /// Share of visits that ran over, as a percentage.
+    pub fn overrun_rate(&self) -> f64 {
+        if self.visits == 0 {
+            return 0.0;
+        }
+        (self.overrun_visits as f64 / self.visits as f64) * 100.0
+    }
+}
+
+/// Work out overrun statistics for each type of completed visit, by type name.
+///
+/// Types are matched ignoring case and surrounding whitespace.
+pub fn overrun_stats(calendar: &DoctorCalendar) -> Vec<OverrunStats> {
+    let mut by_type: BTreeMap<String, Vec<Appointment>> = BTreeMap::new();
+    for apt in calendar.appointments() {
+        if apt.actual_minutes().is_some() {
+            by_type
+                .entry(apt.reason.trim().to_lowercase())
+                .or_default()
+                .push(apt);
+        }
+    }
+
+    by_type
+        .into_values()
+        .map(|visits| {
+            let scheduled: Vec<i64> = visits
+                .iter()
+                .map(|apt| apt.time_slot.duration_minutes())
+                .collect();
+            let mut actual: Vec<i64> = visits
+                .iter()
+                .filter_map(Appointment::actual_minutes)
+                .collect();
+            let overrun_visits = actual
+                .iter()
+                .zip(&scheduled)
+                .filter(|(actual, scheduled)| actual > scheduled)
+                .count();
+            let early_visits = actual
+                .iter()
+                .zip(&scheduled)
+                .filter(|(actual, scheduled)| **actual + EARLY_FINISH_MINUTES <= **scheduled)
+                .count();
+            let mean_scheduled_minutes = mean(&scheduled);
+            let mean_actual_minutes = mean(&actual);
+
+            actual.sort_unstable();
+            let index = ((actual.len() as f64 * SUGGESTION_PERCENTILE).ceil() as usize).max(1) - 1;
+            let suggested_minutes = round_up_to_five(actual[index]);
+
+            OverrunStats {
+                doctor_id: calendar.doctor_id.clone(),
+                doctor_name: calendar.doctor_name.clone(),
+                appointment_type: visits[0].reason.trim().to_string(),
+                visits: visits.len(),
+                mean_scheduled_minutes,
+                mean_actual_minutes,
+                mean_overrun_minutes: mean_actual_minutes - mean_scheduled_minutes,
+                overrun_visits,
+                early_visits,
+                suggested_minutes,
+            }
+        })
+        .collect()
+}
+
+/// Suggest a slot length for an appointment type from its completed visits.
+pub fn suggested_duration(calendar: &DoctorCalendar, appointment_type: &str) -> Option<i64> {
+    let wanted = appointment_type.trim();
+    overrun_stats(calendar)
+        .into_iter()
+        .find(|stats| stats.appointment_type.eq_ignore_ascii_case(wanted))
+        .map(|stats| stats.suggested_minutes)
+}
+
+/// A proposed change to the slot length booked for an appointment type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DurationAdvice {
+    pub stats: OverrunStats,
+    /// Visit template that books this type, if any.
+    pub template: Option<String>,
+    /// The template's duration, or the usual slot length without one.
+    pub current_minutes: i64,
+    pub suggested_minutes: i64,
+}
+
+impl DurationAdvice {
+    /// Check whether the advice is to lengthen the slots.
+    pub fn is_longer(&self) -> bool {
+        self.suggested_minutes > self.current_minutes
+    }
+}
+
+/// Flag appointment types whose visits consistently run over their slots
+/// or finish well inside them, and propose new template durations.
+///
+/// A type is flagged once it has at least five completed visits and three
+/// in four of them ran over, or finished ten or more minutes early.
+pub fn duration_advice(scheduler: &AppointmentScheduler) -> Vec<DurationAdvice> {
+    overrun_stats(&scheduler.calendar)
+        .into_iter()
+        .filter(|stats| stats.visits >= MIN_ADVICE_VISITS)
+        .filter(|stats| {
+            let needed = stats.visits as f64 * CONSISTENT_SHARE;
+            stats.overrun_visits as f64 >= needed || stats.early_visits as f64 >= needed
+        })
+        .filter_map(|stats| {
+            let template = scheduler.request_templates().iter().find(|template| {
+                template
+                    .reason
+                    .eq_ignore_ascii_case(&stats.appointment_type)
+            });
+            let current_minutes = template
+                .and_then(|template| template.duration_minutes)
+                .unwrap_or(stats.mean_scheduled_minutes.round() as i64);
+            if stats.suggested_minutes == current_minutes {
+                return None;
+            }
+            Some(DurationAdvice {
+                template: template.map(|template| template.name.clone()),
+                current_minutes,
+                suggested_minutes: stats.suggested_minutes,
+                stats,
+            })
+        })
+        .collect()
+}
+
+fn mean(values: &[i64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().sum::<i64>() as f64 / values.len() as f64
+}
+
+fn round_up_to_five(minutes: i64) -> i64 {
+    ((minutes.max(1) + 4) / 5) * 5
+}
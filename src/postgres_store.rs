@@ -0,0 +1,541 @@
+#![allow(dead_code)]
+//! PostgreSQL-backed storage (enabled with the `postgres` feature).
+//!
+//! The tables mirror the SQLite store's, one row per slot and appointment
+//! with the full record as JSONB, but one database can be shared by
+//! scheduler processes on different machines. `book_slot` locks the slot's
+//! row with `SELECT ... FOR UPDATE` before booking, so when two processes
+//! book the same slot at once the second waits for the first to commit,
+//! then sees the slot taken and fails. Statements are sent using the
+//! frontend/backend protocol over a plain TCP connection; the server must
+//! accept `trust` or `password` authentication for the user.
+
+use crate::calendar::DoctorCalendar;
+use crate::events::OutboxEntry;
+use crate::models::{
+    Appointment, AppointmentStatus, CancellationActor, CancellationRecord, Patient, Priority,
+    TimeSlot,
+};
+use crate::snapshot::CalendarSnapshot;
+use crate::store::CalendarStore;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::io::{BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS doctors (
+        doctor_id TEXT PRIMARY KEY,
+        doctor_name TEXT NOT NULL,
+        settings JSONB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS slots (
+        slot_id TEXT PRIMARY KEY,
+        doctor_id TEXT NOT NULL REFERENCES doctors (doctor_id),
+        start_time TIMESTAMPTZ NOT NULL,
+        end_time TIMESTAMPTZ NOT NULL,
+        is_available BOOLEAN NOT NULL,
+        data JSONB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS appointments (
+        appointment_id TEXT PRIMARY KEY,
+        doctor_id TEXT NOT NULL REFERENCES doctors (doctor_id),
+        slot_id TEXT NOT NULL,
+        patient_id TEXT NOT NULL,
+        start_time TIMESTAMPTZ NOT NULL,
+        data JSONB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        doctor_id TEXT NOT NULL,
+        sequence BIGINT NOT NULL,
+        occurred_at TIMESTAMPTZ NOT NULL,
+        data JSONB NOT NULL,
+        PRIMARY KEY (doctor_id, sequence)
+    );
+    CREATE INDEX IF NOT EXISTS slots_by_doctor ON slots (doctor_id, start_time);
+    CREATE INDEX IF NOT EXISTS appointments_by_doctor ON appointments (doctor_id, start_time);
+";
+
+/// A row of text values; `None` for SQL NULL.
+type Row = Vec<Option<String>>;
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    /// Send messages in one write, each a tag (none for the startup
+    /// message) and a body.
+    fn send(&mut self, messages: &[(Option<u8>, &[u8])]) -> Result<(), String> {
+        let mut buffer = Vec::new();
+        for (tag, body) in messages {
+            buffer.extend(tag);
+            buffer.extend_from_slice(&(body.len() as i32 + 4).to_be_bytes());
+            buffer.extend_from_slice(body);
+        }
+        self.writer
+            .write_all(&buffer)
+            .map_err(|e| format!("Failed to write to PostgreSQL: {}", e))
+    }
+
+    fn read_message(&mut self) -> Result<(u8, Vec<u8>), String> {
+        let read_error = |e: std::io::Error| format!("Failed to read from PostgreSQL: {}", e);
+        let mut header = [0u8; 5];
+        self.reader.read_exact(&mut header).map_err(read_error)?;
+        let len = i32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+        if len < 4 {
+            return Err(format!("Invalid PostgreSQL message length: {}", len));
+        }
+        let mut body = vec![0u8; len as usize - 4];
+        self.reader.read_exact(&mut body).map_err(read_error)?;
+        Ok((header[0], body))
+    }
+
+    fn startup(
+        &mut self,
+        user: &str,
+        database: &str,
+        password: Option<&str>,
+    ) -> Result<(), String> {
+        let mut body = 196608i32.to_be_bytes().to_vec();
+        for (name, value) in [
+            ("user", user),
+            ("database", database),
+            ("client_encoding", "UTF8"),
+        ] {
+            put_str(&mut body, name);
+            put_str(&mut body, value);
+        }
+        body.push(0);
+        self.send(&[(None, &body)])?;
+
+        loop {
+            let (tag, body) = self.read_message()?;
+            match tag {
+                b'R' => match read_i32(&body, 0)? {
+                    0 => {}
+                    3 => {
+                        let password = password.ok_or_else(|| {
+                            "PostgreSQL asked for a password but none was given".to_string()
+                        })?;
+                        let mut message = Vec::new();
+                        put_str(&mut message, password);
+                        self.send(&[(Some(b'p'), &message)])?;
+                    }
+                    method => {
+                        return Err(format!(
+                            "Unsupported PostgreSQL authentication method {}; \
+                             use trust or password for this user",
+                            method
+                        ))
+                    }
+                },
+                b'E' => return Err(server_error(&body)),
+                b'Z' => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Run one statement with `$1`-style parameters.
+    fn query(&mut self, sql: &str, params: &[&str]) -> Result<Vec<Row>, String> {
+        let mut parse = vec![0u8];
+        put_str(&mut parse, sql);
+        parse.extend_from_slice(&0i16.to_be_bytes());
+
+        let mut bind = vec![0u8, 0u8];
+        bind.extend_from_slice(&0i16.to_be_bytes());
+        bind.extend_from_slice(&(params.len() as i16).to_be_bytes());
+        for param in params {
+            bind.extend_from_slice(&(param.len() as i32).to_be_bytes());
+            bind.extend_from_slice(param.as_bytes());
+        }
+        bind.extend_from_slice(&0i16.to_be_bytes());
+
+        let mut execute = vec![0u8];
+        execute.extend_from_slice(&0i32.to_be_bytes());
+
+        self.send(&[
+            (Some(b'P'), &parse),
+            (Some(b'B'), &bind),
+            (Some(b'E'), &execute),
+            (Some(b'S'), &[]),
+        ])?;
+        self.read_results()
+    }
+
+    /// Run statements separated by semicolons, without parameters.
+    fn batch(&mut self, sql: &str) -> Result<(), String> {
+        let mut body = Vec::new();
+        put_str(&mut body, sql);
+        self.send(&[(Some(b'Q'), &body)])?;
+        self.read_results().map(|_| ())
+    }
+
+    fn read_results(&mut self) -> Result<Vec<Row>, String> {
+        let mut rows = Vec::new();
+        let mut error = None;
+        loop {
+            let (tag, body) = self.read_message()?;
+            match tag {
+                b'D' => rows.push(read_row(&body)?),
+                b'E' => error = error.or(Some(server_error(&body))),
+                b'Z' => break,
+                _ => {}
+            }
+        }
+        match error {
+            Some(error) => Err(error),
+            None => Ok(rows),
+        }
+    }
+
+    /// Run `f` in a database transaction, rolling back if it fails.
+    fn transaction<T, F>(&mut self, f: F) -> Result<T, String>
+    where
+        F: FnOnce(&mut Connection) -> Result<T, String>,
+    {
+        self.query("BEGIN", &[])?;
+        match f(self) {
+            Ok(value) => {
+                self.query("COMMIT", &[])?;
+                Ok(value)
+            }
+            Err(e) => {
+                let _ = self.query("ROLLBACK", &[]);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Calendar store kept in a PostgreSQL database.
+pub struct PostgresStore {
+    connection: RefCell<Connection>,
+}
+
+impl PostgresStore {
+    /// Connect to a database and set up its tables, e.g.
+    /// `PostgresStore::connect("127.0.0.1:5432", "easyappoint", "clinic", None)`.
+    pub fn connect(
+        address: &str,
+        user: &str,
+        database: &str,
+        password: Option<&str>,
+    ) -> Result<Self, String> {
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("Failed to connect to PostgreSQL at {}: {}", address, e))?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(30)))
+            .map_err(|e| format!("Failed to configure PostgreSQL connection: {}", e))?;
+        let writer = stream
+            .try_clone()
+            .map_err(|e| format!("Failed to configure PostgreSQL connection: {}", e))?;
+
+        let mut connection = Connection {
+            reader: BufReader::new(stream),
+            writer,
+        };
+        connection.startup(user, database, password)?;
+        connection.batch(SCHEMA)?;
+        Ok(PostgresStore {
+            connection: RefCell::new(connection),
+        })
+    }
+
+    /// Book a slot and store the booking, all or nothing.
+    ///
+    /// The slot must already be stored. Its row stays locked until the
+    /// booking commits, and a slot another process has booked in the
+    /// meantime is refused even if this calendar still shows it free.
+    pub fn book_slot(
+        &mut self,
+        calendar: &mut DoctorCalendar,
+        slot: &TimeSlot,
+        patient: Patient,
+        priority: Priority,
+        reason: String,
+    ) -> Result<Appointment, String> {
+        let conn = self.connection.get_mut();
+        calendar.transaction(|calendar| {
+            let appointment = conn.transaction(|conn| {
+                let rows = conn.query(
+                    "SELECT is_available FROM slots WHERE slot_id = $1 AND doctor_id = $2
+                     FOR UPDATE",
+                    &[&slot.slot_id, &calendar.doctor_id],
+                )?;
+                match rows.first().and_then(|row| row[0].as_deref()) {
+                    None => return Err(format!("Time slot {} is not stored", slot.slot_id)),
+                    Some("f") => {
+                        return Err(format!("Time slot {} is already booked", slot.slot_id))
+                    }
+                    Some(_) => {}
+                }
+
+                let appointment = calendar.book_slot(slot, patient, priority, reason)?;
+                write_doctor(conn, &calendar.snapshot())?;
+                if let Some(slot) = calendar.slot_by_id(&appointment.time_slot.slot_id) {
+                    write_slot(conn, &calendar.doctor_id, slot)?;
+                }
+                write_appointment(conn, &calendar.doctor_id, &appointment)?;
+                write_new_events(conn, &calendar.doctor_id, calendar.outbox())?;
+                Ok(appointment)
+            })?;
+            calendar.mark_events_logged();
+            Ok(appointment)
+        })
+    }
+
+    /// Cancel an appointment and store the cancellation, all or nothing.
+    ///
+    /// Other steps of a bundle cancelled along with it are stored as
+    /// cancelled too.
+    pub fn cancel_appointment(
+        &mut self,
+        calendar: &mut DoctorCalendar,
+        appointment_id: &str,
+        actor: CancellationActor,
+    ) -> Result<CancellationRecord, String> {
+        let conn = self.connection.get_mut();
+        calendar.transaction(|calendar| {
+            let record = conn.transaction(|conn| {
+                let before: Vec<Appointment> = calendar.appointments();
+                let record = calendar.cancel_appointment_by(appointment_id, actor)?;
+                write_doctor(conn, &calendar.snapshot())?;
+                let cancelled = before.iter().filter_map(|apt| {
+                    calendar
+                        .get_appointment_by_id(&apt.appointment_id)
+                        .filter(|apt| apt.status == AppointmentStatus::Cancelled)
+                });
+                for apt in cancelled {
+                    write_appointment(conn, &calendar.doctor_id, &apt)?;
+                    if let Some(slot) = calendar.slot_by_id(&apt.time_slot.slot_id) {
+                        write_slot(conn, &calendar.doctor_id, slot)?;
+                    }
+                }
+                write_new_events(conn, &calendar.doctor_id, calendar.outbox())?;
+                Ok(record)
+            })?;
+            calendar.mark_events_logged();
+            Ok(record)
+        })
+    }
+
+    fn rows<T: DeserializeOwned>(&self, sql: &str, doctor_id: &str) -> Result<Vec<T>, String> {
+        self.connection
+            .borrow_mut()
+            .query(sql, &[doctor_id])?
+            .iter()
+            .map(|row| from_json(row[0].as_deref().unwrap_or("null")))
+            .collect()
+    }
+}
+
+impl CalendarStore for PostgresStore {
+    fn load(&self, doctor_id: &str) -> Result<Option<CalendarSnapshot>, String> {
+        let rows = self.connection.borrow_mut().query(
+            "SELECT settings FROM doctors WHERE doctor_id = $1",
+            &[doctor_id],
+        )?;
+        let Some(settings) = rows.first().and_then(|row| row[0].clone()) else {
+            return Ok(None);
+        };
+
+        let mut snapshot: CalendarSnapshot = from_json(&settings)?;
+        snapshot.time_slots = self.rows(
+            "SELECT data FROM slots WHERE doctor_id = $1 ORDER BY start_time",
+            doctor_id,
+        )?;
+        snapshot.appointments = self.rows(
+            "SELECT data FROM appointments WHERE doctor_id = $1 ORDER BY start_time",
+            doctor_id,
+        )?;
+        Ok(Some(snapshot))
+    }
+
+    fn save(&mut self, snapshot: &CalendarSnapshot) -> Result<(), String> {
+        self.connection.get_mut().transaction(|conn| {
+            write_doctor(conn, snapshot)?;
+            conn.query(
+                "DELETE FROM slots WHERE doctor_id = $1",
+                &[&snapshot.doctor_id],
+            )?;
+            conn.query(
+                "DELETE FROM appointments WHERE doctor_id = $1",
+                &[&snapshot.doctor_id],
+            )?;
+            for slot in &snapshot.time_slots {
+                write_slot(conn, &snapshot.doctor_id, slot)?;
+            }
+            for appointment in &snapshot.appointments {
+                write_appointment(conn, &snapshot.doctor_id, appointment)?;
+            }
+            Ok(())
+        })
+    }
+
+    fn append_events(&mut self, doctor_id: &str, entries: &[OutboxEntry]) -> Result<(), String> {
+        self.connection
+            .get_mut()
+            .transaction(|conn| write_new_events(conn, doctor_id, entries))
+    }
+
+    fn events(&self, doctor_id: &str) -> Result<Vec<OutboxEntry>, String> {
+        self.rows(
+            "SELECT data FROM events WHERE doctor_id = $1 ORDER BY sequence",
+            doctor_id,
+        )
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        self.connection
+            .borrow_mut()
+            .query("SELECT 1", &[])
+            .map(|_| ())
+    }
+}
+
+/// Store the doctor's settings: the snapshot without its slots and
+/// appointments, which have tables of their own.
+fn write_doctor(conn: &mut Connection, snapshot: &CalendarSnapshot) -> Result<(), String> {
+    let mut settings = snapshot.clone();
+    settings.time_slots.clear();
+    settings.appointments.clear();
+    conn.query(
+        "INSERT INTO doctors (doctor_id, doctor_name, settings) VALUES ($1, $2, $3)
+         ON CONFLICT (doctor_id) DO UPDATE
+         SET doctor_name = EXCLUDED.doctor_name, settings = EXCLUDED.settings",
+        &[
+            &snapshot.doctor_id,
+            &snapshot.doctor_name,
+            &to_json(&settings)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_slot(conn: &mut Connection, doctor_id: &str, slot: &TimeSlot) -> Result<(), String> {
+    conn.query(
+        "INSERT INTO slots (slot_id, doctor_id, start_time, end_time, is_available, data)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (slot_id) DO UPDATE
+         SET doctor_id = EXCLUDED.doctor_id, start_time = EXCLUDED.start_time,
+             end_time = EXCLUDED.end_time, is_available = EXCLUDED.is_available,
+             data = EXCLUDED.data",
+        &[
+            &slot.slot_id,
+            doctor_id,
+            &slot.start_time.to_rfc3339(),
+            &slot.end_time.to_rfc3339(),
+            &slot.is_available.to_string(),
+            &to_json(slot)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_appointment(
+    conn: &mut Connection,
+    doctor_id: &str,
+    appointment: &Appointment,
+) -> Result<(), String> {
+    conn.query(
+        "INSERT INTO appointments
+         (appointment_id, doctor_id, slot_id, patient_id, start_time, data)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (appointment_id) DO UPDATE
+         SET doctor_id = EXCLUDED.doctor_id, slot_id = EXCLUDED.slot_id,
+             patient_id = EXCLUDED.patient_id, start_time = EXCLUDED.start_time,
+             data = EXCLUDED.data",
+        &[
+            &appointment.appointment_id,
+            doctor_id,
+            &appointment.time_slot.slot_id,
+            &appointment.patient.patient_id,
+            &appointment.time_slot.start_time.to_rfc3339(),
+            &to_json(appointment)?,
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_new_events(
+    conn: &mut Connection,
+    doctor_id: &str,
+    outbox: &[OutboxEntry],
+) -> Result<(), String> {
+    for entry in outbox {
+        conn.query(
+            "INSERT INTO events (doctor_id, sequence, occurred_at, data)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (doctor_id, sequence) DO NOTHING",
+            &[
+                doctor_id,
+                &entry.sequence.to_string(),
+                &entry.occurred_at.to_rfc3339(),
+                &to_json(entry)?,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+fn put_str(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(value.as_bytes());
+    buffer.push(0);
+}
+
+fn read_i32(body: &[u8], at: usize) -> Result<i32, String> {
+    body.get(at..at + 4)
+        .map(|bytes| i32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| "Truncated PostgreSQL message".to_string())
+}
+
+fn read_row(body: &[u8]) -> Result<Row, String> {
+    let count = body
+        .get(0..2)
+        .map(|bytes| i16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| "Truncated PostgreSQL row".to_string())?;
+    let mut at = 2;
+    let mut row = Vec::new();
+    for _ in 0..count {
+        let len = read_i32(body, at)?;
+        at += 4;
+        if len < 0 {
+            row.push(None);
+            continue;
+        }
+        let value = body
+            .get(at..at + len as usize)
+            .ok_or_else(|| "Truncated PostgreSQL row".to_string())?;
+        row.push(Some(String::from_utf8_lossy(value).into_owned()));
+        at += len as usize;
+    }
+    Ok(row)
+}
+
+/// Describe an ErrorResponse by its severity and message fields.
+fn server_error(body: &[u8]) -> String {
+    let mut severity = "ERROR".to_string();
+    let mut message = String::new();
+    for field in body.split(|&b| b == 0).filter(|field| !field.is_empty()) {
+        let value = String::from_utf8_lossy(&field[1..]).into_owned();
+        match field[0] {
+            b'S' => severity = value,
+            b'M' => message = value,
+            _ => {}
+        }
+    }
+    format!("PostgreSQL {}: {}", severity, message)
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to encode row: {}", e))
+}
+
+fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| format!("Failed to decode row: {}", e))
+}
@@ -0,0 +1,237 @@
+#![allow(dead_code)]
+//! A doctor's own scheduling preferences.
+//!
+//! Business rules are clinic policy; preferences belong to one doctor and
+//! are stored on their calendar. They're written one per line in the same
+//! style as the rules:
+//!
+//! ```text
+//! max 3 "procedure" per day
+//! prefer "new patient" in morning
+//! keep 12:30-13:00 free
+//! ```
+//!
+//! A preference is hard unless it starts with `prefer`. Hard preferences
+//! are never broken. Soft ones steer the scheduler away from slots that
+//! break them, but a slot breaking them is still booked when it's the only
+//! one inside the patient's window.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{AppointmentRequest, TimeSlot};
+use crate::rules::{reason_matches, split_quoted, Session};
+use chrono::NaiveTime;
+use std::fmt;
+use std::fs;
+
+/// What a doctor prefers about their day.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Preference {
+    /// At most `max` appointments per day whose reason contains `reason`.
+    MaxPerDay { reason: String, max: usize },
+    /// Visits whose reason contains `reason` go in this session.
+    InSession { reason: String, session: Session },
+    /// Nothing is booked overlapping this time of day.
+    KeepFree { start: NaiveTime, end: NaiveTime },
+}
+
+impl Preference {
+    /// Check whether booking `slot` for `request` keeps to the preference.
+    pub fn allows(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slot: &TimeSlot,
+    ) -> bool {
+        match self {
+            Preference::MaxPerDay { reason, max } => {
+                if !reason_matches(&request.reason, reason) {
+                    return true;
+                }
+                let booked = calendar
+                    .get_appointments_on_date(slot.start_time)
+                    .iter()
+                    .filter(|apt| reason_matches(&apt.reason, reason))
+                    .count();
+                booked < *max
+            }
+            Preference::InSession { reason, session } => {
+                !reason_matches(&request.reason, reason) || session.contains(slot)
+            }
+            Preference::KeepFree { start, end } => {
+                let date = slot.start_time.date_naive();
+                let start = date.and_time(*start);
+                let end = date.and_time(*end);
+                slot.end_time.naive_local() <= start || slot.start_time.naive_local() >= end
+            }
+        }
+    }
+}
+
+/// A preference and whether it may be broken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DoctorPreference {
+    pub preference: Preference,
+    /// Hard preferences are never broken; soft ones only when nothing else fits.
+    pub hard: bool,
+}
+
+impl DoctorPreference {
+    /// Parse one preference line.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (hard, body) = match line.strip_prefix("prefer ") {
+            Some(body) => (false, body.trim()),
+            None => (true, line),
+        };
+        let (head, reason, tail) = split_quoted(body)?;
+        let head: Vec<&str> = head.split_whitespace().collect();
+        let tail: Vec<&str> = tail.split_whitespace().collect();
+
+        let preference = match (head.as_slice(), reason) {
+            (["max", count], Some(reason)) => {
+                if tail != ["per", "day"] {
+                    return Err("Expected: max <count> \"<reason>\" per day".to_string());
+                }
+                let max = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?;
+                Preference::MaxPerDay { reason, max }
+            }
+            ([], Some(reason)) => match tail.as_slice() {
+                ["in", session] => Preference::InSession {
+                    reason,
+                    session: Session::from_string(session)?,
+                },
+                _ => return Err("Expected: \"<reason>\" in <session>".to_string()),
+            },
+            (["keep", range, "free"], None) => {
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or_else(|| format!("Invalid time range: {}", range))?;
+                let parse = |time: &str| {
+                    NaiveTime::parse_from_str(time, "%H:%M")
+                        .map_err(|_| format!("Invalid time: {}", time))
+                };
+                let (start, end) = (parse(start)?, parse(end)?);
+                if end <= start {
+                    return Err(format!("Invalid time range: {}", range));
+                }
+                Preference::KeepFree { start, end }
+            }
+            _ => return Err(format!("Unrecognised preference: {}", line)),
+        };
+        Ok(DoctorPreference { preference, hard })
+    }
+}
+
+impl fmt::Display for DoctorPreference {
+    /// Format the preference in the line syntax accepted by `DoctorPreference::parse`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.hard {
+            write!(f, "prefer ")?;
+        }
+        match &self.preference {
+            Preference::MaxPerDay { reason, max } => {
+                write!(f, "max {} \"{}\" per day", max, reason)
+            }
+            Preference::InSession { reason, session } => {
+                write!(f, "\"{}\" in {}", reason, session.name())
+            }
+            Preference::KeepFree { start, end } => write!(
+                f,
+                "keep {}-{} free",
+                start.format("%H:%M"),
+                end.format("%H:%M")
+            ),
+        }
+    }
+}
+
+/// A doctor's scheduling preferences.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DoctorPreferences {
+    preferences: Vec<DoctorPreference>,
+}
+
+impl DoctorPreferences {
+    /// Create an empty set of preferences.
+    pub fn new() -> Self {
+        DoctorPreferences::default()
+    }
+
+    /// Parse preferences from text, reporting the first bad line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut preferences = DoctorPreferences::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let preference =
+                DoctorPreference::parse(line).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+            preferences.push(preference);
+        }
+        Ok(preferences)
+    }
+
+    /// Load preferences from a file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        DoctorPreferences::parse(&text)
+    }
+
+    /// Parse preferences stored one per entry, e.g. in a snapshot.
+    pub fn from_lines(lines: &[String]) -> Result<Self, String> {
+        let mut preferences = DoctorPreferences::new();
+        for line in lines {
+            preferences.push(DoctorPreference::parse(line)?);
+        }
+        Ok(preferences)
+    }
+
+    /// Format the preferences one per entry.
+    pub fn to_lines(&self) -> Vec<String> {
+        self.preferences
+            .iter()
+            .map(|preference| preference.to_string())
+            .collect()
+    }
+
+    /// Add a preference.
+    pub fn push(&mut self, preference: DoctorPreference) {
+        self.preferences.push(preference);
+    }
+
+    /// Get the preferences in order.
+    pub fn preferences(&self) -> &[DoctorPreference] {
+        &self.preferences
+    }
+
+    /// Check whether booking `slot` for `request` keeps every hard preference.
+    pub fn permits(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slot: &TimeSlot,
+    ) -> bool {
+        self.preferences
+            .iter()
+            .filter(|preference| preference.hard)
+            .all(|preference| preference.preference.allows(calendar, request, slot))
+    }
+
+    /// Count the soft preferences booking `slot` for `request` would break.
+    pub fn soft_misses(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slot: &TimeSlot,
+    ) -> usize {
+        self.preferences
+            .iter()
+            .filter(|preference| !preference.hard)
+            .filter(|preference| !preference.preference.allows(calendar, request, slot))
+            .count()
+    }
+}
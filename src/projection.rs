@@ -0,0 +1,287 @@
+#![allow(dead_code)]
+//! Read models built from a calendar's event log.
+//!
+//! Queries such as "who is booked on Tuesday?" or "what has this patient
+//! got coming up?" would otherwise scan every appointment in the calendar.
+//! A CalendarProjection folds the calendar's events into per-day and
+//! per-patient indexes and per-day slot counters as they happen, and can be
+//! rebuilt from the stored event log at any time. Status changes such as
+//! check-ins and no-shows are applied too, so projected appointments show
+//! their current status and attendance times.
+//!
+//! Replaying only the events up to a moment shows the schedule as it stood
+//! then, e.g. to settle whether a patient really was booked at 10:00 on
+//! Monday the 3rd.
+
+use crate::events::{CalendarEvent, OutboxEntry};
+use crate::models::{Appointment, TimeSlot};
+use crate::store::CalendarStore;
+use chrono::{DateTime, Local, NaiveDate};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Slot counters for one day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DayUtilization {
+    pub slots: usize,
+    pub booked: usize,
+}
+
+impl DayUtilization {
+    /// Share of the day's slots that are booked, as a percentage.
+    pub fn utilization(&self) -> f64 {
+        if self.slots == 0 {
+            return 0.0;
+        }
+        (self.booked as f64 / self.slots as f64) * 100.0
+    }
+}
+
+/// Indexes over one doctor's bookings, kept up to date from their events.
+#[derive(Debug, Clone)]
+pub struct CalendarProjection {
+    doctor_id: String,
+    last_sequence: u64,
+    slots: HashMap<String, TimeSlot>,
+    appointments: HashMap<String, Appointment>,
+    by_day: BTreeMap<NaiveDate, BTreeSet<(DateTime<Local>, String)>>,
+    by_patient: HashMap<String, BTreeSet<(DateTime<Local>, String)>>,
+    utilization: BTreeMap<NaiveDate, DayUtilization>,
+}
+
+impl CalendarProjection {
+    /// Create an empty projection for a doctor.
+    pub fn new(doctor_id: String) -> Self {
+        CalendarProjection {
+            doctor_id,
+            last_sequence: 0,
+            slots: HashMap::new(),
+            appointments: HashMap::new(),
+            by_day: BTreeMap::new(),
+            by_patient: HashMap::new(),
+            utilization: BTreeMap::new(),
+        }
+    }
+
+    /// Build a projection by replaying a doctor's events from the start.
+    pub fn rebuild(doctor_id: String, entries: &[OutboxEntry]) -> Self {
+        let mut projection = CalendarProjection::new(doctor_id);
+        projection.apply_all(entries);
+        projection
+    }
+
+    /// Build a projection from the events that occurred at or before
+    /// `timestamp`.
+    pub fn rebuild_as_of(
+        doctor_id: String,
+        entries: &[OutboxEntry],
+        timestamp: DateTime<Local>,
+    ) -> Self {
+        let mut projection = CalendarProjection::new(doctor_id);
+        for entry in entries
+            .iter()
+            .filter(|entry| entry.occurred_at <= timestamp)
+        {
+            projection.apply(entry);
+        }
+        projection
+    }
+
+    /// Build a projection from the event log in a store.
+    pub fn from_store(store: &dyn CalendarStore, doctor_id: &str) -> Result<Self, String> {
+        Ok(CalendarProjection::rebuild(
+            doctor_id.to_string(),
+            &store.events(doctor_id)?,
+        ))
+    }
+
+    /// Apply events in order, skipping any already applied.
+    pub fn apply_all(&mut self, entries: &[OutboxEntry]) {
+        for entry in entries {
+            self.apply(entry);
+        }
+    }
+
+    /// Apply one event, unless its sequence number has been applied already.
+    pub fn apply(&mut self, entry: &OutboxEntry) {
+        if entry.sequence <= self.last_sequence {
+            return;
+        }
+        self.last_sequence = entry.sequence;
+
+        match &entry.event {
+            CalendarEvent::SlotAdded { slot } => self.add_slot(slot.clone()),
+            CalendarEvent::SlotRemoved { slot_id } => {
+                if let Some(slot) = self.slots.remove(slot_id) {
+                    let day = self.utilization.entry(day_of(&slot)).or_default();
+                    day.slots = day.slots.saturating_sub(1);
+                    if !slot.is_available {
+                        day.booked = day.booked.saturating_sub(1);
+                    }
+                }
+            }
+            CalendarEvent::AppointmentBooked { appointment } => {
+                self.add_appointment(appointment.clone())
+            }
+            CalendarEvent::AppointmentCancelled { appointment_id, .. } => {
+                self.remove_appointment(appointment_id);
+            }
+            CalendarEvent::AppointmentRescheduled { appointment, .. } => {
+                self.remove_appointment(&appointment.appointment_id);
+                self.add_appointment(appointment.clone());
+            }
+            CalendarEvent::AppointmentReassigned {
+                from_doctor_id,
+                to_doctor_id,
+                appointment,
+            } => {
+                if *from_doctor_id == self.doctor_id {
+                    self.remove_appointment(&appointment.appointment_id);
+                } else if *to_doctor_id == self.doctor_id {
+                    self.add_appointment(appointment.clone());
+                }
+            }
+            CalendarEvent::AppointmentStatusChanged { appointment, .. } => {
+                if let Some(projected) = self.appointments.get_mut(&appointment.appointment_id) {
+                    *projected = appointment.clone();
+                }
+            }
+        }
+    }
+
+    /// Sequence number of the last event applied.
+    pub fn last_sequence(&self) -> u64 {
+        self.last_sequence
+    }
+
+    /// Get every appointment in time order.
+    pub fn appointments(&self) -> Vec<&Appointment> {
+        self.by_day
+            .values()
+            .flatten()
+            .filter_map(|(_, id)| self.appointments.get(id))
+            .collect()
+    }
+
+    /// Get every slot in time order.
+    pub fn slots(&self) -> Vec<&TimeSlot> {
+        let mut slots: Vec<&TimeSlot> = self.slots.values().collect();
+        slots.sort_by_key(|slot| slot.start_time);
+        slots
+    }
+
+    /// Get a day's appointments in time order.
+    pub fn appointments_on(&self, date: NaiveDate) -> Vec<&Appointment> {
+        self.by_day
+            .get(&date)
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, id)| self.appointments.get(id))
+            .collect()
+    }
+
+    /// Get a patient's appointments in time order.
+    pub fn patient_appointments(&self, patient_id: &str) -> Vec<&Appointment> {
+        self.by_patient
+            .get(patient_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|(_, id)| self.appointments.get(id))
+            .collect()
+    }
+
+    /// Get a day's slot counters.
+    pub fn utilization(&self, date: NaiveDate) -> DayUtilization {
+        self.utilization.get(&date).copied().unwrap_or_default()
+    }
+
+    /// Add up the slot counters for the days from `first` to `last`.
+    pub fn utilization_between(&self, first: NaiveDate, last: NaiveDate) -> DayUtilization {
+        self.utilization
+            .range(first..=last)
+            .fold(DayUtilization::default(), |total, (_, day)| {
+                DayUtilization {
+                    slots: total.slots + day.slots,
+                    booked: total.booked + day.booked,
+                }
+            })
+    }
+
+    fn add_slot(&mut self, slot: TimeSlot) {
+        let day = self.utilization.entry(day_of(&slot)).or_default();
+        day.slots += 1;
+        if !slot.is_available {
+            day.booked += 1;
+        }
+        self.slots.insert(slot.slot_id.clone(), slot);
+    }
+
+    fn add_appointment(&mut self, appointment: Appointment) {
+        let key = (
+            appointment.time_slot.start_time,
+            appointment.appointment_id.clone(),
+        );
+        self.set_booked(&appointment.time_slot.slot_id, true);
+        self.by_day
+            .entry(day_of(&appointment.time_slot))
+            .or_default()
+            .insert(key.clone());
+        self.by_patient
+            .entry(appointment.patient.patient_id.clone())
+            .or_default()
+            .insert(key);
+        self.appointments
+            .insert(appointment.appointment_id.clone(), appointment);
+    }
+
+    fn remove_appointment(&mut self, appointment_id: &str) {
+        let Some(appointment) = self.appointments.remove(appointment_id) else {
+            return;
+        };
+        let key = (
+            appointment.time_slot.start_time,
+            appointment.appointment_id.clone(),
+        );
+        self.set_booked(&appointment.time_slot.slot_id, false);
+        if let Some(day) = self.by_day.get_mut(&day_of(&appointment.time_slot)) {
+            day.remove(&key);
+        }
+        if let Some(patient) = self.by_patient.get_mut(&appointment.patient.patient_id) {
+            patient.remove(&key);
+        }
+    }
+
+    /// Mark a known slot booked or free, keeping its day's counter in step.
+    fn set_booked(&mut self, slot_id: &str, booked: bool) {
+        let Some(slot) = self.slots.get_mut(slot_id) else {
+            return;
+        };
+        if slot.is_available != booked {
+            return;
+        }
+        slot.is_available = !booked;
+        let day = self.utilization.entry(day_of(slot)).or_default();
+        if booked {
+            day.booked += 1;
+        } else {
+            day.booked = day.booked.saturating_sub(1);
+        }
+    }
+}
+
+/// Reconstruct a doctor's schedule as it stood at `timestamp` from the event
+/// log in a store.
+pub fn calendar_as_of(
+    store: &dyn CalendarStore,
+    doctor_id: &str,
+    timestamp: DateTime<Local>,
+) -> Result<CalendarProjection, String> {
+    Ok(CalendarProjection::rebuild_as_of(
+        doctor_id.to_string(),
+        &store.events(doctor_id)?,
+        timestamp,
+    ))
+}
+
+fn day_of(slot: &TimeSlot) -> NaiveDate {
+    slot.start_time.date_naive()
+}
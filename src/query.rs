@@ -0,0 +1,366 @@
+#![allow(dead_code)]
+//! Ad-hoc queries over a doctor's appointments.
+//!
+//! An AppointmentQuery filters the calendar's appointments and optionally
+//! counts them per group. Queries can be built in code or parsed from a
+//! short expression, as the `report` subcommand does:
+//!
+//! ```text
+//! appointments where priority=urgent and date in next_7_days group by day
+//! ```
+//!
+//! Conditions are `priority=`, `status=`, `patient=` and `date` compared
+//! with `=`, `>=` or `<=` to a `YYYY-MM-DD` date, or `date in` one of
+//! `today`, `tomorrow`, `yesterday`, `this_week`, `next_N_days` and
+//! `last_N_days`. Results can be grouped by day, week, priority, status or
+//! patient.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{Appointment, AppointmentStatus, Priority};
+use crate::reports::escape_csv;
+use chrono::{Datelike, Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+/// What a grouped query counts appointments by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    Day,
+    /// Weeks starting on Monday.
+    Week,
+    Priority,
+    Status,
+    Patient,
+}
+
+impl GroupBy {
+    /// Convert a string to a GroupBy value.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "day" | "date" => Ok(GroupBy::Day),
+            "week" => Ok(GroupBy::Week),
+            "priority" => Ok(GroupBy::Priority),
+            "status" => Ok(GroupBy::Status),
+            "patient" => Ok(GroupBy::Patient),
+            _ => Err(format!(
+                "Invalid grouping: '{}'. Must be one of: day, week, priority, status, patient",
+                value
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            GroupBy::Day => "day",
+            GroupBy::Week => "week",
+            GroupBy::Priority => "priority",
+            GroupBy::Status => "status",
+            GroupBy::Patient => "patient",
+        }
+    }
+
+    fn key(&self, appointment: &Appointment) -> String {
+        let date = appointment.time_slot.start_time.date_naive();
+        match self {
+            GroupBy::Day => date.to_string(),
+            GroupBy::Week => {
+                (date - Duration::days(date.weekday().num_days_from_monday() as i64)).to_string()
+            }
+            GroupBy::Priority => appointment.priority.name().to_string(),
+            GroupBy::Status => appointment.status.name().to_string(),
+            GroupBy::Patient => appointment.patient.patient_id.clone(),
+        }
+    }
+}
+
+/// Filters and grouping applied to a calendar's appointments.
+#[derive(Debug, Clone, Default)]
+pub struct AppointmentQuery {
+    pub priority: Option<Priority>,
+    pub status: Option<AppointmentStatus>,
+    pub patient_id: Option<String>,
+    /// First day included.
+    pub from: Option<NaiveDate>,
+    /// Last day included.
+    pub until: Option<NaiveDate>,
+    pub group_by: Option<GroupBy>,
+}
+
+impl AppointmentQuery {
+    /// Create a query matching every appointment.
+    pub fn new() -> Self {
+        AppointmentQuery::default()
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    pub fn status(mut self, status: AppointmentStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn patient(mut self, patient_id: &str) -> Self {
+        self.patient_id = Some(patient_id.to_string());
+        self
+    }
+
+    /// Only match appointments from `from` to `until`, both included.
+    pub fn between(mut self, from: NaiveDate, until: NaiveDate) -> Self {
+        self.from = Some(from);
+        self.until = Some(until);
+        self
+    }
+
+    pub fn group_by(mut self, group_by: GroupBy) -> Self {
+        self.group_by = Some(group_by);
+        self
+    }
+
+    /// Check whether an appointment passes the query's filters.
+    pub fn matches(&self, appointment: &Appointment) -> bool {
+        let date = appointment.time_slot.start_time.date_naive();
+        self.priority
+            .is_none_or(|priority| appointment.priority == priority)
+            && self
+                .status
+                .is_none_or(|status| appointment.status == status)
+            && self
+                .patient_id
+                .as_ref()
+                .is_none_or(|patient_id| appointment.patient.patient_id == *patient_id)
+            && self.from.is_none_or(|from| date >= from)
+            && self.until.is_none_or(|until| date <= until)
+    }
+
+    /// Run the query, listing matching appointments in time order or
+    /// counting them per group.
+    pub fn run(&self, calendar: &DoctorCalendar) -> QueryResult {
+        let appointments: Vec<Appointment> = calendar
+            .appointments()
+            .into_iter()
+            .filter(|apt| self.matches(apt))
+            .collect();
+
+        if let Some(group_by) = self.group_by {
+            let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+            for apt in &appointments {
+                *counts.entry(group_by.key(apt)).or_default() += 1;
+            }
+            return QueryResult {
+                columns: vec![group_by.name().to_string(), "appointments".to_string()],
+                rows: counts
+                    .into_iter()
+                    .map(|(key, count)| vec![key, count.to_string()])
+                    .collect(),
+            };
+        }
+
+        QueryResult {
+            columns: [
+                "date",
+                "time",
+                "patient_id",
+                "patient_name",
+                "priority",
+                "status",
+                "reason",
+            ]
+            .iter()
+            .map(|column| column.to_string())
+            .collect(),
+            rows: appointments
+                .iter()
+                .map(|apt| {
+                    vec![
+                        apt.time_slot.start_time.format("%Y-%m-%d").to_string(),
+                        apt.time_slot.start_time.format("%H:%M").to_string(),
+                        apt.patient.patient_id.clone(),
+                        apt.patient.name.clone(),
+                        apt.priority.name().to_string(),
+                        apt.status.name().to_string(),
+                        apt.reason.clone(),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Rows produced by a query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl QueryResult {
+    /// Render the rows as an aligned text table.
+    pub fn to_table(&self) -> String {
+        let widths: Vec<usize> = (0..self.columns.len())
+            .map(|i| {
+                self.rows
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .chain([self.columns[i].chars().count()])
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        let format_row = |row: &[String]| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{:<width$}", cell, width = *width))
+                .collect();
+            format!("{}\n", cells.join("  ").trim_end())
+        };
+
+        let mut output = format_row(&self.columns);
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(*width)).collect();
+        output.push_str(&format_row(&rule));
+        for row in &self.rows {
+            output.push_str(&format_row(row));
+        }
+        output
+    }
+
+    /// Render the rows as CSV with a header row.
+    pub fn to_csv(&self) -> String {
+        let mut output = String::new();
+        for row in std::iter::once(&self.columns).chain(&self.rows) {
+            let escaped: Vec<String> = row.iter().map(|field| escape_csv(field)).collect();
+            output.push_str(&escaped.join(","));
+            output.push('\n');
+        }
+        output
+    }
+}
+
+/// Parse a query expression such as
+/// `appointments where priority=urgent and date in next_7_days group by day`.
+///
+/// Relative dates are taken from `today`.
+pub fn parse_query(text: &str, today: NaiveDate) -> Result<AppointmentQuery, String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let Some((first, mut rest)) = words.split_first() else {
+        return Err("Query cannot be empty".to_string());
+    };
+    if !first.eq_ignore_ascii_case("appointments") {
+        return Err(format!(
+            "Unknown query subject: '{}'. Try: appointments",
+            first
+        ));
+    }
+
+    let mut query = AppointmentQuery::new();
+    if let Some((word, tail)) = rest.split_first() {
+        if word.eq_ignore_ascii_case("where") {
+            let end = tail
+                .iter()
+                .position(|word| word.eq_ignore_ascii_case("group"))
+                .unwrap_or(tail.len());
+            for condition in tail[..end].split(|word| word.eq_ignore_ascii_case("and")) {
+                apply_condition(&mut query, &condition.join(" "), today)?;
+            }
+            rest = &tail[end..];
+        }
+    }
+
+    match rest {
+        [] => {}
+        [group, by, key]
+            if group.eq_ignore_ascii_case("group") && by.eq_ignore_ascii_case("by") =>
+        {
+            query.group_by = Some(GroupBy::from_string(key)?);
+        }
+        _ => return Err(format!("Unexpected '{}'", rest.join(" "))),
+    }
+    Ok(query)
+}
+
+fn apply_condition(
+    query: &mut AppointmentQuery,
+    condition: &str,
+    today: NaiveDate,
+) -> Result<(), String> {
+    if let Some(range) = strip_prefix_ignore_case(condition, "date in ") {
+        let (from, until) = relative_range(range.trim(), today)?;
+        query.from = Some(max_date(query.from, from));
+        query.until = Some(min_date(query.until, until));
+        return Ok(());
+    }
+
+    let (field, operator, value) = [">=", "<=", "="]
+        .iter()
+        .find_map(|operator| {
+            condition
+                .split_once(operator)
+                .map(|(field, value)| (field.trim(), *operator, value.trim()))
+        })
+        .ok_or_else(|| format!("Invalid condition: '{}'", condition))?;
+
+    match (field.to_lowercase().as_str(), operator) {
+        ("priority", "=") => query.priority = Some(Priority::from_string(value)?),
+        ("status", "=") => query.status = Some(AppointmentStatus::from_string(value)?),
+        ("patient", "=") => query.patient_id = Some(value.to_string()),
+        ("date", _) => {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map_err(|_| format!("Invalid date: {}", value))?;
+            if operator != "<=" {
+                query.from = Some(max_date(query.from, date));
+            }
+            if operator != ">=" {
+                query.until = Some(min_date(query.until, date));
+            }
+        }
+        _ => return Err(format!("Invalid condition: '{}'", condition)),
+    }
+    Ok(())
+}
+
+/// Resolve a named range of days, both ends included.
+fn relative_range(name: &str, today: NaiveDate) -> Result<(NaiveDate, NaiveDate), String> {
+    let name = name.to_lowercase();
+    let days = |prefix: &str| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix("_days"))
+            .and_then(|count| count.parse::<i64>().ok())
+            .filter(|count| *count > 0)
+    };
+
+    if let Some(count) = days("next_") {
+        return Ok((today, today + Duration::days(count - 1)));
+    }
+    if let Some(count) = days("last_") {
+        return Ok((today - Duration::days(count - 1), today));
+    }
+    match name.as_str() {
+        "today" => Ok((today, today)),
+        "tomorrow" => Ok((today + Duration::days(1), today + Duration::days(1))),
+        "yesterday" => Ok((today - Duration::days(1), today - Duration::days(1))),
+        "this_week" => {
+            let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+            Ok((monday, monday + Duration::days(6)))
+        }
+        _ => Err(format!(
+            "Invalid date range: '{}'. Try today, tomorrow, yesterday, this_week, next_7_days or last_30_days",
+            name
+        )),
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> Option<&'a str> {
+    text.get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &text[prefix.len()..])
+}
+
+fn max_date(current: Option<NaiveDate>, date: NaiveDate) -> NaiveDate {
+    current.map_or(date, |current| current.max(date))
+}
+
+fn min_date(current: Option<NaiveDate>, date: NaiveDate) -> NaiveDate {
+    current.map_or(date, |current| current.min(date))
+}
@@ -0,0 +1,158 @@
+#![allow(dead_code)]
+//! Pending-request queues for the scheduler.
+//!
+//! The scheduler talks to its queue through the RequestQueue trait so the
+//! queue can live in process memory (the default) or in a shared store
+//! such as Redis, letting several scheduler instances drain one queue. A
+//! file-backed queue keeps requests between runs of the command-line tool.
+
+use crate::models::{AppointmentRequest, Priority};
+use std::collections::BinaryHeap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Priority-ordered storage for requests awaiting scheduling.
+///
+/// Implementations must pop requests highest priority first and, within a
+/// priority, oldest first (the ordering of `AppointmentRequest`).
+pub trait RequestQueue {
+    /// Add a request to the queue.
+    fn push(&mut self, request: AppointmentRequest) -> Result<(), String>;
+
+    /// Remove and return the next request to schedule.
+    fn pop(&mut self) -> Result<Option<AppointmentRequest>, String>;
+
+    /// Number of requests waiting.
+    fn len(&self) -> Result<usize, String>;
+
+    /// Check whether the queue is empty.
+    fn is_empty(&self) -> Result<bool, String> {
+        Ok(self.len()? == 0)
+    }
+
+    /// Remove all requests, returning how many were dropped.
+    fn clear(&mut self) -> Result<usize, String>;
+
+    /// List waiting requests in pop order without removing them.
+    fn pending(&self) -> Result<Vec<AppointmentRequest>, String>;
+}
+
+/// In-process queue backed by a binary heap.
+#[derive(Debug, Default)]
+pub struct InMemoryQueue {
+    heap: BinaryHeap<AppointmentRequest>,
+}
+
+impl InMemoryQueue {
+    /// Create an empty queue.
+    pub fn new() -> Self {
+        InMemoryQueue::default()
+    }
+}
+
+impl RequestQueue for InMemoryQueue {
+    fn push(&mut self, request: AppointmentRequest) -> Result<(), String> {
+        self.heap.push(request);
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Option<AppointmentRequest>, String> {
+        Ok(self.heap.pop())
+    }
+
+    fn len(&self) -> Result<usize, String> {
+        Ok(self.heap.len())
+    }
+
+    fn clear(&mut self) -> Result<usize, String> {
+        let count = self.heap.len();
+        self.heap.clear();
+        Ok(count)
+    }
+
+    fn pending(&self) -> Result<Vec<AppointmentRequest>, String> {
+        let mut requests = self.heap.clone().into_sorted_vec();
+        requests.reverse();
+        Ok(requests)
+    }
+}
+
+/// Queue kept in a JSON file, rewritten after every change.
+///
+/// Meant for one process at a time, such as successive runs of the
+/// command-line tool; use a shared store for concurrent schedulers.
+#[derive(Debug)]
+pub struct FileQueue {
+    path: PathBuf,
+    queue: InMemoryQueue,
+}
+
+impl FileQueue {
+    /// Open the queue stored at `path`; a missing file is an empty queue.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut queue = InMemoryQueue::new();
+        if path.exists() {
+            let text = fs::read_to_string(&path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let requests: Vec<AppointmentRequest> = serde_json::from_str(&text)
+                .map_err(|e| format!("Failed to decode {}: {}", path.display(), e))?;
+            for request in requests {
+                queue.push(request)?;
+            }
+        }
+        Ok(FileQueue { path, queue })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let text = serde_json::to_string_pretty(&self.queue.pending()?)
+            .map_err(|e| format!("Failed to encode queue: {}", e))?;
+        let temp = self.path.with_extension("tmp");
+        fs::write(&temp, text)
+            .map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+        fs::rename(&temp, &self.path)
+            .map_err(|e| format!("Failed to replace {}: {}", self.path.display(), e))
+    }
+}
+
+impl RequestQueue for FileQueue {
+    fn push(&mut self, request: AppointmentRequest) -> Result<(), String> {
+        self.queue.push(request)?;
+        self.save()
+    }
+
+    fn pop(&mut self) -> Result<Option<AppointmentRequest>, String> {
+        let request = self.queue.pop()?;
+        if request.is_some() {
+            self.save()?;
+        }
+        Ok(request)
+    }
+
+    fn len(&self) -> Result<usize, String> {
+        self.queue.len()
+    }
+
+    fn clear(&mut self) -> Result<usize, String> {
+        let count = self.queue.clear()?;
+        self.save()?;
+        Ok(count)
+    }
+
+    fn pending(&self) -> Result<Vec<AppointmentRequest>, String> {
+        self.queue.pending()
+    }
+}
+
+/// Sort score for shared queues: lower scores are popped first.
+///
+/// Priority forms the high-order band and the creation timestamp (in
+/// milliseconds) orders requests within a band. Both fit exactly in an f64.
+pub fn queue_score(request: &AppointmentRequest) -> f64 {
+    let band = match request.priority {
+        Priority::Emergency => 0.0,
+        Priority::Urgent => 1.0,
+        Priority::Routine => 2.0,
+    };
+    band * 1e13 + request.created_at.timestamp_millis() as f64
+}
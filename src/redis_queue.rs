@@ -0,0 +1,198 @@
+#![allow(dead_code)]
+//! Redis-backed request queue (enabled with the `redis` feature).
+//!
+//! Pending requests are stored as JSON members of a sorted set scored by
+//! `queue::queue_score`, so ZPOPMIN always yields the highest-priority,
+//! oldest request. Several scheduler processes can share one key, and
+//! requests survive a scheduler crash. Commands are sent using the RESP
+//! protocol over a plain TCP connection.
+
+use crate::models::AppointmentRequest;
+use crate::queue::{queue_score, RequestQueue};
+use std::cell::RefCell;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// A decoded RESP reply.
+#[derive(Debug, Clone, PartialEq)]
+enum Reply {
+    Status(String),
+    Integer(i64),
+    Bulk(Option<Vec<u8>>),
+    Array(Vec<Reply>),
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+}
+
+impl Connection {
+    fn command(&mut self, args: &[&[u8]]) -> Result<Reply, String> {
+        let mut buffer = format!("*{}\r\n", args.len()).into_bytes();
+        for arg in args {
+            buffer.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+            buffer.extend_from_slice(arg);
+            buffer.extend_from_slice(b"\r\n");
+        }
+        self.writer
+            .write_all(&buffer)
+            .map_err(|e| format!("Failed to write to Redis: {}", e))?;
+        self.read_reply()
+    }
+
+    fn read_line(&mut self) -> Result<String, String> {
+        let mut line = String::new();
+        self.reader
+            .read_line(&mut line)
+            .map_err(|e| format!("Failed to read from Redis: {}", e))?;
+        if line.is_empty() {
+            return Err("Redis connection closed".to_string());
+        }
+        Ok(line.trim_end_matches("\r\n").to_string())
+    }
+
+    fn read_reply(&mut self) -> Result<Reply, String> {
+        let line = self.read_line()?;
+        let (kind, rest) = line.split_at(1.min(line.len()));
+        let parse_len = |value: &str| {
+            value
+                .parse::<i64>()
+                .map_err(|_| format!("Invalid Redis reply: {}", line))
+        };
+
+        match kind {
+            "+" => Ok(Reply::Status(rest.to_string())),
+            "-" => Err(format!("Redis error: {}", rest)),
+            ":" => Ok(Reply::Integer(parse_len(rest)?)),
+            "$" => {
+                let len = parse_len(rest)?;
+                if len < 0 {
+                    return Ok(Reply::Bulk(None));
+                }
+                let mut data = vec![0u8; len as usize + 2];
+                self.reader
+                    .read_exact(&mut data)
+                    .map_err(|e| format!("Failed to read from Redis: {}", e))?;
+                data.truncate(len as usize);
+                Ok(Reply::Bulk(Some(data)))
+            }
+            "*" => {
+                let len = parse_len(rest)?;
+                let mut items = Vec::new();
+                for _ in 0..len.max(0) {
+                    items.push(self.read_reply()?);
+                }
+                Ok(Reply::Array(items))
+            }
+            _ => Err(format!("Invalid Redis reply: {}", line)),
+        }
+    }
+}
+
+/// Request queue stored in a Redis sorted set.
+pub struct RedisQueue {
+    pub key: String,
+    connection: RefCell<Connection>,
+}
+
+impl RedisQueue {
+    /// Connect to Redis, e.g. `RedisQueue::connect("127.0.0.1:6379", "easyappoint:queue")`.
+    pub fn connect(address: &str, key: &str) -> Result<Self, String> {
+        if key.is_empty() {
+            return Err("Redis queue key cannot be empty".to_string());
+        }
+
+        let stream = TcpStream::connect(address)
+            .map_err(|e| format!("Failed to connect to Redis at {}: {}", address, e))?;
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .map_err(|e| format!("Failed to configure Redis connection: {}", e))?;
+        let writer = stream
+            .try_clone()
+            .map_err(|e| format!("Failed to configure Redis connection: {}", e))?;
+
+        let queue = RedisQueue {
+            key: key.to_string(),
+            connection: RefCell::new(Connection {
+                reader: BufReader::new(stream),
+                writer,
+            }),
+        };
+
+        match queue.command(&[b"PING"])? {
+            Reply::Status(status) if status == "PONG" => Ok(queue),
+            other => Err(format!("Unexpected reply to PING: {:?}", other)),
+        }
+    }
+
+    fn command(&self, args: &[&[u8]]) -> Result<Reply, String> {
+        self.connection.borrow_mut().command(args)
+    }
+}
+
+impl RequestQueue for RedisQueue {
+    fn push(&mut self, request: AppointmentRequest) -> Result<(), String> {
+        let member = serde_json::to_vec(&request)
+            .map_err(|e| format!("Failed to encode request: {}", e))?;
+        let score = queue_score(&request).to_string();
+        self.command(&[b"ZADD", self.key.as_bytes(), score.as_bytes(), &member])?;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Option<AppointmentRequest>, String> {
+        match self.command(&[b"ZPOPMIN", self.key.as_bytes()])? {
+            Reply::Array(items) => match items.first() {
+                Some(Reply::Bulk(Some(member))) => serde_json::from_slice(member)
+                    .map(Some)
+                    .map_err(|e| format!("Failed to decode queued request: {}", e)),
+                None => Ok(None),
+                Some(other) => Err(format!("Unexpected ZPOPMIN reply: {:?}", other)),
+            },
+            other => Err(format!("Unexpected ZPOPMIN reply: {:?}", other)),
+        }
+    }
+
+    fn len(&self) -> Result<usize, String> {
+        match self.command(&[b"ZCARD", self.key.as_bytes()])? {
+            Reply::Integer(count) => Ok(count.max(0) as usize),
+            other => Err(format!("Unexpected ZCARD reply: {:?}", other)),
+        }
+    }
+
+    /// Count and delete the set in one MULTI/EXEC transaction, so a
+    /// request pushed by another process in between is not deleted
+    /// uncounted.
+    fn clear(&mut self) -> Result<usize, String> {
+        self.command(&[b"MULTI"])?;
+        let queued = self
+            .command(&[b"ZCARD", self.key.as_bytes()])
+            .and_then(|_| self.command(&[b"DEL", self.key.as_bytes()]));
+        if let Err(e) = queued {
+            let _ = self.command(&[b"DISCARD"]);
+            return Err(e);
+        }
+        match self.command(&[b"EXEC"])? {
+            Reply::Array(replies) => match replies.first() {
+                Some(Reply::Integer(count)) => Ok((*count).max(0) as usize),
+                other => Err(format!("Unexpected EXEC reply: {:?}", other)),
+            },
+            other => Err(format!("Unexpected EXEC reply: {:?}", other)),
+        }
+    }
+
+    fn pending(&self) -> Result<Vec<AppointmentRequest>, String> {
+        match self.command(&[b"ZRANGE", self.key.as_bytes(), b"0", b"-1"])? {
+            Reply::Array(items) => items
+                .into_iter()
+                .map(|item| match item {
+                    Reply::Bulk(Some(member)) => serde_json::from_slice(&member)
+                        .map_err(|e| format!("Failed to decode queued request: {}", e)),
+                    other => Err(format!("Unexpected ZRANGE reply: {:?}", other)),
+                })
+                .collect(),
+            other => Err(format!("Unexpected ZRANGE reply: {:?}", other)),
+        }
+    }
+}
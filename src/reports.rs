@@ -0,0 +1,183 @@
+#![allow(dead_code)]
+//! Reporting and export for the appointment scheduling system.
+//!
+//! This module turns calendar state into flat exports for other systems,
+//! such as the billing CSV consumed by the practice's accounting software,
+//! and into summaries for staff such as the dashboard.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{Appointment, Priority, TimeSlot};
+use crate::overrun::{duration_advice, DurationAdvice};
+use crate::scheduler::AppointmentScheduler;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+
+/// At-a-glance summary of the schedule for the dashboard screen.
+#[derive(Debug, Clone)]
+pub struct DashboardSummary {
+    pub today: Vec<Appointment>,
+    pub next_free_slot: Option<TimeSlot>,
+    /// Pending requests per priority, most urgent first.
+    pub pending_by_priority: Vec<(Priority, usize)>,
+    pub queue_error: Option<String>,
+    /// Requests that could not be scheduled and are waiting for capacity.
+    pub waitlist: usize,
+    /// Failed requests from processing runs with no disposition recorded.
+    pub open_failures: usize,
+    pub week_booked_slots: usize,
+    pub week_total_slots: usize,
+    /// Minutes the doctor sits idle between today's first and last booking.
+    pub today_idle_gap_minutes: i64,
+    /// Appointment types whose slots don't fit how long visits take.
+    pub duration_advice: Vec<DurationAdvice>,
+}
+
+impl DashboardSummary {
+    /// Share of this week's slots that are booked, as a percentage.
+    pub fn week_utilization(&self) -> f64 {
+        if self.week_total_slots == 0 {
+            return 0.0;
+        }
+        (self.week_booked_slots as f64 / self.week_total_slots as f64) * 100.0
+    }
+}
+
+/// Build the dashboard summary using the calendar's clock.
+///
+/// The week runs Monday to Sunday around the current day.
+pub fn dashboard(scheduler: &AppointmentScheduler) -> DashboardSummary {
+    let calendar = &scheduler.calendar;
+    let now = calendar.now();
+    let today = now.date_naive();
+    let week_start = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let week_end = week_start + Duration::days(7);
+
+    let mut pending_by_priority = vec![
+        (Priority::Emergency, 0),
+        (Priority::Urgent, 0),
+        (Priority::Routine, 0),
+    ];
+    let queue_error = match scheduler.pending_requests() {
+        Ok(pending) => {
+            for request in pending {
+                if let Some(entry) = pending_by_priority
+                    .iter_mut()
+                    .find(|(priority, _)| *priority == request.priority)
+                {
+                    entry.1 += 1;
+                }
+            }
+            None
+        }
+        Err(e) => Some(e),
+    };
+
+    let week_slots: Vec<TimeSlot> = calendar
+        .time_slots()
+        .into_iter()
+        .filter(|slot| {
+            let date = slot.start_time.date_naive();
+            date >= week_start && date < week_end
+        })
+        .collect();
+
+    DashboardSummary {
+        today: calendar.get_appointments_on_date(now),
+        next_free_slot: calendar.find_next_available_slot(now),
+        pending_by_priority,
+        queue_error,
+        waitlist: scheduler.unscheduled_requests().len(),
+        open_failures: scheduler.open_failures().len(),
+        week_booked_slots: week_slots.iter().filter(|slot| !slot.is_available).count(),
+        week_total_slots: week_slots.len(),
+        today_idle_gap_minutes: idle_gap_minutes(calendar, Some(today)),
+        duration_advice: duration_advice(scheduler),
+    }
+}
+
+/// Total idle minutes between consecutive appointments on the same day.
+///
+/// Time before a day's first and after its last appointment is not idle.
+/// Pass a date to measure a single day.
+pub fn idle_gap_minutes(calendar: &DoctorCalendar, date: Option<NaiveDate>) -> i64 {
+    let appointments: Vec<Appointment> = calendar
+        .appointments()
+        .into_iter()
+        .filter(|apt| date.is_none_or(|date| apt.time_slot.start_time.date_naive() == date))
+        .collect();
+
+    appointments
+        .windows(2)
+        .filter(|pair| {
+            pair[0].time_slot.start_time.date_naive() == pair[1].time_slot.start_time.date_naive()
+        })
+        .map(|pair| (pair[1].time_slot.start_time - pair[0].time_slot.end_time).num_minutes())
+        .filter(|gap| *gap > 0)
+        .sum()
+}
+
+/// Export billable items as CSV.
+///
+/// Every confirmed appointment is billed at its slot tier's fee, and late
+/// patient cancellations that carry a fee are billed as separate lines.
+/// Pass a date to export a single day.
+pub fn billing_export(calendar: &DoctorCalendar, date: Option<NaiveDate>) -> String {
+    let mut output = String::from(
+        "item,appointment_id,patient_id,patient_name,date,time,tier,fee_cents\n",
+    );
+    let on_date = |start: DateTime<Local>| date.is_none_or(|date| start.date_naive() == date);
+
+    for apt in calendar
+        .appointments()
+        .into_iter()
+        .filter(|apt| on_date(apt.time_slot.start_time))
+    {
+        let fields = [
+            "appointment".to_string(),
+            apt.appointment_id.clone(),
+            apt.patient.patient_id.clone(),
+            apt.patient.name.clone(),
+            apt.time_slot.start_time.format("%Y-%m-%d").to_string(),
+            apt.time_slot.start_time.format("%H:%M").to_string(),
+            apt.time_slot.tier.name().to_string(),
+            calendar.fee_schedule.fee_for(apt.time_slot.tier).to_string(),
+        ];
+        push_csv_row(&mut output, &fields);
+    }
+
+    for record in calendar
+        .cancellations()
+        .iter()
+        .filter(|record| on_date(record.scheduled_start))
+    {
+        if let Some(fee) = record.fee_cents {
+            let fields = [
+                "late_cancellation".to_string(),
+                record.appointment_id.clone(),
+                record.patient_id.clone(),
+                String::new(),
+                record.scheduled_start.format("%Y-%m-%d").to_string(),
+                record.scheduled_start.format("%H:%M").to_string(),
+                String::new(),
+                fee.to_string(),
+            ];
+            push_csv_row(&mut output, &fields);
+        }
+    }
+
+    output
+}
+
+fn push_csv_row(output: &mut String, fields: &[String]) {
+    let escaped: Vec<String> = fields.iter().map(|f| escape_csv(f)).collect();
+    output.push_str(&escaped.join(","));
+    output.push('\n');
+}
+
+/// Quote a CSV field if it contains a delimiter, quote or line break.
+pub fn escape_csv(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,391 @@
+#![allow(dead_code)]
+//! Clinic business rules expressed in a small declarative format.
+//!
+//! Rules are written one per line so clinics can change policy without
+//! recompiling:
+//!
+//! ```text
+//! # comments and blank lines are ignored
+//! no "new patient" on friday
+//! max 3 "procedure" per day
+//! fallback emergency
+//! hold 2 slots until 11:00
+//! max 2 emergency per morning
+//! at least 4 routine per day
+//! ```
+//!
+//! Quoted text is matched case-insensitively against the request reason.
+//! Session quotas count appointments by priority in the morning (before
+//! noon), the afternoon or the whole day. A minimum keeps that many slots
+//! free for its priority until they are booked.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{AppointmentRequest, Priority, TimeSlot};
+use chrono::{Datelike, NaiveTime, Timelike, Weekday};
+use std::fmt;
+use std::fs;
+
+/// Part of a day a session quota counts over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Session {
+    Morning,
+    Afternoon,
+    Day,
+}
+
+impl Session {
+    pub fn name(&self) -> &str {
+        match self {
+            Session::Morning => "morning",
+            Session::Afternoon => "afternoon",
+            Session::Day => "day",
+        }
+    }
+
+    /// Parse a session name: `morning`, `afternoon` or `day`.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "morning" => Ok(Session::Morning),
+            "afternoon" => Ok(Session::Afternoon),
+            "day" => Ok(Session::Day),
+            _ => Err(format!(
+                "Invalid session: {}. Must be morning, afternoon or day",
+                value
+            )),
+        }
+    }
+
+    /// Check whether a slot falls in this session; mornings end at noon.
+    pub fn contains(&self, slot: &TimeSlot) -> bool {
+        match self {
+            Session::Morning => slot.start_time.hour() < 12,
+            Session::Afternoon => slot.start_time.hour() >= 12,
+            Session::Day => true,
+        }
+    }
+}
+
+/// A single business rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// Requests whose reason contains `reason` cannot be booked on these days.
+    NoVisitsOn { reason: String, days: Vec<Weekday> },
+    /// At most `max` appointments per day whose reason contains `reason`.
+    MaxPerDay { reason: String, max: usize },
+    /// Requests of this priority always fall back to the next free slot.
+    AlwaysFallback { priority: Priority },
+    /// Keep `slots` free slots per day away from routine requests until
+    /// `release_at` on that day, so emergencies always have somewhere to land.
+    EmergencyHold { slots: usize, release_at: NaiveTime },
+    /// At most `max` appointments of this priority per session.
+    MaxPerSession {
+        priority: Priority,
+        max: usize,
+        session: Session,
+    },
+    /// Keep enough free slots per session for `min` appointments of this
+    /// priority; other priorities can't book into them.
+    MinPerSession {
+        priority: Priority,
+        min: usize,
+        session: Session,
+    },
+}
+
+impl Rule {
+    /// Check whether the rule allows booking `slot` for `request`.
+    pub fn permits(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slot: &TimeSlot,
+    ) -> bool {
+        match self {
+            Rule::NoVisitsOn { reason, days } => {
+                !(reason_matches(&request.reason, reason)
+                    && days.contains(&slot.start_time.weekday()))
+            }
+            Rule::MaxPerDay { reason, max } => {
+                if !reason_matches(&request.reason, reason) {
+                    return true;
+                }
+                let booked = calendar
+                    .get_appointments_on_date(slot.start_time)
+                    .iter()
+                    .filter(|apt| reason_matches(&apt.reason, reason))
+                    .count();
+                booked < *max
+            }
+            Rule::AlwaysFallback { .. } => true,
+            Rule::EmergencyHold { slots, release_at } => {
+                if request.priority != Priority::Routine
+                    || calendar.now().naive_local()
+                        >= slot.start_time.date_naive().and_time(*release_at)
+                {
+                    return true;
+                }
+                calendar.find_available_slots_on_date(slot.start_time).len() > *slots
+            }
+            Rule::MaxPerSession {
+                priority,
+                max,
+                session,
+            } => {
+                request.priority != *priority
+                    || !session.contains(slot)
+                    || booked_in_session(calendar, slot, *session, *priority) < *max
+            }
+            Rule::MinPerSession {
+                priority,
+                min,
+                session,
+            } => {
+                if request.priority == *priority || !session.contains(slot) {
+                    return true;
+                }
+                let reserved =
+                    min.saturating_sub(booked_in_session(calendar, slot, *session, *priority));
+                let free = calendar
+                    .find_available_slots_on_date(slot.start_time)
+                    .iter()
+                    .filter(|free| session.contains(free))
+                    .count();
+                free > reserved
+            }
+        }
+    }
+
+    /// Check whether the rule is a per-session priority quota.
+    pub fn is_quota(&self) -> bool {
+        matches!(
+            self,
+            Rule::MaxPerSession { .. } | Rule::MinPerSession { .. }
+        )
+    }
+
+    /// Parse one rule line.
+    fn parse(line: &str) -> Result<Self, String> {
+        let (head, reason, tail) = split_quoted(line)?;
+        let head: Vec<&str> = head.split_whitespace().collect();
+        let tail: Vec<&str> = tail.split_whitespace().collect();
+
+        match (head.as_slice(), reason) {
+            (["no"], Some(reason)) => match tail.split_first() {
+                Some((&"on", days)) if !days.is_empty() => {
+                    let days = days
+                        .join(" ")
+                        .split(',')
+                        .map(|day| {
+                            day.trim()
+                                .parse::<Weekday>()
+                                .map_err(|_| format!("Invalid weekday: {}", day.trim()))
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(Rule::NoVisitsOn { reason, days })
+                }
+                _ => Err("Expected: no \"<reason>\" on <weekday>[, <weekday>...]".to_string()),
+            },
+            (["max", count], Some(reason)) => {
+                if tail != ["per", "day"] {
+                    return Err("Expected: max <count> \"<reason>\" per day".to_string());
+                }
+                let max = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?;
+                Ok(Rule::MaxPerDay { reason, max })
+            }
+            (["fallback", priority], None) if tail.is_empty() => {
+                let priority = Priority::from_string(priority)?;
+                Ok(Rule::AlwaysFallback { priority })
+            }
+            (["hold", count, "slots", "until", time], None) if tail.is_empty() => {
+                let slots = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?;
+                let release_at = NaiveTime::parse_from_str(time, "%H:%M")
+                    .map_err(|_| format!("Invalid time: {}", time))?;
+                Ok(Rule::EmergencyHold { slots, release_at })
+            }
+            (["max", count, priority, "per", session], None) if tail.is_empty() => {
+                let max = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?;
+                Ok(Rule::MaxPerSession {
+                    priority: Priority::from_string(priority)?,
+                    max,
+                    session: Session::from_string(session)?,
+                })
+            }
+            (["at", "least", count, priority, "per", session], None) if tail.is_empty() => {
+                let min = count
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid count: {}", count))?;
+                Ok(Rule::MinPerSession {
+                    priority: Priority::from_string(priority)?,
+                    min,
+                    session: Session::from_string(session)?,
+                })
+            }
+            _ => Err(format!("Unrecognised rule: {}", line)),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    /// Format the rule in the line syntax accepted by `RuleSet::parse`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rule::NoVisitsOn { reason, days } => {
+                let days: Vec<String> = days.iter().map(|day| day.to_string()).collect();
+                write!(f, "no \"{}\" on {}", reason, days.join(", "))
+            }
+            Rule::MaxPerDay { reason, max } => write!(f, "max {} \"{}\" per day", max, reason),
+            Rule::AlwaysFallback { priority } => {
+                write!(f, "fallback {}", priority.name().to_lowercase())
+            }
+            Rule::EmergencyHold { slots, release_at } => {
+                write!(f, "hold {} slots until {}", slots, release_at.format("%H:%M"))
+            }
+            Rule::MaxPerSession {
+                priority,
+                max,
+                session,
+            } => write!(
+                f,
+                "max {} {} per {}",
+                max,
+                priority.name().to_lowercase(),
+                session.name()
+            ),
+            Rule::MinPerSession {
+                priority,
+                min,
+                session,
+            } => write!(
+                f,
+                "at least {} {} per {}",
+                min,
+                priority.name().to_lowercase(),
+                session.name()
+            ),
+        }
+    }
+}
+
+/// An ordered collection of business rules.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RuleSet {
+    rules: Vec<Rule>,
+}
+
+impl RuleSet {
+    /// Create an empty rule set.
+    pub fn new() -> Self {
+        RuleSet::default()
+    }
+
+    /// Parse rules from text, reporting the first bad line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut rules = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = Rule::parse(line).map_err(|e| format!("Line {}: {}", index + 1, e))?;
+            rules.push(rule);
+        }
+
+        Ok(RuleSet { rules })
+    }
+
+    /// Load rules from a file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text =
+            fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        RuleSet::parse(&text)
+    }
+
+    /// Add a rule.
+    pub fn push(&mut self, rule: Rule) {
+        self.rules.push(rule);
+    }
+
+    /// Get the rules in order.
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Check whether every rule allows booking `slot` for `request`.
+    pub fn permits(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slot: &TimeSlot,
+    ) -> bool {
+        self.rules
+            .iter()
+            .all(|rule| rule.permits(calendar, request, slot))
+    }
+
+    /// Get the session quotas that refuse any of `slots` for `request`.
+    pub fn binding_quotas(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        slots: &[TimeSlot],
+    ) -> Vec<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.is_quota())
+            .filter(|rule| {
+                slots
+                    .iter()
+                    .any(|slot| !rule.permits(calendar, request, slot))
+            })
+            .collect()
+    }
+
+    /// Check whether requests of this priority must always fall back.
+    pub fn forces_fallback(&self, priority: Priority) -> bool {
+        self.rules.contains(&Rule::AlwaysFallback { priority })
+    }
+}
+
+/// Count a day's appointments of one priority in a slot's session.
+fn booked_in_session(
+    calendar: &DoctorCalendar,
+    slot: &TimeSlot,
+    session: Session,
+    priority: Priority,
+) -> usize {
+    calendar
+        .get_appointments_on_date(slot.start_time)
+        .iter()
+        .filter(|apt| apt.priority == priority && session.contains(&apt.time_slot))
+        .count()
+}
+
+/// Case-insensitive substring match on a reason.
+pub fn reason_matches(reason: &str, pattern: &str) -> bool {
+    reason.to_lowercase().contains(&pattern.to_lowercase())
+}
+
+/// Split a line around its quoted text, if any.
+pub fn split_quoted(line: &str) -> Result<(&str, Option<String>, &str), String> {
+    match line.find('"') {
+        None => Ok((line, None, "")),
+        Some(open) => {
+            let rest = &line[open + 1..];
+            let close = rest
+                .find('"')
+                .ok_or_else(|| "Unterminated quote".to_string())?;
+            let quoted = rest[..close].trim();
+            if quoted.is_empty() {
+                return Err("Quoted reason cannot be empty".to_string());
+            }
+            Ok((&line[..open], Some(quoted.to_string()), &rest[close + 1..]))
+        }
+    }
+}
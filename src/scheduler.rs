@@ -1,15 +1,34 @@
 #![allow(dead_code)]
-/// Appointment scheduling algorithm with priority-based scheduling.
-///
-/// This module provides the AppointmentScheduler struct which processes
-/// appointment requests and schedules them efficiently based on priority
-/// and time preferences.
+//! Appointment scheduling algorithm with priority-based scheduling.
+//!
+//! This module provides the AppointmentScheduler struct which processes
+//! appointment requests and schedules them efficiently based on priority
+//! and time preferences.
 
-use crate::calendar::DoctorCalendar;
-use crate::models::{Appointment, AppointmentRequest, Patient, Priority, TimeSlot};
-use chrono::{DateTime, Local};
-use std::collections::BinaryHeap;
-use uuid::Uuid;
+use crate::bringforward::BringForwardOffer;
+use crate::calendar::{DoctorCalendar, SlotTemplate};
+use crate::claims::{ClaimResolution, SlotClaim};
+use crate::clinic::ClinicSlot;
+use crate::fill::{fits, FillPlan, FillProposal, FillReport};
+use crate::ids::IdKind;
+use crate::lateness::{Arrival, ArrivalReport, LateArrivalAction, LateArrivalPolicy};
+use crate::notifications::NotificationChannel;
+use crate::overrun::suggested_duration;
+use crate::models::{
+    Appointment, AppointmentRequest, AppointmentStatus, CancellationActor, CancellationRecord,
+    Patient, Priority, RequestSource, RequestTemplate, TimeSlot,
+};
+use crate::queue::{InMemoryQueue, RequestQueue};
+use crate::rules::{Rule, RuleSet};
+use crate::strategy::{NearestPreferred, SlotStrategy};
+use crate::timeoff::{MovedAppointment, TimeOffReport, TimeOffRequest, TimeOffStatus};
+use crate::validation::{RequestValidator, ValidationContext};
+use crate::waitlist::{Waitlist, WaitlistImport};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Timelike};
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Result of a scheduling attempt for a single request.
 #[derive(Debug, Clone)]
@@ -18,6 +37,82 @@ pub struct SchedulingResult {
     pub appointment: Option<Appointment>,
     pub success: bool,
     pub message: String,
+    /// Nearest free slots outside the flexibility window, offered when scheduling failed.
+    pub alternatives: Vec<TimeSlot>,
+    /// Whether the failed request was kept on the waitlist for later runs.
+    pub waitlisted: bool,
+    /// Slot-grid time the preferred time was rounded to before booking, if any.
+    pub rounded_to: Option<DateTime<Local>>,
+    /// What could get the request booked, worked out when no slot was found.
+    pub remediation: Option<Remediation>,
+}
+
+impl SchedulingResult {
+    /// Create a failed result with no alternatives.
+    pub fn failure(request: AppointmentRequest, message: impl Into<String>) -> Self {
+        SchedulingResult {
+            request,
+            appointment: None,
+            success: false,
+            message: message.into(),
+            alternatives: Vec::new(),
+            waitlisted: false,
+            rounded_to: None,
+            remediation: None,
+        }
+    }
+
+    /// Create a successful result.
+    fn booked(request: AppointmentRequest, appointment: Appointment, message: String) -> Self {
+        SchedulingResult {
+            request,
+            appointment: Some(appointment),
+            success: true,
+            message,
+            alternatives: Vec::new(),
+            waitlisted: false,
+            rounded_to: None,
+            remediation: None,
+        }
+    }
+}
+
+/// Ways a request that found no slot could still be booked.
+#[derive(Debug, Clone, Default)]
+pub struct Remediation {
+    /// Date of the nearest free slot the request could take outside its window.
+    pub nearest_date: Option<NaiveDate>,
+    /// Fewest extra minutes of flexibility that would bring a free slot into the window.
+    pub widen_flexibility_by: Option<i64>,
+    /// Other doctors' slots the request would get, filled in by the clinic.
+    pub other_doctors: Vec<ClinicSlot>,
+}
+
+impl Remediation {
+    /// Describe each hint in a sentence for the front desk.
+    pub fn hints(&self) -> Vec<String> {
+        let mut hints = Vec::new();
+        if let Some(date) = self.nearest_date {
+            hints.push(format!(
+                "Nearest availability is on {}",
+                date.format("%A, %Y-%m-%d")
+            ));
+        }
+        if let Some(minutes) = self.widen_flexibility_by {
+            hints.push(format!(
+                "Widening flexibility by {} minutes would fit a free slot",
+                minutes
+            ));
+        }
+        for other in &self.other_doctors {
+            hints.push(format!(
+                "{} has a slot at {}",
+                other.doctor_name,
+                other.slot.start_time.format("%Y-%m-%d %H:%M")
+            ));
+        }
+        hints
+    }
 }
 
 /// Result of scheduling multiple requests.
@@ -26,6 +121,9 @@ pub struct BatchSchedulingResult {
     pub confirmed: Vec<Appointment>,
     pub failed: Vec<SchedulingResult>,
     pub total_requests: usize,
+    pub queue_error: Option<String>,
+    /// ID of the run recorded in the scheduler's run history.
+    pub run_id: String,
 }
 
 impl BatchSchedulingResult {
@@ -38,6 +136,167 @@ impl BatchSchedulingResult {
     }
 }
 
+/// Operator follow-up recorded against a failed request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disposition {
+    pub note: String,
+    pub recorded_at: DateTime<Local>,
+}
+
+/// A request that could not be scheduled in a run.
+#[derive(Debug, Clone)]
+pub struct FailedRequest {
+    pub request: AppointmentRequest,
+    pub message: String,
+    pub disposition: Option<Disposition>,
+}
+
+/// One processing run kept in the scheduler's history.
+#[derive(Debug, Clone)]
+pub struct SchedulingRun {
+    pub run_id: String,
+    pub ran_at: DateTime<Local>,
+    pub total_requests: usize,
+    pub confirmed: usize,
+    pub failed: Vec<FailedRequest>,
+}
+
+/// A cancellation made through the scheduler and the waitlisted requests
+/// booked into the freed slots.
+#[derive(Debug, Clone)]
+pub struct CancellationOutcome {
+    /// One record per cancelled appointment, the requested one first.
+    pub records: Vec<CancellationRecord>,
+    pub promoted: Vec<Appointment>,
+}
+
+/// Scheduling outcomes for requests from one source channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SourceStats {
+    pub booked: usize,
+    pub failed: usize,
+}
+
+impl SourceStats {
+    /// Total scheduling attempts.
+    pub fn attempts(&self) -> usize {
+        self.booked + self.failed
+    }
+
+    /// Share of attempts that failed, as a percentage.
+    pub fn failure_rate(&self) -> f64 {
+        if self.attempts() == 0 {
+            return 0.0;
+        }
+        (self.failed as f64 / self.attempts() as f64) * 100.0
+    }
+}
+
+/// Outcome of a what-if capacity simulation.
+#[derive(Debug)]
+pub struct WhatIfReport {
+    pub slots_added: usize,
+    pub evaluated_requests: usize,
+    pub newly_scheduled: Vec<Appointment>,
+    pub still_failed: Vec<SchedulingResult>,
+}
+
+/// How requests of one priority are handled when added to the scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProcessingMode {
+    /// Try to book the request as soon as it is added.
+    Immediate,
+    /// Queue the request for the next batch run.
+    Batched,
+}
+
+/// How a batch run shares slots between the queued requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BatchMode {
+    /// Book each request in turn into the best slot still free.
+    #[default]
+    Greedy,
+    /// Assign slots across the whole batch, scheduling as many requests as
+    /// possible at times as close to their preferences as possible.
+    Optimal,
+}
+
+impl BatchMode {
+    /// Parse a batch mode name such as `greedy` or `optimal`.
+    pub fn from_string(name: &str) -> Result<Self, String> {
+        match name.trim().to_lowercase().as_str() {
+            "greedy" => Ok(BatchMode::Greedy),
+            "optimal" => Ok(BatchMode::Optimal),
+            _ => Err(format!(
+                "Unknown batch mode: '{}'. Must be one of: greedy, optimal",
+                name
+            )),
+        }
+    }
+
+    /// Get the name used in configuration files.
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchMode::Greedy => "greedy",
+            BatchMode::Optimal => "optimal",
+        }
+    }
+}
+
+/// Per-priority processing modes for incoming requests.
+///
+/// `batch_interval` is how often queued requests are processed by
+/// `process_if_due`; `None` leaves batches to manual runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessingPolicy {
+    pub emergency: ProcessingMode,
+    pub urgent: ProcessingMode,
+    pub routine: ProcessingMode,
+    pub batch_interval: Option<Duration>,
+}
+
+impl ProcessingPolicy {
+    /// Book emergencies immediately and batch everything else every 15 minutes.
+    pub fn auto() -> Self {
+        ProcessingPolicy {
+            emergency: ProcessingMode::Immediate,
+            urgent: ProcessingMode::Batched,
+            routine: ProcessingMode::Batched,
+            batch_interval: Some(Duration::minutes(15)),
+        }
+    }
+
+    /// Get the mode used for a priority.
+    pub fn mode_for(&self, priority: Priority) -> ProcessingMode {
+        match priority {
+            Priority::Emergency => self.emergency,
+            Priority::Urgent => self.urgent,
+            Priority::Routine => self.routine,
+        }
+    }
+
+    /// Set the mode used for a priority.
+    pub fn set_mode(&mut self, priority: Priority, mode: ProcessingMode) {
+        match priority {
+            Priority::Emergency => self.emergency = mode,
+            Priority::Urgent => self.urgent = mode,
+            Priority::Routine => self.routine = mode,
+        }
+    }
+}
+
+impl Default for ProcessingPolicy {
+    /// Queue every request until the queue is processed manually.
+    fn default() -> Self {
+        ProcessingPolicy {
+            emergency: ProcessingMode::Batched,
+            urgent: ProcessingMode::Batched,
+            routine: ProcessingMode::Batched,
+            batch_interval: None,
+        }
+    }
+}
+
 /// Priority-based appointment scheduler.
 ///
 /// This scheduler processes appointment requests using a priority queue,
@@ -47,79 +306,544 @@ impl BatchSchedulingResult {
 pub struct AppointmentScheduler {
     pub calendar: DoctorCalendar,
     pub allow_fallback: bool,
-    request_queue: BinaryHeap<AppointmentRequest>,
+    /// Round off-grid preferred times to neighbouring slot starts when nothing fits.
+    pub snap_to_grid: bool,
+    pub processing_policy: ProcessingPolicy,
+    pub batch_mode: BatchMode,
+    pub rules: RuleSet,
+    /// How patients arriving late are handled; `None` checks everyone in.
+    pub late_arrival_policy: Option<LateArrivalPolicy>,
+    strategy: Arc<dyn SlotStrategy>,
+    validators: Vec<Arc<dyn RequestValidator>>,
+    last_batch_run: Option<DateTime<Local>>,
+    request_queue: Box<dyn RequestQueue>,
+    waitlist: Waitlist,
+    booked_requests: HashMap<String, AppointmentRequest>,
+    source_stats: HashMap<RequestSource, SourceStats>,
+    bumps: HashMap<String, usize>,
+    runs: Vec<SchedulingRun>,
+    referral_quotas: HashMap<String, usize>,
+    time_off: Vec<TimeOffRequest>,
+    request_templates: Vec<RequestTemplate>,
+    bring_forward_offers: Vec<BringForwardOffer>,
+    slot_claims: Vec<SlotClaim>,
 }
 
 impl AppointmentScheduler {
     /// Initialize the scheduler.
     pub fn new(calendar: DoctorCalendar, allow_fallback: bool) -> Self {
+        AppointmentScheduler::with_queue(calendar, allow_fallback, Box::new(InMemoryQueue::new()))
+    }
+
+    /// Initialize the scheduler with a custom pending-request queue.
+    pub fn with_queue(
+        calendar: DoctorCalendar,
+        allow_fallback: bool,
+        request_queue: Box<dyn RequestQueue>,
+    ) -> Self {
         AppointmentScheduler {
             calendar,
             allow_fallback,
-            request_queue: BinaryHeap::new(),
+            snap_to_grid: false,
+            processing_policy: ProcessingPolicy::default(),
+            batch_mode: BatchMode::default(),
+            rules: RuleSet::new(),
+            late_arrival_policy: None,
+            strategy: Arc::new(NearestPreferred),
+            validators: Vec::new(),
+            last_batch_run: None,
+            request_queue,
+            waitlist: Waitlist::new(),
+            booked_requests: HashMap::new(),
+            source_stats: HashMap::new(),
+            bumps: HashMap::new(),
+            runs: Vec::new(),
+            referral_quotas: HashMap::new(),
+            time_off: Vec::new(),
+            request_templates: Vec::new(),
+            bring_forward_offers: Vec::new(),
+            slot_claims: Vec::new(),
+        }
+    }
+
+    /// Choose how the scheduler picks between slots fitting a request.
+    pub fn set_strategy(&mut self, strategy: Arc<dyn SlotStrategy>) {
+        self.strategy = strategy;
+    }
+
+    /// Get the slot selection strategy in use.
+    pub fn strategy(&self) -> &dyn SlotStrategy {
+        self.strategy.as_ref()
+    }
+
+    /// Register a validator run on every added request.
+    pub fn add_validator(&mut self, validator: Box<dyn RequestValidator>) {
+        self.validators.push(Arc::from(validator));
+    }
+
+    /// Add a request template, replacing any template with the same name.
+    pub fn add_request_template(&mut self, template: RequestTemplate) {
+        self.request_templates
+            .retain(|existing| !existing.name.eq_ignore_ascii_case(&template.name));
+        self.request_templates.push(template);
+    }
+
+    /// Get the request templates in the order they were added.
+    pub fn request_templates(&self) -> &[RequestTemplate] {
+        &self.request_templates
+    }
+
+    /// Look up a request template by name, ignoring case.
+    pub fn request_template(&self, name: &str) -> Option<&RequestTemplate> {
+        self.request_templates
+            .iter()
+            .find(|template| template.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// Suggest a slot length for a visit template from how long the visits
+    /// it books have actually taken.
+    pub fn suggested_template_duration(&self, name: &str) -> Option<i64> {
+        let template = self.request_template(name)?;
+        suggested_duration(&self.calendar, &template.reason)
+    }
+
+    /// Allow an external referrer this many bookings per week.
+    pub fn set_referral_quota(&mut self, referrer: &str, slots_per_week: usize) {
+        self.referral_quotas
+            .insert(referrer.trim().to_string(), slots_per_week);
+    }
+
+    /// Get every referrer's weekly quota.
+    pub fn referral_quotas(&self) -> &HashMap<String, usize> {
+        &self.referral_quotas
+    }
+
+    /// Get the weekly quota set for a referrer, if any.
+    pub fn referral_quota(&self, referrer: &str) -> Option<usize> {
+        self.referral_quotas.get(referrer.trim()).copied()
+    }
+
+    /// Count a referrer's booked and queued requests in the week containing `date`.
+    ///
+    /// Weeks run Monday to Sunday. Cancelled appointments do not count.
+    pub fn referral_quota_used(
+        &self,
+        referrer: &str,
+        date: DateTime<Local>,
+    ) -> Result<usize, String> {
+        let referrer = referrer.trim();
+        let week = date.iso_week();
+        let from_referrer =
+            |request: &AppointmentRequest| request.referrer.as_deref() == Some(referrer);
+
+        let booked = self
+            .booked_requests
+            .iter()
+            .filter(|(_, request)| from_referrer(request))
+            .filter_map(|(appointment_id, _)| self.calendar.get_appointment_by_id(appointment_id))
+            .filter(|apt| apt.status != AppointmentStatus::Cancelled)
+            .filter(|apt| apt.time_slot.start_time.iso_week() == week)
+            .count();
+        let queued = self
+            .request_queue
+            .pending()?
+            .iter()
+            .filter(|request| from_referrer(request) && request.preferred_time.iso_week() == week)
+            .count();
+
+        Ok(booked + queued)
+    }
+
+    /// Describe the quota violation if the request's referrer has used up its week.
+    fn referral_quota_violation(
+        &self,
+        request: &AppointmentRequest,
+    ) -> Result<Option<String>, String> {
+        let Some(referrer) = &request.referrer else {
+            return Ok(None);
+        };
+        let Some(quota) = self.referral_quota(referrer) else {
+            return Ok(None);
+        };
+
+        if self.referral_quota_used(referrer, request.preferred_time)? < quota {
+            return Ok(None);
+        }
+        Ok(Some(format!(
+            "Quota exceeded: {} has used all {} referral slots for that week",
+            referrer.trim(),
+            quota
+        )))
+    }
+
+    /// Collect validator and referral quota violations for a request.
+    fn request_violations(&self, request: &AppointmentRequest) -> Result<Vec<String>, String> {
+        let mut violations = self.validate_request(request)?;
+        violations.extend(self.referral_quota_violation(request)?);
+        Ok(violations)
+    }
+
+    /// Run every validator against a request, collecting all violations.
+    pub fn validate_request(&self, request: &AppointmentRequest) -> Result<Vec<String>, String> {
+        if self.validators.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let pending = self.request_queue.pending()?;
+        let context = ValidationContext {
+            calendar: &self.calendar,
+            pending: &pending,
+            now: self.calendar.now(),
+        };
+
+        Ok(self
+            .validators
+            .iter()
+            .flat_map(|validator| validator.validate(request, &context))
+            .collect())
+    }
+
+    /// Add a request according to the processing policy.
+    ///
+    /// The request is first checked by every validator and rejected with
+    /// all violations if any fail, and requests from a referrer over its
+    /// weekly quota are rejected. Requests whose priority is processed
+    /// immediately are scheduled right away and their result returned;
+    /// unsuccessful ones are put on the waitlist and marked `waitlisted`.
+    /// Other requests are queued and `None` returned.
+    pub fn add_request(
+        &mut self,
+        request: AppointmentRequest,
+    ) -> Result<Option<SchedulingResult>, String> {
+        let violations = self.request_violations(&request)?;
+        if !violations.is_empty() {
+            return Err(violations.join("; "));
         }
+        self.submit_request(request)
     }
 
-    /// Add a request to the scheduling queue.
-    pub fn add_request(&mut self, request: AppointmentRequest) {
-        self.request_queue.push(request);
+    /// Schedule or queue a request that has already been validated.
+    fn submit_request(
+        &mut self,
+        request: AppointmentRequest,
+    ) -> Result<Option<SchedulingResult>, String> {
+        match self.processing_policy.mode_for(request.priority) {
+            ProcessingMode::Immediate => {
+                let mut result = self.schedule_single(request);
+                if !result.success {
+                    self.waitlist.park(result.request.clone());
+                    result.waitlisted = true;
+                }
+                Ok(Some(result))
+            }
+            ProcessingMode::Batched => {
+                self.request_queue.push(request)?;
+                Ok(None)
+            }
+        }
     }
 
-    /// Add multiple requests to the queue.
-    pub fn add_requests(&mut self, requests: Vec<AppointmentRequest>) {
+    /// Add multiple requests, returning results for those scheduled immediately.
+    pub fn add_requests(
+        &mut self,
+        requests: Vec<AppointmentRequest>,
+    ) -> Result<Vec<SchedulingResult>, String> {
+        let mut results = Vec::new();
         for request in requests {
-            self.add_request(request);
+            if let Some(result) = self.add_request(request)? {
+                results.push(result);
+            }
+        }
+        Ok(results)
+    }
+
+    /// Check whether the batch interval has elapsed since the last run.
+    pub fn is_batch_due(&self) -> bool {
+        match (self.processing_policy.batch_interval, self.last_batch_run) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(interval), Some(last)) => self.calendar.now() - last >= interval,
+        }
+    }
+
+    /// Process the queue if the batch interval has elapsed.
+    ///
+    /// The calendar's slot horizon is topped up first, so a run on a new
+    /// day can book into the day just opened.
+    pub fn process_if_due(&mut self) -> Option<BatchSchedulingResult> {
+        self.calendar.extend_horizon();
+        if self.is_batch_due() {
+            Some(self.process_queue())
+        } else {
+            None
         }
     }
 
     /// Find the best available slot for a request.
-    fn find_slot_for_request(&self, request: &AppointmentRequest) -> Option<TimeSlot> {
-        let mut slot = self
+    ///
+    /// Only slots the request and the business rules accept are considered.
+    /// The strategy picks among the slots inside the flexibility window,
+    /// by default the one nearest the preferred time. With grid snapping
+    /// enabled, the search is retried from the neighbouring slot starts,
+    /// returning the grid time used. With fallback
+    /// enabled (globally or by a rule for the request's priority), the next
+    /// acceptable slot after the preferred time is used.
+    fn find_slot_for_request(
+        &self,
+        request: &AppointmentRequest,
+    ) -> Option<(TimeSlot, Option<DateTime<Local>>)> {
+        if let Some(slot) = self.find_slot_in_window(request) {
+            return Some((slot, None));
+        }
+        if self.snap_to_grid {
+            if let Some((grid_time, slot)) = self.find_slot_on_grid(request) {
+                return Some((slot, Some(grid_time)));
+            }
+        }
+
+        let fallback = self.allow_fallback || self.rules.forces_fallback(request.priority);
+        if !fallback {
+            return None;
+        }
+        self.acceptable_slots(request)
+            .into_iter()
+            .find(|slot| slot.start_time >= request.preferred_time)
+            .map(|slot| (slot, None))
+    }
+
+    /// Retry the window search from the slot starts either side of the preferred time.
+    ///
+    /// For example, with 30-minute slots a preferred 10:10 is tried as 10:00
+    /// and then 10:30, nearest first.
+    fn find_slot_on_grid(
+        &self,
+        request: &AppointmentRequest,
+    ) -> Option<(DateTime<Local>, TimeSlot)> {
+        let preferred = request.preferred_time;
+        let starts: Vec<DateTime<Local>> = self
             .calendar
-            .find_available_slot(request.preferred_time, request.flexibility_minutes);
+            .time_slots()
+            .into_iter()
+            .map(|slot| slot.start_time)
+            .filter(|start| start.date_naive() == preferred.date_naive())
+            .collect();
+        let before = starts.iter().filter(|start| **start < preferred).max();
+        let after = starts.iter().filter(|start| **start > preferred).min();
+
+        let mut grid_times: Vec<DateTime<Local>> =
+            before.into_iter().chain(after).copied().collect();
+        grid_times.sort_by_key(|time| (*time - preferred).num_seconds().abs());
+
+        grid_times.into_iter().find_map(|grid_time| {
+            let mut snapped = request.clone();
+            snapped.preferred_time = grid_time;
+            self.find_slot_in_window(&snapped)
+                .map(|slot| (grid_time, slot))
+        })
+    }
+
+    /// Let the strategy pick among the acceptable slots inside the flexibility window.
+    ///
+    /// Only the slots breaking the fewest of the doctor's soft preferences
+    /// are offered to the strategy.
+    fn find_slot_in_window(&self, request: &AppointmentRequest) -> Option<TimeSlot> {
+        let candidates: Vec<TimeSlot> = self
+            .acceptable_slots(request)
+            .into_iter()
+            .filter(|slot| request.is_time_acceptable(slot))
+            .collect();
+        let misses = |slot: &TimeSlot| {
+            self.calendar
+                .preferences
+                .soft_misses(&self.calendar, request, slot)
+        };
+        let fewest = candidates.iter().map(misses).min().unwrap_or(0);
+        let candidates = candidates
+            .into_iter()
+            .filter(|slot| misses(slot) == fewest)
+            .collect();
+        self.strategy.choose(&self.calendar, request, candidates)
+    }
 
-        if slot.is_none() && self.allow_fallback {
-            slot = self.calendar.find_next_available_slot(request.preferred_time);
+    /// Find up to three upcoming acceptable slots outside the flexibility window, nearest first.
+    fn suggest_alternatives(&self, request: &AppointmentRequest) -> Vec<TimeSlot> {
+        let now = self.calendar.now();
+        let mut candidates: Vec<TimeSlot> = self
+            .acceptable_slots(request)
+            .into_iter()
+            .filter(|slot| slot.start_time >= now && !request.is_time_acceptable(slot))
+            .collect();
+        candidates.sort_by_key(|slot| (slot.start_time - request.preferred_time).num_seconds().abs());
+        candidates.truncate(3);
+        candidates
+    }
+
+    /// Find the fewest extra minutes of flexibility that would bring a free
+    /// slot the request could take into its window.
+    ///
+    /// Requests with a date window don't use flexibility, so get `None`.
+    fn flexibility_needed(&self, request: &AppointmentRequest) -> Option<i64> {
+        if request.date_window.is_some() {
+            return None;
         }
+        let now = self.calendar.now();
+        let earliest = request.earliest_acceptable();
+        let latest = request.latest_acceptable();
+        self.acceptable_slots(request)
+            .into_iter()
+            .filter(|slot| slot.start_time >= now && !request.is_time_acceptable(slot))
+            .map(|slot| {
+                let outside = if slot.start_time < earliest {
+                    earliest - slot.start_time
+                } else {
+                    slot.start_time - latest
+                };
+                (outside.num_seconds() + 59) / 60
+            })
+            .min()
+    }
+
+    /// Get the session quotas that keep a request out of free slots in its
+    /// window it would otherwise take.
+    pub fn binding_quotas(&self, request: &AppointmentRequest) -> Vec<Rule> {
+        let slots: Vec<TimeSlot> = self
+            .free_slots_for(request)
+            .into_iter()
+            .filter(|slot| request.accepts_slot(slot) && request.is_time_acceptable(slot))
+            .collect();
+        self.rules
+            .binding_quotas(&self.calendar, request, &slots)
+            .into_iter()
+            .cloned()
+            .collect()
+    }
 
-        slot
+    /// Check whether the business rules and the doctor's hard preferences
+    /// allow booking `slot` for `request`.
+    fn permits(&self, request: &AppointmentRequest, slot: &TimeSlot) -> bool {
+        self.rules.permits(&self.calendar, request, slot)
+            && self
+                .calendar
+                .preferences
+                .permits(&self.calendar, request, slot)
+    }
+
+    /// Get free slots the request is willing to take and the rules permit, sorted by start time.
+    fn acceptable_slots(&self, request: &AppointmentRequest) -> Vec<TimeSlot> {
+        self.free_slots_for(request)
+            .into_iter()
+            .filter(|slot| request.accepts_slot(slot))
+            .filter(|slot| self.permits(request, slot))
+            .collect()
+    }
+
+    /// Get the free slots not held for an offer, sorted by start time.
+    ///
+    /// A visit longer than the slots also gets the spans of back-to-back
+    /// free slots it fits, which are merged when booked.
+    fn free_slots_for(&self, request: &AppointmentRequest) -> Vec<TimeSlot> {
+        let spans = match request.duration_minutes {
+            Some(minutes) => self.calendar.free_spans(minutes),
+            None => Vec::new(),
+        };
+        let mut slots: Vec<TimeSlot> = self
+            .calendar
+            .available_slots()
+            .into_iter()
+            .chain(spans)
+            .filter(|slot| !self.overlaps_offer(slot))
+            .collect();
+        slots.sort_by_key(|slot| slot.start_time);
+        slots
     }
 
     /// Schedule a single appointment request.
+    ///
+    /// A failed result carries the nearest alternative slots so the patient
+    /// can be offered another time straight away.
     pub fn schedule_single(&mut self, request: AppointmentRequest) -> SchedulingResult {
-        let slot = self.find_slot_for_request(&request);
+        let result = self.try_schedule(request);
+        self.count_result(&result);
+        result
+    }
 
-        let slot = match slot {
-            Some(s) => s,
+    fn count_result(&mut self, result: &SchedulingResult) {
+        let stats = self.source_stats.entry(result.request.source).or_default();
+        if result.success {
+            stats.booked += 1;
+        } else {
+            stats.failed += 1;
+        }
+    }
+
+    /// Find the slot `schedule_single` would book for a request, without
+    /// booking it.
+    pub fn preview_slot(&self, request: &AppointmentRequest) -> Option<TimeSlot> {
+        self.find_slot_for_request(request).map(|(slot, _)| slot)
+    }
+
+    fn try_schedule(&mut self, request: AppointmentRequest) -> SchedulingResult {
+        let (slot, rounded_to) = match self.find_slot_for_request(&request) {
+            Some(found) => found,
             None => {
-                return SchedulingResult {
-                    request,
-                    appointment: None,
-                    success: false,
-                    message: "No available time slots found".to_string(),
+                let quotas: Vec<String> = self
+                    .binding_quotas(&request)
+                    .iter()
+                    .map(|rule| rule.to_string())
+                    .collect();
+                let message = if quotas.is_empty() {
+                    "No available time slots found".to_string()
+                } else {
+                    format!(
+                        "No available time slots found; quota reached: {}",
+                        quotas.join(", ")
+                    )
                 };
+                let mut result = SchedulingResult::failure(request, message);
+                result.alternatives = self.suggest_alternatives(&result.request);
+                result.remediation = Some(Remediation {
+                    nearest_date: result
+                        .alternatives
+                        .first()
+                        .map(|slot| slot.start_time.date_naive()),
+                    widen_flexibility_by: self.flexibility_needed(&result.request),
+                    other_doctors: Vec::new(),
+                });
+                return result;
             }
         };
 
+        self.book_request(request, slot, rounded_to)
+    }
+
+    /// Book a request into the slot found for it, counting fallback bumps.
+    fn book_request(
+        &mut self,
+        request: AppointmentRequest,
+        slot: TimeSlot,
+        rounded_to: Option<DateTime<Local>>,
+    ) -> SchedulingResult {
         let was_preferred = request.is_time_acceptable(&slot);
-        let preferred_time = request.preferred_time;
-        
-        // Clone what we need to keep for the return value
-        let request_id = request.request_id.clone();
-        let patient = request.patient.clone();
-        let priority = request.priority;
-        let reason = request.reason.clone();
-        let flexibility_minutes = request.flexibility_minutes;
-        let created_at = request.created_at;
-
-        match self.calendar.book_slot(
-            &slot,
-            request.patient,  // Move into book_slot
-            request.priority,
-            request.reason,   // Move into book_slot
-        ) {
+        let booking = self.calendar.transaction(|calendar| {
+            let slot = claim_span(calendar, &slot)?;
+            calendar.book_slot(
+                &slot,
+                request.patient.clone(),
+                request.priority,
+                request.reason.clone(),
+            )
+        });
+
+        match booking {
             Ok(appointment) => {
-                let message = if was_preferred {
+                let message = if let Some(grid_time) = rounded_to {
+                    format!(
+                        "Scheduled at {} (preferred {} rounded to {})",
+                        slot.start_time.format("%Y-%m-%d %H:%M"),
+                        request.preferred_time.format("%H:%M"),
+                        grid_time.format("%H:%M")
+                    )
+                } else if was_preferred {
                     format!(
                         "Scheduled at preferred time: {}",
                         slot.start_time.format("%Y-%m-%d %H:%M")
@@ -128,64 +852,77 @@ impl AppointmentScheduler {
                     format!(
                         "Scheduled at alternative time: {} (preferred was {})",
                         slot.start_time.format("%Y-%m-%d %H:%M"),
-                        preferred_time.format("%H:%M")
+                        request.preferred_time.format("%H:%M")
                     )
                 };
 
-                // Reconstruct request for return value
-                let returned_request = AppointmentRequest {
-                    request_id,
-                    patient,
-                    priority,
-                    preferred_time,
-                    reason,
-                    flexibility_minutes,
-                    created_at,
-                };
-
-                SchedulingResult {
-                    request: returned_request,
-                    appointment: Some(appointment),
-                    success: true,
-                    message,
+                self.booked_requests
+                    .insert(appointment.appointment_id.clone(), request.clone());
+                if !was_preferred && rounded_to.is_none() {
+                    *self
+                        .bumps
+                        .entry(request.patient.patient_id.clone())
+                        .or_default() += 1;
                 }
-            }
-            Err(e) => {
-                // Reconstruct request for error case
-                let returned_request = AppointmentRequest {
-                    request_id,
-                    patient,
-                    priority,
-                    preferred_time,
-                    reason,
-                    flexibility_minutes,
-                    created_at,
-                };
 
-                SchedulingResult {
-                    request: returned_request,
-                    appointment: None,
-                    success: false,
-                    message: e,
-                }
+                let mut result = SchedulingResult::booked(request, appointment, message);
+                result.rounded_to = rounded_to;
+                result
             }
+            Err(e) => SchedulingResult::failure(request, e),
         }
     }
 
     /// Process all requests in the queue by priority.
+    ///
+    /// Within a priority, patients who were pushed to a fallback time more
+    /// often in the past are scheduled first, so the same people are not
+    /// always the ones left with unpopular slots. In `BatchMode::Optimal`
+    /// the batch is assigned as a whole rather than one request at a time.
     pub fn process_queue(&mut self) -> BatchSchedulingResult {
+        let result = self.drain_queue();
+        self.record_run(result)
+    }
+
+    fn drain_queue(&mut self) -> BatchSchedulingResult {
         let mut confirmed = Vec::new();
         let mut failed = Vec::new();
-        let total = self.request_queue.len();
+        let mut queue_error = None;
+        let mut requests = Vec::new();
+        self.last_batch_run = Some(self.calendar.now());
+
+        loop {
+            match self.request_queue.pop() {
+                Ok(Some(request)) => requests.push(request),
+                Ok(None) => break,
+                Err(e) => {
+                    queue_error = Some(e);
+                    break;
+                }
+            }
+        }
 
-        while let Some(request) = self.request_queue.pop() {
-            let result = self.schedule_single(request);
+        // Within a priority, patients bumped off their preference before go first
+        requests.sort_by_key(|request| {
+            std::cmp::Reverse((request.priority, self.bump_count(&request.patient.patient_id)))
+        });
 
+        let total = requests.len();
+        let results = match self.batch_mode {
+            BatchMode::Greedy => requests
+                .into_iter()
+                .map(|request| self.schedule_single(request))
+                .collect(),
+            BatchMode::Optimal => self.schedule_optimal(requests),
+        };
+        for mut result in results {
             if result.success {
                 if let Some(appointment) = result.appointment {
                     confirmed.push(appointment);
                 }
             } else {
+                self.waitlist.park(result.request.clone());
+                result.waitlisted = true;
                 failed.push(result);
             }
         }
@@ -194,13 +931,203 @@ impl AppointmentScheduler {
             confirmed,
             failed,
             total_requests: total,
+            queue_error,
+            run_id: String::new(),
+        }
+    }
+
+    /// Schedule a batch of requests, already in priority order, together.
+    ///
+    /// Requests are first matched to free slots inside their flexibility
+    /// windows. As in `plan_fill_day`, each request is placed in order and
+    /// a request once placed is only moved to make room, so the most
+    /// requests are scheduled without leaving anyone out for a less urgent
+    /// one. The placed requests are then shared between their slots so the
+    /// total distance from preferred times is as small as possible, keeping
+    /// clear of slots that break the doctor's soft preferences.
+    /// Requests left unmatched, or whose slot the rules no longer permit
+    /// once the others have been booked, are scheduled one at a time as
+    /// usual, with fallback and grid rounding.
+    fn schedule_optimal(&mut self, requests: Vec<AppointmentRequest>) -> Vec<SchedulingResult> {
+        let slots: Vec<TimeSlot> = self
+            .calendar
+            .available_slots()
+            .into_iter()
+            .filter(|slot| !self.overlaps_offer(slot))
+            .collect();
+
+        let candidates: Vec<Vec<usize>> = requests
+            .iter()
+            .map(|request| {
+                let mut fitting: Vec<usize> = (0..slots.len())
+                    .filter(|&index| {
+                        let slot = &slots[index];
+                        request.accepts_slot(slot)
+                            && request.is_time_acceptable(slot)
+                            && self.permits(request, slot)
+                    })
+                    .collect();
+                fitting.sort_by_key(|&index| self.slot_cost(request, &slots[index]));
+                fitting
+            })
+            .collect();
+
+        let mut owners: Vec<Option<usize>> = vec![None; slots.len()];
+        for request in 0..requests.len() {
+            let mut visited = vec![false; slots.len()];
+            place_request(request, &candidates, &mut owners, &mut visited);
+        }
+
+        let placed: Vec<usize> = (0..requests.len())
+            .filter(|request| owners.contains(&Some(*request)))
+            .collect();
+        let costs: Vec<Vec<Option<i64>>> = placed
+            .iter()
+            .map(|&request| {
+                let mut row = vec![None; slots.len()];
+                for &slot in &candidates[request] {
+                    row[slot] = Some(self.slot_cost(&requests[request], &slots[slot]));
+                }
+                row
+            })
+            .collect();
+        let mut assigned: Vec<Option<usize>> = vec![None; requests.len()];
+        for (row, slot) in min_cost_assignment(&costs, slots.len())
+            .into_iter()
+            .enumerate()
+        {
+            assigned[placed[row]] = slot;
+        }
+
+        let mut results: Vec<Option<SchedulingResult>> = vec![None; requests.len()];
+        let mut leftover = Vec::new();
+        for (index, request) in requests.into_iter().enumerate() {
+            match assigned[index].map(|slot| &slots[slot]) {
+                Some(slot) if self.permits(&request, slot) => {
+                    let result = self.book_request(request, slot.clone(), None);
+                    self.count_result(&result);
+                    results[index] = Some(result);
+                }
+                _ => leftover.push((index, request)),
+            }
+        }
+        for (index, request) in leftover {
+            results[index] = Some(self.schedule_single(request));
+        }
+        results.into_iter().flatten().collect()
+    }
+
+    /// Score a slot for a request in an optimal batch: minutes from the
+    /// preferred time, plus a day for each soft preference it breaks.
+    fn slot_cost(&self, request: &AppointmentRequest, slot: &TimeSlot) -> i64 {
+        let misses = self
+            .calendar
+            .preferences
+            .soft_misses(&self.calendar, request, slot);
+        preference_cost(request, slot) + misses as i64 * 24 * 60
+    }
+
+    /// Store a run in the history and stamp the result with its ID.
+    fn record_run(&mut self, mut result: BatchSchedulingResult) -> BatchSchedulingResult {
+        result.run_id = self.calendar.next_id(IdKind::Run);
+        self.runs.push(SchedulingRun {
+            run_id: result.run_id.clone(),
+            ran_at: self.calendar.now(),
+            total_requests: result.total_requests,
+            confirmed: result.confirmed.len(),
+            failed: result
+                .failed
+                .iter()
+                .map(|failure| FailedRequest {
+                    request: failure.request.clone(),
+                    message: failure.message.clone(),
+                    disposition: None,
+                })
+                .collect(),
+        });
+        result
+    }
+
+    /// Get every recorded processing run, oldest first.
+    pub fn runs(&self) -> &[SchedulingRun] {
+        &self.runs
+    }
+
+    /// Record what staff did about a failed request, replacing any earlier note.
+    pub fn set_disposition(
+        &mut self,
+        run_id: &str,
+        request_id: &str,
+        note: &str,
+    ) -> Result<(), String> {
+        if note.trim().is_empty() {
+            return Err("Disposition cannot be empty".to_string());
         }
+
+        let recorded_at = self.calendar.now();
+        let failure = self
+            .runs
+            .iter_mut()
+            .find(|run| run.run_id == run_id)
+            .ok_or("Run not found")?
+            .failed
+            .iter_mut()
+            .find(|failure| failure.request.request_id == request_id)
+            .ok_or("Request did not fail in this run")?;
+
+        failure.disposition = Some(Disposition {
+            note: note.trim().to_string(),
+            recorded_at,
+        });
+        Ok(())
+    }
+
+    /// Get failed requests across all runs that have no disposition yet.
+    pub fn open_failures(&self) -> Vec<(&str, &FailedRequest)> {
+        self.runs
+            .iter()
+            .flat_map(|run| {
+                run.failed
+                    .iter()
+                    .filter(|failure| failure.disposition.is_none())
+                    .map(move |failure| (run.run_id.as_str(), failure))
+            })
+            .collect()
     }
 
     /// Schedule a batch of requests in priority order.
+    ///
+    /// The whole batch is queued regardless of the processing policy so
+    /// that priority order holds across it. Requests failing validation
+    /// or over their referrer's quota are reported as failures without
+    /// being queued.
     pub fn schedule_batch(&mut self, requests: Vec<AppointmentRequest>) -> BatchSchedulingResult {
-        self.add_requests(requests);
-        self.process_queue()
+        let mut rejected = Vec::new();
+
+        for request in requests {
+            let pushed = match self.request_violations(&request) {
+                Ok(violations) if !violations.is_empty() => {
+                    rejected.push(SchedulingResult::failure(request, violations.join("; ")));
+                    continue;
+                }
+                Ok(_) => self.request_queue.push(request),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = pushed {
+                return self.record_run(BatchSchedulingResult {
+                    confirmed: Vec::new(),
+                    total_requests: rejected.len(),
+                    failed: rejected,
+                    queue_error: Some(e),
+                    run_id: String::new(),
+                });
+            }
+        }
+
+        let mut result = self.drain_queue();
+        result.total_requests += rejected.len();
+        result.failed.extend(rejected);
+        self.record_run(result)
     }
 
     /// Reschedule an existing appointment to a new time.
@@ -211,13 +1138,17 @@ impl AppointmentScheduler {
         flexibility_minutes: i64,
     ) -> SchedulingResult {
         // Get the original appointment or return early if not found
-        let appointment = match self.calendar.get_appointment_by_id(appointment_id) {
+        let appointment = match self
+            .calendar
+            .get_appointment_by_id(appointment_id)
+            .filter(|apt| apt.status != AppointmentStatus::Cancelled)
+        {
             Some(apt) => apt,
             None => {
                 // Create a minimal error response without panicking
-                return SchedulingResult {
-                    request: AppointmentRequest {
-                        request_id: Uuid::new_v4().to_string(),
+                return SchedulingResult::failure(
+                    AppointmentRequest {
+                        request_id: self.calendar.next_id(IdKind::Request),
                         patient: Patient {
                             patient_id: "unknown".to_string(),
                             name: "Unknown".to_string(),
@@ -227,78 +1158,1081 @@ impl AppointmentScheduler {
                         preferred_time: new_preferred_time,
                         reason: "Reschedule".to_string(),
                         flexibility_minutes,
-                        created_at: Local::now(),
+                        can_come_earlier: None,
+                        can_come_later: None,
+                        unavailable_times: Vec::new(),
+                        date_window: None,
+                        accepts_premium: false,
+                        source: RequestSource::Unspecified,
+                        referrer: None,
+                        duration_minutes: None,
+                        created_at: self.calendar.now(),
                     },
-                    appointment: None,
-                    success: false,
-                    message: "Original appointment not found".to_string(),
-                };
+                    "Original appointment not found",
+                );
             }
         };
 
-        // Build the reschedule request once
-        let reschedule_request = AppointmentRequest {
-            request_id: Uuid::new_v4().to_string(),
+        let reschedule_request =
+            self.reschedule_request(&appointment, new_preferred_time, flexibility_minutes);
+
+        let new_slot = self.find_slot_in_window(&reschedule_request);
+
+        let new_slot = match new_slot {
+            Some(slot) => slot,
+            None => {
+                return SchedulingResult::failure(
+                    reschedule_request,
+                    "No available slots at the requested time",
+                );
+            }
+        };
+
+        match self.calendar.transaction(|calendar| {
+            let slot = claim_span(calendar, &new_slot)?;
+            calendar.move_appointment(appointment_id, &slot)
+        }) {
+            Ok(new_appointment) => {
+                self.booked_requests
+                    .insert(appointment_id.to_string(), reschedule_request.clone());
+
+                SchedulingResult::booked(
+                    reschedule_request,
+                    new_appointment,
+                    format!(
+                        "Rescheduled to {}",
+                        new_slot.start_time.format("%Y-%m-%d %H:%M")
+                    ),
+                )
+            }
+            Err(e) => SchedulingResult::failure(
+                reschedule_request,
+                format!("Failed to reschedule: {}", e),
+            ),
+        }
+    }
+
+    /// Build a request for moving an appointment, keeping the original request's constraints.
+    fn reschedule_request(
+        &self,
+        appointment: &Appointment,
+        preferred_time: DateTime<Local>,
+        flexibility_minutes: i64,
+    ) -> AppointmentRequest {
+        let original = self.booked_requests.get(&appointment.appointment_id);
+        AppointmentRequest {
+            request_id: self.calendar.next_id(IdKind::Request),
             patient: appointment.patient.clone(),
             priority: appointment.priority,
-            preferred_time: new_preferred_time,
+            preferred_time,
             reason: appointment.reason.clone(),
             flexibility_minutes,
-            created_at: Local::now(),
+            can_come_earlier: None,
+            can_come_later: None,
+            unavailable_times: original
+                .map(|original| original.unavailable_times.clone())
+                .unwrap_or_default(),
+            date_window: None,
+            accepts_premium: original.is_some_and(|original| original.accepts_premium),
+            source: original.map(|original| original.source).unwrap_or_default(),
+            referrer: original.and_then(|original| original.referrer.clone()),
+            duration_minutes: original.and_then(|original| original.duration_minutes),
+            created_at: self.calendar.now(),
+        }
+    }
+
+    /// Start a request for another visit like a previous appointment.
+    ///
+    /// The patient, reason and priority come from the appointment. The
+    /// flexibility, slot length and other preferences come from the request
+    /// it was booked from when known; dated preferences such as unavailable
+    /// times and date windows are dropped. The request is not queued.
+    pub fn rebook_like(
+        &self,
+        appointment_id: &str,
+        new_preferred_time: DateTime<Local>,
+    ) -> Result<AppointmentRequest, String> {
+        let appointment = self
+            .calendar
+            .get_appointment_by_id(appointment_id)
+            .ok_or("Appointment not found")?;
+
+        let mut request = match self.booked_requests.get(appointment_id) {
+            Some(original) => original.clone(),
+            None => self.reschedule_request(
+                &appointment,
+                new_preferred_time,
+                self.calendar.default_slot_duration,
+            ),
         };
+        request.request_id = self.calendar.next_id(IdKind::Request);
+        request.patient = appointment.patient;
+        request.priority = appointment.priority;
+        request.reason = appointment.reason;
+        request.preferred_time = new_preferred_time;
+        request.unavailable_times.clear();
+        request.date_window = None;
+        request.created_at = self.calendar.now();
+        Ok(request)
+    }
 
-        let new_slot = self
+    /// Check in an arriving patient, applying the late arrival policy.
+    ///
+    /// Patients within the grace period, or with no policy set, are checked
+    /// in. Past it, the next free slot today that the patient accepts is
+    /// offered (see `accept_late_offer`), or the booking is cancelled and
+    /// the patient added as a walk-in request for the rest of the day.
+    pub fn arrive(&mut self, appointment_id: &str) -> Result<ArrivalReport, String> {
+        let appointment = self
             .calendar
-            .find_available_slot(new_preferred_time, flexibility_minutes);
+            .get_appointment_by_id(appointment_id)
+            .ok_or("Appointment not found")?;
+        let now = self.calendar.now();
+        let minutes_late = (now - appointment.time_slot.start_time).num_minutes();
 
-        let new_slot = match new_slot {
-            Some(slot) => slot,
-            None => {
-                return SchedulingResult {
-                    request: reschedule_request,
-                    appointment: None,
-                    success: false,
-                    message: "No available slots at the requested time".to_string(),
-                };
+        let policy = match self.late_arrival_policy {
+            Some(policy) if minutes_late > policy.grace_minutes => policy,
+            _ => {
+                return Ok(ArrivalReport {
+                    arrival: Arrival::CheckedIn(self.calendar.check_in(appointment_id)?),
+                    backfilled: None,
+                })
             }
         };
+        if appointment.checked_in_at.is_some() {
+            return Err("Patient is already checked in".to_string());
+        }
 
-        // Cancel old, book new
-        self.calendar.cancel_appointment(appointment_id);
-        
-        match self.calendar.book_slot(
-            &new_slot,
-            appointment.patient,  // Move, don't clone
-            appointment.priority,
-            appointment.reason,   // Move, don't clone
-        ) {
-            Ok(new_appointment) => SchedulingResult {
-                request: reschedule_request,
-                appointment: Some(new_appointment),
-                success: true,
-                message: format!(
-                    "Rescheduled to {}",
-                    new_slot.start_time.format("%Y-%m-%d %H:%M")
-                ),
-            },
-            Err(e) => SchedulingResult {
-                request: reschedule_request,
-                appointment: None,
-                success: false,
-                message: format!("Failed to reschedule: {}", e),
+        if policy.action == LateArrivalAction::OfferNextGap {
+            if let Some(slot) = self.next_gap_for(&appointment) {
+                return Ok(ArrivalReport {
+                    arrival: Arrival::Offered {
+                        appointment,
+                        slot,
+                        minutes_late,
+                    },
+                    backfilled: None,
+                });
+            }
+        }
+
+        let minutes_left = 24 * 60 - i64::from(now.time().num_seconds_from_midnight()) / 60;
+        let mut request = self.reschedule_request(&appointment, now, 0);
+        request.source = RequestSource::WalkIn;
+        request.referrer = None;
+        request.set_window(0, minutes_left)?;
+        // Validate while the booking still stands, so a rejected walk-in
+        // leaves the patient with their appointment.
+        let violations = self.request_violations(&request)?;
+        if !violations.is_empty() {
+            return Err(violations.join("; "));
+        }
+
+        self.calendar.add_appointment_note(
+            appointment_id,
+            format!("Arrived {} minutes late; converted to walk-in", minutes_late),
+        )?;
+        self.calendar
+            .cancel_appointment_by(appointment_id, CancellationActor::Staff)?;
+        self.booked_requests.remove(appointment_id);
+
+        let backfilled = if policy.release_to_waitlist {
+            self.backfill(&appointment.time_slot)
+        } else {
+            None
+        };
+        let result = self.submit_request(request)?.map(Box::new);
+        Ok(ArrivalReport {
+            arrival: Arrival::WalkIn {
+                minutes_late,
+                result,
             },
+            backfilled,
+        })
+    }
+
+    /// Move a late patient into the slot they were offered and check them in.
+    pub fn accept_late_offer(
+        &mut self,
+        appointment_id: &str,
+        slot: &TimeSlot,
+    ) -> Result<ArrivalReport, String> {
+        let previous = self
+            .calendar
+            .get_appointment_by_id(appointment_id)
+            .map(|apt| apt.time_slot)
+            .ok_or("Appointment not found")?;
+        self.calendar.transaction(|calendar| {
+            let slot = claim_span(calendar, slot)?;
+            calendar.move_appointment(appointment_id, &slot)
+        })?;
+        let appointment = self.calendar.check_in(appointment_id)?;
+
+        let release = self
+            .late_arrival_policy
+            .is_some_and(|policy| policy.release_to_waitlist);
+        Ok(ArrivalReport {
+            arrival: Arrival::CheckedIn(appointment),
+            backfilled: if release { self.backfill(&previous) } else { None },
+        })
+    }
+
+    /// Find the next free slot today, from now on, that a patient accepts.
+    fn next_gap_for(&self, appointment: &Appointment) -> Option<TimeSlot> {
+        let now = self.calendar.now();
+        let request = self.reschedule_request(appointment, now, 0);
+        self.acceptable_slots(&request)
+            .into_iter()
+            .find(|slot| {
+                slot.start_time >= now
+                    && slot.start_time.date_naive() == now.date_naive()
+                    && slot.is_released(now)
+            })
+    }
+
+    /// Book the most urgent waitlisted request that fits a vacated slot.
+    fn backfill(&mut self, slot: &TimeSlot) -> Option<Appointment> {
+        if self.is_on_offer(&slot.slot_id) {
+            return None;
+        }
+        let slot = self.calendar.slot_by_id(&slot.slot_id)?.clone();
+        let request = self
+            .waitlist
+            .in_order()
+            .into_iter()
+            .find(|request| fits(request, &slot) && self.permits(request, &slot))?;
+
+        let plan = FillPlan {
+            date: slot.start_time.date_naive(),
+            proposals: vec![FillProposal { request, slot }],
+            open_slots: Vec::new(),
+            unplaced: Vec::new(),
+        };
+        self.commit_fill(plan).booked.pop()
+    }
+
+    /// Find booked patients who would take a freed slot instead of their
+    /// own, later appointment, most urgent first, then those waiting longest.
+    ///
+    /// The preferences stored with each booking must accept the slot, and
+    /// patients who are part of a bundle or already hold an offer are left
+    /// alone.
+    pub fn bring_forward_candidates(&self, slot: &TimeSlot) -> Vec<Appointment> {
+        let mut candidates: Vec<Appointment> = self
+            .calendar
+            .appointments()
+            .into_iter()
+            .filter(|apt| {
+                apt.time_slot.start_time > slot.start_time
+                    && apt.checked_in_at.is_none()
+                    && apt.bundle_id.is_none()
+                    && !self.calendar.is_day_locked(apt.time_slot.start_time.date_naive())
+                    && !self
+                        .bring_forward_offers
+                        .iter()
+                        .any(|offer| offer.appointment_id == apt.appointment_id)
+            })
+            .filter(|apt| {
+                let request = match self.booked_requests.get(&apt.appointment_id) {
+                    Some(request) => request.clone(),
+                    None => self.reschedule_request(apt, slot.start_time, 0),
+                };
+                fits(&request, slot)
+            })
+            .collect();
+        candidates.sort_by_key(|apt| (Reverse(apt.priority), Reverse(apt.time_slot.start_time)));
+        candidates
+    }
+
+    /// Offer a freed slot to the first patient who would come earlier for it.
+    ///
+    /// The patient is sent a token to confirm with (see
+    /// `accept_bring_forward`), and the slot is held back from the waitlist
+    /// until they answer. Returns `None` when nobody booked later wants it.
+    pub fn offer_bring_forward(
+        &mut self,
+        slot_id: &str,
+        channel: &mut dyn NotificationChannel,
+    ) -> Result<Option<BringForwardOffer>, String> {
+        let now = self.calendar.now();
+        let slot = self
+            .calendar
+            .slot_by_id(slot_id)
+            .cloned()
+            .ok_or("Time slot not found in calendar")?;
+        if !slot.is_available || slot.start_time <= now || !slot.is_released(now) {
+            return Err("Time slot is not available".to_string());
+        }
+        if self.is_on_offer(slot_id) {
+            return Err("Time slot is already on offer".to_string());
+        }
+        let Some(appointment) = self.bring_forward_candidates(&slot).into_iter().next() else {
+            return Ok(None);
+        };
+
+        let offer = BringForwardOffer::new(
+            self.calendar.id_generator(),
+            appointment.appointment_id,
+            appointment.patient,
+            appointment.time_slot,
+            slot,
+            now,
+        );
+        channel.send(&offer.to_notification(self.calendar.id_generator(), now)?)?;
+        self.bring_forward_offers.push(offer.clone());
+        Ok(Some(offer))
+    }
+
+    /// Get the offers awaiting an answer.
+    pub fn bring_forward_offers(&self) -> &[BringForwardOffer] {
+        &self.bring_forward_offers
+    }
+
+    /// Move a patient into the earlier slot they confirmed with their token.
+    pub fn accept_bring_forward(&mut self, token: &str) -> Result<Appointment, String> {
+        let offer = self.take_offer(token)?;
+        if offer.slot.start_time <= self.calendar.now() {
+            return Err("The offered slot has already passed".to_string());
+        }
+        self.calendar
+            .move_appointment(&offer.appointment_id, &offer.slot)
+    }
+
+    /// Record that a patient keeps their appointment, and offer the slot to
+    /// the waitlist instead.
+    pub fn decline_bring_forward(&mut self, token: &str) -> Result<Option<Appointment>, String> {
+        let offer = self.take_offer(token)?;
+        Ok(self.backfill(&offer.slot))
+    }
+
+    fn take_offer(&mut self, token: &str) -> Result<BringForwardOffer, String> {
+        let index = self
+            .bring_forward_offers
+            .iter()
+            .position(|offer| offer.token.eq_ignore_ascii_case(token.trim()))
+            .ok_or("Unknown bring-forward token")?;
+        Ok(self.bring_forward_offers.remove(index))
+    }
+
+    /// Offer a freed slot to the top `invites` waitlisted requests it fits
+    /// at once; the first patient to confirm gets it (see `confirm_claim`).
+    ///
+    /// The slot is held back from other bookings until it is claimed or the
+    /// claim is closed. Returns `None` when no waitlisted request fits.
+    pub fn open_slot_claim(
+        &mut self,
+        slot_id: &str,
+        invites: usize,
+        channel: &mut dyn NotificationChannel,
+    ) -> Result<Option<SlotClaim>, String> {
+        let now = self.calendar.now();
+        let slot = self
+            .calendar
+            .slot_by_id(slot_id)
+            .cloned()
+            .ok_or("Time slot not found in calendar")?;
+        if !slot.is_available || slot.start_time <= now || !slot.is_released(now) {
+            return Err("Time slot is not available".to_string());
+        }
+        if self.is_on_offer(slot_id) {
+            return Err("Time slot is already on offer".to_string());
+        }
+        let requests: Vec<AppointmentRequest> = self
+            .waitlist
+            .in_order()
+            .into_iter()
+            .filter(|request| fits(request, &slot) && self.permits(request, &slot))
+            .take(invites)
+            .collect();
+        if requests.is_empty() {
+            return Ok(None);
+        }
+
+        let claim = SlotClaim::new(self.calendar.id_generator(), slot, requests, now);
+        for invite in &claim.invites {
+            channel.send(&invite.to_notification(
+                self.calendar.id_generator(),
+                now,
+                &claim.slot,
+            )?)?;
+        }
+        self.slot_claims.push(claim.clone());
+        Ok(Some(claim))
+    }
+
+    /// Get the claims still waiting for a patient to confirm.
+    pub fn open_claims(&self) -> Vec<&SlotClaim> {
+        self.slot_claims
+            .iter()
+            .filter(|claim| claim.is_open())
+            .collect()
+    }
+
+    /// Book a claimed slot for the first patient to confirm with their token.
+    ///
+    /// The claim is resolved before anything is booked, so only one
+    /// confirmation can ever win; later ones are turned down. The other
+    /// invited patients are told the slot has gone and stay waitlisted,
+    /// including when the slot can't be booked after all, e.g. because it
+    /// was removed while the claim was open.
+    pub fn confirm_claim(
+        &mut self,
+        token: &str,
+        channel: &mut dyn NotificationChannel,
+    ) -> Result<ClaimResolution, String> {
+        let index = self
+            .slot_claims
+            .iter()
+            .position(|claim| claim.invite(token).is_some())
+            .ok_or("Unknown claim token")?;
+        let invite = self.slot_claims[index].resolve(token)?.clone();
+        let slot = self.slot_claims[index].slot.clone();
+        let request = invite.request;
+
+        let appointment = match self.calendar.book_slot(
+            &slot,
+            request.patient.clone(),
+            request.priority,
+            request.reason.clone(),
+        ) {
+            Ok(appointment) => appointment,
+            Err(e) => {
+                let claim = self.slot_claims.remove(index);
+                let undelivered: Vec<String> = claim
+                    .others()
+                    .into_iter()
+                    .filter_map(|other| {
+                        other
+                            .withdrawn_notification(
+                                self.calendar.id_generator(),
+                                self.calendar.now(),
+                                &slot,
+                            )
+                            .and_then(|notification| channel.send(&notification))
+                            .err()
+                            .map(|delivery| {
+                                format!("{} ({})", other.request.patient.name, delivery)
+                            })
+                    })
+                    .collect();
+                if undelivered.is_empty() {
+                    return Err(e);
+                }
+                return Err(format!("{}; could not tell {}", e, undelivered.join(", ")));
+            }
+        };
+        self.waitlist.remove(&request.request_id);
+        self.booked_requests
+            .insert(appointment.appointment_id.clone(), request);
+
+        let mut resolution = ClaimResolution {
+            appointment,
+            informed: Vec::new(),
+            undelivered: Vec::new(),
+        };
+        for other in self.slot_claims[index].others() {
+            let patient = other.request.patient.clone();
+            match other
+                .taken_notification(self.calendar.id_generator(), self.calendar.now(), &slot)
+                .and_then(|notification| channel.send(&notification))
+            {
+                Ok(()) => resolution.informed.push(patient),
+                Err(e) => resolution.undelivered.push((patient, e)),
+            }
         }
+        Ok(resolution)
+    }
+
+    /// Withdraw an unclaimed slot from its claim and offer it to the
+    /// waitlist as usual, e.g. when nobody answered in time.
+    pub fn close_claim(&mut self, slot_id: &str) -> Result<Option<Appointment>, String> {
+        let index = self
+            .slot_claims
+            .iter()
+            .position(|claim| claim.is_open() && claim.slot.slot_id == slot_id)
+            .ok_or("No open claim on that slot")?;
+        let claim = self.slot_claims.remove(index);
+        Ok(self.backfill(&claim.slot))
+    }
+
+    fn is_on_offer(&self, slot_id: &str) -> bool {
+        self.bring_forward_offers
+            .iter()
+            .map(|offer| &offer.slot)
+            .chain(self.open_claims().into_iter().map(|claim| &claim.slot))
+            .any(|slot| slot.slot_id == slot_id)
+    }
+
+    /// Check whether a slot or span covers any slot held for an offer.
+    fn overlaps_offer(&self, slot: &TimeSlot) -> bool {
+        self.bring_forward_offers
+            .iter()
+            .map(|offer| &offer.slot)
+            .chain(self.open_claims().into_iter().map(|claim| &claim.slot))
+            .any(|held| held.start_time < slot.end_time && slot.start_time < held.end_time)
+    }
+
+    /// Raise a time-off request for this scheduler's doctor.
+    pub fn request_time_off(
+        &mut self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        reason: String,
+    ) -> Result<TimeOffRequest, String> {
+        let request = TimeOffRequest::new(
+            self.calendar.id_generator(),
+            self.calendar.doctor_id.clone(),
+            start,
+            end,
+            reason,
+            self.calendar.now(),
+        )?;
+        self.time_off.push(request.clone());
+        Ok(request)
+    }
+
+    /// Get all time-off requests in the order they were raised.
+    pub fn time_off_requests(&self) -> &[TimeOffRequest] {
+        &self.time_off
+    }
+
+    /// Get the time-off requests awaiting a decision.
+    pub fn pending_time_off(&self) -> Vec<&TimeOffRequest> {
+        self.time_off
+            .iter()
+            .filter(|request| request.is_pending())
+            .collect()
+    }
+
+    /// Turn down a pending time-off request.
+    pub fn reject_time_off(&mut self, request_id: &str) -> Result<(), String> {
+        let now = self.calendar.now();
+        let request = self.pending_time_off_mut(request_id)?;
+        request.status = TimeOffStatus::Rejected;
+        request.decided_at = Some(now);
+        Ok(())
+    }
+
+    /// Approve a pending time-off request and clear the period.
+    ///
+    /// The period is blocked with `DoctorCalendar::block_period`, which
+    /// removes its free slots and keeps new slots and bookings out of it
+    /// until it is unblocked. Each appointment booked in it moves to the
+    /// slot its original request would pick, or failing that the nearest
+    /// one the patient accepts; the block drops the slots this frees.
+    /// Appointments with nowhere to go stay put and are reported as
+    /// unresolved.
+    pub fn approve_time_off(&mut self, request_id: &str) -> Result<TimeOffReport, String> {
+        let now = self.calendar.now();
+        let request = self.pending_time_off_mut(request_id)?.clone();
+        if request.doctor_id != self.calendar.doctor_id {
+            return Err("Doctors can only manage their own availability".to_string());
+        }
+
+        let mut report = TimeOffReport {
+            block: self.calendar.block_period(
+                request.start,
+                request.end,
+                request.reason.clone(),
+            )?,
+            ..TimeOffReport::default()
+        };
+
+        // Highest priority first, so emergencies get the nearest free slots
+        let mut conflicts = report.block.conflicts.clone();
+        conflicts.sort_by_key(|apt| std::cmp::Reverse(apt.priority));
+        for appointment in &conflicts {
+            let Some(moved) = self.move_out_of_time_off(appointment, &request) else {
+                report.unresolved.push(appointment.clone());
+                continue;
+            };
+            let mut vacated = appointment.time_slot.clone();
+            vacated.is_available = true;
+            match self.calendar.slot_by_id(&vacated.slot_id) {
+                None => report.block.removed.push(vacated),
+                Some(_) => report.block.refused.push(vacated),
+            }
+            report.moved.push(MovedAppointment {
+                previous_start: appointment.time_slot.start_time,
+                appointment: moved,
+            });
+        }
+        report.block.conflicts = report.unresolved.clone();
+
+        let stored = self.pending_time_off_mut(request_id)?;
+        stored.status = TimeOffStatus::Approved;
+        stored.decided_at = Some(now);
+        Ok(report)
+    }
+
+    fn pending_time_off_mut(&mut self, request_id: &str) -> Result<&mut TimeOffRequest, String> {
+        let request = self
+            .time_off
+            .iter_mut()
+            .find(|request| request.request_id == request_id)
+            .ok_or("Time-off request not found")?;
+        if !request.is_pending() {
+            return Err(format!(
+                "Time-off request is already {}",
+                request.status.name().to_lowercase()
+            ));
+        }
+        Ok(request)
+    }
+
+    /// Move an appointment out of time off to the best free slot its patient accepts.
+    fn move_out_of_time_off(
+        &mut self,
+        appointment: &Appointment,
+        time_off: &TimeOffRequest,
+    ) -> Option<Appointment> {
+        let mut request = match self.booked_requests.get(&appointment.appointment_id) {
+            Some(request) => request.clone(),
+            None => self.reschedule_request(
+                appointment,
+                appointment.time_slot.start_time,
+                self.calendar.default_slot_duration,
+            ),
+        };
+        // Free slots the block could not remove are still inside the period
+        request
+            .unavailable_times
+            .push((time_off.start, time_off.end));
+        let slot = self
+            .find_slot_in_window(&request)
+            .or_else(|| self.suggest_alternatives(&request).into_iter().next())?;
+        self.calendar
+            .transaction(|calendar| {
+                let slot = claim_span(calendar, &slot)?;
+                calendar.move_appointment(&appointment.appointment_id, &slot)
+            })
+            .ok()
+    }
+
+    /// Get the request an appointment was originally booked from.
+    pub fn request_for_appointment(&self, appointment_id: &str) -> Option<&AppointmentRequest> {
+        self.booked_requests.get(appointment_id)
+    }
+
+    /// Offer up to three alternative slots for a patient-initiated reschedule.
+    ///
+    /// Alternatives are the free slots nearest the original preferred time
+    /// within the original request's flexibility window. Appointments booked
+    /// outside this scheduler fall back to a window of one slot length
+    /// around the current appointment time.
+    pub fn self_reschedule_options(&self, confirmation_code: &str) -> Result<Vec<TimeSlot>, String> {
+        let appointment = self
+            .calendar
+            .find_appointment_by_code(confirmation_code)
+            .ok_or("Unknown confirmation code")?;
+
+        let request = self.booked_requests.get(&appointment.appointment_id);
+        let preferred = request.map_or(appointment.time_slot.start_time, |request| {
+            request.preferred_time
+        });
+        let window = Duration::minutes(self.calendar.default_slot_duration);
+
+        let mut candidates: Vec<TimeSlot> = self
+            .calendar
+            .available_slots()
+            .into_iter()
+            .filter(|slot| !self.is_on_offer(&slot.slot_id))
+            .filter(|slot| match request {
+                Some(request) => request.is_time_acceptable(slot),
+                None => (slot.start_time - preferred).abs() <= window,
+            })
+            .collect();
+        candidates.sort_by_key(|slot| (slot.start_time - preferred).num_seconds().abs());
+        candidates.truncate(3);
+
+        Ok(candidates)
+    }
+
+    /// Move a patient's appointment to one of their offered alternatives.
+    pub fn self_reschedule(
+        &mut self,
+        confirmation_code: &str,
+        slot_id: &str,
+    ) -> Result<Appointment, String> {
+        let options = self.self_reschedule_options(confirmation_code)?;
+        let slot = options
+            .into_iter()
+            .find(|slot| slot.slot_id == slot_id)
+            .ok_or("Selected slot is not one of the offered alternatives")?;
+        let appointment = self
+            .calendar
+            .find_appointment_by_code(confirmation_code)
+            .ok_or("Unknown confirmation code")?;
+
+        self.calendar
+            .move_appointment(&appointment.appointment_id, &slot)
+    }
+
+    /// Get requests that failed to schedule in previous queue runs.
+    pub fn unscheduled_requests(&self) -> &[AppointmentRequest] {
+        self.waitlist.requests()
+    }
+
+    /// Forget previously failed requests.
+    pub fn clear_unscheduled(&mut self) -> usize {
+        self.waitlist.clear()
+    }
+
+    /// Get the waitlist of requests waiting for a slot.
+    pub fn waitlist(&self) -> &Waitlist {
+        &self.waitlist
+    }
+
+    /// Park a request on the waitlist, e.g. after `schedule_single` failed
+    /// to book it, returning its position.
+    ///
+    /// It is booked automatically when a slot it fits is freed by
+    /// `cancel_appointment`.
+    pub fn park_request(&mut self, request: AppointmentRequest) -> usize {
+        self.waitlist.park(request)
+    }
+
+    /// Replace the waitlist with an edited CSV export, e.g. after staff
+    /// worked the call-back list in a spreadsheet during downtime.
+    pub fn import_waitlist_csv(&mut self, text: &str) -> WaitlistImport {
+        self.waitlist.import_csv(text, &self.calendar)
+    }
+
+    /// Cancel an appointment, along with the other steps of its bundle the
+    /// calendar's bundle policy covers, and fill each freed future slot from
+    /// the waitlist.
+    pub fn cancel_appointment(
+        &mut self,
+        appointment_id: &str,
+        actor: CancellationActor,
+    ) -> Result<CancellationOutcome, String> {
+        let slots: HashMap<String, TimeSlot> = self
+            .calendar
+            .appointments()
+            .into_iter()
+            .map(|apt| (apt.appointment_id, apt.time_slot))
+            .collect();
+        let policy = self.calendar.bundle_cancellation;
+        let records = self
+            .calendar
+            .cancel_with_bundle(appointment_id, actor, policy)?;
+
+        let now = self.calendar.now();
+        let mut promoted = Vec::new();
+        for record in &records {
+            self.booked_requests.remove(&record.appointment_id);
+            let Some(slot) = slots.get(&record.appointment_id) else {
+                continue;
+            };
+            if slot.start_time > now {
+                promoted.extend(self.backfill(slot));
+            }
+        }
+        Ok(CancellationOutcome { records, promoted })
+    }
+
+    /// Propose how to fill a day's remaining free slots from the waitlist.
+    ///
+    /// As many slots as possible are filled. Requests are placed most
+    /// urgent (then longest waiting) first, and a request once placed is
+    /// only ever moved to another slot to make room, never dropped, so no
+    /// patient is left out for a less urgent one. Each request is tried in
+    /// the slots nearest its preferred time of day first. Nothing is booked.
+    pub fn plan_fill_day(&self, date: NaiveDate) -> FillPlan {
+        let now = self.calendar.now();
+        let slots: Vec<TimeSlot> = self
+            .calendar
+            .available_slots()
+            .into_iter()
+            .filter(|slot| slot.start_time.date_naive() == date)
+            .filter(|slot| slot.start_time >= now && slot.is_released(now))
+            .filter(|slot| !self.is_on_offer(&slot.slot_id))
+            .collect();
+
+        let requests = self.waitlist.in_order();
+
+        let candidates: Vec<Vec<usize>> = requests
+            .iter()
+            .map(|request| {
+                let preferred = request.preferred_time.time();
+                let mut fitting: Vec<usize> = (0..slots.len())
+                    .filter(|&index| {
+                        let slot = &slots[index];
+                        fits(request, slot) && self.permits(request, slot)
+                    })
+                    .collect();
+                fitting.sort_by_key(|&index| {
+                    (slots[index].start_time.time() - preferred).num_seconds().abs()
+                });
+                fitting
+            })
+            .collect();
+
+        let mut owners: Vec<Option<usize>> = vec![None; slots.len()];
+        for request in 0..requests.len() {
+            let mut visited = vec![false; slots.len()];
+            place_request(request, &candidates, &mut owners, &mut visited);
+        }
+
+        let mut placed = vec![false; requests.len()];
+        let mut proposals = Vec::new();
+        let mut open_slots = Vec::new();
+        for (slot, owner) in slots.into_iter().zip(owners) {
+            match owner {
+                Some(request) => {
+                    placed[request] = true;
+                    proposals.push(FillProposal {
+                        request: requests[request].clone(),
+                        slot,
+                    });
+                }
+                None => open_slots.push(slot),
+            }
+        }
+        let unplaced = requests
+            .into_iter()
+            .zip(placed)
+            .filter(|(_, placed)| !placed)
+            .map(|(request, _)| request)
+            .collect();
+
+        FillPlan {
+            date,
+            proposals,
+            open_slots,
+            unplaced,
+        }
+    }
+
+    /// Book a reviewed fill plan, taking booked requests off the waitlist.
+    ///
+    /// Each proposal is booked on its own; one whose slot was taken in the
+    /// meantime, or that the rules no longer permit, is reported and the
+    /// request stays on the waitlist.
+    pub fn commit_fill(&mut self, plan: FillPlan) -> FillReport {
+        let mut report = FillReport::default();
+        for proposal in plan.proposals {
+            let request = &proposal.request;
+            if !self.permits(request, &proposal.slot) {
+                report.failed.push((
+                    proposal,
+                    "Not permitted by the business rules or doctor preferences".to_string(),
+                ));
+                continue;
+            }
+            match self.calendar.book_slot(
+                &proposal.slot,
+                request.patient.clone(),
+                request.priority,
+                request.reason.clone(),
+            ) {
+                Ok(appointment) => {
+                    self.waitlist.remove(&request.request_id);
+                    self.booked_requests
+                        .insert(appointment.appointment_id.clone(), request.clone());
+                    report.booked.push(appointment);
+                }
+                Err(e) => report.failed.push((proposal, e)),
+            }
+        }
+        report
+    }
+
+    /// Simulate adding capacity and report which failed requests would fit.
+    ///
+    /// The template is applied to a copy of the calendar for every matching
+    /// day in `[start, end]`, and all previously unscheduled requests are
+    /// re-run in priority order against it, under the same validators,
+    /// quotas, rules and batch settings, with offered slots still held and
+    /// fallback bumps counted. The real calendar is untouched, and its ID
+    /// generator is forked, so the run doesn't use up the calendar's IDs.
+    pub fn what_if_add_slots(
+        &self,
+        template: &SlotTemplate,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> WhatIfReport {
+        let mut calendar = self.calendar.clone();
+        let slots_added = calendar.apply_template(template, start, end).created.len();
+
+        let mut simulation = AppointmentScheduler::new(calendar, self.allow_fallback);
+        simulation.snap_to_grid = self.snap_to_grid;
+        simulation.processing_policy = self.processing_policy;
+        simulation.batch_mode = self.batch_mode;
+        simulation.rules = self.rules.clone();
+        simulation.strategy = Arc::clone(&self.strategy);
+        simulation.validators = self.validators.clone();
+        simulation.booked_requests = self.booked_requests.clone();
+        simulation.referral_quotas = self.referral_quotas.clone();
+        simulation.bring_forward_offers = self.bring_forward_offers.clone();
+        simulation.slot_claims = self.slot_claims.clone();
+        simulation.bumps = self.bumps.clone();
+        let result = simulation.schedule_batch(self.waitlist.requests().to_vec());
+
+        WhatIfReport {
+            slots_added,
+            evaluated_requests: result.total_requests,
+            newly_scheduled: result.confirmed,
+            still_failed: result.failed,
+        }
+    }
+
+    /// Get how often a patient was booked outside their preferred window.
+    pub fn bump_count(&self, patient_id: &str) -> usize {
+        self.bumps.get(patient_id).copied().unwrap_or(0)
+    }
+
+    /// Get patients booked outside their preference, most often bumped first.
+    pub fn bumped_patients(&self) -> Vec<(String, usize)> {
+        let mut bumps: Vec<(String, usize)> = self
+            .bumps
+            .iter()
+            .map(|(patient_id, count)| (patient_id.clone(), *count))
+            .collect();
+        bumps.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        bumps
+    }
+
+    /// Get booking and failure counts per source channel, in channel order.
+    pub fn source_stats(&self) -> Vec<(RequestSource, SourceStats)> {
+        let mut stats: Vec<(RequestSource, SourceStats)> =
+            self.source_stats.iter().map(|(source, stats)| (*source, *stats)).collect();
+        stats.sort_by_key(|(source, _)| *source);
+        stats
     }
 
     /// Get the number of pending requests in the queue.
+    ///
+    /// Returns 0 if the queue backend cannot be reached.
     pub fn get_pending_count(&self) -> usize {
+        self.request_queue.len().unwrap_or(0)
+    }
+
+    /// Get the number of pending requests, or why the queue backend could
+    /// not be reached.
+    pub fn queue_depth(&self) -> Result<usize, String> {
         self.request_queue.len()
     }
 
+    /// List pending requests in the order they will be processed.
+    pub fn pending_requests(&self) -> Result<Vec<AppointmentRequest>, String> {
+        self.request_queue.pending()
+    }
+
     /// Clear all pending requests from the queue.
-    pub fn clear_queue(&mut self) -> usize {
-        let count = self.request_queue.len();
-        self.request_queue.clear();
-        count
+    pub fn clear_queue(&mut self) -> Result<usize, String> {
+        self.request_queue.clear()
+    }
+}
+
+/// Find a slot for `request`, moving earlier placements along to other
+/// slots if that frees one up (an augmenting path).
+fn place_request(
+    request: usize,
+    candidates: &[Vec<usize>],
+    owners: &mut [Option<usize>],
+    visited: &mut [bool],
+) -> bool {
+    for &slot in &candidates[request] {
+        if visited[slot] {
+            continue;
+        }
+        visited[slot] = true;
+        let free = match owners[slot] {
+            None => true,
+            Some(owner) => place_request(owner, candidates, owners, visited),
+        };
+        if free {
+            owners[slot] = Some(request);
+            return true;
+        }
+    }
+    false
+}
+
+/// How far a slot is from the time a request prefers, in minutes.
+fn preference_cost(request: &AppointmentRequest, slot: &TimeSlot) -> i64 {
+    (slot.start_time - request.preferred_time)
+        .num_minutes()
+        .abs()
+}
+
+/// Assign each row a distinct column so the total cost is as small as
+/// possible (the Hungarian method), where `None` marks a column a row
+/// can't take. There must be at least as many columns as rows. A row is
+/// left unassigned only if every assignment would need a forbidden cell.
+fn min_cost_assignment(costs: &[Vec<Option<i64>>], columns: usize) -> Vec<Option<usize>> {
+    const FORBIDDEN: i64 = 1 << 40;
+    let rows = costs.len();
+    let cost = |row: usize, column: usize| costs[row - 1][column - 1].unwrap_or(FORBIDDEN);
+
+    // Potentials and matches are 1-based; row 0 and column 0 are sentinels
+    let mut row_potential = vec![0i64; rows + 1];
+    let mut column_potential = vec![0i64; columns + 1];
+    let mut matched = vec![0usize; columns + 1];
+    let mut previous = vec![0usize; columns + 1];
+    for row in 1..=rows {
+        matched[0] = row;
+        let mut column = 0;
+        let mut slack = vec![i64::MAX; columns + 1];
+        let mut used = vec![false; columns + 1];
+        loop {
+            used[column] = true;
+            let current = matched[column];
+            let mut delta = i64::MAX;
+            let mut next = 0;
+            for candidate in 1..=columns {
+                if used[candidate] {
+                    continue;
+                }
+                let reduced =
+                    cost(current, candidate) - row_potential[current] - column_potential[candidate];
+                if reduced < slack[candidate] {
+                    slack[candidate] = reduced;
+                    previous[candidate] = column;
+                }
+                if slack[candidate] < delta {
+                    delta = slack[candidate];
+                    next = candidate;
+                }
+            }
+            for candidate in 0..=columns {
+                if used[candidate] {
+                    row_potential[matched[candidate]] += delta;
+                    column_potential[candidate] -= delta;
+                } else {
+                    slack[candidate] -= delta;
+                }
+            }
+            column = next;
+            if matched[column] == 0 {
+                break;
+            }
+        }
+        while column != 0 {
+            let before = previous[column];
+            matched[column] = matched[before];
+            column = before;
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for column in 1..=columns {
+        let row = matched[column];
+        if row != 0 && costs[row - 1][column - 1].is_some() {
+            assignment[row - 1] = Some(column - 1);
+        }
+    }
+    assignment
+}
+
+/// Merge the slots under a span from `DoctorCalendar::free_spans` so it can
+/// be booked; a plain slot is returned as it is.
+fn claim_span(calendar: &mut DoctorCalendar, slot: &TimeSlot) -> Result<TimeSlot, String> {
+    match calendar.slot_by_id(&slot.slot_id) {
+        Some(stored) if stored.end_time != slot.end_time => {
+            calendar.merge_slots(&slot.slot_id, slot.duration_minutes())
+        }
+        _ => Ok(slot.clone()),
     }
 }
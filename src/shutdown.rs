@@ -0,0 +1,96 @@
+#![allow(dead_code)]
+//! Flushing a long-running service's state before it exits.
+//!
+//! A server or daemon built on the library keeps its scheduler in memory.
+//! When it is asked to stop, e.g. on SIGINT or SIGTERM, its signal handler
+//! sets a ShutdownFlag; the service finishes the booking it is working on,
+//! stops taking new ones once it sees the flag, and calls `shutdown`. That
+//! saves the calendar and its event log to the store before anything else,
+//! so a confirmed booking is never lost, then publishes pending events and
+//! attempts every queued notification once more.
+
+use crate::events::EventPublisher;
+use crate::notifications::{DeliveryQueue, DeliveryStats, NotificationChannel};
+use crate::scheduler::AppointmentScheduler;
+use crate::store::{save_calendar, CalendarStore};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Flag a signal handler sets to ask the service to stop.
+///
+/// Clones share the flag, so one can be moved into the handler while the
+/// service loop checks another.
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownFlag(Arc<AtomicBool>);
+
+impl ShutdownFlag {
+    /// Create a flag that has not been raised.
+    pub fn new() -> Self {
+        ShutdownFlag::default()
+    }
+
+    /// Ask the service to stop.
+    pub fn request(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    /// Check whether a stop has been requested.
+    pub fn is_requested(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// What was flushed on shutdown.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub events_published: usize,
+    /// Events the publisher refused; they stay in the saved outbox.
+    pub events_unpublished: usize,
+    pub deliveries: DeliveryStats,
+    /// Notifications still queued for a retry, which are dropped on exit.
+    pub notifications_pending: usize,
+    /// Requests waiting for the next batch run, or `None` if the queue
+    /// could not be read.
+    pub requests_queued: Option<usize>,
+}
+
+/// Save the scheduler's calendar, publish its events and send queued
+/// notifications, in that order.
+///
+/// Fails only if the calendar can't be saved. Events the publisher refuses
+/// are kept in the saved outbox for the next start; the calendar is saved
+/// a second time so the store knows which events went out.
+pub fn shutdown(
+    scheduler: &mut AppointmentScheduler,
+    store: &mut dyn CalendarStore,
+    publisher: &mut dyn EventPublisher,
+    deliveries: &mut DeliveryQueue,
+    channel: &mut dyn NotificationChannel,
+) -> Result<ShutdownReport, String> {
+    save_calendar(store, &mut scheduler.calendar)?;
+
+    let events_published = scheduler.calendar.dispatch_outbox(publisher);
+    let events_unpublished = scheduler.calendar.undispatched_events().len();
+    if events_published > 0 {
+        save_calendar(store, &mut scheduler.calendar)?;
+    }
+
+    // Try every notification now, even those still backing off.
+    let now = deliveries
+        .pending()
+        .iter()
+        .map(|delivery| delivery.next_attempt_at)
+        .fold(scheduler.calendar.now(), |latest, at| latest.max(at));
+    let stats = deliveries.process_due(channel, now);
+
+    Ok(ShutdownReport {
+        events_published,
+        events_unpublished,
+        deliveries: stats,
+        notifications_pending: deliveries.pending().len(),
+        requests_queued: scheduler
+            .pending_requests()
+            .ok()
+            .map(|requests| requests.len()),
+    })
+}
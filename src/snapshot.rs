@@ -0,0 +1,129 @@
+#![allow(dead_code)]
+//! Serializable snapshots of a doctor's calendar.
+//!
+//! A CalendarSnapshot captures the full state of a DoctorCalendar so it can
+//! be written out and restored later. Snapshots can be encoded as JSON for
+//! readability or as MessagePack, which is considerably smaller for large
+//! multi-year calendars and better suited to mobile clients.
+
+use crate::bundle::BundleCancellation;
+use crate::caselist::CaseList;
+use crate::calendar::{BlockedPeriod, SlotTemplate};
+use crate::events::OutboxEntry;
+use crate::ics::BusyTime;
+use crate::labels::LabelScheme;
+use crate::models::{
+    Appointment, AppointmentChange, CancellationPolicy, CancellationRecord, FeeSchedule, TimeSlot,
+};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet};
+use serde::{Deserialize, Serialize};
+
+/// Wire format used to encode a snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotFormat {
+    Json,
+    MessagePack,
+}
+
+impl SnapshotFormat {
+    /// Convert a string to a SnapshotFormat value.
+    pub fn from_string(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().trim() {
+            "json" => Ok(SnapshotFormat::Json),
+            "msgpack" | "messagepack" => Ok(SnapshotFormat::MessagePack),
+            _ => Err(format!(
+                "Invalid snapshot format: '{}'. Must be one of: json, msgpack",
+                value
+            )),
+        }
+    }
+
+    /// MIME type to use when sending this format over the network.
+    pub fn content_type(&self) -> &str {
+        match self {
+            SnapshotFormat::Json => "application/json",
+            SnapshotFormat::MessagePack => "application/msgpack",
+        }
+    }
+}
+
+/// Point-in-time copy of a calendar's state.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarSnapshot {
+    pub doctor_name: String,
+    pub doctor_id: String,
+    pub default_slot_duration: i64,
+    pub feed_token: String,
+    pub time_slots: Vec<TimeSlot>,
+    pub appointments: Vec<Appointment>,
+    #[serde(default)]
+    pub outbox: Vec<OutboxEntry>,
+    #[serde(default)]
+    pub next_event_sequence: u64,
+    #[serde(default)]
+    pub dispatched_through: u64,
+    #[serde(default)]
+    pub logged_through: u64,
+    #[serde(default)]
+    pub history: BTreeMap<String, Vec<AppointmentChange>>,
+    #[serde(default)]
+    pub cancellation_policy: Option<CancellationPolicy>,
+    #[serde(default)]
+    pub cancellations: Vec<CancellationRecord>,
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub specialty: Option<String>,
+    #[serde(default)]
+    pub templates: Vec<SlotTemplate>,
+    #[serde(default)]
+    pub labels: LabelScheme,
+    #[serde(default)]
+    pub locked_days: BTreeSet<NaiveDate>,
+    #[serde(default)]
+    pub lock_past_days: bool,
+    #[serde(default)]
+    pub require_confirmation: bool,
+    #[serde(default)]
+    pub bundle_cancellation: BundleCancellation,
+    #[serde(default)]
+    pub day_notes: BTreeMap<NaiveDate, Vec<String>>,
+    #[serde(default)]
+    pub case_lists: BTreeMap<NaiveDate, CaseList>,
+    #[serde(default)]
+    pub busy_times: Vec<BusyTime>,
+    #[serde(default)]
+    pub blocked_periods: Vec<BlockedPeriod>,
+    /// Doctor preferences in the `DoctorPreferences::parse` line syntax.
+    #[serde(default)]
+    pub preferences: Vec<String>,
+    #[serde(default)]
+    pub horizon_weeks: Option<u32>,
+    #[serde(default)]
+    pub generated_until: Option<NaiveDate>,
+}
+
+impl CalendarSnapshot {
+    /// Encode the snapshot in the given format.
+    pub fn encode(&self, format: SnapshotFormat) -> Result<Vec<u8>, String> {
+        match format {
+            SnapshotFormat::Json => serde_json::to_vec(self)
+                .map_err(|e| format!("Failed to encode snapshot as JSON: {}", e)),
+            SnapshotFormat::MessagePack => rmp_serde::to_vec_named(self)
+                .map_err(|e| format!("Failed to encode snapshot as MessagePack: {}", e)),
+        }
+    }
+
+    /// Decode a snapshot previously produced by `encode`.
+    pub fn decode(bytes: &[u8], format: SnapshotFormat) -> Result<Self, String> {
+        match format {
+            SnapshotFormat::Json => serde_json::from_slice(bytes)
+                .map_err(|e| format!("Failed to decode JSON snapshot: {}", e)),
+            SnapshotFormat::MessagePack => rmp_serde::from_slice(bytes)
+                .map_err(|e| format!("Failed to decode MessagePack snapshot: {}", e)),
+        }
+    }
+}
@@ -0,0 +1,423 @@
+#![allow(dead_code)]
+//! SQLite-backed storage (enabled with the `sqlite` feature).
+//!
+//! Doctors, slots, appointments and pending requests each get a table, so
+//! a small clinic can keep its schedule in one database file and inspect
+//! it with ordinary SQL tools. Slots and appointments are stored one row
+//! each, keyed by ID, with the full record as JSON alongside the columns
+//! worth querying. `book_slot` and `cancel_appointment` write their rows in
+//! the same database transaction as the calendar change, so a failed write
+//! leaves both the file and the in-memory calendar as they were.
+
+use crate::calendar::DoctorCalendar;
+use crate::events::OutboxEntry;
+use crate::models::{
+    Appointment, AppointmentRequest, AppointmentStatus, CancellationActor, CancellationRecord,
+    Patient, Priority, TimeSlot,
+};
+use crate::queue::{queue_score, RequestQueue};
+use crate::snapshot::CalendarSnapshot;
+use crate::store::CalendarStore;
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS doctors (
+        doctor_id TEXT PRIMARY KEY,
+        doctor_name TEXT NOT NULL,
+        settings TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS slots (
+        slot_id TEXT PRIMARY KEY,
+        doctor_id TEXT NOT NULL REFERENCES doctors (doctor_id),
+        start_time TEXT NOT NULL,
+        end_time TEXT NOT NULL,
+        is_available INTEGER NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS appointments (
+        appointment_id TEXT PRIMARY KEY,
+        doctor_id TEXT NOT NULL REFERENCES doctors (doctor_id),
+        slot_id TEXT NOT NULL,
+        patient_id TEXT NOT NULL,
+        start_time TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS pending_requests (
+        request_id TEXT PRIMARY KEY,
+        queue TEXT NOT NULL,
+        score REAL NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        doctor_id TEXT NOT NULL,
+        sequence INTEGER NOT NULL,
+        occurred_at TEXT NOT NULL,
+        data TEXT NOT NULL,
+        PRIMARY KEY (doctor_id, sequence)
+    );
+    CREATE INDEX IF NOT EXISTS slots_by_doctor ON slots (doctor_id, start_time);
+    CREATE INDEX IF NOT EXISTS appointments_by_doctor ON appointments (doctor_id, start_time);
+    CREATE INDEX IF NOT EXISTS pending_by_score ON pending_requests (queue, score);
+";
+
+/// Calendar store kept in a SQLite database.
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open (or create) a database file and set up its tables.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        SqliteStore::with_connection(conn)
+    }
+
+    /// Use a private in-memory database.
+    pub fn open_in_memory() -> Result<Self, String> {
+        let conn = Connection::open_in_memory().map_err(sql_error)?;
+        SqliteStore::with_connection(conn)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self, String> {
+        conn.execute_batch(SCHEMA).map_err(sql_error)?;
+        Ok(SqliteStore { conn })
+    }
+
+    /// Book a slot and store the booking, all or nothing.
+    pub fn book_slot(
+        &mut self,
+        calendar: &mut DoctorCalendar,
+        slot: &TimeSlot,
+        patient: Patient,
+        priority: Priority,
+        reason: String,
+    ) -> Result<Appointment, String> {
+        let conn = &mut self.conn;
+        calendar.transaction(|calendar| {
+            let appointment = calendar.book_slot(slot, patient, priority, reason)?;
+            let tx = conn.transaction().map_err(sql_error)?;
+            write_doctor(&tx, &calendar.snapshot())?;
+            if let Some(slot) = calendar.slot_by_id(&appointment.time_slot.slot_id) {
+                write_slot(&tx, &calendar.doctor_id, slot)?;
+            }
+            write_appointment(&tx, &calendar.doctor_id, &appointment)?;
+            write_new_events(&tx, &calendar.doctor_id, calendar.outbox())?;
+            tx.commit().map_err(sql_error)?;
+            calendar.mark_events_logged();
+            Ok(appointment)
+        })
+    }
+
+    /// Cancel an appointment and store the cancellation, all or nothing.
+    ///
+    /// Other steps of a bundle cancelled along with it are stored as
+    /// cancelled too.
+    pub fn cancel_appointment(
+        &mut self,
+        calendar: &mut DoctorCalendar,
+        appointment_id: &str,
+        actor: CancellationActor,
+    ) -> Result<CancellationRecord, String> {
+        let conn = &mut self.conn;
+        calendar.transaction(|calendar| {
+            let before: Vec<Appointment> = calendar.appointments();
+            let record = calendar.cancel_appointment_by(appointment_id, actor)?;
+            let tx = conn.transaction().map_err(sql_error)?;
+            write_doctor(&tx, &calendar.snapshot())?;
+            let cancelled = before.iter().filter_map(|apt| {
+                calendar
+                    .get_appointment_by_id(&apt.appointment_id)
+                    .filter(|apt| apt.status == AppointmentStatus::Cancelled)
+            });
+            for apt in cancelled {
+                write_appointment(&tx, &calendar.doctor_id, &apt)?;
+                if let Some(slot) = calendar.slot_by_id(&apt.time_slot.slot_id) {
+                    write_slot(&tx, &calendar.doctor_id, slot)?;
+                }
+            }
+            write_new_events(&tx, &calendar.doctor_id, calendar.outbox())?;
+            tx.commit().map_err(sql_error)?;
+            calendar.mark_events_logged();
+            Ok(record)
+        })
+    }
+}
+
+impl CalendarStore for SqliteStore {
+    fn load(&self, doctor_id: &str) -> Result<Option<CalendarSnapshot>, String> {
+        let settings: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT settings FROM doctors WHERE doctor_id = ?1",
+                params![doctor_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        let Some(settings) = settings else {
+            return Ok(None);
+        };
+
+        let mut snapshot: CalendarSnapshot = from_json(&settings)?;
+        snapshot.time_slots = self.rows(
+            "SELECT data FROM slots WHERE doctor_id = ?1 ORDER BY start_time",
+            doctor_id,
+        )?;
+        snapshot.appointments = self.rows(
+            "SELECT data FROM appointments WHERE doctor_id = ?1 ORDER BY start_time",
+            doctor_id,
+        )?;
+        Ok(Some(snapshot))
+    }
+
+    fn save(&mut self, snapshot: &CalendarSnapshot) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        write_doctor(&tx, snapshot)?;
+        tx.execute(
+            "DELETE FROM slots WHERE doctor_id = ?1",
+            params![snapshot.doctor_id],
+        )
+        .map_err(sql_error)?;
+        tx.execute(
+            "DELETE FROM appointments WHERE doctor_id = ?1",
+            params![snapshot.doctor_id],
+        )
+        .map_err(sql_error)?;
+        for slot in &snapshot.time_slots {
+            write_slot(&tx, &snapshot.doctor_id, slot)?;
+        }
+        for appointment in &snapshot.appointments {
+            write_appointment(&tx, &snapshot.doctor_id, appointment)?;
+        }
+        tx.commit().map_err(sql_error)
+    }
+
+    fn append_events(&mut self, doctor_id: &str, entries: &[OutboxEntry]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        for entry in entries {
+            write_event(&tx, doctor_id, entry)?;
+        }
+        tx.commit().map_err(sql_error)
+    }
+
+    fn events(&self, doctor_id: &str) -> Result<Vec<OutboxEntry>, String> {
+        self.rows(
+            "SELECT data FROM events WHERE doctor_id = ?1 ORDER BY sequence",
+            doctor_id,
+        )
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        self.conn
+            .query_row("SELECT 1", [], |row| row.get::<_, i64>(0))
+            .map(|_| ())
+            .map_err(sql_error)
+    }
+}
+
+impl SqliteStore {
+    fn rows<T: DeserializeOwned>(&self, sql: &str, doctor_id: &str) -> Result<Vec<T>, String> {
+        let mut statement = self.conn.prepare(sql).map_err(sql_error)?;
+        let rows = statement
+            .query_map(params![doctor_id], |row| row.get::<_, String>(0))
+            .map_err(sql_error)?;
+        rows.map(|row| from_json(&row.map_err(sql_error)?))
+            .collect()
+    }
+}
+
+/// Pending-request queue kept in a SQLite table.
+///
+/// Several scheduler processes can share one database file; SQLite
+/// serializes their writes.
+pub struct SqliteQueue {
+    conn: Connection,
+    name: String,
+}
+
+impl SqliteQueue {
+    /// Open the queue with this name in a database file.
+    ///
+    /// Queues with different names are kept apart, e.g. one per doctor.
+    pub fn open(path: impl AsRef<Path>, name: &str) -> Result<Self, String> {
+        let store = SqliteStore::open(path)?;
+        Ok(SqliteQueue {
+            conn: store.conn,
+            name: name.to_string(),
+        })
+    }
+}
+
+impl RequestQueue for SqliteQueue {
+    fn push(&mut self, request: AppointmentRequest) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO pending_requests (request_id, queue, score, data)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![
+                    request.request_id,
+                    self.name,
+                    queue_score(&request),
+                    to_json(&request)?
+                ],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<Option<AppointmentRequest>, String> {
+        let tx = self.conn.transaction().map_err(sql_error)?;
+        let next: Option<(String, String)> = tx
+            .query_row(
+                "SELECT request_id, data FROM pending_requests WHERE queue = ?1
+                 ORDER BY score LIMIT 1",
+                params![self.name],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        let Some((request_id, data)) = next else {
+            return Ok(None);
+        };
+        tx.execute(
+            "DELETE FROM pending_requests WHERE request_id = ?1",
+            params![request_id],
+        )
+        .map_err(sql_error)?;
+        tx.commit().map_err(sql_error)?;
+        from_json(&data).map(Some)
+    }
+
+    fn len(&self) -> Result<usize, String> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*) FROM pending_requests WHERE queue = ?1",
+                params![self.name],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .map_err(sql_error)
+    }
+
+    fn clear(&mut self) -> Result<usize, String> {
+        self.conn
+            .execute(
+                "DELETE FROM pending_requests WHERE queue = ?1",
+                params![self.name],
+            )
+            .map_err(sql_error)
+    }
+
+    fn pending(&self) -> Result<Vec<AppointmentRequest>, String> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT data FROM pending_requests WHERE queue = ?1 ORDER BY score")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map(params![self.name], |row| row.get::<_, String>(0))
+            .map_err(sql_error)?;
+        rows.map(|row| from_json(&row.map_err(sql_error)?))
+            .collect()
+    }
+}
+
+/// Store the doctor's settings: the snapshot without its slots and
+/// appointments, which have tables of their own.
+fn write_doctor(tx: &Transaction, snapshot: &CalendarSnapshot) -> Result<(), String> {
+    let mut settings = snapshot.clone();
+    settings.time_slots.clear();
+    settings.appointments.clear();
+    tx.execute(
+        "INSERT OR REPLACE INTO doctors (doctor_id, doctor_name, settings) VALUES (?1, ?2, ?3)",
+        params![
+            snapshot.doctor_id,
+            snapshot.doctor_name,
+            to_json(&settings)?
+        ],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+fn write_slot(tx: &Transaction, doctor_id: &str, slot: &TimeSlot) -> Result<(), String> {
+    tx.execute(
+        "INSERT OR REPLACE INTO slots (slot_id, doctor_id, start_time, end_time, is_available, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            slot.slot_id,
+            doctor_id,
+            slot.start_time.to_rfc3339(),
+            slot.end_time.to_rfc3339(),
+            slot.is_available,
+            to_json(slot)?
+        ],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+fn write_appointment(
+    tx: &Transaction,
+    doctor_id: &str,
+    appointment: &Appointment,
+) -> Result<(), String> {
+    tx.execute(
+        "INSERT OR REPLACE INTO appointments
+         (appointment_id, doctor_id, slot_id, patient_id, start_time, data)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            appointment.appointment_id,
+            doctor_id,
+            appointment.time_slot.slot_id,
+            appointment.patient.patient_id,
+            appointment.time_slot.start_time.to_rfc3339(),
+            to_json(appointment)?
+        ],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+fn write_event(tx: &Transaction, doctor_id: &str, entry: &OutboxEntry) -> Result<(), String> {
+    tx.execute(
+        "INSERT OR IGNORE INTO events (doctor_id, sequence, occurred_at, data)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![
+            doctor_id,
+            entry.sequence as i64,
+            entry.occurred_at.to_rfc3339(),
+            to_json(entry)?
+        ],
+    )
+    .map_err(sql_error)?;
+    Ok(())
+}
+
+/// Log the outbox entries the events table doesn't have yet.
+fn write_new_events(
+    tx: &Transaction,
+    doctor_id: &str,
+    outbox: &[OutboxEntry],
+) -> Result<(), String> {
+    for entry in outbox {
+        write_event(tx, doctor_id, entry)?;
+    }
+    Ok(())
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| format!("Failed to encode row: {}", e))
+}
+
+fn from_json<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    serde_json::from_str(text).map_err(|e| format!("Failed to decode row: {}", e))
+}
+
+fn sql_error(error: rusqlite::Error) -> String {
+    format!("SQLite error: {}", error)
+}
@@ -0,0 +1,233 @@
+#![allow(dead_code)]
+//! Storage backends for doctors' calendars.
+//!
+//! A CalendarStore keeps each doctor's latest calendar snapshot together
+//! with an append-only log of the events the calendar has emitted. The
+//! calendar and scheduler never talk to a store directly: callers save a
+//! snapshot after a change and load it on start-up, so a database backend
+//! only needs to implement this trait.
+
+use crate::calendar::DoctorCalendar;
+use crate::events::OutboxEntry;
+use crate::snapshot::{CalendarSnapshot, SnapshotFormat};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Persistent storage for calendar snapshots and their event logs.
+pub trait CalendarStore {
+    /// Load a doctor's latest snapshot, or `None` if nothing is stored.
+    fn load(&self, doctor_id: &str) -> Result<Option<CalendarSnapshot>, String>;
+
+    /// Store a snapshot, replacing the doctor's previous one.
+    fn save(&mut self, snapshot: &CalendarSnapshot) -> Result<(), String>;
+
+    /// Add entries to the end of a doctor's event log.
+    fn append_events(&mut self, doctor_id: &str, entries: &[OutboxEntry]) -> Result<(), String>;
+
+    /// Read a doctor's event log, oldest first.
+    fn events(&self, doctor_id: &str) -> Result<Vec<OutboxEntry>, String>;
+
+    /// Check that the store can be reached, for health checks.
+    ///
+    /// Stores with nothing to connect to are always reachable.
+    fn ping(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Save a calendar's snapshot and log any events not logged before.
+///
+/// Events are matched by sequence number, so saving the same calendar
+/// twice doesn't log its events twice. The calendar keeps events until
+/// they are logged, so events already published with `dispatch_outbox`
+/// are logged too.
+pub fn save_calendar(
+    store: &mut dyn CalendarStore,
+    calendar: &mut DoctorCalendar,
+) -> Result<(), String> {
+    let last_logged = store
+        .events(&calendar.doctor_id)?
+        .last()
+        .map_or(0, |entry| entry.sequence);
+    let new_events: Vec<OutboxEntry> = calendar
+        .outbox()
+        .iter()
+        .filter(|entry| entry.sequence > last_logged)
+        .cloned()
+        .collect();
+
+    store.save(&calendar.snapshot())?;
+    if !new_events.is_empty() {
+        store.append_events(&calendar.doctor_id, &new_events)?;
+    }
+    calendar.mark_events_logged();
+    Ok(())
+}
+
+/// Rebuild a doctor's calendar from its stored snapshot, if there is one.
+pub fn load_calendar(
+    store: &dyn CalendarStore,
+    doctor_id: &str,
+) -> Result<Option<DoctorCalendar>, String> {
+    store
+        .load(doctor_id)?
+        .map(DoctorCalendar::from_snapshot)
+        .transpose()
+}
+
+/// Store that keeps everything in process memory.
+#[derive(Debug, Default)]
+pub struct InMemoryStore {
+    snapshots: HashMap<String, CalendarSnapshot>,
+    events: HashMap<String, Vec<OutboxEntry>>,
+}
+
+impl InMemoryStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        InMemoryStore::default()
+    }
+}
+
+impl CalendarStore for InMemoryStore {
+    fn load(&self, doctor_id: &str) -> Result<Option<CalendarSnapshot>, String> {
+        Ok(self.snapshots.get(doctor_id).cloned())
+    }
+
+    fn save(&mut self, snapshot: &CalendarSnapshot) -> Result<(), String> {
+        self.snapshots
+            .insert(snapshot.doctor_id.clone(), snapshot.clone());
+        Ok(())
+    }
+
+    fn append_events(&mut self, doctor_id: &str, entries: &[OutboxEntry]) -> Result<(), String> {
+        self.events
+            .entry(doctor_id.to_string())
+            .or_default()
+            .extend_from_slice(entries);
+        Ok(())
+    }
+
+    fn events(&self, doctor_id: &str) -> Result<Vec<OutboxEntry>, String> {
+        Ok(self.events.get(doctor_id).cloned().unwrap_or_default())
+    }
+}
+
+/// Store that keeps one snapshot file and one event log per doctor in a
+/// directory.
+///
+/// Snapshots are written to a temporary file and renamed into place, so a
+/// crash mid-save leaves the previous snapshot intact. The event log holds
+/// one JSON entry per line.
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    dir: PathBuf,
+    format: SnapshotFormat,
+}
+
+impl FileStore {
+    /// Use a directory for storage, creating it if needed.
+    pub fn new(dir: impl AsRef<Path>, format: SnapshotFormat) -> Result<Self, String> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        Ok(FileStore { dir, format })
+    }
+
+    fn snapshot_path(&self, doctor_id: &str) -> Result<PathBuf, String> {
+        let extension = match self.format {
+            SnapshotFormat::Json => "json",
+            SnapshotFormat::MessagePack => "msgpack",
+        };
+        Ok(self
+            .dir
+            .join(format!("{}.{}", file_stem(doctor_id)?, extension)))
+    }
+
+    fn events_path(&self, doctor_id: &str) -> Result<PathBuf, String> {
+        Ok(self
+            .dir
+            .join(format!("{}.events.jsonl", file_stem(doctor_id)?)))
+    }
+}
+
+impl CalendarStore for FileStore {
+    fn load(&self, doctor_id: &str) -> Result<Option<CalendarSnapshot>, String> {
+        let path = self.snapshot_path(doctor_id)?;
+        if !path.exists() {
+            return Ok(None);
+        }
+        let bytes =
+            fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        CalendarSnapshot::decode(&bytes, self.format).map(Some)
+    }
+
+    fn save(&mut self, snapshot: &CalendarSnapshot) -> Result<(), String> {
+        let path = self.snapshot_path(&snapshot.doctor_id)?;
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, snapshot.encode(self.format)?)
+            .map_err(|e| format!("Failed to write {}: {}", temp.display(), e))?;
+        fs::rename(&temp, &path).map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+
+    fn append_events(&mut self, doctor_id: &str, entries: &[OutboxEntry]) -> Result<(), String> {
+        let path = self.events_path(doctor_id)?;
+        let mut lines = String::new();
+        for entry in entries {
+            let line = serde_json::to_string(entry)
+                .map_err(|e| format!("Failed to encode event {}: {}", entry.sequence, e))?;
+            lines.push_str(&line);
+            lines.push('\n');
+        }
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .map_err(|e| format!("Failed to append to {}: {}", path.display(), e))
+    }
+
+    fn events(&self, doctor_id: &str) -> Result<Vec<OutboxEntry>, String> {
+        let path = self.events_path(doctor_id)?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line)
+                    .map_err(|e| format!("{} line {}: {}", path.display(), index + 1, e))
+            })
+            .collect()
+    }
+
+    fn ping(&self) -> Result<(), String> {
+        let metadata = fs::metadata(&self.dir)
+            .map_err(|e| format!("Failed to read {}: {}", self.dir.display(), e))?;
+        if !metadata.is_dir() {
+            return Err(format!("{} is not a directory", self.dir.display()));
+        }
+        Ok(())
+    }
+}
+
+/// Check that a doctor ID is safe to use as a file name.
+fn file_stem(doctor_id: &str) -> Result<&str, String> {
+    let valid = !doctor_id.is_empty()
+        && doctor_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(doctor_id)
+    } else {
+        Err(format!(
+            "Doctor ID '{}' cannot be used as a file name",
+            doctor_id
+        ))
+    }
+}
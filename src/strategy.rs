@@ -0,0 +1,91 @@
+#![allow(dead_code)]
+//! Strategies for choosing between acceptable slots.
+//!
+//! When several free slots fit a request's window, the scheduler asks its
+//! SlotStrategy which to book. The default books the slot nearest the
+//! preferred time; clinics can instead keep the doctor's day compact.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{AppointmentRequest, TimeSlot};
+use std::sync::Arc;
+
+/// Picks one slot from the candidates fitting a request.
+pub trait SlotStrategy: Send + Sync {
+    /// Short name used in reports.
+    fn name(&self) -> &str;
+
+    /// Choose a slot from `candidates`, all of which fit the request.
+    fn choose(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        candidates: Vec<TimeSlot>,
+    ) -> Option<TimeSlot>;
+}
+
+/// Books the slot nearest the preferred time.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NearestPreferred;
+
+impl SlotStrategy for NearestPreferred {
+    fn name(&self) -> &str {
+        "nearest-preferred"
+    }
+
+    fn choose(
+        &self,
+        _calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        candidates: Vec<TimeSlot>,
+    ) -> Option<TimeSlot> {
+        candidates
+            .into_iter()
+            .min_by_key(|slot| distance_seconds(request, slot))
+    }
+}
+
+/// Prefers slots next to existing bookings to keep the doctor's day compact.
+///
+/// Among adjacent slots, and when none are adjacent, the one nearest the
+/// preferred time wins.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CompactDay;
+
+impl SlotStrategy for CompactDay {
+    fn name(&self) -> &str {
+        "compact-day"
+    }
+
+    fn choose(
+        &self,
+        calendar: &DoctorCalendar,
+        request: &AppointmentRequest,
+        candidates: Vec<TimeSlot>,
+    ) -> Option<TimeSlot> {
+        let booked = calendar.unavailable_slots();
+        candidates.into_iter().min_by_key(|slot| {
+            let adjacent = booked.iter().any(|other| {
+                other.end_time == slot.start_time || other.start_time == slot.end_time
+            });
+            (!adjacent, distance_seconds(request, slot))
+        })
+    }
+}
+
+/// Look up a built-in strategy by the name it reports.
+pub fn strategy_by_name(name: &str) -> Result<Arc<dyn SlotStrategy>, String> {
+    match name.trim() {
+        "nearest-preferred" => Ok(Arc::new(NearestPreferred)),
+        "compact-day" => Ok(Arc::new(CompactDay)),
+        _ => Err(format!(
+            "Unknown slot strategy: '{}'. Must be one of: nearest-preferred, compact-day",
+            name
+        )),
+    }
+}
+
+fn distance_seconds(request: &AppointmentRequest, slot: &TimeSlot) -> i64 {
+    (slot.start_time - request.preferred_time)
+        .num_seconds()
+        .abs()
+}
@@ -0,0 +1,142 @@
+#![allow(dead_code)]
+//! Swapping sessions between two doctors.
+//!
+//! When Dr. A covers Dr. B's Thursday afternoon and B takes A's Tuesday
+//! morning in return, each session's slots move to the other doctor's
+//! calendar. Free slots simply move. Booked slots move with their
+//! appointment, which keeps its time and records the handover in its
+//! history. Anything that would clash with the receiving doctor's own
+//! slots stays where it is and is reported for staff to sort out.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{Appointment, TimeSlot};
+use chrono::{DateTime, Local};
+
+/// A block of a doctor's time, `[start, end)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+}
+
+impl Session {
+    /// Create a session with validation.
+    pub fn new(start: DateTime<Local>, end: DateTime<Local>) -> Result<Self, String> {
+        if end <= start {
+            return Err("Session end must be after its start".to_string());
+        }
+        Ok(Session { start, end })
+    }
+
+    fn contains(&self, slot: &TimeSlot) -> bool {
+        slot.start_time < self.end && slot.end_time > self.start
+    }
+}
+
+/// What happened to the slots of one side of a swap.
+#[derive(Debug, Clone, Default)]
+pub struct HandoverReport {
+    /// Free slots now in the other doctor's calendar.
+    pub moved_slots: Vec<TimeSlot>,
+    /// Appointments now with the other doctor.
+    pub reassigned: Vec<Appointment>,
+    /// Free slots kept because the other doctor already has a slot then.
+    pub kept_slots: Vec<TimeSlot>,
+    /// Appointments left with their doctor, with the reason.
+    pub flagged: Vec<(Appointment, String)>,
+}
+
+/// Outcome of swapping two sessions.
+#[derive(Debug, Clone, Default)]
+pub struct SwapReport {
+    /// The first doctor's session, handed to the second doctor.
+    pub first_to_second: HandoverReport,
+    /// The second doctor's session, handed to the first doctor.
+    pub second_to_first: HandoverReport,
+}
+
+impl SwapReport {
+    /// Get every appointment that still needs attention.
+    pub fn flagged(&self) -> Vec<&(Appointment, String)> {
+        self.first_to_second
+            .flagged
+            .iter()
+            .chain(&self.second_to_first.flagged)
+            .collect()
+    }
+}
+
+/// Swap `first_session` of the first doctor with `second_session` of the second.
+///
+/// Both sessions' slots are chosen before anything moves, so each slot
+/// moves at most once.
+pub fn swap_sessions(
+    first: &mut DoctorCalendar,
+    first_session: Session,
+    second: &mut DoctorCalendar,
+    second_session: Session,
+) -> Result<SwapReport, String> {
+    if first.doctor_id == second.doctor_id {
+        return Err("Cannot swap sessions with the same doctor".to_string());
+    }
+
+    let first_slots = session_slots(first, first_session);
+    let second_slots = session_slots(second, second_session);
+
+    Ok(SwapReport {
+        first_to_second: hand_over(first, second, first_slots),
+        second_to_first: hand_over(second, first, second_slots),
+    })
+}
+
+/// Hand one doctor's session to another without anything in return.
+pub fn cover_session(
+    from: &mut DoctorCalendar,
+    to: &mut DoctorCalendar,
+    session: Session,
+) -> Result<HandoverReport, String> {
+    if from.doctor_id == to.doctor_id {
+        return Err("Cannot hand a session to the same doctor".to_string());
+    }
+    let slots = session_slots(from, session);
+    Ok(hand_over(from, to, slots))
+}
+
+fn session_slots(calendar: &DoctorCalendar, session: Session) -> Vec<TimeSlot> {
+    calendar
+        .time_slots()
+        .into_iter()
+        .filter(|slot| session.contains(slot))
+        .collect()
+}
+
+fn hand_over(
+    from: &mut DoctorCalendar,
+    to: &mut DoctorCalendar,
+    slots: Vec<TimeSlot>,
+) -> HandoverReport {
+    let mut report = HandoverReport::default();
+
+    for slot in slots {
+        let booking = from
+            .appointments()
+            .into_iter()
+            .find(|apt| apt.time_slot.slot_id == slot.slot_id);
+
+        match booking {
+            Some(appointment) => match from.transfer_appointment(&appointment.appointment_id, to) {
+                Ok(appointment) => report.reassigned.push(appointment),
+                Err(reason) => report.flagged.push((appointment, reason)),
+            },
+            None => match to.add_time_slot(slot.clone()) {
+                Ok(()) => {
+                    from.remove_time_slot(&slot.slot_id);
+                    report.moved_slots.push(slot);
+                }
+                Err(_) => report.kept_slots.push(slot),
+            },
+        }
+    }
+
+    report
+}
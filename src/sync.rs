@@ -0,0 +1,190 @@
+#![allow(dead_code)]
+//! Syncing intermittently connected clients with a doctor's calendar.
+//!
+//! A tablet at a satellite clinic may go hours without a connection. When it
+//! reconnects it asks for the changes since the last version it saw, the
+//! sequence number of the last event it applied, and pushes the bookings it
+//! took while offline. An offline booking whose slot was taken or removed in
+//! the meantime comes back as a conflict with nearby free slots to offer
+//! instead. A push retried after a lost reply finds its bookings already
+//! made and does not book them twice.
+
+use crate::boundary::{self, InputError};
+use crate::calendar::DoctorCalendar;
+use crate::events::OutboxEntry;
+use crate::models::{Appointment, Patient, Priority, TimeSlot};
+use crate::store::CalendarStore;
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+/// Events a client has not seen yet, oldest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChangeSet {
+    pub doctor_id: String,
+    /// Version to ask for changes since next time.
+    pub version: u64,
+    pub changes: Vec<OutboxEntry>,
+}
+
+/// Get the events logged after version `since`.
+///
+/// Only events saved to the store are included, so save the calendar with
+/// `save_calendar` before answering clients.
+pub fn changes_since(
+    store: &dyn CalendarStore,
+    doctor_id: &str,
+    since: u64,
+) -> Result<ChangeSet, String> {
+    let changes: Vec<OutboxEntry> = store
+        .events(doctor_id)?
+        .into_iter()
+        .filter(|entry| entry.sequence > since)
+        .collect();
+    Ok(ChangeSet {
+        doctor_id: doctor_id.to_string(),
+        version: changes.last().map_or(since, |entry| entry.sequence),
+        changes,
+    })
+}
+
+/// A booking a client took while offline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OfflineBooking {
+    /// ID the client gave the booking, echoed back in its outcome.
+    pub local_id: String,
+    pub slot_id: String,
+    pub patient: Patient,
+    pub priority: Priority,
+    pub reason: String,
+    /// When the booking was taken on the client.
+    pub booked_at: DateTime<Local>,
+}
+
+/// What happened to a pushed offline booking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SyncStatus {
+    Booked(Appointment),
+    /// The patient already held the slot, e.g. from an earlier push.
+    AlreadyBooked(Appointment),
+    Conflict {
+        reason: String,
+        /// Up to three free slots nearest the one asked for.
+        alternatives: Vec<TimeSlot>,
+    },
+    /// The booking's own details were invalid, so it was not tried.
+    Rejected(InputError),
+}
+
+/// Outcome of one pushed offline booking.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncOutcome {
+    pub local_id: String,
+    pub status: SyncStatus,
+}
+
+/// Read a JSON array of offline bookings pushed by a client.
+pub fn parse_offline_bookings(body: &str) -> Result<Vec<OfflineBooking>, InputError> {
+    let bookings: Vec<OfflineBooking> = boundary::parse_json(body)?;
+    boundary::check_count(bookings.len())?;
+    Ok(bookings)
+}
+
+/// Apply bookings taken offline, in the order they were taken.
+///
+/// A booking is only made if its slot is still free; otherwise it is
+/// reported as a conflict and the calendar is left unchanged. Names and
+/// reasons are normalized first, and bookings with invalid details or an
+/// implausible booking time are rejected. A push of more than
+/// `boundary::MAX_ITEMS` bookings is refused whole.
+pub fn push_offline_bookings(
+    calendar: &mut DoctorCalendar,
+    mut bookings: Vec<OfflineBooking>,
+) -> Result<Vec<SyncOutcome>, InputError> {
+    boundary::check_count(bookings.len())?;
+    bookings.sort_by_key(|booking| booking.booked_at);
+    Ok(bookings
+        .into_iter()
+        .map(|booking| SyncOutcome {
+            local_id: booking.local_id.clone(),
+            status: match checked_booking(booking, calendar.now()) {
+                Ok(booking) => apply_booking(calendar, booking),
+                Err(error) => SyncStatus::Rejected(error),
+            },
+        })
+        .collect())
+}
+
+/// Normalize a pushed booking's text and check its details.
+fn checked_booking(
+    booking: OfflineBooking,
+    now: DateTime<Local>,
+) -> Result<OfflineBooking, InputError> {
+    let mut errors = ValidationErrors::new();
+    let local_id = boundary::check_text("local_id", &booking.local_id, &mut errors);
+    let patient = boundary::check_patient(
+        &booking.patient.patient_id,
+        &booking.patient.name,
+        &booking.patient.contact,
+        &mut errors,
+    );
+    let reason = boundary::check_text("reason", &booking.reason, &mut errors);
+    if reason.is_empty() {
+        errors.add("reason", "Appointment reason cannot be empty");
+    }
+    boundary::check_time("booked_at", booking.booked_at, now, &mut errors);
+    match patient {
+        Some(patient) if errors.is_empty() => Ok(OfflineBooking {
+            local_id,
+            patient,
+            reason,
+            ..booking
+        }),
+        _ => Err(InputError::invalid(errors)),
+    }
+}
+
+fn apply_booking(calendar: &mut DoctorCalendar, booking: OfflineBooking) -> SyncStatus {
+    let Some(slot) = calendar.slot_by_id(&booking.slot_id).cloned() else {
+        return SyncStatus::Conflict {
+            reason: "The slot no longer exists".to_string(),
+            alternatives: Vec::new(),
+        };
+    };
+
+    if let Some(holder) = calendar
+        .appointments()
+        .into_iter()
+        .find(|apt| apt.time_slot.slot_id == slot.slot_id)
+    {
+        if holder.patient.patient_id == booking.patient.patient_id {
+            return SyncStatus::AlreadyBooked(holder);
+        }
+        return SyncStatus::Conflict {
+            reason: "The slot was booked by another patient".to_string(),
+            alternatives: alternatives(calendar, &slot),
+        };
+    }
+
+    match calendar.book_slot(&slot, booking.patient, booking.priority, booking.reason) {
+        Ok(appointment) => SyncStatus::Booked(appointment),
+        Err(e) => SyncStatus::Conflict {
+            reason: e,
+            alternatives: alternatives(calendar, &slot),
+        },
+    }
+}
+
+/// Find up to three free slots of at least the same length, nearest first.
+fn alternatives(calendar: &DoctorCalendar, wanted: &TimeSlot) -> Vec<TimeSlot> {
+    let now = calendar.now();
+    let mut slots: Vec<TimeSlot> = calendar
+        .available_slots()
+        .into_iter()
+        .filter(|slot| slot.start_time >= now)
+        .filter(|slot| slot.duration_minutes() >= wanted.duration_minutes())
+        .collect();
+    slots.sort_by_key(|slot| (slot.start_time - wanted.start_time).num_seconds().abs());
+    slots.truncate(3);
+    slots
+}
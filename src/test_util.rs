@@ -0,0 +1,258 @@
+#![allow(dead_code)]
+//! Fixtures for scenario tests (enabled with the `test_util` feature).
+//!
+//! Code built on the library can use these to set up calendars and
+//! schedulers without copying fixture code around. A CalendarFixture runs
+//! on a FixedClock and seeded IDs, so the same test produces the same slot
+//! IDs, confirmation codes and queue order on every run. The `assert_`
+//! helpers panic with every violation they find, for use in `#[test]`
+//! functions.
+
+use crate::calendar::DoctorCalendar;
+use crate::clock::FixedClock;
+use crate::ids::SeededIds;
+use crate::models::{Appointment, AppointmentRequest, AppointmentStatus, Patient, Priority};
+use crate::scheduler::{AppointmentScheduler, BatchSchedulingResult, SchedulingResult};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// Get a local time, panicking if it doesn't exist.
+pub fn local_time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|date| date.and_hms_opt(hour, minute, 0))
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .unwrap_or_else(|| {
+            panic!(
+                "Invalid local time: {}-{:02}-{:02} {:02}:{:02}",
+                year, month, day, hour, minute
+            )
+        })
+}
+
+/// Builder for a calendar with slots on consecutive days, on a fixed clock.
+///
+/// By default Dr. Test has 30-minute slots from 9:00 to 17:00 on the five
+/// days after `now`, with IDs seeded from 0.
+#[derive(Debug, Clone)]
+pub struct CalendarFixture {
+    now: DateTime<Local>,
+    doctor_name: String,
+    slot_minutes: i64,
+    days: u32,
+    start_hour: u32,
+    end_hour: u32,
+    seed: u64,
+}
+
+impl CalendarFixture {
+    /// Start a fixture whose clock reads `now`.
+    pub fn new(now: DateTime<Local>) -> Self {
+        CalendarFixture {
+            now,
+            doctor_name: "Dr. Test".to_string(),
+            slot_minutes: 30,
+            days: 5,
+            start_hour: 9,
+            end_hour: 17,
+            seed: 0,
+        }
+    }
+
+    pub fn doctor(mut self, doctor_name: &str) -> Self {
+        self.doctor_name = doctor_name.to_string();
+        self
+    }
+
+    pub fn slot_minutes(mut self, slot_minutes: i64) -> Self {
+        self.slot_minutes = slot_minutes;
+        self
+    }
+
+    /// Generate slots on this many days, starting the day after `now`.
+    pub fn days(mut self, days: u32) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// Set the working hours slots are generated in.
+    pub fn hours(mut self, start_hour: u32, end_hour: u32) -> Self {
+        self.start_hour = start_hour;
+        self.end_hour = end_hour;
+        self
+    }
+
+    /// Seed the ID generator, e.g. to get different IDs in two calendars.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Build the calendar, returning the clock to move time on with.
+    pub fn build(&self) -> (DoctorCalendar, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock::new(self.now));
+        let mut calendar = DoctorCalendar::with_generators(
+            self.doctor_name.clone(),
+            self.slot_minutes,
+            clock.clone(),
+            Arc::new(SeededIds::new(self.seed)),
+        )
+        .unwrap_or_else(|e| panic!("Invalid calendar fixture: {}", e));
+        for offset in 1..=self.days {
+            calendar.generate_daily_slots(
+                self.now + Duration::days(offset as i64),
+                self.start_hour,
+                self.end_hour,
+                None,
+                None,
+                None,
+            );
+        }
+        (calendar, clock)
+    }
+
+    /// Build a scheduler on the calendar, without fallback to later slots.
+    pub fn scheduler(&self) -> (AppointmentScheduler, Arc<FixedClock>) {
+        let (calendar, clock) = self.build();
+        (AppointmentScheduler::new(calendar, false), clock)
+    }
+}
+
+/// Get test patient `n`, e.g. `P001`, Patient 1, `patient1@example.com`.
+pub fn patient(n: usize) -> Patient {
+    Patient::new(
+        format!("P{:03}", n),
+        format!("Patient {}", n),
+        format!("patient{}@example.com", n),
+    )
+    .unwrap_or_else(|e| panic!("Invalid test patient: {}", e))
+}
+
+/// Build a checkup request from `patient(n)` with an hour's flexibility.
+///
+/// The ID comes from the calendar's generator and the request is created
+/// `n` milliseconds after the calendar's current time, so requests built
+/// in order queue in that order within a priority.
+pub fn request(
+    calendar: &DoctorCalendar,
+    n: usize,
+    priority: Priority,
+    preferred_time: DateTime<Local>,
+) -> AppointmentRequest {
+    AppointmentRequest::new(
+        calendar.id_generator(),
+        calendar.now() + Duration::milliseconds(n as i64),
+        patient(n),
+        priority,
+        preferred_time,
+        "Checkup".to_string(),
+        60,
+    )
+    .unwrap_or_else(|e| panic!("Invalid test request: {}", e))
+}
+
+/// Build `count` requests cycling through routine, urgent and emergency,
+/// each preferring the start of the next free slot.
+///
+/// There are at least as many free slots as requests, so every request
+/// fits unless the test takes slots away.
+pub fn canned_requests(calendar: &DoctorCalendar, count: usize) -> Vec<AppointmentRequest> {
+    const PRIORITIES: [Priority; 3] = [Priority::Routine, Priority::Urgent, Priority::Emergency];
+    let mut slots = calendar.available_slots();
+    slots.sort_by_key(|slot| slot.start_time);
+    assert!(
+        slots.len() >= count,
+        "Only {} free slots for {} canned requests",
+        slots.len(),
+        count
+    );
+    slots
+        .iter()
+        .take(count)
+        .enumerate()
+        .map(|(i, slot)| request(calendar, i + 1, PRIORITIES[i % 3], slot.start_time))
+        .collect()
+}
+
+/// Panic if the calendar's invariants don't hold.
+pub fn assert_invariants(calendar: &DoctorCalendar) {
+    if let Err(violations) = calendar.verify_invariants() {
+        panic!("Calendar invariants violated:\n{}", violations.join("\n"));
+    }
+}
+
+/// Panic unless a request was booked, returning its appointment.
+pub fn assert_booked(result: &SchedulingResult) -> Appointment {
+    match (&result.appointment, result.success) {
+        (Some(appointment), true) => appointment.clone(),
+        _ => panic!(
+            "Request {} for {} was not booked: {}",
+            result.request.request_id, result.request.patient.name, result.message
+        ),
+    }
+}
+
+/// Panic unless every request in a batch was either booked into the
+/// calendar or reported as failed, and the calendar is still consistent.
+pub fn assert_batch_accounted(calendar: &DoctorCalendar, result: &BatchSchedulingResult) {
+    let mut problems = Vec::new();
+    if let Some(e) = &result.queue_error {
+        problems.push(format!("Queue error: {}", e));
+    }
+    let accounted = result.confirmed.len() + result.failed.len();
+    if accounted != result.total_requests {
+        problems.push(format!(
+            "{} of {} requests were neither booked nor failed",
+            result.total_requests.saturating_sub(accounted),
+            result.total_requests
+        ));
+    }
+    for appointment in &result.confirmed {
+        if calendar
+            .get_appointment_by_id(&appointment.appointment_id)
+            .is_none_or(|apt| apt.status == AppointmentStatus::Cancelled)
+        {
+            problems.push(format!(
+                "Booked appointment {} is not in the calendar",
+                appointment.appointment_id
+            ));
+        }
+    }
+    if let Err(violations) = calendar.verify_invariants() {
+        problems.extend(violations);
+    }
+    if !problems.is_empty() {
+        panic!("Batch not accounted for:\n{}", problems.join("\n"));
+    }
+}
+
+/// Panic unless the calendar survives a snapshot round trip with the same
+/// slots and appointments.
+pub fn assert_snapshot_round_trip(calendar: &DoctorCalendar) {
+    let restored = DoctorCalendar::from_snapshot(calendar.snapshot())
+        .unwrap_or_else(|e| panic!("Snapshot did not restore: {}", e));
+    let slot_ids = |calendar: &DoctorCalendar| -> BTreeSet<String> {
+        calendar
+            .time_slots()
+            .into_iter()
+            .map(|slot| slot.slot_id)
+            .collect()
+    };
+    let appointment_ids = |calendar: &DoctorCalendar| -> BTreeSet<String> {
+        calendar
+            .appointments()
+            .into_iter()
+            .map(|apt| apt.appointment_id)
+            .collect()
+    };
+    assert_eq!(
+        slot_ids(&restored),
+        slot_ids(calendar),
+        "Slots changed in the snapshot round trip"
+    );
+    assert_eq!(
+        appointment_ids(&restored),
+        appointment_ids(calendar),
+        "Appointments changed in the snapshot round trip"
+    );
+}
@@ -0,0 +1,94 @@
+#![allow(dead_code)]
+//! Time-off requests raised by doctors and decided by an administrator.
+//!
+//! A request only records the period; nothing changes in the calendar
+//! until it is approved. Approval blocks the period and moves any
+//! appointments booked in it to the nearest slot the patient accepts.
+
+use crate::availability::BlockReport;
+use crate::ids::{IdGenerator, IdKind};
+use crate::models::Appointment;
+use chrono::{DateTime, Local};
+
+/// Where a time-off request is in its review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOffStatus {
+    Pending,
+    Approved,
+    Rejected,
+}
+
+impl TimeOffStatus {
+    pub fn name(&self) -> &str {
+        match self {
+            TimeOffStatus::Pending => "PENDING",
+            TimeOffStatus::Approved => "APPROVED",
+            TimeOffStatus::Rejected => "REJECTED",
+        }
+    }
+}
+
+/// A doctor's request to take `[start, end)` off.
+#[derive(Debug, Clone)]
+pub struct TimeOffRequest {
+    pub request_id: String,
+    pub doctor_id: String,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    pub reason: String,
+    pub status: TimeOffStatus,
+    pub requested_at: DateTime<Local>,
+    pub decided_at: Option<DateTime<Local>>,
+}
+
+impl TimeOffRequest {
+    /// Create a pending request with validation.
+    pub fn new(
+        ids: &dyn IdGenerator,
+        doctor_id: String,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+        reason: String,
+        requested_at: DateTime<Local>,
+    ) -> Result<Self, String> {
+        if end <= start {
+            return Err("End time must be after start time".to_string());
+        }
+        if reason.trim().is_empty() {
+            return Err("Time-off reason cannot be empty".to_string());
+        }
+
+        Ok(TimeOffRequest {
+            request_id: ids.next_id(IdKind::Request, requested_at),
+            doctor_id,
+            start,
+            end,
+            reason: reason.trim().to_string(),
+            status: TimeOffStatus::Pending,
+            requested_at,
+            decided_at: None,
+        })
+    }
+
+    /// Check whether the request still awaits a decision.
+    pub fn is_pending(&self) -> bool {
+        self.status == TimeOffStatus::Pending
+    }
+}
+
+/// An appointment moved out of approved time off.
+#[derive(Debug, Clone)]
+pub struct MovedAppointment {
+    pub previous_start: DateTime<Local>,
+    pub appointment: Appointment,
+}
+
+/// Outcome of approving a time-off request.
+#[derive(Debug, Clone, Default)]
+pub struct TimeOffReport {
+    /// Slots removed from the period, including those freed by moves.
+    pub block: BlockReport,
+    pub moved: Vec<MovedAppointment>,
+    /// Appointments no free slot could be found for, left for staff.
+    pub unresolved: Vec<Appointment>,
+}
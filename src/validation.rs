@@ -0,0 +1,245 @@
+#![allow(dead_code)]
+//! Pluggable validation of incoming appointment requests.
+//!
+//! The scheduler runs every registered RequestValidator before accepting a
+//! request and reports all violations together, so a caller can fix every
+//! problem in one pass. Clinics can add their own validators alongside the
+//! built-in ones.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{AppointmentRequest, Priority};
+use chrono::{DateTime, Duration, Local};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Every problem found while validating a value, grouped by field.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors {
+    errors: BTreeMap<String, Vec<String>>,
+}
+
+impl ValidationErrors {
+    /// Create an empty collection.
+    pub fn new() -> Self {
+        ValidationErrors::default()
+    }
+
+    /// Record a problem with a field.
+    pub fn add(&mut self, field: &str, message: impl Into<String>) {
+        self.errors
+            .entry(field.to_string())
+            .or_default()
+            .push(message.into());
+    }
+
+    /// Move every problem from another collection into this one.
+    pub fn merge(&mut self, other: ValidationErrors) {
+        for (field, messages) in other.errors {
+            self.errors.entry(field).or_default().extend(messages);
+        }
+    }
+
+    /// Check whether no problems were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// Get the fields with problems, in name order.
+    pub fn fields(&self) -> Vec<&str> {
+        self.errors.keys().map(String::as_str).collect()
+    }
+
+    /// Get the problems recorded for a field.
+    pub fn messages(&self, field: &str) -> &[String] {
+        self.errors.get(field).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Iterate over `(field, message)` pairs.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.errors.iter().flat_map(|(field, messages)| {
+            messages
+                .iter()
+                .map(move |message| (field.as_str(), message.as_str()))
+        })
+    }
+
+    /// Return `value` if no problems were recorded, otherwise the errors.
+    pub fn finish<T>(self, value: T) -> Result<T, ValidationErrors> {
+        if self.is_empty() {
+            Ok(value)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<&str> = self.iter().map(|(_, message)| message).collect();
+        write!(f, "{}", messages.join("; "))
+    }
+}
+
+impl From<ValidationErrors> for String {
+    fn from(errors: ValidationErrors) -> Self {
+        errors.to_string()
+    }
+}
+
+/// State a validator may inspect when checking a request.
+pub struct ValidationContext<'a> {
+    pub calendar: &'a DoctorCalendar,
+    pub pending: &'a [AppointmentRequest],
+    pub now: DateTime<Local>,
+}
+
+/// A check applied to every request added to the scheduler.
+pub trait RequestValidator {
+    /// Short name used in reports.
+    fn name(&self) -> &str;
+
+    /// Return every problem found with the request; empty means valid.
+    fn validate(&self, request: &AppointmentRequest, context: &ValidationContext) -> Vec<String>;
+}
+
+/// Requires the patient contact to look like an email address or phone number.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContactFormatValidator;
+
+impl RequestValidator for ContactFormatValidator {
+    fn name(&self) -> &str {
+        "contact-format"
+    }
+
+    fn validate(&self, request: &AppointmentRequest, _context: &ValidationContext) -> Vec<String> {
+        let contact = request.patient.contact.trim();
+        if is_email(contact) || is_phone(contact) {
+            Vec::new()
+        } else {
+            vec![format!(
+                "Patient contact '{}' is not a valid email address or phone number",
+                contact
+            )]
+        }
+    }
+}
+
+/// Rejects a request when the patient already has one pending or booked on the same day.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DuplicateRequestValidator;
+
+impl RequestValidator for DuplicateRequestValidator {
+    fn name(&self) -> &str {
+        "duplicate"
+    }
+
+    fn validate(&self, request: &AppointmentRequest, context: &ValidationContext) -> Vec<String> {
+        let date = request.preferred_time.date_naive();
+        let patient_id = &request.patient.patient_id;
+        let mut violations = Vec::new();
+
+        if context.pending.iter().any(|pending| {
+            &pending.patient.patient_id == patient_id
+                && pending.preferred_time.date_naive() == date
+        }) {
+            violations.push(format!(
+                "{} already has a pending request on {}",
+                request.patient.name, date
+            ));
+        }
+
+        if context
+            .calendar
+            .get_appointments_on_date(request.preferred_time)
+            .iter()
+            .any(|apt| &apt.patient.patient_id == patient_id)
+        {
+            violations.push(format!(
+                "{} already has an appointment on {}",
+                request.patient.name, date
+            ));
+        }
+
+        violations
+    }
+}
+
+/// Enforces how far ahead of time requests may be made.
+///
+/// Emergencies are exempt from the minimum notice.
+#[derive(Debug, Clone, Copy)]
+pub struct LeadTimeValidator {
+    pub min_notice_minutes: i64,
+    pub max_advance_days: Option<i64>,
+}
+
+impl LeadTimeValidator {
+    /// Create a lead-time validator with validation.
+    pub fn new(min_notice_minutes: i64, max_advance_days: Option<i64>) -> Result<Self, String> {
+        if min_notice_minutes < 0 {
+            return Err("Minimum notice cannot be negative".to_string());
+        }
+        if max_advance_days.is_some_and(|days| days <= 0) {
+            return Err("Maximum advance booking must be at least one day".to_string());
+        }
+
+        Ok(LeadTimeValidator {
+            min_notice_minutes,
+            max_advance_days,
+        })
+    }
+}
+
+impl RequestValidator for LeadTimeValidator {
+    fn name(&self) -> &str {
+        "lead-time"
+    }
+
+    fn validate(&self, request: &AppointmentRequest, context: &ValidationContext) -> Vec<String> {
+        let mut violations = Vec::new();
+        let notice = request.preferred_time - context.now;
+
+        if request.priority != Priority::Emergency
+            && notice < Duration::minutes(self.min_notice_minutes)
+        {
+            violations.push(format!(
+                "Requests need at least {} minutes notice",
+                self.min_notice_minutes
+            ));
+        }
+
+        if let Some(days) = self.max_advance_days {
+            if notice > Duration::days(days) {
+                violations.push(format!(
+                    "Requests cannot be made more than {} days ahead",
+                    days
+                ));
+            }
+        }
+
+        violations
+    }
+}
+
+/// Check for a plausible `local@domain.tld` address.
+fn is_email(contact: &str) -> bool {
+    match contact.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !contact.contains(char::is_whitespace)
+                && domain
+                    .split_once('.')
+                    .is_some_and(|(host, tld)| !host.is_empty() && !tld.is_empty())
+        }
+        None => false,
+    }
+}
+
+/// Check for a phone number of at least seven digits with common separators.
+fn is_phone(contact: &str) -> bool {
+    let digits = contact.chars().filter(|c| c.is_ascii_digit()).count();
+    digits >= 7
+        && contact
+            .chars()
+            .all(|c| c.is_ascii_digit() || " +-().".contains(c))
+}
@@ -0,0 +1,396 @@
+#![allow(dead_code)]
+//! Requests waiting for a slot to free up.
+//!
+//! A request that can't be booked is parked on the waitlist. Whenever a
+//! slot frees up, for example through a cancellation, the scheduler books
+//! the most urgent waitlisted request that fits it; among requests of the
+//! same priority, the one waiting longest goes first.
+//!
+//! During downtime staff work the call-back list in a spreadsheet. The
+//! waitlist exports to CSV with a header row and these columns:
+//!
+//! ```text
+//! position,request_id,patient_id,patient_name,contact,priority,preferred_time,
+//! flexibility_minutes,earlier_minutes,later_minutes,duration_minutes,
+//! accepts_premium,window_first_day,window_last_day,window_weekdays,
+//! window_times,unavailable,reason
+//! ```
+//!
+//! Times use `YYYY-MM-DD HH:MM` in local time. Weekdays are listed as
+//! `Mon Wed`, times of day as `09:00-12:00 14:00-17:00`, and unavailable
+//! times as `2024-03-04 15:00 to 2024-03-04 16:00`, separated by `;`. Empty
+//! optional columns mean no constraint.
+
+use crate::boundary;
+use crate::calendar::DoctorCalendar;
+use crate::import::{parse_time, split_csv_line};
+use crate::models::{AppointmentRequest, DateWindow, Priority};
+use crate::reports::escape_csv;
+use crate::validation::ValidationErrors;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Weekday};
+use std::cmp::Reverse;
+use std::collections::HashSet;
+
+const HEADER: &str = concat!(
+    "position,request_id,patient_id,patient_name,contact,priority,preferred_time,",
+    "flexibility_minutes,earlier_minutes,later_minutes,duration_minutes,",
+    "accepts_premium,window_first_day,window_last_day,window_weekdays,",
+    "window_times,unavailable,reason"
+);
+const COLUMNS: usize = 18;
+
+/// Outcome of re-importing an edited waitlist.
+#[derive(Debug, Clone, Default)]
+pub struct WaitlistImport {
+    /// IDs of requests updated from their rows.
+    pub updated: Vec<String>,
+    /// IDs given to rows added without a request ID.
+    pub added: Vec<String>,
+    /// Requests whose rows were deleted from the file.
+    pub removed: Vec<AppointmentRequest>,
+    /// Rows that could not be read, by line number. Requests on these rows
+    /// are left as they were.
+    pub errors: Vec<(usize, String)>,
+}
+
+/// Requests waiting for a slot, in the order they were parked.
+#[derive(Debug, Clone, Default)]
+pub struct Waitlist {
+    requests: Vec<AppointmentRequest>,
+}
+
+impl Waitlist {
+    /// Create an empty waitlist.
+    pub fn new() -> Self {
+        Waitlist::default()
+    }
+
+    /// Park a request, replacing any earlier entry with the same request ID.
+    ///
+    /// Returns the request's position in the waitlist order, from 1.
+    pub fn park(&mut self, request: AppointmentRequest) -> usize {
+        let request_id = request.request_id.clone();
+        self.requests
+            .retain(|waiting| waiting.request_id != request_id);
+        self.requests.push(request);
+        self.position(&request_id).unwrap_or(self.requests.len())
+    }
+
+    /// Take a request off the waitlist.
+    pub fn remove(&mut self, request_id: &str) -> Option<AppointmentRequest> {
+        let index = self
+            .requests
+            .iter()
+            .position(|waiting| waiting.request_id == request_id)?;
+        Some(self.requests.remove(index))
+    }
+
+    /// Empty the waitlist, returning how many requests were on it.
+    pub fn clear(&mut self) -> usize {
+        let count = self.requests.len();
+        self.requests.clear();
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Get the requests in the order they were parked.
+    pub fn requests(&self) -> &[AppointmentRequest] {
+        &self.requests
+    }
+
+    /// Get the requests in the order they are offered slots: most urgent
+    /// first, then longest waiting.
+    pub fn in_order(&self) -> Vec<AppointmentRequest> {
+        let mut requests = self.requests.clone();
+        requests.sort_by_key(|request| (Reverse(request.priority), request.created_at));
+        requests
+    }
+
+    /// Get a request's position in the waitlist order, from 1.
+    pub fn position(&self, request_id: &str) -> Option<usize> {
+        self.in_order()
+            .iter()
+            .position(|request| request.request_id == request_id)
+            .map(|index| index + 1)
+    }
+
+    /// Export the waitlist as CSV, in the order requests are offered slots.
+    pub fn to_csv(&self) -> String {
+        let mut output = format!("{}\n", HEADER);
+        for (index, request) in self.in_order().iter().enumerate() {
+            let window = request.date_window.as_ref();
+            let fields = [
+                (index + 1).to_string(),
+                request.request_id.clone(),
+                request.patient.patient_id.clone(),
+                request.patient.name.clone(),
+                request.patient.contact.clone(),
+                request.priority.name().to_lowercase(),
+                format_time(request.preferred_time),
+                request.flexibility_minutes.to_string(),
+                format_optional(request.can_come_earlier),
+                format_optional(request.can_come_later),
+                format_optional(request.duration_minutes),
+                if request.accepts_premium { "yes" } else { "no" }.to_string(),
+                window.map_or(String::new(), |w| w.first_day.to_string()),
+                window.map_or(String::new(), |w| w.last_day.to_string()),
+                window.map_or(String::new(), |w| {
+                    let days: Vec<String> = w.weekdays.iter().map(|day| day.to_string()).collect();
+                    days.join(" ")
+                }),
+                window.map_or(String::new(), |w| {
+                    let times: Vec<String> = w
+                        .times_of_day
+                        .iter()
+                        .map(|(start, end)| {
+                            format!("{}-{}", start.format("%H:%M"), end.format("%H:%M"))
+                        })
+                        .collect();
+                    times.join(" ")
+                }),
+                request
+                    .unavailable_times
+                    .iter()
+                    .map(|(start, end)| format!("{} to {}", format_time(*start), format_time(*end)))
+                    .collect::<Vec<String>>()
+                    .join("; "),
+                request.reason.clone(),
+            ];
+            let escaped: Vec<String> = fields.iter().map(|f| escape_csv(f)).collect();
+            output.push_str(&escaped.join(","));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Replace the waitlist with an edited export.
+    ///
+    /// Rows are matched to requests by ID, and rows with an empty ID are
+    /// added as new requests, with IDs and creation times from the
+    /// calendar. Requests whose rows were deleted are taken off the
+    /// waitlist. The position column is ignored:
+    /// the order always follows priority and time waiting.
+    ///
+    /// Names and reasons are normalized, and rows with overlong text or a
+    /// preferred time more than a year or two from today are reported as
+    /// errors. A file over the size limit is refused whole.
+    pub fn import_csv(&mut self, text: &str, calendar: &DoctorCalendar) -> WaitlistImport {
+        let mut report = WaitlistImport::default();
+        if let Err(e) = boundary::check_size(text, boundary::MAX_FILE_BYTES) {
+            report.errors.push((0, e.to_string()));
+            return report;
+        }
+        let mut kept = HashSet::new();
+        let mut requests = Vec::new();
+
+        for (index, line) in text.lines().enumerate().skip(1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields = match split_csv_line(line) {
+                Ok(fields) => fields,
+                Err(e) => {
+                    report.errors.push((index + 1, e));
+                    continue;
+                }
+            };
+            let request_id = fields.get(1).map_or("", |id| id.trim()).to_string();
+            let existing = self
+                .requests
+                .iter()
+                .find(|request| request.request_id == request_id);
+            let parsed = if !request_id.is_empty() && existing.is_none() {
+                Err(format!("Request {} is not on the waitlist", request_id))
+            } else if !kept.insert(request_id.clone()) && !request_id.is_empty() {
+                Err(format!("Request {} appears more than once", request_id))
+            } else {
+                parse_row(&fields, existing, calendar)
+            };
+            match parsed {
+                Ok(request) => {
+                    if request_id.is_empty() {
+                        report.added.push(request.request_id.clone());
+                    } else {
+                        report.updated.push(request_id);
+                    }
+                    requests.push(request);
+                }
+                Err(e) => {
+                    if let Some(existing) = existing {
+                        requests.retain(|request: &AppointmentRequest| {
+                            request.request_id != existing.request_id
+                        });
+                        requests.push(existing.clone());
+                    }
+                    report.errors.push((index + 1, e));
+                }
+            }
+        }
+
+        report.removed = self
+            .requests
+            .iter()
+            .filter(|request| !kept.contains(&request.request_id))
+            .cloned()
+            .collect();
+        self.requests = requests;
+        report
+    }
+}
+
+/// Build a request from a row, updating `existing` if the row has its ID.
+fn parse_row(
+    fields: &[String],
+    existing: Option<&AppointmentRequest>,
+    calendar: &DoctorCalendar,
+) -> Result<AppointmentRequest, String> {
+    if fields.len() != COLUMNS {
+        return Err(format!(
+            "Expected {} columns, found {}",
+            COLUMNS,
+            fields.len()
+        ));
+    }
+
+    let priority = Priority::from_string(&fields[5])?;
+    let preferred_time = parse_time(&fields[6])?;
+    let flexibility_minutes = parse_minutes(&fields[7])?.unwrap_or(0);
+
+    let mut errors = ValidationErrors::new();
+    let patient = boundary::check_patient(&fields[2], &fields[3], &fields[4], &mut errors);
+    let reason = boundary::check_text("reason", &fields[17], &mut errors);
+    boundary::check_time(
+        "preferred_time",
+        preferred_time,
+        calendar.now(),
+        &mut errors,
+    );
+    let patient = match patient {
+        Some(patient) if errors.is_empty() => patient,
+        _ => return Err(errors.into()),
+    };
+
+    let mut request = match existing {
+        Some(existing) => {
+            let mut request = AppointmentRequest::with_id(
+                existing.request_id.clone(),
+                existing.created_at,
+                patient,
+                priority,
+                preferred_time,
+                reason,
+                flexibility_minutes,
+            )?;
+            request.source = existing.source;
+            request.referrer = existing.referrer.clone();
+            request
+        }
+        None => AppointmentRequest::new(
+            calendar.id_generator(),
+            calendar.now(),
+            patient,
+            priority,
+            preferred_time,
+            reason,
+            flexibility_minutes,
+        )?,
+    };
+
+    match (parse_minutes(&fields[8])?, parse_minutes(&fields[9])?) {
+        (None, None) => {}
+        (earlier, later) => request.set_window(
+            earlier.unwrap_or(flexibility_minutes),
+            later.unwrap_or(flexibility_minutes),
+        )?,
+    }
+    request.duration_minutes = parse_minutes(&fields[10])?;
+    request.accepts_premium = parse_yes_no(&fields[11])?;
+    request.date_window = parse_window(&fields[12], &fields[13], &fields[14], &fields[15])?;
+    for range in fields[16]
+        .split(';')
+        .filter(|range| !range.trim().is_empty())
+    {
+        let (start, end) = range
+            .split_once(" to ")
+            .ok_or_else(|| format!("Invalid unavailable time: {}", range.trim()))?;
+        request.add_unavailable_time(parse_time(start)?, parse_time(end)?)?;
+    }
+    Ok(request)
+}
+
+fn parse_window(
+    first_day: &str,
+    last_day: &str,
+    weekdays: &str,
+    times: &str,
+) -> Result<Option<DateWindow>, String> {
+    if first_day.trim().is_empty() && last_day.trim().is_empty() {
+        if !weekdays.trim().is_empty() || !times.trim().is_empty() {
+            return Err("Window weekdays and times need first and last days".to_string());
+        }
+        return Ok(None);
+    }
+    let first_day = parse_date(first_day)?;
+    let last_day = parse_date(last_day)?;
+    let weekdays = weekdays
+        .split_whitespace()
+        .map(|day| {
+            day.parse::<Weekday>()
+                .map_err(|_| format!("Invalid weekday: {}", day))
+        })
+        .collect::<Result<Vec<Weekday>, String>>()?;
+    let times = times
+        .split_whitespace()
+        .map(|range| {
+            let (start, end) = range
+                .split_once('-')
+                .ok_or_else(|| format!("Invalid time of day range: {}", range))?;
+            Ok((parse_time_of_day(start)?, parse_time_of_day(end)?))
+        })
+        .collect::<Result<Vec<(NaiveTime, NaiveTime)>, String>>()?;
+    DateWindow::new(first_day, last_day, weekdays, times).map(Some)
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid date: {}", value.trim()))
+}
+
+fn parse_time_of_day(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time of day: {}", value.trim()))
+}
+
+fn parse_minutes(value: &str) -> Result<Option<i64>, String> {
+    if value.trim().is_empty() {
+        return Ok(None);
+    }
+    value
+        .trim()
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| format!("Invalid number of minutes: {}", value.trim()))
+}
+
+fn parse_yes_no(value: &str) -> Result<bool, String> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "n" | "no" | "false" => Ok(false),
+        "y" | "yes" | "true" => Ok(true),
+        _ => Err(format!("Expected yes or no, found: {}", value.trim())),
+    }
+}
+
+fn format_time(time: DateTime<Local>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+fn format_optional(value: Option<i64>) -> String {
+    value.map_or(String::new(), |value| value.to_string())
+}
@@ -0,0 +1,156 @@
+#![allow(dead_code)]
+//! Week view for graphical front ends.
+//!
+//! Staff who would rather not use a terminal get a grid of the doctor's
+//! week, drag appointments between slots and book through a dialog. This
+//! module builds that grid from the calendar and turns the two gestures
+//! into calendar calls, so a front end written with egui, Tauri or similar
+//! only has to draw the grid and report what was dropped where. The
+//! toolkit itself belongs in a companion crate, so building the CLI never
+//! pulls in its dependencies.
+
+use crate::calendar::DoctorCalendar;
+use crate::models::{Appointment, Patient, Priority, TimeSlot};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// One slot in the grid and the appointment booked into it, if any.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekCell {
+    pub slot: TimeSlot,
+    pub appointment: Option<Appointment>,
+}
+
+impl WeekCell {
+    /// Check whether an appointment can be dropped onto the cell.
+    pub fn is_free(&self) -> bool {
+        self.appointment.is_none() && self.slot.is_available
+    }
+}
+
+/// One column of the grid.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekDay {
+    pub date: NaiveDate,
+    /// The day's slots in time order.
+    pub cells: Vec<WeekCell>,
+}
+
+/// A doctor's slots and bookings from Monday to Sunday.
+#[derive(Debug, Clone, Serialize)]
+pub struct WeekGrid {
+    pub doctor_name: String,
+    pub week_start: NaiveDate,
+    pub days: Vec<WeekDay>,
+}
+
+impl WeekGrid {
+    /// Build the grid for the week containing `date`.
+    pub fn for_calendar(calendar: &DoctorCalendar, date: DateTime<Local>) -> Self {
+        let date = date.date_naive();
+        let week_start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        let mut booked: HashMap<String, Appointment> = calendar
+            .appointments()
+            .into_iter()
+            .map(|apt| (apt.time_slot.slot_id.clone(), apt))
+            .collect();
+
+        let mut days: Vec<WeekDay> = (0..7)
+            .map(|offset| WeekDay {
+                date: week_start + Duration::days(offset),
+                cells: Vec::new(),
+            })
+            .collect();
+        for slot in calendar.time_slots() {
+            let offset = (slot.start_time.date_naive() - week_start).num_days();
+            if !(0..7).contains(&offset) {
+                continue;
+            }
+            let appointment = booked.remove(&slot.slot_id);
+            days[offset as usize]
+                .cells
+                .push(WeekCell { slot, appointment });
+        }
+
+        WeekGrid {
+            doctor_name: calendar.doctor_name.clone(),
+            week_start,
+            days,
+        }
+    }
+
+    /// Find the cell showing a slot.
+    pub fn cell(&self, slot_id: &str) -> Option<&WeekCell> {
+        self.days
+            .iter()
+            .flat_map(|day| &day.cells)
+            .find(|cell| cell.slot.slot_id == slot_id)
+    }
+}
+
+/// Move an appointment to the slot it was dropped on.
+///
+/// The move goes through `DoctorCalendar::move_appointment`, so it is
+/// refused, leaving the booking where it was, whenever the calendar would
+/// refuse it.
+pub fn drop_on_slot(
+    calendar: &mut DoctorCalendar,
+    appointment_id: &str,
+    slot_id: &str,
+) -> Result<Appointment, String> {
+    let slot = calendar
+        .slot_by_id(slot_id)
+        .cloned()
+        .ok_or("Time slot not found in calendar")?;
+    calendar.move_appointment(appointment_id, &slot)
+}
+
+/// The fields of the booking dialog, as typed by staff.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BookingForm {
+    pub slot_id: String,
+    pub patient_id: String,
+    pub patient_name: String,
+    pub contact: String,
+    /// `routine`, `urgent` or `emergency`.
+    pub priority: String,
+    pub reason: String,
+}
+
+impl BookingForm {
+    /// Start a form for booking a slot.
+    pub fn for_slot(slot_id: &str) -> Self {
+        BookingForm {
+            slot_id: slot_id.to_string(),
+            priority: "routine".to_string(),
+            ..BookingForm::default()
+        }
+    }
+
+    /// Book the slot, returning every problem with the form if it fails.
+    pub fn submit(&self, calendar: &mut DoctorCalendar) -> Result<Appointment, String> {
+        let patient = Patient::new(
+            self.patient_id.trim().to_string(),
+            self.patient_name.trim().to_string(),
+            self.contact.trim().to_string(),
+        )
+        .map_err(String::from);
+        let priority = Priority::from_string(&self.priority);
+        let slot = calendar
+            .slot_by_id(&self.slot_id)
+            .cloned()
+            .ok_or_else(|| "Time slot not found in calendar".to_string());
+
+        match (patient, priority, slot) {
+            (Ok(patient), Ok(priority), Ok(slot)) => {
+                calendar.book_slot(&slot, patient, priority, self.reason.trim().to_string())
+            }
+            (patient, priority, slot) => Err([patient.err(), priority.err(), slot.err()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join("; ")),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Doctor self-service availability.
+
+use easyappoint::availability::DoctorAvailability;
+use easyappoint::test_util::{local_time, CalendarFixture};
+
+#[test]
+fn blocking_time_on_a_locked_day_reports_the_slots_as_refused() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(1)
+        .build();
+    let tuesday = local_time(2030, 3, 5, 9, 0);
+    calendar.lock_day(tuesday.date_naive());
+    let doctor_id = calendar.doctor_id.clone();
+
+    let mut availability = DoctorAvailability::for_doctor(&mut calendar, &doctor_id).unwrap();
+    let report = availability
+        .block_time(tuesday, local_time(2030, 3, 5, 12, 0))
+        .unwrap();
+
+    assert!(report.removed.is_empty());
+    assert_eq!(report.refused.len(), 6);
+    assert_eq!(calendar.time_slots().len(), 16);
+}
+
+#[test]
+fn doctors_cannot_manage_another_calendar() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0)).build();
+    assert!(DoctorAvailability::for_doctor(&mut calendar, "someone-else").is_err());
+}
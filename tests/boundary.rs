@@ -0,0 +1,141 @@
+//! Input checks at the kiosk, sync and import boundaries.
+
+use chrono::{DateTime, Duration, Local};
+use easyappoint::boundary::{normalize_text, InputErrorKind, MAX_BODY_BYTES, MAX_ITEMS};
+use easyappoint::import::import_appointments_csv;
+use easyappoint::kiosk::{Kiosk, KioskResponse, KioskStatus};
+use easyappoint::models::Priority;
+use easyappoint::notifications::InMemoryChannel;
+use easyappoint::sync::{
+    parse_offline_bookings, push_offline_bookings, OfflineBooking, SyncStatus,
+};
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+
+fn monday() -> DateTime<Local> {
+    local_time(2030, 3, 4, 8, 0)
+}
+
+fn rejection(response: &str) -> InputErrorKind {
+    match serde_json::from_str::<KioskResponse>(response)
+        .unwrap()
+        .status
+    {
+        KioskStatus::Rejected(error) => {
+            assert_eq!(error.status, error.kind.status());
+            error.kind
+        }
+        other => panic!("Expected a rejection, got {:?}", other),
+    }
+}
+
+#[test]
+fn kiosk_rejects_bad_bodies_before_looking_anything_up() {
+    let (mut scheduler, _clock) = CalendarFixture::new(monday()).days(1).scheduler();
+    let kiosk = Kiosk::new("doctor@example.com".to_string()).unwrap();
+    let mut channel = InMemoryChannel::default();
+    let mut answer = |body: &str| kiosk.handle_json(&mut scheduler, &mut channel, body);
+
+    let oversized = format!(
+        "{{\"confirmation_code\":\"{}\"}}",
+        "A".repeat(MAX_BODY_BYTES)
+    );
+    assert_eq!(rejection(&answer(&oversized)), InputErrorKind::TooLarge);
+    assert_eq!(rejection(&answer("{\"code\":")), InputErrorKind::Malformed);
+    assert_eq!(
+        rejection(&answer("{\"confirmation_code\":\"\u{200B} \"}")),
+        InputErrorKind::Invalid
+    );
+    assert!(channel.sent.is_empty());
+}
+
+#[test]
+fn pushed_bookings_are_normalized_or_rejected() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    let slots = calendar.time_slots();
+    let booking = |local_id: &str, slot: usize, name: &str, booked_at: DateTime<Local>| {
+        let mut patient = patient(slot + 1);
+        patient.name = name.to_string();
+        OfflineBooking {
+            local_id: local_id.to_string(),
+            slot_id: slots[slot].slot_id.clone(),
+            patient,
+            priority: Priority::Routine,
+            reason: "  Check\tup ".to_string(),
+            booked_at,
+        }
+    };
+    let outcomes = push_offline_bookings(
+        &mut calendar,
+        vec![
+            booking("a", 0, "Ann\u{202E} \n Lee", monday()),
+            booking("b", 1, "\u{200B}", monday()),
+            booking("c", 2, "Bo Chen", monday() - Duration::days(4000)),
+        ],
+    )
+    .unwrap();
+
+    match &outcomes[1].status {
+        SyncStatus::Booked(appointment) => {
+            assert_eq!(appointment.patient.name, "Ann Lee");
+            assert_eq!(appointment.reason, "Check up");
+        }
+        other => panic!("Expected a booking, got {:?}", other),
+    }
+    let rejected: Vec<(&str, Vec<&String>)> = outcomes
+        .iter()
+        .filter_map(|outcome| match &outcome.status {
+            SyncStatus::Rejected(error) => {
+                assert_eq!(error.kind, InputErrorKind::Invalid);
+                Some((outcome.local_id.as_str(), error.fields.keys().collect()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        rejected,
+        vec![
+            ("c", vec![&"booked_at".to_string()]),
+            ("b", vec![&"name".to_string()])
+        ]
+    );
+    assert_eq!(calendar.appointments().len(), 1);
+
+    let too_many = vec![booking("d", 3, "Di Lu", monday()); MAX_ITEMS + 1];
+    assert_eq!(
+        push_offline_bookings(&mut calendar, too_many)
+            .unwrap_err()
+            .kind,
+        InputErrorKind::TooLarge
+    );
+    assert_eq!(
+        parse_offline_bookings("[{").unwrap_err().kind,
+        InputErrorKind::Malformed
+    );
+}
+
+#[test]
+fn imported_rows_with_implausible_times_or_text_are_errors() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    let legacy = format!(
+        "patient_id,patient_name,contact,start,end,priority,reason\n\
+         P1,Ann\u{200B}  Lee,555-0101,2030-03-05 09:00,2030-03-05 09:30,routine,Checkup\n\
+         P2,Bo Chen,555-0102,2090-03-05 09:00,2090-03-05 09:30,routine,Checkup\n\
+         P3,Cy Diaz,555-0103,2030-03-05 10:00,2030-03-05 10:30,routine,{}\n",
+        "x".repeat(300)
+    );
+
+    let report = import_appointments_csv(&mut calendar, &legacy, false).unwrap();
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.imported[0].patient.name, "Ann Lee");
+    let lines: Vec<usize> = report.errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines, vec![3, 4]);
+}
+
+#[test]
+fn text_is_normalized_to_one_visible_line() {
+    assert_eq!(
+        normalize_text("\u{FEFF} Jane\u{00A0}\u{00A0}Doe\r\n"),
+        "Jane Doe"
+    );
+    assert_eq!(normalize_text("\u{202E}\u{0007}"), "");
+}
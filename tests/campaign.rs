@@ -0,0 +1,43 @@
+//! Recall campaigns.
+
+use chrono::NaiveDate;
+use easyappoint::campaign::RecallCampaign;
+use easyappoint::models::Priority;
+use easyappoint::notifications::InMemoryChannel;
+use easyappoint::test_util::{local_time, patient, request, CalendarFixture};
+
+#[test]
+fn only_the_campaigns_own_bookings_are_reported_and_notified() {
+    let (mut scheduler, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0)).scheduler();
+    let queued = request(
+        &scheduler.calendar,
+        1,
+        Priority::Routine,
+        local_time(2030, 3, 5, 9, 0),
+    );
+    assert!(scheduler.add_request(queued).unwrap().is_none());
+
+    let campaign = RecallCampaign::new(
+        "Flu shots".to_string(),
+        "Flu shot".to_string(),
+        NaiveDate::from_ymd_opt(2030, 3, 5).unwrap(),
+        NaiveDate::from_ymd_opt(2030, 3, 8).unwrap(),
+        vec![patient(1), patient(2)],
+    )
+    .unwrap();
+    let mut channel = InMemoryChannel::default();
+    let report = campaign.run(&mut scheduler, &mut channel).unwrap();
+
+    assert_eq!(scheduler.calendar.appointments().len(), 3);
+    let booked: Vec<(&str, &str)> = report
+        .booked
+        .iter()
+        .map(|apt| (apt.patient.patient_id.as_str(), apt.reason.as_str()))
+        .collect();
+    assert_eq!(booked.len(), 2);
+    assert!(booked.contains(&("P001", "Flu shot")));
+    assert!(booked.contains(&("P002", "Flu shot")));
+    assert!(report.unscheduled.is_empty());
+    assert_eq!(report.notified, 2);
+    assert_eq!(channel.sent.len(), 2);
+}
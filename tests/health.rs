@@ -0,0 +1,57 @@
+//! Health and readiness reports.
+
+use chrono::Duration;
+use easyappoint::health::check_health;
+use easyappoint::models::Priority;
+use easyappoint::notifications::{DeliveryQueue, RetryPolicy};
+use easyappoint::snapshot::SnapshotFormat;
+use easyappoint::store::{save_calendar, FileStore, InMemoryStore};
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+
+#[test]
+fn unsaved_changes_make_the_service_unhealthy_once_overdue() {
+    let now = local_time(2030, 3, 4, 8, 0);
+    let (mut scheduler, clock) = CalendarFixture::new(now).days(1).scheduler();
+    let mut store = InMemoryStore::new();
+    let deliveries = DeliveryQueue::new(RetryPolicy::default());
+    let max_age = Duration::minutes(5);
+
+    let report = check_health(&scheduler, &store, &deliveries);
+    assert!(report.is_ready());
+    assert_eq!(report.last_saved_at, None);
+    assert!(report.events_unsaved > 0);
+    assert_eq!(report.health_status(max_age), 503);
+
+    save_calendar(&mut store, &mut scheduler.calendar).unwrap();
+    let report = check_health(&scheduler, &store, &deliveries);
+    assert_eq!(report.last_saved_at, Some(now));
+    assert_eq!(report.events_unsaved, 0);
+    assert_eq!(report.health_status(max_age), 200);
+
+    let slot = scheduler.calendar.time_slots()[0].clone();
+    scheduler
+        .calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    clock.advance(Duration::minutes(10));
+    let report = check_health(&scheduler, &store, &deliveries);
+    assert_eq!(report.events_unsaved, 1);
+    assert!(report.is_save_overdue(max_age));
+    assert!(!report.is_save_overdue(Duration::minutes(15)));
+    assert_eq!(report.readiness_status(), 200);
+    assert_eq!(report.health_status(max_age), 503);
+}
+
+#[test]
+fn an_unreachable_store_is_not_ready() {
+    let (scheduler, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0)).scheduler();
+    let dir = std::env::temp_dir().join(format!("easyappoint-health-{}", std::process::id()));
+    let store = FileStore::new(&dir, SnapshotFormat::Json).unwrap();
+    let deliveries = DeliveryQueue::new(RetryPolicy::default());
+    assert!(check_health(&scheduler, &store, &deliveries).is_ready());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+    let report = check_health(&scheduler, &store, &deliveries);
+    assert!(report.store_error.is_some());
+    assert_eq!(report.readiness_status(), 503);
+}
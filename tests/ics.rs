@@ -0,0 +1,165 @@
+//! ICS export and busy-time import.
+
+use chrono::{Duration, Local, TimeZone, Utc};
+use easyappoint::ics::{parse_busy_times, render_calendar};
+use easyappoint::models::Priority;
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+
+const HOSPITAL_FEED: &str = "BEGIN:VCALENDAR\r
+VERSION:2.0\r
+BEGIN:VEVENT\r
+UID:ward-round@hospital\r
+SUMMARY:Ward round\\, level 3\r
+DTSTART:20300305T090000\r
+DTEND:20300305T100000\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+#[test]
+fn transfers_into_an_imported_busy_time_are_refused() {
+    let now = local_time(2030, 3, 4, 8, 0);
+    let (mut from, _clock) = CalendarFixture::new(now).days(1).build();
+    let (mut to, _clock) = CalendarFixture::new(now).days(0).seed(1).build();
+    to.set_busy_times(parse_busy_times(HOSPITAL_FEED).unwrap());
+    let slot = from.time_slots()[0].clone();
+    let appointment = from
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+
+    let refused = from
+        .transfer_appointment(&appointment.appointment_id, &mut to)
+        .unwrap_err();
+    assert!(refused.contains("Busy in external calendar: Ward round, level 3"));
+    assert!(from
+        .get_appointment_by_id(&appointment.appointment_id)
+        .is_some());
+    assert!(to.appointments().is_empty());
+}
+
+#[test]
+fn exported_events_are_stamped_with_the_calendar_clock() {
+    let now = local_time(2030, 3, 4, 8, 0);
+    let (mut calendar, _clock) = CalendarFixture::new(now).days(1).build();
+    let slot = calendar.time_slots()[0].clone();
+    calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+
+    let stamp = format!(
+        "DTSTAMP:{}",
+        now.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ")
+    );
+    assert!(render_calendar(&calendar)
+        .lines()
+        .any(|line| line.trim_end() == stamp));
+}
+
+#[test]
+fn busy_times_are_read_from_each_form_of_event() {
+    let feed = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:clinic@hospital\r
+SUMMARY:Outpatient clinic\\; room 4\\nBring the\r
+  pager\r
+DTSTART;TZID=Europe/London:20300306T140000\r
+DURATION:PT1H30M\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:conference@personal\r
+SUMMARY:Conference\r
+DTSTART;VALUE=DATE:20300307\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:call@hospital\r
+DTSTART:20300305T120000Z\r
+DTEND:20300305T123000Z\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:lunch@personal\r
+TRANSP:TRANSPARENT\r
+DTSTART:20300305T120000\r
+DTEND:20300305T130000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:moved@hospital\r
+STATUS:CANCELLED\r
+DTSTART:20300305T150000\r
+DTEND:20300305T160000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:reminder@personal\r
+DTSTART:20300305T080000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:backwards@personal\r
+DTSTART:20300305T170000\r
+DTEND:20300305T160000\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+    let busy = parse_busy_times(feed).unwrap();
+    let read: Vec<(&str, &str, _, _)> = busy
+        .iter()
+        .map(|time| {
+            (
+                time.uid.as_str(),
+                time.summary.as_str(),
+                time.start,
+                time.end,
+            )
+        })
+        .collect();
+    let call = Utc.with_ymd_and_hms(2030, 3, 5, 12, 0, 0).unwrap();
+    assert_eq!(
+        read,
+        vec![
+            (
+                "call@hospital",
+                "",
+                call.with_timezone(&Local),
+                (call + Duration::minutes(30)).with_timezone(&Local),
+            ),
+            (
+                "clinic@hospital",
+                "Outpatient clinic; room 4 Bring the pager",
+                local_time(2030, 3, 6, 14, 0),
+                local_time(2030, 3, 6, 15, 30),
+            ),
+            (
+                "conference@personal",
+                "Conference",
+                local_time(2030, 3, 7, 0, 0),
+                local_time(2030, 3, 8, 0, 0),
+            ),
+        ]
+    );
+}
+
+#[test]
+fn malformed_events_are_errors_and_own_events_are_skipped() {
+    let event = |lines: &str| format!("BEGIN:VCALENDAR\nBEGIN:VEVENT\n{}END:VCALENDAR\n", lines);
+    for bad in [
+        event("UID:a\nDTSTART:20300305T090000\n"),
+        event("UID:a\nDTEND:20300305T090000\nEND:VEVENT\n"),
+        event("UID:a\nDTSTART:2030-03-05 09:00\nDTEND:20300305T100000\nEND:VEVENT\n"),
+        event("UID:a\nDTSTART:20300305T090000\nDURATION:PT1X\nEND:VEVENT\n"),
+        event("UID:a\nDTSTART:20300305T090000\nDURATION:P1H\nEND:VEVENT\n"),
+        "BEGIN:VCALENDAR\nEND:VEVENT\nEND:VCALENDAR\n".to_string(),
+    ] {
+        assert!(parse_busy_times(&bad).is_err(), "{:?} parsed", bad);
+    }
+
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(1)
+        .build();
+    let slot = calendar.time_slots()[0].clone();
+    calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    assert_eq!(
+        parse_busy_times(&render_calendar(&calendar)).unwrap(),
+        vec![]
+    );
+}
@@ -0,0 +1,88 @@
+//! Import of confirmed appointments from legacy CSV.
+
+use easyappoint::import::{import_appointments_csv, parse_time, split_csv_line};
+use easyappoint::test_util::{assert_invariants, local_time, CalendarFixture};
+
+const LEGACY: &str = "patient_id,patient_name,contact,start,end,priority,reason
+P1,Ann Lee,555-0101,2030-03-05 09:00,2030-03-05 09:30,routine,Checkup
+P2,Bo Chen,555-0102,2030-03-05 18:00,2030-03-05 18:30,urgent,\"Follow-up, bloods\"
+
+P3,Cy Diaz,555-0103,2030-03-05 09:00,2030-03-05 09:30,routine,Checkup
+P4,Di Lu,555-0104,2030-03-05 09:15,2030-03-05 09:45,routine,Checkup
+P5,Ed Moss,555-0105,2030-03-05 10:00,2030-03-05 10:30,soon,Checkup
+P6,Flo Ng,555-0106,2030-03-05 10:00
+P7,Gus Orr,555-0107,5 March 10:00,2030-03-05 10:30,routine,Checkup
+";
+
+#[test]
+fn csv_lines_honour_quotes() {
+    assert_eq!(
+        split_csv_line("a,\"b, c\",\"say \"\"hi\"\"\",").unwrap(),
+        vec!["a", "b, c", "say \"hi\"", ""]
+    );
+    assert_eq!(split_csv_line("one").unwrap(), vec!["one"]);
+    assert!(split_csv_line("a,\"b").is_err());
+
+    assert_eq!(
+        parse_time(" 2030-03-05 09:00 ").unwrap(),
+        local_time(2030, 3, 5, 9, 0)
+    );
+    assert!(parse_time("2030-03-05T09:00").is_err());
+    assert!(parse_time("2030-02-30 09:00").is_err());
+}
+
+#[test]
+fn dry_run_reports_the_plan_without_changing_the_calendar() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(1)
+        .build();
+    let slots_before = calendar.time_slots().len();
+
+    let report = import_appointments_csv(&mut calendar, LEGACY, true).unwrap();
+    assert!(report.dry_run);
+    let imported: Vec<(usize, &str, bool)> = report
+        .imported
+        .iter()
+        .map(|row| (row.line, row.patient.patient_id.as_str(), row.slot_created))
+        .collect();
+    assert_eq!(imported, vec![(2, "P1", false), (3, "P2", true)]);
+    assert_eq!(report.slots_created(), 1);
+    let conflicts: Vec<usize> = report.conflicts.iter().map(|(line, _)| *line).collect();
+    assert_eq!(conflicts, vec![5, 6]);
+    let errors: Vec<usize> = report.errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(errors, vec![7, 8, 9]);
+
+    assert!(calendar.appointments().is_empty());
+    assert_eq!(calendar.time_slots().len(), slots_before);
+}
+
+#[test]
+fn import_books_planned_rows_and_creates_missing_slots() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(1)
+        .build();
+    let slots_before = calendar.time_slots().len();
+
+    let report = import_appointments_csv(&mut calendar, LEGACY, false).unwrap();
+    assert!(!report.dry_run);
+    assert_eq!(report.imported.len(), 2);
+    assert_eq!(calendar.time_slots().len(), slots_before + 1);
+
+    let appointments = calendar.appointments();
+    assert_eq!(appointments.len(), 2);
+    let follow_up = appointments
+        .iter()
+        .find(|apt| apt.patient.patient_id == "P2")
+        .unwrap();
+    assert_eq!(follow_up.reason, "Follow-up, bloods");
+    assert_eq!(
+        follow_up.time_slot.start_time,
+        local_time(2030, 3, 5, 18, 0)
+    );
+    assert_invariants(&calendar);
+
+    let again = import_appointments_csv(&mut calendar, LEGACY, false).unwrap();
+    assert!(again.imported.is_empty());
+    assert_eq!(again.conflicts.len(), 4);
+    assert_eq!(calendar.appointments().len(), 2);
+}
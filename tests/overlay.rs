@@ -0,0 +1,48 @@
+//! Copy-on-write calendar overlays.
+
+use easyappoint::ids::SequentialIds;
+use easyappoint::models::Priority;
+use easyappoint::overlay::CalendarOverlay;
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+use std::sync::Arc;
+
+#[test]
+fn overlay_bookings_leave_the_base_ids_and_checks_alone() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(2)
+        .build();
+    calendar.set_id_generator(Arc::new(SequentialIds::new()));
+    let slots = calendar.time_slots();
+    let (tuesday, wednesday) = (slots[0].clone(), slots.last().unwrap().clone());
+    calendar.lock_day(wednesday.start_time.date_naive());
+
+    let mut overlay = CalendarOverlay::new(&calendar);
+    let hypothetical = overlay
+        .book_slot(
+            &tuesday,
+            patient(1),
+            Priority::Routine,
+            "Checkup".to_string(),
+        )
+        .unwrap();
+    assert!(overlay
+        .book_slot(
+            &wednesday,
+            patient(2),
+            Priority::Routine,
+            "Checkup".to_string()
+        )
+        .is_err());
+    assert_eq!(overlay.appointments().len(), 1);
+
+    let real = calendar
+        .book_slot(
+            &tuesday,
+            patient(1),
+            Priority::Routine,
+            "Checkup".to_string(),
+        )
+        .unwrap();
+    assert_eq!(real.appointment_id, "APT-2030-0001");
+    assert_eq!(hypothetical.appointment_id, real.appointment_id);
+}
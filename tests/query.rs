@@ -0,0 +1,108 @@
+//! Query expressions and their results.
+
+use chrono::NaiveDate;
+use easyappoint::models::{AppointmentStatus, Priority};
+use easyappoint::query::{parse_query, GroupBy};
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+
+fn date(month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2030, month, day).unwrap()
+}
+
+#[test]
+fn conditions_and_grouping_are_parsed() {
+    let today = date(3, 5);
+    let query = parse_query(
+        "appointments where priority=urgent and date in next_7_days group by day",
+        today,
+    )
+    .unwrap();
+    assert_eq!(query.priority, Some(Priority::Urgent));
+    assert_eq!((query.from, query.until), (Some(today), Some(date(3, 11))));
+    assert_eq!(query.group_by, Some(GroupBy::Day));
+
+    let query = parse_query(
+        "APPOINTMENTS Where status = cancelled AND patient=P001 and date >= 2030-03-06 \
+         and date<=2030-03-20 and date in this_week",
+        today,
+    )
+    .unwrap();
+    assert_eq!(query.status, Some(AppointmentStatus::Cancelled));
+    assert_eq!(query.patient_id.as_deref(), Some("P001"));
+    assert_eq!(
+        (query.from, query.until),
+        (Some(date(3, 6)), Some(date(3, 10)))
+    );
+    assert_eq!(query.group_by, None);
+
+    let query = parse_query("appointments where date=2030-03-07", today).unwrap();
+    assert_eq!(
+        (query.from, query.until),
+        (Some(date(3, 7)), Some(date(3, 7)))
+    );
+    let query = parse_query("appointments where date in last_3_days", today).unwrap();
+    assert_eq!((query.from, query.until), (Some(date(3, 3)), Some(today)));
+    let query = parse_query("appointments group by week", today).unwrap();
+    assert!(query.from.is_none() && query.priority.is_none());
+    assert_eq!(query.group_by, Some(GroupBy::Week));
+}
+
+#[test]
+fn malformed_queries_are_rejected() {
+    let today = date(3, 5);
+    for text in [
+        "",
+        "patients where priority=urgent",
+        "appointments where colour=red",
+        "appointments where priority>=urgent",
+        "appointments where priority=soon",
+        "appointments where date=2030-13-01",
+        "appointments where date in next_0_days",
+        "appointments where date in fortnight",
+        "appointments where priority",
+        "appointments group by colour",
+        "appointments group day",
+        "appointments sorted",
+    ] {
+        assert!(parse_query(text, today).is_err(), "{:?} parsed", text);
+    }
+}
+
+#[test]
+fn queries_list_or_count_matching_appointments() {
+    let (mut calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(2)
+        .build();
+    let slots = calendar.time_slots();
+    let tuesday = slots[0].start_time.date_naive();
+    let wednesday = slots.last().unwrap().start_time.date_naive();
+    let booked = [
+        (&slots[0], 1, Priority::Routine),
+        (&slots[1], 2, Priority::Urgent),
+        (slots.last().unwrap(), 3, Priority::Urgent),
+    ];
+    for (slot, n, priority) in booked {
+        calendar
+            .book_slot(slot, patient(n), priority, "Checkup".to_string())
+            .unwrap();
+    }
+
+    let counts = parse_query("appointments group by priority", tuesday)
+        .unwrap()
+        .run(&calendar);
+    assert_eq!(counts.columns, vec!["priority", "appointments"]);
+    assert_eq!(counts.rows, vec![vec!["ROUTINE", "1"], vec!["URGENT", "2"]]);
+
+    let listed = parse_query(
+        "appointments where priority=urgent and date in tomorrow",
+        tuesday,
+    )
+    .unwrap()
+    .run(&calendar);
+    assert_eq!(listed.rows.len(), 1);
+    assert_eq!(listed.rows[0][0], wednesday.to_string());
+    assert_eq!(listed.rows[0][2], "P003");
+    assert!(listed
+        .to_csv()
+        .starts_with("date,time,patient_id,patient_name,priority,status,reason\n"));
+}
@@ -0,0 +1,100 @@
+//! Business rules files.
+
+use chrono::{NaiveTime, Weekday};
+use easyappoint::models::Priority;
+use easyappoint::rules::{Rule, RuleSet, Session};
+use easyappoint::test_util::{local_time, request, CalendarFixture};
+
+const RULES: &str = "# Clinic policy
+
+no \"New Patient\" on friday
+no \"procedure\" on Sat, sun
+max 3 \"procedure\" per day
+fallback emergency
+  hold 2 slots until 11:00
+max 2 emergency per morning
+at least 4 routine per day
+";
+
+#[test]
+fn every_rule_form_parses_and_prints_back() {
+    let rules = RuleSet::parse(RULES).unwrap();
+    assert_eq!(
+        rules.rules(),
+        &[
+            Rule::NoVisitsOn {
+                reason: "New Patient".to_string(),
+                days: vec![Weekday::Fri],
+            },
+            Rule::NoVisitsOn {
+                reason: "procedure".to_string(),
+                days: vec![Weekday::Sat, Weekday::Sun],
+            },
+            Rule::MaxPerDay {
+                reason: "procedure".to_string(),
+                max: 3,
+            },
+            Rule::AlwaysFallback {
+                priority: Priority::Emergency,
+            },
+            Rule::EmergencyHold {
+                slots: 2,
+                release_at: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            },
+            Rule::MaxPerSession {
+                priority: Priority::Emergency,
+                max: 2,
+                session: Session::Morning,
+            },
+            Rule::MinPerSession {
+                priority: Priority::Routine,
+                min: 4,
+                session: Session::Day,
+            },
+        ]
+    );
+    assert!(rules.forces_fallback(Priority::Emergency));
+    assert!(!rules.forces_fallback(Priority::Urgent));
+
+    let printed: Vec<String> = rules.rules().iter().map(Rule::to_string).collect();
+    assert_eq!(printed[1], "no \"procedure\" on Sat, Sun");
+    assert_eq!(RuleSet::parse(&printed.join("\n")).unwrap(), rules);
+}
+
+#[test]
+fn a_bad_line_is_reported_by_number() {
+    for bad in [
+        "no \"new patient\" friday",
+        "no \"new patient\" on funday",
+        "no \"\" on friday",
+        "no \"new patient on friday",
+        "max three \"procedure\" per day",
+        "max 3 \"procedure\" per week",
+        "fallback sometimes",
+        "hold 2 slots until 25:00",
+        "max 2 emergency per evening",
+        "at least 4 routine",
+        "be nice",
+    ] {
+        let text = format!("# policy\nfallback emergency\n{}\n", bad);
+        let error = RuleSet::parse(&text).unwrap_err();
+        assert!(error.starts_with("Line 3: "), "{:?} gave {}", bad, error);
+    }
+}
+
+#[test]
+fn loaded_rules_apply_to_bookings() {
+    let path = std::env::temp_dir().join(format!("easyappoint-rules-{}.txt", std::process::id()));
+    std::fs::write(&path, "no \"checkup\" on tuesday\n").unwrap();
+    let rules = RuleSet::load(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(RuleSet::load(path.to_str().unwrap()).is_err());
+
+    let (calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(2)
+        .build();
+    let slots = calendar.time_slots();
+    let checkup = request(&calendar, 1, Priority::Routine, slots[0].start_time);
+    assert!(!rules.permits(&calendar, &checkup, &slots[0]));
+    assert!(rules.permits(&calendar, &checkup, slots.last().unwrap()));
+}
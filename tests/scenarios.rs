@@ -0,0 +1,244 @@
+//! Scenario tests built on the `test_util` fixtures.
+
+use chrono::{DateTime, Duration, Local};
+use easyappoint::bundle::{BundleRequest, BundleStep};
+use easyappoint::calendar::{DoctorCalendar, SlotTemplate};
+use easyappoint::events::{CalendarEvent, InMemoryPublisher};
+use easyappoint::ids::SequentialIds;
+use easyappoint::import::import_appointments_csv;
+use easyappoint::models::{AppointmentStatus, Priority};
+use easyappoint::projection::CalendarProjection;
+use easyappoint::store::{save_calendar, CalendarStore, InMemoryStore};
+use easyappoint::test_util::{
+    assert_batch_accounted, assert_booked, assert_invariants, assert_snapshot_round_trip,
+    canned_requests, local_time, patient, request, CalendarFixture,
+};
+use std::sync::Arc;
+
+/// 8:00 on a Monday; fixture slots start on the Tuesday.
+fn monday() -> DateTime<Local> {
+    local_time(2030, 3, 4, 8, 0)
+}
+
+fn snapshot_json(calendar: &DoctorCalendar) -> String {
+    serde_json::to_string(&calendar.snapshot()).unwrap()
+}
+
+#[test]
+fn failed_transaction_restores_all_calendar_state() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).build();
+    let before = snapshot_json(&calendar);
+    let tuesday = local_time(2030, 3, 5, 9, 0);
+
+    let result: Result<(), String> = calendar.transaction(|calendar| {
+        let slot = calendar.time_slots()[0].clone();
+        calendar.book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())?;
+        calendar.add_day_note(tuesday.date_naive(), "Fire drill at 11:00".to_string())?;
+        calendar.lock_day(tuesday.date_naive() + Duration::days(1));
+        calendar.block_period(
+            local_time(2030, 3, 7, 9, 0),
+            local_time(2030, 3, 7, 12, 0),
+            "Training".to_string(),
+        )?;
+        Err("Abandoned".to_string())
+    });
+
+    assert_eq!(result, Err("Abandoned".to_string()));
+    assert_eq!(snapshot_json(&calendar), before);
+    assert_invariants(&calendar);
+}
+
+#[test]
+fn what_if_runs_are_not_broadcast_or_published() {
+    let (mut scheduler, _clock) = CalendarFixture::new(monday()).days(1).scheduler();
+    let saturday = local_time(2030, 3, 9, 10, 0);
+    let saturday_request = request(&scheduler.calendar, 1, Priority::Routine, saturday);
+    scheduler.add_request(saturday_request).unwrap();
+    let batch = scheduler.process_queue();
+    assert_eq!(batch.failed.len(), 1);
+
+    let events = scheduler.calendar.subscribe();
+    let outbox_len = scheduler.calendar.outbox().len();
+    let template = SlotTemplate::new(vec![5], 9, 12).unwrap();
+    let report = scheduler.what_if_add_slots(&template, saturday, saturday);
+
+    assert_eq!(report.newly_scheduled.len(), 1);
+    assert!(events.try_recv().is_err());
+    assert_eq!(scheduler.calendar.outbox().len(), outbox_len);
+    assert!(scheduler
+        .calendar
+        .find_available_slots_on_date(saturday)
+        .is_empty());
+}
+
+#[test]
+fn what_if_runs_do_not_use_up_real_ids() {
+    let saturday = local_time(2030, 3, 9, 10, 0);
+    let template = SlotTemplate::new(vec![5], 9, 12).unwrap();
+    let run = |what_if: bool| {
+        let (mut scheduler, _clock) = CalendarFixture::new(monday()).days(1).scheduler();
+        scheduler
+            .calendar
+            .set_id_generator(Arc::new(SequentialIds::new()));
+        let saturday_request = request(&scheduler.calendar, 1, Priority::Routine, saturday);
+        scheduler.add_request(saturday_request).unwrap();
+        scheduler.process_queue();
+        if what_if {
+            let report = scheduler.what_if_add_slots(&template, saturday, saturday);
+            assert_eq!(report.newly_scheduled.len(), 1);
+        }
+        let created = scheduler
+            .calendar
+            .apply_template(&template, saturday, saturday)
+            .created;
+        created[0].slot_id.clone()
+    };
+
+    assert_eq!(run(true), run(false));
+}
+
+#[test]
+fn imports_take_slot_ids_without_reusing_them() {
+    let legacy = "patient_id,patient_name,contact,start,end,priority,reason\n\
+                  P1,Ann Lee,555-0101,2030-03-06 09:00,2030-03-06 09:30,routine,Checkup\n";
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    calendar.set_id_generator(Arc::new(SequentialIds::new()));
+
+    let planned = import_appointments_csv(&mut calendar, legacy, true).unwrap();
+    let imported = import_appointments_csv(&mut calendar, legacy, false).unwrap();
+    assert_eq!(
+        planned.imported[0].slot.slot_id,
+        imported.imported[0].slot.slot_id
+    );
+
+    let template = SlotTemplate::new(vec![5], 9, 10).unwrap();
+    let saturday = local_time(2030, 3, 9, 9, 0);
+    let created = calendar
+        .apply_template(&template, saturday, saturday)
+        .created;
+    assert_eq!(created.len(), 2);
+    assert!(created
+        .iter()
+        .all(|slot| slot.slot_id != imported.imported[0].slot.slot_id));
+    assert_eq!(calendar.time_slots().len(), 16 + 1 + 2);
+}
+
+#[test]
+fn events_published_before_a_save_are_still_logged() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).hours(9, 10).build();
+    let mut publisher = InMemoryPublisher::default();
+    let mut store = InMemoryStore::new();
+
+    assert_eq!(calendar.dispatch_outbox(&mut publisher), 2);
+    save_calendar(&mut store, &mut calendar).unwrap();
+    let slot = calendar.time_slots()[0].clone();
+    calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    save_calendar(&mut store, &mut calendar).unwrap();
+    assert_eq!(calendar.dispatch_outbox(&mut publisher), 1);
+
+    let logged: Vec<u64> = store
+        .events(&calendar.doctor_id)
+        .unwrap()
+        .iter()
+        .map(|entry| entry.sequence)
+        .collect();
+    let published: Vec<u64> = publisher
+        .published
+        .iter()
+        .map(|entry| entry.sequence)
+        .collect();
+    assert_eq!(logged, vec![1, 2, 3]);
+    assert_eq!(published, logged);
+    assert!(calendar.outbox().is_empty());
+}
+
+#[test]
+fn status_changes_reach_subscribers_and_projections() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    let slot = calendar.time_slots()[0].clone();
+    let appointment = calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    let events = calendar.subscribe();
+
+    calendar.check_in(&appointment.appointment_id).unwrap();
+
+    match events.try_recv() {
+        Ok(CalendarEvent::AppointmentStatusChanged {
+            previous_status,
+            appointment: changed,
+        }) => {
+            assert_eq!(previous_status, AppointmentStatus::Confirmed);
+            assert_eq!(changed.status, AppointmentStatus::CheckedIn);
+        }
+        other => panic!("Expected a status change, got {:?}", other),
+    }
+    let projection = CalendarProjection::rebuild(calendar.doctor_id.clone(), calendar.outbox());
+    assert_eq!(
+        projection.appointments()[0].status,
+        AppointmentStatus::CheckedIn
+    );
+}
+
+#[test]
+fn bundle_cancellation_is_all_or_nothing() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(2).build();
+    let steps = vec![
+        BundleStep::new("Blood test".to_string(), 0, 0).unwrap(),
+        BundleStep::new("Results review".to_string(), 24 * 60 - 30, 24 * 60).unwrap(),
+    ];
+    let bundle_request = BundleRequest::new(
+        patient(1),
+        Priority::Routine,
+        local_time(2030, 3, 5, 9, 0),
+        0,
+        steps,
+    )
+    .unwrap();
+    let booking = calendar.book_bundle(&bundle_request).unwrap();
+    let second_day = booking.appointments[1].time_slot.start_time.date_naive();
+    assert_ne!(
+        booking.appointments[0].time_slot.start_time.date_naive(),
+        second_day
+    );
+
+    calendar.lock_day(second_day);
+    assert!(calendar.cancel_bundle(&booking.bundle_id).is_err());
+    let remaining = calendar.bundle_appointments(&booking.bundle_id);
+    assert_eq!(remaining.len(), 2);
+    assert!(remaining
+        .iter()
+        .all(|apt| apt.status == AppointmentStatus::Confirmed));
+}
+
+fn seeded_scenario() -> (String, Vec<String>, String) {
+    let (mut scheduler, _clock) = CalendarFixture::new(monday()).seed(7).scheduler();
+    for request in canned_requests(&scheduler.calendar, 6) {
+        scheduler.add_request(request).unwrap();
+    }
+    let batch = scheduler.process_queue();
+    assert_batch_accounted(&scheduler.calendar, &batch);
+    let booked = assert_booked(&scheduler.schedule_single(request(
+        &scheduler.calendar,
+        7,
+        Priority::Urgent,
+        local_time(2030, 3, 6, 14, 0),
+    )));
+    scheduler.calendar.check_in(&booked.appointment_id).unwrap();
+    assert_snapshot_round_trip(&scheduler.calendar);
+
+    let codes = scheduler
+        .calendar
+        .appointments()
+        .into_iter()
+        .map(|apt| apt.confirmation_code)
+        .collect();
+    (snapshot_json(&scheduler.calendar), codes, batch.run_id)
+}
+
+#[test]
+fn seeded_runs_on_a_fixed_clock_are_identical() {
+    assert_eq!(seeded_scenario(), seeded_scenario());
+}
@@ -0,0 +1,47 @@
+//! Time-off requests and approval.
+
+use chrono::Duration;
+use easyappoint::models::{Priority, TimeSlot};
+use easyappoint::test_util::{local_time, patient, request, CalendarFixture};
+
+#[test]
+fn approved_time_off_stays_closed_to_new_slots_and_bookings() {
+    let (mut scheduler, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0))
+        .days(2)
+        .scheduler();
+    let start = local_time(2030, 3, 5, 9, 0);
+    let end = local_time(2030, 3, 5, 12, 0);
+    let booked = scheduler.schedule_single(request(
+        &scheduler.calendar,
+        1,
+        Priority::Routine,
+        local_time(2030, 3, 5, 10, 0),
+    ));
+    assert!(booked.success);
+
+    let time_off = scheduler
+        .request_time_off(start, end, "Conference".to_string())
+        .unwrap();
+    let report = scheduler.approve_time_off(&time_off.request_id).unwrap();
+
+    assert_eq!(report.moved.len(), 1);
+    assert!(report.moved[0].appointment.time_slot.start_time >= end);
+    assert_eq!(report.block.removed.len(), 6);
+    assert_eq!(scheduler.calendar.blocked_periods().len(), 1);
+
+    let regenerated = scheduler
+        .calendar
+        .generate_daily_slots(start, 9, 17, None, None, None);
+    assert!(regenerated
+        .created
+        .iter()
+        .all(|slot| slot.start_time >= end));
+    assert_eq!(regenerated.skipped_busy.len(), 6);
+
+    let inside = TimeSlot::new(start, start + Duration::minutes(30)).unwrap();
+    let refused = scheduler
+        .calendar
+        .book_slot(&inside, patient(2), Priority::Urgent, "Checkup".to_string())
+        .unwrap_err();
+    assert!(refused.starts_with("Blocked: Conference"), "{}", refused);
+}
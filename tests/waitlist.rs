@@ -0,0 +1,140 @@
+//! Waitlist export to CSV and re-import of the edited file.
+
+use chrono::{NaiveDate, NaiveTime, Weekday};
+use easyappoint::models::{AppointmentRequest, DateWindow, Priority};
+use easyappoint::test_util::{local_time, request, CalendarFixture};
+use easyappoint::waitlist::Waitlist;
+
+fn encoded(requests: &[AppointmentRequest]) -> Vec<serde_json::Value> {
+    requests
+        .iter()
+        .map(|request| serde_json::to_value(request).unwrap())
+        .collect()
+}
+
+#[test]
+fn an_unedited_export_imports_as_it_was() {
+    let (calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0)).build();
+    let mut waitlist = Waitlist::new();
+    let mut windowed = request(
+        &calendar,
+        1,
+        Priority::Routine,
+        local_time(2030, 3, 5, 9, 0),
+    );
+    windowed.reason = "Bloods, \"fasting\"".to_string();
+    windowed.set_window(15, 90).unwrap();
+    windowed.duration_minutes = Some(60);
+    windowed.accepts_premium = true;
+    windowed.date_window = Some(
+        DateWindow::new(
+            NaiveDate::from_ymd_opt(2030, 3, 5).unwrap(),
+            NaiveDate::from_ymd_opt(2030, 3, 12).unwrap(),
+            vec![Weekday::Tue, Weekday::Thu],
+            vec![
+                (
+                    NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(12, 0, 0).unwrap(),
+                ),
+                (
+                    NaiveTime::from_hms_opt(14, 0, 0).unwrap(),
+                    NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                ),
+            ],
+        )
+        .unwrap(),
+    );
+    windowed
+        .add_unavailable_time(local_time(2030, 3, 5, 15, 0), local_time(2030, 3, 5, 16, 0))
+        .unwrap();
+    windowed
+        .add_unavailable_time(local_time(2030, 3, 7, 9, 0), local_time(2030, 3, 7, 10, 30))
+        .unwrap();
+    waitlist.park(windowed);
+    waitlist.park(request(
+        &calendar,
+        2,
+        Priority::Emergency,
+        local_time(2030, 3, 5, 10, 0),
+    ));
+    waitlist.park(request(
+        &calendar,
+        3,
+        Priority::Routine,
+        local_time(2030, 3, 6, 11, 0),
+    ));
+    let before = encoded(waitlist.requests());
+
+    let csv = waitlist.to_csv();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with("position,request_id,patient_id,"));
+    assert!(lines[1].starts_with("1,") && lines[1].contains(",emergency,"));
+
+    let report = waitlist.import_csv(&csv, &calendar);
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    assert!(report.added.is_empty() && report.removed.is_empty());
+    assert_eq!(report.updated.len(), 3);
+    let in_order = vec![before[1].clone(), before[0].clone(), before[2].clone()];
+    assert_eq!(encoded(waitlist.requests()), in_order);
+    assert_eq!(waitlist.to_csv(), csv);
+}
+
+#[test]
+fn edits_update_add_and_remove_requests() {
+    let (calendar, _clock) = CalendarFixture::new(local_time(2030, 3, 4, 8, 0)).build();
+    let mut waitlist = Waitlist::new();
+    for n in 1..=3 {
+        waitlist.park(request(
+            &calendar,
+            n,
+            Priority::Routine,
+            local_time(2030, 3, 5, 9, 0),
+        ));
+    }
+    let [first, second, third] = [0, 1, 2].map(|i| waitlist.requests()[i].clone());
+
+    let csv = waitlist.to_csv();
+    let mut lines: Vec<String> = csv.lines().map(str::to_string).collect();
+    lines[1] = lines[1].replace(",routine,", ",urgent,");
+    lines[2] = lines[2].replace(",no,,,,,,", ",no,,,Someday,,,");
+    lines.remove(3);
+    lines.push(
+        "9,,P009,New Patient,new@example.com,routine,2030-03-06 14:00,30,,,,no,,,,,,Call back"
+            .to_string(),
+    );
+    lines.push("9,R-GONE,P001,Ann,a@example.com,routine".to_string());
+    let report = waitlist.import_csv(&lines.join("\n"), &calendar);
+
+    assert_eq!(report.updated, vec![first.request_id.clone()]);
+    assert_eq!(report.added.len(), 1);
+    let removed: Vec<&str> = report
+        .removed
+        .iter()
+        .map(|request| request.request_id.as_str())
+        .collect();
+    assert_eq!(removed, vec![third.request_id.as_str()]);
+    let lines_with_errors: Vec<usize> = report.errors.iter().map(|(line, _)| *line).collect();
+    assert_eq!(lines_with_errors, vec![3, 5]);
+
+    let order: Vec<(String, Priority)> = waitlist
+        .in_order()
+        .into_iter()
+        .map(|request| (request.request_id, request.priority))
+        .collect();
+    assert_eq!(
+        order,
+        vec![
+            (first.request_id.clone(), Priority::Urgent),
+            (report.added[0].clone(), Priority::Routine),
+            (second.request_id.clone(), Priority::Routine),
+        ]
+    );
+    let added = waitlist
+        .requests()
+        .iter()
+        .find(|request| request.request_id == report.added[0])
+        .unwrap();
+    assert_eq!(added.patient.name, "New Patient");
+    assert_eq!(added.created_at, calendar.now());
+}
@@ -0,0 +1,70 @@
+//! Appointment status workflow.
+
+use chrono::{DateTime, Local};
+use easyappoint::calendar::DoctorCalendar;
+use easyappoint::models::{AppointmentStatus, CancellationActor, Priority};
+use easyappoint::test_util::{local_time, patient, CalendarFixture};
+
+fn monday() -> DateTime<Local> {
+    local_time(2030, 3, 4, 8, 0)
+}
+
+#[test]
+fn bookings_needing_confirmation_walk_the_whole_workflow() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    calendar.require_confirmation = true;
+    let slot = calendar.time_slots()[0].clone();
+    let booked = calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    assert_eq!(booked.status, AppointmentStatus::Requested);
+    assert!(calendar.check_in(&booked.appointment_id).is_err());
+
+    let id = booked.appointment_id;
+    assert_eq!(
+        calendar.confirm(&id).unwrap().status,
+        AppointmentStatus::Confirmed
+    );
+    assert!(calendar.confirm(&id).is_err());
+    assert_eq!(
+        calendar.check_in(&id).unwrap().status,
+        AppointmentStatus::CheckedIn
+    );
+    assert_eq!(
+        calendar.complete(&id).unwrap().status,
+        AppointmentStatus::Completed
+    );
+    assert!(calendar
+        .cancel_appointment_by(&id, CancellationActor::Staff)
+        .is_err());
+}
+
+#[test]
+fn cancelled_appointments_are_kept_and_free_their_slot() {
+    let (mut calendar, _clock) = CalendarFixture::new(monday()).days(1).build();
+    let slot = calendar.time_slots()[0].clone();
+    let booked = calendar
+        .book_slot(&slot, patient(1), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+
+    assert!(calendar.cancel_appointment(&booked.appointment_id));
+    let cancelled = calendar
+        .get_appointment_by_id(&booked.appointment_id)
+        .unwrap();
+    assert_eq!(cancelled.status, AppointmentStatus::Cancelled);
+    assert!(calendar.appointments().is_empty());
+    assert_eq!(calendar.cancelled_appointments().len(), 1);
+    assert!(!calendar.cancel_appointment(&booked.appointment_id));
+    assert!(calendar.confirm(&booked.appointment_id).is_err());
+
+    let rebooked = calendar
+        .book_slot(&slot, patient(2), Priority::Routine, "Checkup".to_string())
+        .unwrap();
+    let restored = DoctorCalendar::from_snapshot(calendar.snapshot()).unwrap();
+    assert_eq!(restored.appointments().len(), 1);
+    assert_eq!(
+        restored.appointments()[0].appointment_id,
+        rebooked.appointment_id
+    );
+    assert_eq!(restored.cancelled_appointments().len(), 1);
+}